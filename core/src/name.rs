@@ -5,6 +5,7 @@ use crate::{
 };
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use shared_deps::bevy_turborand::DelegatedRng;
 
 pub struct NamePlugin;
 
@@ -30,23 +31,30 @@ pub struct EntityName {
 }
 
 impl EntityName {
+    // The id is left at 0, randomness comes from the caller's rng through with_id
     pub fn new(first_name: impl Into<String>) -> Self {
         Self {
             first_name: first_name.into(),
             middle_name: None,
             last_name: None,
-            id: shared_deps::rand::random(),
+            id: 0,
         }
     }
 
-    pub fn random(text_db: &TextDatabase) -> Self {
-        let first_name = text_db.random_given_name_key();
-        let middle_name = text_db.random_given_name_key();
-        let last_name = text_db.random_surname_key();
+    pub fn random<T: DelegatedRng>(text_db: &TextDatabase, rng: &mut T) -> Self {
+        let first_name = text_db.random_given_name_key(rng);
+        let middle_name = text_db.random_given_name_key(rng);
+        let last_name = text_db.random_surname_key(rng);
 
         Self::new(first_name)
             .with_middle_name(middle_name)
             .with_last_name(last_name)
+            .with_id(rng.u32(..))
+    }
+
+    pub fn with_id(mut self, id: u32) -> Self {
+        self.id = id;
+        self
     }
 
     pub fn with_middle_name(mut self, middle_name: impl Into<String>) -> Self {
@@ -3,7 +3,7 @@ use std::{collections::HashMap, str::FromStr};
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 use shared_deps::bevy_common_assets::ron::RonAssetPlugin;
-use shared_deps::rand::seq::SliceRandom;
+use shared_deps::bevy_turborand::DelegatedRng;
 use strum_macros::EnumIter;
 
 use crate::GameState;
@@ -95,17 +95,15 @@ impl TextDatabase {
             .any(|language_map| language_map.contains_key(key))
     }
 
-    pub fn random_given_name_key(&self) -> &str {
-        let mut rng = shared_deps::rand::thread_rng();
-        self.default_given_names_keys.choose(&mut rng).unwrap()
+    pub fn random_given_name_key<T: DelegatedRng>(&self, rng: &mut T) -> &str {
+        &self.default_given_names_keys[rng.usize(0..self.default_given_names_keys.len())]
     }
 
-    pub fn random_surname_key(&self) -> &str {
-        let mut rng = shared_deps::rand::thread_rng();
-        self.default_surnames_keys.choose(&mut rng).unwrap()
+    pub fn random_surname_key<T: DelegatedRng>(&self, rng: &mut T) -> &str {
+        &self.default_surnames_keys[rng.usize(0..self.default_surnames_keys.len())]
     }
 
-    fn populate_default_name_keys(&mut self) {
+    pub fn populate_default_name_keys(&mut self) {
         // English is the base language, so we can just use that to find the default name keys
         for key in self.values.get(&Language::English).unwrap().keys() {
             if key.starts_with("names.default.given") {
@@ -114,6 +112,10 @@ impl TextDatabase {
                 self.default_surnames_keys.push(key.clone());
             }
         }
+
+        // Map order changes every run, sorted so a seeded rng picks the same names
+        self.default_given_names_keys.sort();
+        self.default_surnames_keys.sort();
     }
 }
 
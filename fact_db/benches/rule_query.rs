@@ -0,0 +1,70 @@
+use std::{hint::black_box, time::Instant};
+
+use fact_db::{parse::RawRuleSet, Concept, FactDb, FactQuery, RuleSet};
+use shared_deps::{bevy_turborand::RngComponent, ron};
+
+// cargo bench -p fact_db --bench rule_query
+const KEYS: usize = 500;
+const QUERIES: u32 = 10_000;
+
+// Dialogue shaped like ours, a couple of shared facts plus a few specific ones per rule
+fn rule_set(rule_count: usize) -> RuleSet {
+    let mut source = String::from("RawRuleSet(entries: [");
+    source.push_str("Response(RawResponse(id: \"Response\", now: [\"RandomText bench\"])),");
+    for i in 0..rule_count {
+        let concept = if i % 4 == 0 {
+            "ThinkJustAte"
+        } else {
+            "ThinkIdle"
+        };
+        source.push_str(&format!(
+            "Rule(RawRule(id: \"Rule{i}\", criteria: Criteria(concept: {concept}, facts: [\
+             \"Kind = Creature\", \"Mood > 1.5\", \"Fact{} || Fact{}\", \"Fact{} > 0.5\"]), \
+             response: \"Response\")),",
+            i % KEYS,
+            (i * 7) % KEYS,
+            (i * 13) % KEYS,
+        ));
+    }
+    source.push_str("])");
+
+    let raw_rule_set: RawRuleSet = ron::from_str(&source).unwrap();
+    raw_rule_set.try_into().unwrap()
+}
+
+fn main() {
+    let mut global = FactDb::default();
+    global.add("Mood", 2.0);
+    global.add_str("Kind", "Creature");
+
+    // A pet only ever knows a handful of the facts the dialogue talks about
+    let mut pet = FactDb::default();
+    for i in 0..20 {
+        pet.add(format!("Fact{}", i * 31 % KEYS), 1.0);
+    }
+
+    for rule_count in [100, 1_000, 5_000, 20_000] {
+        let rule_set = rule_set(rule_count);
+
+        let mut rng = RngComponent::with_seed(0);
+        let start = Instant::now();
+        let mut matched = 0;
+        for _ in 0..QUERIES {
+            let query = FactQuery::new(Concept::ThinkIdle)
+                .add_fact_db(&global)
+                .add_fact_db(&pet);
+            if black_box(query.run_with_rng(black_box(&rule_set), &mut rng)).is_some() {
+                matched += 1;
+            }
+        }
+        let elapsed = start.elapsed();
+
+        println!(
+            "{:>6} rules: {:>8.2?} per query ({} of {} matched)",
+            rule_set.len(),
+            elapsed / QUERIES,
+            matched,
+            QUERIES
+        );
+    }
+}
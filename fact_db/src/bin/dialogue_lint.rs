@@ -0,0 +1,44 @@
+use std::{fs, process::ExitCode};
+
+use fact_db::validate::validate_dialogue;
+
+// cargo run -p fact_db --bin dialogue_lint -- run/assets/dialogue/main.dialogue.ron
+fn main() -> ExitCode {
+    let paths = std::env::args().skip(1).collect::<Vec<_>>();
+    if paths.is_empty() {
+        eprintln!("usage: dialogue_lint <file.dialogue.ron>...");
+        return ExitCode::from(2);
+    }
+
+    let mut failed = false;
+    for path in &paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                failed = true;
+                continue;
+            }
+        };
+
+        match validate_dialogue(&source) {
+            Ok(_) => println!("{}: ok", path),
+            Err(errors) => {
+                failed = true;
+                for error in &errors {
+                    eprintln!("{}: {}", path, error);
+                    if let Some(line) = error.line.and_then(|line| source.lines().nth(line - 1)) {
+                        eprintln!("    | {}", line.trim());
+                    }
+                }
+                eprintln!("{}: {} problem(s)", path, errors.len());
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -5,18 +5,44 @@ extern crate lazy_static;
 extern crate maplit;
 
 pub mod parse;
+pub mod validate;
 
 use core::fmt;
 use std::{collections::HashMap, time::Duration};
 
-use bevy::prelude::*;
+use bevy::{
+    ecs::{intern::Interned, schedule::ScheduleLabel},
+    prelude::*,
+};
 use serde::{
     de::{self, Visitor},
     Deserialize, Deserializer, Serialize,
 };
-use shared_deps::rand::prelude::SliceRandom;
+use shared_deps::bevy_turborand::DelegatedRng;
+
+pub struct FactsPlugin {
+    expire_schedules: Vec<Interned<dyn ScheduleLabel>>,
+}
+
+impl FactsPlugin {
+    // Expiring facts count down with the Time of the schedules they run in
+    pub fn new(expire_schedule: impl ScheduleLabel) -> Self {
+        Self {
+            expire_schedules: vec![expire_schedule.intern()],
+        }
+    }
+
+    pub fn with_expire_schedule(mut self, expire_schedule: impl ScheduleLabel) -> Self {
+        self.expire_schedules.push(expire_schedule.intern());
+        self
+    }
+}
 
-pub struct FactsPlugin;
+impl Default for FactsPlugin {
+    fn default() -> Self {
+        Self::new(Update)
+    }
+}
 
 impl Plugin for FactsPlugin {
     fn build(&self, app: &mut App) {
@@ -24,46 +50,165 @@ impl Plugin for FactsPlugin {
             .register_type::<EntityFactDatabase>()
             .insert_resource(GlobalFactDatabase::default())
             .add_event::<ActionEvent>()
-            .add_event::<FactInsert>();
+            .add_event::<FactChange>();
+
+        // Actions are applied wherever they might be sent from and always before the
+        // expiries in the same schedule tick, whichever runs first takes the pending ones
+        let mut apply_schedules = vec![Update.intern()];
+        for schedule in &self.expire_schedules {
+            if !apply_schedules.contains(schedule) {
+                apply_schedules.push(*schedule);
+            }
+        }
+        for schedule in apply_schedules {
+            app.configure_sets(
+                schedule,
+                FactSystems::ApplyActions.before(FactSystems::Expire),
+            )
+            .add_systems(
+                schedule,
+                (apply_pending_action, read_fact_changes)
+                    .chain()
+                    .in_set(FactSystems::ApplyActions),
+            );
+        }
+
+        for schedule in &self.expire_schedules {
+            app.add_systems(*schedule, expire_facts.in_set(FactSystems::Expire));
+        }
     }
 }
-pub fn fact_str_hash(s: impl ToString) -> f32 {
-    let mut s = s.to_string().to_lowercase();
-    s.retain(|c| !c.is_whitespace());
 
-    let hash = shared_deps::fxhash::hash32(&s);
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FactSystems {
+    ApplyActions,
+    Expire,
+}
+
+// Untagged so saves from when every fact was a float still load
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FactValue {
+    Number(f32),
+    Text(String),
+}
+
+// What a fact that was never set compares as
+const MISSING_FACT: FactValue = FactValue::Number(0.);
+
+impl FactValue {
+    // Text facts ignore case and whitespace so "Was Guard" matches "wasguard"
+    pub fn text(value: impl ToString) -> Self {
+        let mut value = value.to_string().to_lowercase();
+        value.retain(|c| !c.is_whitespace());
+        Self::Text(value)
+    }
+
+    pub fn as_number(&self) -> Option<f32> {
+        match self {
+            FactValue::Number(value) => Some(*value),
+            FactValue::Text(_) => None,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            FactValue::Number(_) => None,
+            FactValue::Text(value) => Some(value),
+        }
+    }
+}
 
-    let bytes = hash.to_be_bytes();
+impl From<f32> for FactValue {
+    fn from(value: f32) -> Self {
+        FactValue::Number(value)
+    }
+}
 
-    f32::from_be_bytes(bytes)
+impl fmt::Display for FactValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FactValue::Number(value) => write!(f, "{}", value),
+            FactValue::Text(value) => write!(f, "\"{}\"", value),
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, Reflect, PartialEq)]
 #[reflect_value(PartialEq, Serialize, Deserialize)]
 pub struct FactDb {
-    facts: HashMap<String, f32>,
+    facts: HashMap<String, FactValue>,
+    // Time left on facts that remove themselves, saved with the facts so they still go after a reload
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    expiries: HashMap<String, Duration>,
 }
 
 impl FactDb {
     pub fn add<T: ToString>(&mut self, key: T, value: f32) {
-        self.facts.insert(key.to_string(), value);
+        self.facts.insert(key.to_string(), value.into());
     }
 
     pub fn add_str<T: ToString, J: ToString>(&mut self, key: T, value: J) {
-        self.facts
-            .insert(key.to_string(), fact_str_hash(value.to_string()));
+        self.facts.insert(key.to_string(), FactValue::text(value));
+    }
+
+    pub fn set<T: ToString>(&mut self, key: T, value: FactValue) {
+        self.facts.insert(key.to_string(), value);
+    }
+
+    // A text fact is replaced as if it was never set
+    pub fn increment<T: ToString>(&mut self, key: T, amount: f32) {
+        let value = self.get(&key.to_string()) + amount;
+        self.add(key, value);
     }
 
     pub fn remove<T: ToString>(&mut self, key: T) {
-        self.facts.remove(&key.to_string());
+        let key = key.to_string();
+        self.facts.remove(&key);
+        self.expiries.remove(&key);
+    }
+
+    // The fact is removed once the duration has passed, None keeps it until removed
+    pub fn set_expiry<T: ToString>(&mut self, key: T, expire: Option<Duration>) {
+        match expire {
+            Some(expire) => self.expiries.insert(key.to_string(), expire),
+            None => self.expiries.remove(&key.to_string()),
+        };
+    }
+
+    pub fn expires_in(&self, key: &str) -> Option<Duration> {
+        self.expiries.get(key).copied()
     }
 
+    pub fn tick(&mut self, delta: Duration) {
+        let facts = &mut self.facts;
+        self.expiries.retain(|key, left| {
+            *left = left.saturating_sub(delta);
+            if left.is_zero() {
+                facts.remove(key);
+            }
+            !left.is_zero()
+        });
+    }
+
+    // Numeric value of the fact, 0 when it is not set or is text
     pub fn get(&self, key: &str) -> f32 {
-        if let Some(value) = self.facts.get(key) {
-            *value
-        } else {
-            0.
-        }
+        self.facts
+            .get(key)
+            .and_then(|value| value.as_number())
+            .unwrap_or(0.)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.facts.get(key).and_then(|value| value.as_text())
+    }
+
+    pub fn get_value(&self, key: &str) -> Option<&FactValue> {
+        self.facts.get(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &FactValue)> {
+        self.facts.iter()
     }
 
     // SLOW POINT
@@ -76,7 +221,7 @@ impl FactDb {
             .collect();
 
         for key in keys {
-            self.facts.remove(&key);
+            self.remove(key);
         }
     }
 }
@@ -101,56 +246,42 @@ pub struct EntityFactDatabase(pub FactDb);
 #[derive(Resource, Default)]
 pub struct GlobalFactDatabase(pub FactDb);
 
-#[allow(dead_code)]
-#[derive(Event)]
-enum FactInsert {
-    Global(String, f32, Option<Duration>),
-    Entity(Entity, String, f32, Option<Duration>),
+#[derive(Debug, Clone, PartialEq)]
+enum FactOp {
+    Set(FactValue, Option<Duration>),
+    Increment(f32),
+    Remove,
 }
 
-#[derive(Component)]
-struct PendingFactDelete {
-    key: String,
-    expire: Timer,
+#[derive(Event)]
+enum FactChange {
+    Global(String, FactOp),
+    Entity(Entity, String, FactOp),
 }
 
-impl PendingFactDelete {
-    fn new<T: ToString>(key: T, duration: Duration) -> Self {
-        Self {
-            key: key.to_string(),
-            expire: Timer::new(duration, TimerMode::Once),
+fn apply_fact_op(fact_db: &mut FactDb, key: &str, op: &FactOp) {
+    match op {
+        // A new value drops whatever expiry the old value had
+        FactOp::Set(value, expire) => {
+            fact_db.set(key, value.clone());
+            fact_db.set_expiry(key, *expire);
         }
+        FactOp::Increment(amount) => fact_db.increment(key, *amount),
+        FactOp::Remove => fact_db.remove(key),
     }
 }
 
-#[allow(dead_code)]
-#[derive(Component)]
-struct PendingFactDeleteEntity(Entity);
-
-#[allow(dead_code)]
-fn read_fact_inserts(
-    mut commands: Commands,
+fn read_fact_changes(
     mut fact_db: ResMut<GlobalFactDatabase>,
     mut fact_db_entities: Query<&mut EntityFactDatabase>,
-    mut fact_inserts: EventReader<FactInsert>,
+    mut fact_changes: ResMut<Events<FactChange>>,
 ) {
-    for insert in fact_inserts.read() {
-        match insert {
-            FactInsert::Global(key, value, expire) => {
-                fact_db.0.add(key.clone(), *value);
-                if let Some(expire) = expire {
-                    commands.spawn(PendingFactDelete::new(key.clone(), *expire));
-                }
-            }
-            FactInsert::Entity(entity, key, value, expire) => {
-                if let Ok(mut fact_db) = fact_db_entities.get_mut(*entity) {
-                    fact_db.0.add(key.clone(), *value);
-                    if let Some(expire) = expire {
-                        commands.spawn((
-                            PendingFactDelete::new(key.clone(), *expire),
-                            PendingFactDeleteEntity(*entity),
-                        ));
-                    }
+    for change in fact_changes.drain() {
+        match change {
+            FactChange::Global(key, op) => apply_fact_op(&mut fact_db.0, &key, &op),
+            FactChange::Entity(entity, key, op) => {
+                if let Ok(mut fact_db) = fact_db_entities.get_mut(entity) {
+                    apply_fact_op(&mut fact_db.0, &key, &op);
                 } else {
                     error!("Entity {:?} does not have a fact database", entity);
                 }
@@ -159,40 +290,19 @@ fn read_fact_inserts(
     }
 }
 
-#[allow(dead_code)]
-fn tick_pending_fact_deletes(time: Res<Time>, mut query: Query<&mut PendingFactDelete>) {
-    for mut delete in query.iter_mut() {
-        delete.expire.tick(time.delta());
-    }
-}
-
-#[allow(dead_code)]
-fn expire_global_facts(
-    mut commands: Commands,
-    mut fact_db: ResMut<GlobalFactDatabase>,
-    query: Query<(Entity, &PendingFactDelete), Without<PendingFactDeleteEntity>>,
-) {
-    for (entity, delete) in &query {
-        if delete.expire.finished() {
-            fact_db.0.facts.remove(&delete.key);
-            commands.entity(entity).despawn();
-        }
-    }
-}
-
-#[allow(dead_code)]
-fn expire_entity_facts(
-    mut commands: Commands,
+// Only databases with something counting down are touched so the rest don't show as changed
+fn expire_facts(
+    time: Res<Time>,
+    mut global_fact_db: ResMut<GlobalFactDatabase>,
     mut fact_dbs: Query<&mut EntityFactDatabase>,
-    deletes: Query<(Entity, &PendingFactDelete, &PendingFactDeleteEntity)>,
 ) {
-    for (entity, delete, target) in &deletes {
-        if delete.expire.finished() {
-            if let Ok(mut fact_db) = fact_dbs.get_mut(target.0) {
-                fact_db.0.facts.remove(&delete.key);
-            }
+    if !global_fact_db.0.expiries.is_empty() {
+        global_fact_db.0.tick(time.delta());
+    }
 
-            commands.entity(entity).despawn();
+    for mut fact_db in &mut fact_dbs {
+        if !fact_db.0.expiries.is_empty() {
+            fact_db.0.tick(time.delta());
         }
     }
 }
@@ -217,25 +327,35 @@ impl ActionEvent {
     }
 }
 
-#[allow(dead_code)]
+// Drains rather than reads so running in several schedules never applies an action twice
 fn apply_pending_action(
-    mut action_events: EventReader<ActionEvent>,
-    mut inserts: EventWriter<FactInsert>,
+    mut action_events: ResMut<Events<ActionEvent>>,
+    mut changes: EventWriter<FactChange>,
 ) {
-    for event in action_events.read() {
+    for event in action_events.drain() {
         for action in &event.action_set.actions {
-            match action {
+            let (key, op, is_entity) = match action {
                 Action::InsertGlobalFact(key, value, expire) => {
-                    inserts.send(FactInsert::Global(key.clone(), *value, *expire));
+                    (key, FactOp::Set(value.clone(), *expire), false)
                 }
                 Action::InsertEntityFact(key, value, expire) => {
-                    if let Some(entity) = event.entity {
-                        inserts.send(FactInsert::Entity(entity, key.clone(), *value, *expire));
-                    } else {
-                        error!("No entity provided for entity fact insert");
-                    }
+                    (key, FactOp::Set(value.clone(), *expire), true)
                 }
-                Action::RandomText(_) => {}
+                Action::IncrementGlobalFact(key, amount) => {
+                    (key, FactOp::Increment(*amount), false)
+                }
+                Action::IncrementEntityFact(key, amount) => (key, FactOp::Increment(*amount), true),
+                Action::RemoveGlobalFact(key) => (key, FactOp::Remove, false),
+                Action::RemoveEntityFact(key) => (key, FactOp::Remove, true),
+                Action::RandomText(_) => continue,
+            };
+
+            if !is_entity {
+                changes.send(FactChange::Global(key.clone(), op));
+            } else if let Some(entity) = event.entity {
+                changes.send(FactChange::Entity(entity, key.clone(), op));
+            } else {
+                error!("No entity provided for entity fact action {:?}", action);
             }
         }
     }
@@ -246,15 +366,27 @@ pub enum Concept {
     ThinkIdle,
     ThinkJustAte,
     ThinkStartingEating,
+    ThinkMiniGameFinished,
+    ThinkSteppedInPoop,
+    ThinkSawHatch,
+    ThinkNewAccessory,
+    ThinkPortfolioRising,
+    ThinkPortfolioCrashing,
+    ThinkPetted,
+    ThinkCompanyDelisted,
     Evolve,
 }
 
-// Add cooldown
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Action {
     RandomText(Vec<String>),
-    InsertGlobalFact(String, f32, Option<Duration>),
-    InsertEntityFact(String, f32, Option<Duration>),
+    InsertGlobalFact(String, FactValue, Option<Duration>),
+    InsertEntityFact(String, FactValue, Option<Duration>),
+    // Decrements are negative increments
+    IncrementGlobalFact(String, f32),
+    IncrementEntityFact(String, f32),
+    RemoveGlobalFact(String),
+    RemoveEntityFact(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -285,22 +417,191 @@ pub struct Response {
     pub after: ActionSet,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    // Text only has equality, comparing text to a number is never equal
+    fn compare(self, lhs: &FactValue, rhs: &FactValue) -> bool {
+        match (lhs, rhs) {
+            (FactValue::Number(a), FactValue::Number(b)) => match self {
+                CompareOp::Eq => a == b,
+                CompareOp::Ne => a != b,
+                CompareOp::Lt => a < b,
+                CompareOp::Le => a <= b,
+                CompareOp::Gt => a > b,
+                CompareOp::Ge => a >= b,
+            },
+            (FactValue::Text(a), FactValue::Text(b)) => match self {
+                CompareOp::Eq => a == b,
+                CompareOp::Ne => a != b,
+                _ => false,
+            },
+            _ => self == CompareOp::Ne,
+        }
+    }
+}
+
+impl fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            CompareOp::Eq => "=",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Value(FactValue),
+    Fact(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum FactTest {
+    Compare {
+        key: String,
+        op: CompareOp,
+        operand: Operand,
+    },
+    // start..end or start..=end, numbers only
+    Range {
+        key: String,
+        start: f32,
+        end: f32,
+        inclusive: bool,
+    },
+}
+
+impl FactTest {
+    fn key(&self) -> &str {
+        match self {
+            FactTest::Compare { key, .. } => key,
+            FactTest::Range { key, .. } => key,
+        }
+    }
+
+    fn check(&self, value: &FactValue, operand: &FactValue) -> bool {
+        match self {
+            FactTest::Compare { op, .. } => op.compare(value, operand),
+            FactTest::Range {
+                start,
+                end,
+                inclusive,
+                ..
+            } => match value.as_number() {
+                Some(value) if *inclusive => value >= *start && value <= *end,
+                Some(value) => value >= *start && value < *end,
+                None => false,
+            },
+        }
+    }
+
+    fn evaluate(&self, fact_dbs: &FactDataBaseSet) -> bool {
+        let value = fact_dbs.value(self.key()).unwrap_or(&MISSING_FACT);
+        let operand = match self {
+            FactTest::Compare {
+                operand: Operand::Value(operand),
+                ..
+            } => operand,
+            FactTest::Compare {
+                operand: Operand::Fact(other),
+                ..
+            } => fact_dbs.value(other).unwrap_or(&MISSING_FACT),
+            FactTest::Range { .. } => &MISSING_FACT,
+        };
+
+        self.check(value, operand)
+    }
+
+    // Only known for tests against a fixed value
+    fn passes_when_missing(&self) -> bool {
+        match self {
+            FactTest::Compare {
+                operand: Operand::Fact(_),
+                ..
+            } => true,
+            FactTest::Compare {
+                operand: Operand::Value(operand),
+                ..
+            } => self.check(&MISSING_FACT, operand),
+            FactTest::Range { .. } => self.check(&MISSING_FACT, &MISSING_FACT),
+        }
+    }
+}
+
+impl fmt::Display for FactTest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FactTest::Compare {
+                key,
+                op,
+                operand: Operand::Value(value),
+            } => write!(f, "{} {} {}", key, op, value),
+            FactTest::Compare {
+                key,
+                op,
+                operand: Operand::Fact(other),
+            } => write!(f, "{} {} ${}", key, op, other),
+            FactTest::Range {
+                key,
+                start,
+                end,
+                inclusive,
+            } => write!(
+                f,
+                "{} in {}..{}{}",
+                key,
+                start,
+                if *inclusive { "=" } else { "" },
+                end
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Criterion {
-    key: String,
-    fa: f32,
-    fb: f32,
+    // Matches when any of these do, a plain criterion only has one
+    any: Vec<FactTest>,
 }
 
 impl Criterion {
-    pub fn evaluate(&self, value: f32) -> bool {
-        value >= self.fa && value <= self.fb
+    fn evaluate(&self, fact_dbs: &FactDataBaseSet) -> bool {
+        self.any.iter().any(|test| test.evaluate(fact_dbs))
+    }
+
+    // The key when the criterion can only match with that fact set
+    fn required_key(&self) -> Option<&str> {
+        match self.any.as_slice() {
+            [test] if !test.passes_when_missing() => Some(test.key()),
+            _ => None,
+        }
     }
 }
 
 impl fmt::Display for Criterion {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} in [{}, {}]", self.key, self.fa, self.fb)
+        for (i, test) in self.any.iter().enumerate() {
+            if i > 0 {
+                write!(f, " || ")?;
+            }
+            write!(f, "{}", test)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -317,7 +618,7 @@ impl Visitor<'_> for CriterionVisitor {
     where
         E: de::Error,
     {
-        Ok(parse::parse_criterion(v))
+        parse::parse_criterion(v).map_err(E::custom)
     }
 }
 
@@ -345,13 +646,15 @@ impl Criteria {
     }
 
     fn evaluate(&self, fact_dbs: &FactDataBaseSet) -> bool {
-        for criterion in &self.criterion {
-            if !criterion.evaluate(fact_dbs.get(&criterion.key)) {
-                return false;
-            }
-        }
+        self.criterion
+            .iter()
+            .all(|criterion| criterion.evaluate(fact_dbs))
+    }
 
-        true
+    fn required_keys(&self) -> impl Iterator<Item = &str> {
+        self.criterion
+            .iter()
+            .filter_map(|criterion| criterion.required_key())
     }
 }
 
@@ -372,6 +675,18 @@ pub struct Rule {
     id: String,
     criteria: Criteria,
     response: Response,
+    // Seconds of the thinker's time before the rule can match again
+    cooldown: Option<f32>,
+    max_fires: Option<u32>,
+}
+
+// Remembered in the thinker's own fact db so it is saved with them
+pub fn rule_last_fired_key(rule_id: &str) -> String {
+    format!("RuleLastFired.{}", rule_id)
+}
+
+pub fn rule_times_fired_key(rule_id: &str) -> String {
+    format!("RuleTimesFired.{}", rule_id)
 }
 
 impl fmt::Display for Rule {
@@ -380,9 +695,86 @@ impl fmt::Display for Rule {
     }
 }
 
+#[derive(Debug, Default, Clone)]
+struct ConceptIndex {
+    // Rules that can only match when their most selective key is a known fact
+    by_key: HashMap<String, Vec<usize>>,
+    // Rules that may match with none of their facts set
+    always: Vec<usize>,
+}
+
 #[derive(Debug, Resource, Clone)]
 pub struct RuleSet {
+    // Most criteria first, indexes below point into this
     rules: Vec<Rule>,
+    index: HashMap<Concept, ConceptIndex>,
+}
+
+impl RuleSet {
+    pub fn new(mut rules: Vec<Rule>) -> Self {
+        rules.sort_by(|a, b| b.criteria.criterion.len().cmp(&a.criteria.criterion.len()));
+
+        // Keys used by fewer rules narrow a query down the most
+        let mut key_uses = HashMap::<&str, usize>::new();
+        for rule in &rules {
+            for key in rule.criteria.required_keys() {
+                *key_uses.entry(key).or_default() += 1;
+            }
+        }
+
+        let mut index = HashMap::<Concept, ConceptIndex>::new();
+        for (i, rule) in rules.iter().enumerate() {
+            let concept_index = index.entry(rule.criteria.concept).or_default();
+            match rule
+                .criteria
+                .required_keys()
+                .min_by_key(|key| key_uses[key])
+            {
+                Some(key) => concept_index
+                    .by_key
+                    .entry(key.to_string())
+                    .or_default()
+                    .push(i),
+                None => concept_index.always.push(i),
+            }
+        }
+
+        Self { rules, index }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    // Rules of the concept that could match, in rule order
+    fn candidates(&self, concept: Concept, fact_dbs: &FactDataBaseSet) -> Vec<usize> {
+        let Some(index) = self.index.get(&concept) else {
+            return Vec::new();
+        };
+
+        let mut candidates = index.always.clone();
+        if index.by_key.len() < fact_dbs.key_count() {
+            for (key, rules) in &index.by_key {
+                if fact_dbs.contains(key) {
+                    candidates.extend(rules);
+                }
+            }
+        } else {
+            for key in fact_dbs.keys() {
+                if let Some(rules) = index.by_key.get(key) {
+                    candidates.extend(rules);
+                }
+            }
+        }
+
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -391,14 +783,37 @@ struct FactDataBaseSet<'a> {
 }
 
 impl<'a> FactDataBaseSet<'a> {
+    // The first db with the fact wins
+    fn value(&self, key: &str) -> Option<&'a FactValue> {
+        self.fact_dbs
+            .iter()
+            .find_map(|fact_db| fact_db.facts.get(key))
+    }
+
     fn get(&self, key: &str) -> f32 {
-        for fact_db in &self.fact_dbs {
-            if let Some(value) = fact_db.facts.get(key) {
-                return *value;
-            }
-        }
+        self.value(key)
+            .and_then(|value| value.as_number())
+            .unwrap_or(0.)
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.fact_dbs
+            .iter()
+            .any(|fact_db| fact_db.facts.contains_key(key))
+    }
+
+    // May repeat keys set in more than one db
+    fn keys(&self) -> impl Iterator<Item = &String> {
+        self.fact_dbs
+            .iter()
+            .flat_map(|fact_db| fact_db.facts.keys())
+    }
 
-        0.
+    fn key_count(&self) -> usize {
+        self.fact_dbs
+            .iter()
+            .map(|fact_db| fact_db.facts.len())
+            .sum()
     }
 
     fn add(&mut self, fact_db: &'a FactDb) {
@@ -422,11 +837,24 @@ impl fmt::Display for FactDataBaseSet<'_> {
     }
 }
 
+// How one rule fared against a query, for working out why dialogue did or didn't fire
+#[derive(Debug)]
+pub struct RuleCheck<'r> {
+    pub rule_id: &'r str,
+    // Empty when every criterion passed
+    pub failed: Vec<&'r Criterion>,
+    // False while on cooldown or out of fires
+    pub ready: bool,
+    // One of the rules run would pick from
+    pub chosen: bool,
+}
+
 #[derive(Debug)]
 pub struct FactQuery<'a> {
     pub concept: Concept,
     fact_dbs: FactDataBaseSet<'a>,
     query_fact_db: FactDb,
+    now: Option<f32>,
 }
 
 impl<'a> FactQuery<'a> {
@@ -435,9 +863,16 @@ impl<'a> FactQuery<'a> {
             concept,
             query_fact_db: FactDb::default(),
             fact_dbs: FactDataBaseSet::default(),
+            now: None,
         }
     }
 
+    // The thinker's clock in seconds, enables cooldowns and remembering what fired
+    pub fn at_time(mut self, now: f32) -> Self {
+        self.now = Some(now);
+        self
+    }
+
     pub fn add_fact<T: ToString>(mut self, key: T, value: f32) -> Self {
         self.query_fact_db.add(key, value);
         self
@@ -448,23 +883,70 @@ impl<'a> FactQuery<'a> {
         self
     }
 
-    pub fn run(&self, rule_set: &RuleSet) -> Option<Response> {
+    // Picks between equally good matches with the given rng
+    pub fn run_with_rng<T: DelegatedRng>(
+        &self,
+        rule_set: &RuleSet,
+        rng: &mut T,
+    ) -> Option<Response> {
+        let matches = self.matches(rule_set);
+        if matches.is_empty() {
+            return None;
+        }
+
+        Some(self.respond(matches[rng.usize(0..matches.len())]))
+    }
+
+    // Entity actions recording the fire ride along with the response
+    fn respond(&self, rule: &Rule) -> Response {
+        let mut response = rule.response.clone();
+        if let Some(now) = self.now {
+            response.now.actions.push(Action::InsertEntityFact(
+                rule_last_fired_key(&rule.id),
+                now.into(),
+                None,
+            ));
+            response.now.actions.push(Action::IncrementEntityFact(
+                rule_times_fired_key(&rule.id),
+                1.0,
+            ));
+        }
+        response
+    }
+
+    fn ready(&self, rule: &Rule, fact_dbs: &FactDataBaseSet) -> bool {
+        if let Some(max_fires) = rule.max_fires {
+            if fact_dbs.get(&rule_times_fired_key(&rule.id)) >= max_fires as f32 {
+                return false;
+            }
+        }
+
+        if let (Some(cooldown), Some(now)) = (rule.cooldown, self.now) {
+            let last_fired = rule_last_fired_key(&rule.id);
+            if fact_dbs.contains(&last_fired) && now - fact_dbs.get(&last_fired) < cooldown {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn matches<'r>(&self, rule_set: &'r RuleSet) -> Vec<&'r Rule> {
         debug!(
             "Running fact query with {} dbs and concept {:?}",
             self.fact_dbs.len(),
             self.concept
         );
 
-        // This is maybe bad
-        let mut fact_dbs = self.fact_dbs.clone();
-        fact_dbs.add(&self.query_fact_db);
-
+        let fact_dbs = self.all_fact_dbs();
         debug!("Query fact_dbs loaded: {}", fact_dbs);
 
         let mut matches = Vec::new();
         let mut level = None;
 
-        for rule in &rule_set.rules {
+        for i in rule_set.candidates(self.concept, &fact_dbs) {
+            let rule = &rule_set.rules[i];
+
             // Found all possible matches for the current level
             if let Some(level) = level {
                 debug!("Checking level {} {}", level, rule.criteria.criterion.len());
@@ -473,33 +955,58 @@ impl<'a> FactQuery<'a> {
                 }
             }
 
-            if rule.criteria.concept != self.concept {
-                debug!("rule {} does not match concept", rule.id);
-                continue;
-            }
-
             // find all matching rules with the same criteria count
             debug!("Checking rule {}", rule);
             if rule.criteria.evaluate(&fact_dbs) {
+                // Resting rules leave the way open for less specific ones
+                if !self.ready(rule, &fact_dbs) {
+                    debug!("Rule {} matches but is resting", rule.id);
+                    continue;
+                }
+
                 debug!("Rule {} matches", rule.id);
                 if matches.is_empty() {
                     level = Some(rule.criteria.criterion.len());
                 }
-                matches.push(&rule.response);
+                matches.push(rule);
             }
         }
 
         if matches.is_empty() {
             debug!("No matches found");
-            return None;
         }
 
-        Some(
-            (*matches
-                .choose(&mut shared_deps::rand::thread_rng())
-                .unwrap())
-            .clone(),
-        )
+        matches
+    }
+
+    // Checks every rule for the concept rather than stopping at the best matches
+    pub fn check<'r>(&self, rule_set: &'r RuleSet) -> Vec<RuleCheck<'r>> {
+        let fact_dbs = self.all_fact_dbs();
+        let chosen = self.matches(rule_set);
+
+        rule_set
+            .rules
+            .iter()
+            .filter(|rule| rule.criteria.concept == self.concept)
+            .map(|rule| RuleCheck {
+                rule_id: &rule.id,
+                failed: rule
+                    .criteria
+                    .criterion
+                    .iter()
+                    .filter(|criterion| !criterion.evaluate(&fact_dbs))
+                    .collect(),
+                ready: self.ready(rule, &fact_dbs),
+                chosen: chosen.iter().any(|chosen| std::ptr::eq(*chosen, rule)),
+            })
+            .collect()
+    }
+
+    // This is maybe bad
+    fn all_fact_dbs(&self) -> FactDataBaseSet<'_> {
+        let mut fact_dbs = self.fact_dbs.clone();
+        fact_dbs.add(&self.query_fact_db);
+        fact_dbs
     }
 
     pub fn single_criteria(&self, criteria: &Criteria) -> bool {
@@ -518,6 +1025,7 @@ lazy_static! {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use shared_deps::bevy_turborand::RngComponent;
     use shared_deps::ron;
     use std::path::PathBuf;
 
@@ -537,6 +1045,8 @@ mod tests {
                     },
                     response: "Greet".to_string(),
                     apply_facts: vec![],
+                    cooldown: None,
+                    max_fires: None,
                 }),
                 parse::Entry::Response(parse::RawResponse {
                     id: "GreetRaining".to_string(),
@@ -551,6 +1061,8 @@ mod tests {
                     },
                     response: "GreetRaining".to_string(),
                     apply_facts: vec![],
+                    cooldown: None,
+                    max_fires: None,
                 }),
                 parse::Entry::Response(parse::RawResponse {
                     id: "LunchTime".to_string(),
@@ -565,6 +1077,8 @@ mod tests {
                     },
                     response: "LunchTime".to_string(),
                     apply_facts: vec![],
+                    cooldown: None,
+                    max_fires: None,
                 }),
                 parse::Entry::Response(parse::RawResponse {
                     id: "QueryFacts".to_string(),
@@ -583,6 +1097,8 @@ mod tests {
                     },
                     response: "QueryFacts".to_string(),
                     apply_facts: vec![],
+                    cooldown: None,
+                    max_fires: None,
                 }),
                 parse::Entry::Response(parse::RawResponse {
                     id: "InsertGlobalFact".to_string(),
@@ -600,6 +1116,8 @@ mod tests {
                     },
                     response: "InsertGlobalFact".to_string(),
                     apply_facts: vec![],
+                    cooldown: None,
+                    max_fires: None,
                 }),
                 parse::Entry::Response(parse::RawResponse {
                     id: "InsertEntityFact".to_string(),
@@ -617,10 +1135,12 @@ mod tests {
                     },
                     response: "InsertEntityFact".to_string(),
                     apply_facts: vec![],
+                    cooldown: None,
+                    max_fires: None,
                 })
             ],
         };
-        static ref EXAMPLE_RULE_SET: RuleSet = EXAMPLE_RAW_RULE_SET.clone().into();
+        static ref EXAMPLE_RULE_SET: RuleSet = EXAMPLE_RAW_RULE_SET.clone().try_into().unwrap();
     }
 
     #[test]
@@ -630,9 +1150,17 @@ mod tests {
 
         let data = std::fs::read_to_string(file_path).unwrap();
 
-        let raw_rule_set: parse::RawRuleSet = ron::from_str(&data).unwrap();
-
-        let rule_set: RuleSet = raw_rule_set.clone().into();
+        let rule_set = match validate::validate_dialogue(&data) {
+            Ok(rule_set) => rule_set,
+            Err(errors) => panic!(
+                "main.dialogue.ron has problems:\n{}",
+                errors
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+        };
 
         assert!(!rule_set.rules.is_empty());
     }
@@ -641,14 +1169,15 @@ mod tests {
     fn test_more_specific_response() {
         let fact_db = FactDb {
             facts: hashmap! {
-                "TimeOfDay".to_string() => 12.0,
-                "IsRaining".to_string() => 1.0,
+                "TimeOfDay".to_string() => FactValue::Number(12.0),
+                "IsRaining".to_string() => FactValue::Number(1.0),
             },
+            ..default()
         };
 
         let f_query = FactQuery::new(Concept::ThinkIdle).add_fact_db(&fact_db);
 
-        let response = f_query.run(&EXAMPLE_RULE_SET);
+        let response = f_query.run_with_rng(&EXAMPLE_RULE_SET, &mut test_rng());
 
         assert!(response.is_some(), "Eval failed to find a response");
         assert!(response.unwrap().now.get_text()[0].contains("raining"),);
@@ -658,13 +1187,14 @@ mod tests {
     fn test_response() {
         let fact_db = FactDb {
             facts: hashmap! {
-                "TimeOfDay".to_string() => 12.0,
+                "TimeOfDay".to_string() => FactValue::Number(12.0),
             },
+            ..default()
         };
 
         let fact_query = FactQuery::new(Concept::ThinkIdle).add_fact_db(&fact_db);
 
-        let response = fact_query.run(&EXAMPLE_RULE_SET);
+        let response = fact_query.run_with_rng(&EXAMPLE_RULE_SET, &mut test_rng());
 
         assert!(response.is_some(), "Eval failed to find a response");
     }
@@ -673,21 +1203,23 @@ mod tests {
     fn test_response_entity_fact() {
         let global_fact_db = FactDb {
             facts: hashmap! {
-                "TimeOfDay".to_string() => 14.0,
+                "TimeOfDay".to_string() => FactValue::Number(14.0),
             },
+            ..default()
         };
 
         let entity_fact_db = FactDb {
             facts: hashmap! {
-                "Hunger".to_string() => 0.6,
+                "Hunger".to_string() => FactValue::Number(0.6),
             },
+            ..default()
         };
 
         let fact_query = FactQuery::new(Concept::ThinkIdle)
             .add_fact_db(&global_fact_db)
             .add_fact_db(&entity_fact_db);
 
-        let response = fact_query.run(&EXAMPLE_RULE_SET);
+        let response = fact_query.run_with_rng(&EXAMPLE_RULE_SET, &mut test_rng());
 
         assert!(
             response.unwrap().now.get_text()[0].contains("lunch"),
@@ -699,14 +1231,16 @@ mod tests {
     fn test_response_query_fact() {
         let global_fact_db = FactDb {
             facts: hashmap! {
-                "TimeOfDay".to_string() => 14.0,
+                "TimeOfDay".to_string() => FactValue::Number(14.0),
             },
+            ..default()
         };
 
         let entity_fact_db = FactDb {
             facts: hashmap! {
-                "Hunger".to_string() => 0.6,
+                "Hunger".to_string() => FactValue::Number(0.6),
             },
+            ..default()
         };
 
         let fact_query = FactQuery::new(Concept::ThinkIdle)
@@ -714,7 +1248,7 @@ mod tests {
             .add_fact_db(&entity_fact_db)
             .add_fact("IsQueryFact", 1.0);
 
-        let response = fact_query.run(&EXAMPLE_RULE_SET);
+        let response = fact_query.run_with_rng(&EXAMPLE_RULE_SET, &mut test_rng());
 
         assert!(
             response.unwrap().now.get_text()[0].contains("query"),
@@ -739,41 +1273,323 @@ mod tests {
                     },
                     response: "OrRule".to_string(),
                     apply_facts: vec![],
+                    cooldown: None,
+                    max_fires: None,
                 }),
             ],
         };
 
-        let rule_set: RuleSet = raw_rule_set.clone().into();
+        let rule_set: RuleSet = raw_rule_set.clone().try_into().unwrap();
 
-        // Single rule should expand into four variations
-        assert_eq!(rule_set.rules.len(), 4);
+        // OR groups stay inside a single rule
+        assert_eq!(rule_set.rules.len(), 1);
+        assert_eq!(rule_set.rules[0].criteria.criterion.len(), 3);
 
         // A OR B, C OR D, E
-        // means
-        // A C, E
-        // A D, E
-        // B C, E
-        // B D, E
-        let expected_combos = vec![
-            "ACE".to_string(),
-            "ADE".to_string(),
-            "BCE".to_string(),
-            "BDE".to_string(),
+        let cases = [
+            (vec!["A", "C", "E"], true),
+            (vec!["A", "D", "E"], true),
+            (vec!["B", "C", "E"], true),
+            (vec!["B", "D", "E"], true),
+            (vec!["A", "B", "E"], false),
+            (vec!["A", "C"], false),
         ];
+        for (facts, expected) in cases {
+            let query = facts
+                .iter()
+                .fold(FactQuery::new(Concept::ThinkIdle), |query, fact| {
+                    query.add_fact(fact, 1.0)
+                });
+            assert_eq!(
+                query.run_with_rng(&rule_set, &mut test_rng()).is_some(),
+                expected,
+                "{:?}",
+                facts
+            );
+        }
+    }
 
-        let combos: Vec<_> = rule_set
-            .rules
-            .iter()
-            .map(|r| {
-                r.criteria
-                    .criterion
-                    .iter()
-                    .map(|c| c.key.clone())
-                    .collect::<String>()
-            })
-            .collect();
+    #[test]
+    fn test_criterion_operators() {
+        let mut fact_db = FactDb::default();
+        fact_db.add("Hunger", 0.5);
+        fact_db.add("Full", 0.5);
+        fact_db.add("Mood", 2.0);
+        fact_db.add_str("Species", "Was Guard");
+        let mut fact_dbs = FactDataBaseSet::default();
+        fact_dbs.add(&fact_db);
+
+        let cases = [
+            ("Hunger in 0.5..1", true),
+            ("Hunger in 0..0.5", false),
+            ("Hunger in 0..=0.5", true),
+            ("Hunger < 0.5", false),
+            ("Hunger <= 0.5", true),
+            ("Hunger > 0.5", false),
+            ("Hunger >= 0.5", true),
+            ("Hunger != 0.5", false),
+            ("Mood != 1", true),
+            ("Hunger = $Full", true),
+            ("Mood > Full", true),
+            ("Mood < $Full", false),
+            ("Hunger <= Missing", false),
+            ("Species = wasguard", true),
+            ("Species = WAS", false),
+            ("Species != wasguard", false),
+            ("Species != pet", true),
+            ("Species > 1", false),
+            ("Kind != object", true),
+        ];
+        for (criterion, expected) in cases {
+            let parsed = parse::parse_criterion(criterion).unwrap();
+            assert_eq!(parsed.evaluate(&fact_dbs), expected, "{}", criterion);
+        }
+
+        assert!(parse::parse_criterion("Hunger ~ 0.5").is_err());
+        assert!(parse::parse_criterion("Hunger in 0.5").is_err());
+        assert!(parse::parse_criterion("Hunger in a..b").is_err());
+    }
+
+    #[test]
+    fn test_criterion_required_key() {
+        let required = |criterion: &str| {
+            parse::parse_criterion(criterion)
+                .unwrap()
+                .required_key()
+                .map(|key| key.to_string())
+        };
+
+        assert_eq!(required("Species = wasguard"), Some("Species".to_string()));
+        assert_eq!(required("Mood in 1..2"), Some("Mood".to_string()));
+        assert_eq!(required("Mood in -1..2"), None);
+        assert_eq!(required("Mood != 1"), None);
+        assert_eq!(required("Mood <= 1"), None);
+        assert_eq!(required("Mood > $Other"), None);
+    }
+
+    #[test]
+    fn test_fact_db_reads_number_and_text() {
+        let fact_db: FactDb =
+            ron::from_str(r#"(facts: {"Mood": 1.5, "Species": "wasguard"})"#).unwrap();
+
+        assert_eq!(fact_db.get("Mood"), 1.5);
+        assert_eq!(fact_db.get("Species"), 0.);
+        assert_eq!(fact_db.get_str("Species"), Some("wasguard"));
+
+        let round_trip: FactDb = ron::from_str(&ron::to_string(&fact_db).unwrap()).unwrap();
+        assert_eq!(round_trip, fact_db);
+    }
+
+    const MEMORY_RULE_SET: &str = r#"RawRuleSet(entries: [
+        Response(RawResponse(id: "Complain", now: ["RandomText complain"])),
+        Rule(RawRule(
+            id: "Complain",
+            criteria: Criteria(concept: ThinkIdle, facts: ["PoopCount > 1", "Grumpy"]),
+            response: "Complain",
+            cooldown: Some(60),
+        )),
+        Response(RawResponse(id: "Sigh", now: ["RandomText sigh"])),
+        Rule(RawRule(
+            id: "Sigh",
+            criteria: Criteria(concept: ThinkIdle, facts: ["PoopCount > 1"]),
+            response: "Sigh",
+            max_fires: Some(2),
+        )),
+    ])"#;
+
+    // What apply_pending_action would do for the thinker
+    fn remember(fact_db: &mut FactDb, response: &Response) {
+        for action in &response.now.actions {
+            match action {
+                Action::InsertEntityFact(key, value, _) => fact_db.set(key, value.clone()),
+                Action::IncrementEntityFact(key, amount) => fact_db.increment(key, *amount),
+                _ => {}
+            }
+        }
+    }
+
+    fn think(rule_set: &RuleSet, fact_db: &mut FactDb, now: f32) -> Option<String> {
+        let response = FactQuery::new(Concept::ThinkIdle)
+            .add_fact_db(fact_db)
+            .at_time(now)
+            .run_with_rng(rule_set, &mut test_rng())?;
+        remember(fact_db, &response);
+        response.now.get_text().first().cloned()
+    }
+
+    #[test]
+    fn test_rule_cooldown_and_max_fires() {
+        let raw_rule_set: parse::RawRuleSet = ron::from_str(MEMORY_RULE_SET).unwrap();
+        let rule_set: RuleSet = raw_rule_set.try_into().unwrap();
+
+        let mut fact_db = FactDb::default();
+        fact_db.add("PoopCount", 3.0);
+        fact_db.add("Grumpy", 1.0);
+
+        assert_eq!(
+            think(&rule_set, &mut fact_db, 0.),
+            Some("complain".to_string())
+        );
+        assert_eq!(fact_db.get(&rule_last_fired_key("Complain")), 0.);
+        assert_eq!(fact_db.get(&rule_times_fired_key("Complain")), 1.);
+
+        // Complain rests, so the less specific rule gets a turn until it runs out
+        assert_eq!(
+            think(&rule_set, &mut fact_db, 10.),
+            Some("sigh".to_string())
+        );
+        assert_eq!(
+            think(&rule_set, &mut fact_db, 20.),
+            Some("sigh".to_string())
+        );
+        assert_eq!(think(&rule_set, &mut fact_db, 30.), None);
+
+        assert_eq!(
+            think(&rule_set, &mut fact_db, 60.),
+            Some("complain".to_string())
+        );
+        assert_eq!(fact_db.get(&rule_times_fired_key("Complain")), 2.);
+
+        // Without a time cooldowns are not tracked
+        let response = FactQuery::new(Concept::ThinkIdle)
+            .add_fact_db(&fact_db)
+            .run_with_rng(&rule_set, &mut test_rng())
+            .unwrap();
+        assert_eq!(response.now.actions.len(), 1);
+    }
+
+    #[test]
+    fn test_check_rules() {
+        let raw_rule_set: parse::RawRuleSet = ron::from_str(MEMORY_RULE_SET).unwrap();
+        let rule_set: RuleSet = raw_rule_set.try_into().unwrap();
+
+        let mut fact_db = FactDb::default();
+        fact_db.add("PoopCount", 2.);
+        fact_db.add(rule_times_fired_key("Sigh"), 2.);
+
+        let query = FactQuery::new(Concept::ThinkIdle).add_fact_db(&fact_db);
+        let checks = query.check(&rule_set);
+        assert_eq!(checks.len(), 2);
+
+        let complain = checks.iter().find(|c| c.rule_id == "Complain").unwrap();
+        assert_eq!(
+            complain
+                .failed
+                .iter()
+                .map(|criterion| criterion.to_string())
+                .collect::<Vec<_>>(),
+            vec!["Grumpy = 1"]
+        );
+        assert!(complain.ready);
+        assert!(!complain.chosen);
+
+        // Out of fires, so nothing gets picked even though it matches
+        let sigh = checks.iter().find(|c| c.rule_id == "Sigh").unwrap();
+        assert!(sigh.failed.is_empty());
+        assert!(!sigh.ready);
+        assert!(!sigh.chosen);
+
+        fact_db.add("Grumpy", 1.);
+        let query = FactQuery::new(Concept::ThinkIdle).add_fact_db(&fact_db);
+        let checks = query.check(&rule_set);
+        let complain = checks.iter().find(|c| c.rule_id == "Complain").unwrap();
+        assert!(complain.failed.is_empty());
+        assert!(complain.chosen);
+    }
+
+    #[test]
+    fn test_negative_cooldown_is_invalid() {
+        let source = MEMORY_RULE_SET.replace("cooldown: Some(60)", "cooldown: Some(-1)");
+        let errors = validate::validate_dialogue(&source).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].problem,
+            validate::DialogueProblem::InvalidCooldown
+        );
+        assert_eq!(errors[0].line, Some(7));
+    }
+
+    #[test]
+    fn test_indexed_matches_linear_scan() {
+        // Small deterministic generator so failures can be replayed
+        let mut seed = 7_u64;
+        let mut next = move |max: usize| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((seed >> 33) as usize) % max
+        };
+
+        let keys = ["A", "B", "C", "D", "E", "F", "G", "H"];
+        let forms = ["{}", "{} !", "{} > 0.5", "{} < 0.5", "{} = 2", "{} || {}"];
+        let concepts = [Concept::ThinkIdle, Concept::ThinkJustAte];
+
+        let mut entries = vec![parse::Entry::Response(parse::RawResponse {
+            id: "Response".to_string(),
+            now: vec!["RandomText response".to_string()],
+            after: vec![],
+        })];
+        for i in 0..300 {
+            let facts = (0..1 + next(4))
+                .map(|_| {
+                    forms[next(forms.len())]
+                        .replacen("{}", keys[next(keys.len())], 1)
+                        .replacen("{}", keys[next(keys.len())], 1)
+                })
+                .collect();
+            entries.push(parse::Entry::Rule(parse::RawRule {
+                id: format!("Rule{}", i),
+                criteria: parse::Criteria {
+                    concept: concepts[next(concepts.len())],
+                    facts,
+                },
+                response: "Response".to_string(),
+                apply_facts: vec![],
+                cooldown: None,
+                max_fires: None,
+            }));
+        }
+        let rule_set: RuleSet = parse::RawRuleSet { entries }.try_into().unwrap();
+
+        for _ in 0..200 {
+            let mut fact_db = FactDb::default();
+            for key in keys {
+                match next(4) {
+                    0 => {}
+                    1 => fact_db.add(key, 1.0),
+                    2 => fact_db.add(key, 2.0),
+                    _ => fact_db.add(key, 0.),
+                }
+            }
+            let concept = concepts[next(concepts.len())];
+            let query = FactQuery::new(concept).add_fact_db(&fact_db);
+
+            let mut fact_dbs = FactDataBaseSet::default();
+            fact_dbs.add(&fact_db);
+            let mut expected = Vec::new();
+            let mut level = None;
+            for rule in &rule_set.rules {
+                if level.is_some_and(|level| rule.criteria.criterion.len() < level) {
+                    break;
+                }
+                if rule.criteria.concept == concept && rule.criteria.evaluate(&fact_dbs) {
+                    level = Some(rule.criteria.criterion.len());
+                    expected.push(rule.id.clone());
+                }
+            }
 
-        assert_eq!(combos, expected_combos);
+            let found = rule_set
+                .candidates(concept, &fact_dbs)
+                .into_iter()
+                .map(|i| &rule_set.rules[i])
+                .filter(|rule| rule.criteria.evaluate(&fact_dbs))
+                .take_while(|rule| rule.criteria.criterion.len() >= level.unwrap_or(usize::MAX))
+                .map(|rule| rule.id.clone())
+                .collect::<Vec<_>>();
+
+            assert_eq!(found, expected, "{}", fact_db);
+            assert_eq!(query.matches(&rule_set).len(), expected.len());
+        }
     }
 
     #[test]
@@ -791,7 +1607,7 @@ mod tests {
                 .add_fact_db(&global_fact_db.0)
                 .add_fact("DoGlobalFact", 1.0);
 
-            let response = query.run(&rule_set);
+            let response = query.run_with_rng(&rule_set, &mut test_rng());
 
             match local.0 {
                 0 => {
@@ -820,15 +1636,15 @@ mod tests {
 
         let mut app = App::new();
 
-        let rule_set: RuleSet = EXAMPLE_RAW_RULE_SET.clone().into();
+        let rule_set: RuleSet = EXAMPLE_RAW_RULE_SET.clone().try_into().unwrap();
 
         app.add_event::<ActionEvent>();
-        app.add_event::<FactInsert>();
+        app.add_event::<FactChange>();
         app.insert_resource(rule_set);
         app.insert_resource(GlobalFactDatabase::default());
         app.add_systems(
             Update,
-            (test_query, read_fact_inserts, apply_pending_action).chain(),
+            (test_query, read_fact_changes, apply_pending_action).chain(),
         );
 
         app.update();
@@ -836,6 +1652,128 @@ mod tests {
         app.update();
     }
 
+    #[test]
+    fn test_parse_fact_actions() {
+        let action_set = parse::raw_action_to_action_set(&[
+            "AddGlobalFact Seen 5".to_string(),
+            "SetGlobalFact Weather rain 10".to_string(),
+            "SetEntityFact Mood 0.5".to_string(),
+            "IncEntityFact TimesComplainedAboutPoop".to_string(),
+            "IncGlobalFact PoopCount 3".to_string(),
+            "DecEntityFact Patience 2".to_string(),
+            "RemoveGlobalFact Seen".to_string(),
+            "RemoveEntityFact Mood".to_string(),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            action_set.actions,
+            vec![
+                Action::InsertGlobalFact(
+                    "Seen".to_string(),
+                    1.0.into(),
+                    Some(Duration::from_secs(5))
+                ),
+                Action::InsertGlobalFact(
+                    "Weather".to_string(),
+                    FactValue::text("rain"),
+                    Some(Duration::from_secs(10))
+                ),
+                Action::InsertEntityFact("Mood".to_string(), 0.5.into(), None),
+                Action::IncrementEntityFact("TimesComplainedAboutPoop".to_string(), 1.0),
+                Action::IncrementGlobalFact("PoopCount".to_string(), 3.0),
+                Action::IncrementEntityFact("Patience".to_string(), -2.0),
+                Action::RemoveGlobalFact("Seen".to_string()),
+                Action::RemoveEntityFact("Mood".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_fact_actions() {
+        let errors = parse::raw_action_to_action_set(&[
+            "SetGlobalFact Weather".to_string(),
+            "IncEntityFact Count many".to_string(),
+            "AddGlobalFact Seen -5".to_string(),
+            "ShoutText hello".to_string(),
+        ])
+        .unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                parse::ParseError::InvalidAction {
+                    action: "SetGlobalFact Weather".to_string(),
+                    reason: "missing arguments",
+                },
+                parse::ParseError::InvalidAction {
+                    action: "IncEntityFact Count many".to_string(),
+                    reason: "invalid amount",
+                },
+                parse::ParseError::InvalidAction {
+                    action: "AddGlobalFact Seen -5".to_string(),
+                    reason: "invalid expire time",
+                },
+                parse::ParseError::UnknownAction("ShoutText hello".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_response_update_facts() {
+        #[derive(Component)]
+        struct TestTag;
+
+        let mut app = App::new();
+
+        app.add_event::<ActionEvent>();
+        app.add_event::<FactChange>();
+        app.insert_resource(GlobalFactDatabase(FactDb {
+            facts: hashmap! {
+                "PoopCount".to_string() => FactValue::Number(2.0),
+                "Seen".to_string() => FactValue::Number(1.0),
+            },
+            ..default()
+        }));
+        app.add_systems(Update, (apply_pending_action, read_fact_changes).chain());
+
+        let entity = app
+            .world_mut()
+            .spawn((
+                TestTag,
+                EntityFactDatabase(FactDb {
+                    facts: hashmap! { "Mood".to_string() => FactValue::Number(1.0) },
+                    ..default()
+                }),
+            ))
+            .id();
+
+        let action_set = parse::raw_action_to_action_set(&[
+            "SetGlobalFact Weather rain".to_string(),
+            "IncGlobalFact PoopCount 3".to_string(),
+            "RemoveGlobalFact Seen".to_string(),
+            "IncEntityFact TimesComplainedAboutPoop".to_string(),
+            "IncEntityFact TimesComplainedAboutPoop".to_string(),
+            "DecEntityFact Patience 2".to_string(),
+            "RemoveEntityFact Mood".to_string(),
+        ])
+        .unwrap();
+        app.world_mut()
+            .send_event(ActionEvent::new(action_set).with_entity(entity));
+
+        app.update();
+
+        let global = &app.world().resource::<GlobalFactDatabase>().0;
+        assert_eq!(global.get_str("Weather"), Some("rain"));
+        assert_eq!(global.get("PoopCount"), 5.0);
+        assert!(!global.facts.contains_key("Seen"));
+
+        let local = &app.world().get::<EntityFactDatabase>(entity).unwrap().0;
+        assert_eq!(local.get("TimesComplainedAboutPoop"), 2.0);
+        assert_eq!(local.get("Patience"), -2.0);
+        assert!(!local.facts.contains_key("Mood"));
+    }
+
     #[test]
     fn test_response_insert_entity_fact() {
         #[derive(Debug, Default)]
@@ -856,7 +1794,7 @@ mod tests {
                 .add_fact_db(&fact_db.0)
                 .add_fact("DoEntityFact", 1.0);
 
-            let response = query.run(&rule_set);
+            let response = query.run_with_rng(&rule_set, &mut test_rng());
 
             match local.0 {
                 0 => {
@@ -885,15 +1823,15 @@ mod tests {
 
         let mut app = App::new();
 
-        let rule_set: RuleSet = EXAMPLE_RAW_RULE_SET.clone().into();
+        let rule_set: RuleSet = EXAMPLE_RAW_RULE_SET.clone().try_into().unwrap();
 
         app.add_event::<ActionEvent>();
-        app.add_event::<FactInsert>();
+        app.add_event::<FactChange>();
         app.insert_resource(rule_set);
         app.insert_resource(GlobalFactDatabase::default());
         app.add_systems(
             Update,
-            (test_query, read_fact_inserts, apply_pending_action).chain(),
+            (test_query, read_fact_changes, apply_pending_action).chain(),
         );
 
         app.world_mut()
@@ -904,6 +1842,179 @@ mod tests {
         app.update();
     }
 
+    fn test_rng() -> RngComponent {
+        RngComponent::with_seed(0)
+    }
+
+    fn expire_app(plugin: FactsPlugin) -> App {
+        let mut app = App::new();
+        app.add_plugins(plugin);
+        app.insert_resource(Time::<()>::default());
+        app
+    }
+
+    fn send_actions(app: &mut App, actions: &[&str], entity: Option<Entity>) {
+        let actions = actions.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let mut event = ActionEvent::new(parse::raw_action_to_action_set(&actions).unwrap());
+        event.entity = entity;
+        app.world_mut().send_event(event);
+    }
+
+    fn advance(app: &mut App, secs: u64) {
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(secs));
+        app.update();
+    }
+
+    fn global_fact(app: &App, key: &str) -> Option<f32> {
+        app.world()
+            .resource::<GlobalFactDatabase>()
+            .0
+            .get_value(key)
+            .and_then(|value| value.as_number())
+    }
+
+    #[test]
+    fn test_global_fact_expires() {
+        let mut app = expire_app(FactsPlugin::default());
+
+        send_actions(&mut app, &["AddGlobalFact Greeted 5"], None);
+        app.update();
+        assert_eq!(global_fact(&app, "Greeted"), Some(1.0));
+
+        advance(&mut app, 3);
+        assert_eq!(global_fact(&app, "Greeted"), Some(1.0));
+
+        advance(&mut app, 3);
+        assert_eq!(global_fact(&app, "Greeted"), None);
+    }
+
+    #[test]
+    fn test_entity_fact_expires() {
+        let mut app = expire_app(FactsPlugin::default());
+        let entity = app.world_mut().spawn(EntityFactDatabase::default()).id();
+        let other = app.world_mut().spawn(EntityFactDatabase::default()).id();
+
+        send_actions(&mut app, &["SetEntityFact Mood happy 5"], Some(entity));
+        send_actions(&mut app, &["SetEntityFact Mood sad"], Some(other));
+        app.update();
+
+        let mood = |app: &App, entity: Entity| {
+            app.world()
+                .get::<EntityFactDatabase>(entity)
+                .unwrap()
+                .0
+                .get_value("Mood")
+                .cloned()
+        };
+        assert_eq!(mood(&app, entity), Some(FactValue::text("happy")));
+
+        advance(&mut app, 6);
+        assert_eq!(mood(&app, entity), None);
+        assert_eq!(mood(&app, other), Some(FactValue::text("sad")));
+    }
+
+    #[test]
+    fn test_setting_fact_again_restarts_expiry() {
+        let mut app = expire_app(FactsPlugin::default());
+
+        send_actions(&mut app, &["AddGlobalFact Greeted 5"], None);
+        app.update();
+        advance(&mut app, 3);
+
+        send_actions(&mut app, &["AddGlobalFact Greeted 5"], None);
+        advance(&mut app, 3);
+        assert_eq!(global_fact(&app, "Greeted"), Some(1.0));
+
+        advance(&mut app, 3);
+        assert_eq!(global_fact(&app, "Greeted"), None);
+    }
+
+    #[test]
+    fn test_facts_expire_in_expire_schedule() {
+        #[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+        struct TestSimulationUpdate;
+
+        let mut app = expire_app(FactsPlugin::new(TestSimulationUpdate));
+
+        send_actions(&mut app, &["AddGlobalFact Greeted 5"], None);
+        app.update();
+
+        // Update alone never ticks the expiry
+        advance(&mut app, 10);
+        assert_eq!(global_fact(&app, "Greeted"), Some(1.0));
+
+        app.world_mut().run_schedule(TestSimulationUpdate);
+        assert_eq!(global_fact(&app, "Greeted"), None);
+    }
+
+    #[test]
+    fn test_fact_set_and_expires_in_same_frame() {
+        #[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+        struct TestSimulationUpdate;
+
+        let mut app = expire_app(FactsPlugin::new(TestSimulationUpdate));
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(5));
+
+        // Applied first then ticked by the whole frame
+        send_actions(
+            &mut app,
+            &["AddGlobalFact Greeted 5", "AddGlobalFact Waved 10"],
+            None,
+        );
+        app.world_mut().run_schedule(TestSimulationUpdate);
+        assert_eq!(global_fact(&app, "Greeted"), None);
+        assert_eq!(global_fact(&app, "Waved"), Some(1.0));
+
+        // Already taken so Update doesn't apply them again
+        send_actions(&mut app, &["IncGlobalFact Count 1"], None);
+        app.world_mut().run_schedule(TestSimulationUpdate);
+        app.update();
+        assert_eq!(global_fact(&app, "Count"), Some(1.0));
+        assert_eq!(global_fact(&app, "Greeted"), None);
+    }
+
+    #[test]
+    fn test_expiry_survives_save_and_load() {
+        let mut app = expire_app(FactsPlugin::default());
+        let entity = app.world_mut().spawn(EntityFactDatabase::default()).id();
+
+        send_actions(&mut app, &["SetEntityFact Mood happy 5"], Some(entity));
+        app.update();
+        advance(&mut app, 3);
+
+        let saved = ron::to_string(app.world().get::<EntityFactDatabase>(entity).unwrap()).unwrap();
+        let loaded: EntityFactDatabase = ron::from_str(&saved).unwrap();
+        assert_eq!(loaded.0.expires_in("Mood"), Some(Duration::from_secs(2)));
+
+        let mut app = expire_app(FactsPlugin::default());
+        let entity = app.world_mut().spawn(loaded).id();
+        let mood = |app: &App| {
+            app.world()
+                .get::<EntityFactDatabase>(entity)
+                .unwrap()
+                .0
+                .get_value("Mood")
+                .cloned()
+        };
+
+        advance(&mut app, 1);
+        assert_eq!(mood(&app), Some(FactValue::text("happy")));
+
+        advance(&mut app, 1);
+        assert_eq!(mood(&app), None);
+    }
+
+    #[test]
+    fn test_facts_without_expiry_load() {
+        let loaded: FactDb = ron::from_str(r#"(facts: {"Mood": "happy"})"#).unwrap();
+        assert_eq!(loaded.get_str("Mood"), Some("happy"));
+        assert_eq!(loaded.expires_in("Mood"), None);
+    }
+
     #[test]
     fn test_rules_sorted_by_criterion_count() {
         let raw_rule_set = parse::RawRuleSet {
@@ -921,6 +2032,8 @@ mod tests {
                     },
                     response: "Response".to_string(),
                     apply_facts: vec![],
+                    cooldown: None,
+                    max_fires: None,
                 }),
                 parse::Entry::Rule(parse::RawRule {
                     id: "Single".to_string(),
@@ -930,6 +2043,8 @@ mod tests {
                     },
                     response: "Response".to_string(),
                     apply_facts: vec![],
+                    cooldown: None,
+                    max_fires: None,
                 }),
                 parse::Entry::Rule(parse::RawRule {
                     id: "Triple".to_string(),
@@ -939,11 +2054,13 @@ mod tests {
                     },
                     response: "Response".to_string(),
                     apply_facts: vec![],
+                    cooldown: None,
+                    max_fires: None,
                 }),
             ],
         };
 
-        let rule_set: RuleSet = raw_rule_set.clone().into();
+        let rule_set: RuleSet = raw_rule_set.clone().try_into().unwrap();
 
         assert_eq!(rule_set.rules[0].id, "Triple");
         assert_eq!(rule_set.rules[1].id, "Double");
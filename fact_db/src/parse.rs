@@ -1,9 +1,14 @@
-use std::time::Duration;
+use core::fmt;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::fact_str_hash;
+use crate::validate::{DialogueError, DialogueId, DialogueProblem};
+use crate::{CompareOp, FactTest, FactValue, Operand};
 
 use super::Concept;
 
@@ -31,6 +36,11 @@ pub(super) struct RawRule {
     pub(super) response: String,
     #[serde(default)]
     pub(super) apply_facts: Vec<ApplyFact>,
+    // Seconds before the same thinker can use the rule again
+    #[serde(default)]
+    pub(super) cooldown: Option<f32>,
+    #[serde(default)]
+    pub(super) max_fires: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -45,229 +55,439 @@ pub struct RawRuleSet {
 }
 
 impl RawRuleSet {
-    fn get_response(&self, id: &str) -> &RawResponse {
-        match self
-            .entries
+    fn responses(&self) -> impl Iterator<Item = (usize, &RawResponse)> {
+        self.entries
             .iter()
-            .filter_map(|e| match e {
-                Entry::Response(r) => Some(r),
+            .enumerate()
+            .filter_map(|(i, e)| match e {
+                Entry::Response(r) => Some((i, r)),
                 Entry::Rule(_) => None,
             })
-            .find(|r| r.id == id)
-        {
-            Some(response) => response,
-            None => panic!("Response {} not found", id),
-        }
     }
 
-    fn get_rules(&self) -> Vec<&RawRule> {
+    fn rules(&self) -> impl Iterator<Item = (usize, &RawRule)> {
         self.entries
             .iter()
-            .filter_map(|e| match e {
-                Entry::Rule(r) => Some(r),
+            .enumerate()
+            .filter_map(|(i, e)| match e {
+                Entry::Rule(r) => Some((i, r)),
                 Entry::Response(_) => None,
             })
-            .collect()
     }
-}
 
-fn raw_action_to_action_set(actions: &[String]) -> super::ActionSet {
-    let mut result = Vec::new();
+    // Every rule that parses is kept, the problems with the rest are returned alongside
+    pub fn build(&self) -> (super::RuleSet, Vec<DialogueError>) {
+        let mut rules = Vec::new();
+        let mut errors = Vec::new();
+
+        let mut responses = HashMap::new();
+        for (entry, raw_response) in self.responses() {
+            let id = DialogueId::Response(raw_response.id.clone());
+            if responses.contains_key(&raw_response.id) {
+                errors.push(DialogueError::new(
+                    entry,
+                    id,
+                    DialogueProblem::DuplicateResponse,
+                ));
+                continue;
+            }
 
-    for action in actions {
-        let splits = action.split(" ").collect::<Vec<_>>();
+            let response = match (
+                raw_action_to_action_set(&raw_response.now),
+                raw_action_to_action_set(&raw_response.after),
+            ) {
+                (Ok(now), Ok(after)) => Some(super::Response { now, after }),
+                (now, after) => {
+                    for error in now.err().into_iter().chain(after.err()).flatten() {
+                        errors.push(DialogueError::new(
+                            entry,
+                            id.clone(),
+                            DialogueProblem::Parse(error),
+                        ));
+                    }
+                    None
+                }
+            };
+            responses.insert(raw_response.id.clone(), (entry, response));
+        }
 
-        match splits[0] {
-            "RandomText" => {
-                let strings: Vec<_> = splits[1].split(",").map(|s| s.to_owned()).collect();
-                result.push(super::Action::RandomText(strings));
+        let mut used_responses = HashSet::new();
+        let mut rule_ids = HashSet::new();
+        for (entry, rule) in self.rules() {
+            let id = DialogueId::Rule(rule.id.clone());
+            if !rule_ids.insert(&rule.id) {
+                errors.push(DialogueError::new(
+                    entry,
+                    id,
+                    DialogueProblem::DuplicateRule,
+                ));
+                continue;
             }
-            "AddGlobalFact" => {
-                let key = splits[1].to_string();
-                let expire = if splits.len() > 2 {
-                    Some(Duration::from_secs_f32(splits[2].parse::<f32>().unwrap()))
-                } else {
+
+            let criteria = match parse_criteria(&rule.criteria) {
+                Ok(criteria) => Some(criteria),
+                Err(criteria_errors) => {
+                    for error in criteria_errors {
+                        errors.push(DialogueError::new(
+                            entry,
+                            id.clone(),
+                            DialogueProblem::Parse(error),
+                        ));
+                    }
                     None
-                };
-                result.push(super::Action::InsertGlobalFact(key, 1.0, expire));
-            }
-            "AddEntityFact" => {
-                let key = splits[1].to_string();
-                let expire = if splits.len() > 2 {
-                    Some(Duration::from_secs_f32(splits[2].parse::<f32>().unwrap()))
-                } else {
+                }
+            };
+
+            let response = match responses.get(&rule.response) {
+                Some((_, response)) => {
+                    used_responses.insert(&rule.response);
+                    response.as_ref()
+                }
+                None => {
+                    errors.push(DialogueError::new(
+                        entry,
+                        id.clone(),
+                        DialogueProblem::MissingResponse(rule.response.clone()),
+                    ));
                     None
-                };
-                result.push(super::Action::InsertEntityFact(key, 1.0, expire));
+                }
+            };
+
+            let cooldown_valid = rule
+                .cooldown
+                .is_none_or(|cooldown| cooldown.is_finite() && cooldown >= 0.);
+            if !cooldown_valid {
+                errors.push(DialogueError::new(
+                    entry,
+                    id.clone(),
+                    DialogueProblem::InvalidCooldown,
+                ));
+            }
+
+            if let (Some(criteria), Some(response), true) = (criteria, response, cooldown_valid) {
+                rules.push(super::Rule {
+                    id: rule.id.clone(),
+                    criteria,
+                    response: response.clone(),
+                    cooldown: rule.cooldown,
+                    max_fires: rule.max_fires,
+                });
             }
-            _ => panic!("Invalid action: {}", action),
         }
+
+        for (id, (entry, _)) in &responses {
+            if !used_responses.contains(id) {
+                errors.push(DialogueError::new(
+                    *entry,
+                    DialogueId::Response(id.clone()),
+                    DialogueProblem::UnusedResponse,
+                ));
+            }
+        }
+        errors.sort_by_key(|error| error.entry);
+
+        (super::RuleSet::new(rules), errors)
     }
+}
+
+impl TryFrom<RawRuleSet> for super::RuleSet {
+    type Error = Vec<DialogueError>;
 
-    super::ActionSet::new(result)
+    fn try_from(val: RawRuleSet) -> Result<super::RuleSet, Self::Error> {
+        let (rule_set, errors) = val.build();
+        if errors.is_empty() {
+            Ok(rule_set)
+        } else {
+            Err(errors)
+        }
+    }
 }
 
-impl From<RawRuleSet> for super::RuleSet {
-    fn from(val: RawRuleSet) -> super::RuleSet {
-        let mut result = super::RuleSet { rules: Vec::new() };
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    InvalidCriterion(String),
+    UnknownOperator {
+        operator: String,
+        criterion: String,
+    },
+    UnknownAction(String),
+    InvalidAction {
+        action: String,
+        reason: &'static str,
+    },
+}
 
-        let rules = val.get_rules();
+impl ParseError {
+    // The criterion or action as written in the dialogue file
+    pub fn text(&self) -> &str {
+        match self {
+            ParseError::InvalidCriterion(criterion) => criterion,
+            ParseError::UnknownOperator { criterion, .. } => criterion,
+            ParseError::UnknownAction(action) => action,
+            ParseError::InvalidAction { action, .. } => action,
+        }
+    }
+}
 
-        for rule in rules {
-            let possible_criteria = parse_criteria(&rule.criteria);
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidCriterion(criterion) => {
+                write!(f, "invalid criterion `{}`", criterion)
+            }
+            ParseError::UnknownOperator {
+                operator,
+                criterion,
+            } => write!(f, "unknown operator `{}` in `{}`", operator, criterion),
+            ParseError::UnknownAction(action) => write!(f, "unknown action `{}`", action),
+            ParseError::InvalidAction { action, reason } => {
+                write!(f, "{} in action `{}`", reason, action)
+            }
+        }
+    }
+}
 
-            for criteria in possible_criteria.into_iter() {
-                let raw_response = val.get_response(&rule.response);
+fn invalid_action(action: &str, reason: &'static str) -> ParseError {
+    ParseError::InvalidAction {
+        action: action.to_string(),
+        reason,
+    }
+}
 
-                let response = super::Response {
-                    now: raw_action_to_action_set(&raw_response.now),
-                    after: raw_action_to_action_set(&raw_response.after),
-                };
+fn parse_expire(action: &str, secs: Option<&&str>) -> Result<Option<Duration>, ParseError> {
+    secs.map(|secs| {
+        secs.parse::<f32>()
+            .ok()
+            .and_then(|secs| Duration::try_from_secs_f32(secs).ok())
+            .ok_or_else(|| invalid_action(action, "invalid expire time"))
+    })
+    .transpose()
+}
 
-                result.rules.push(super::Rule {
-                    id: rule.id.clone(),
-                    criteria,
-                    response,
-                });
-            }
+fn parse_amount(action: &str, amount: Option<&&str>) -> Result<f32, ParseError> {
+    match amount {
+        Some(amount) => amount
+            .parse::<f32>()
+            .map_err(|_| invalid_action(action, "invalid amount")),
+        None => Ok(1.0),
+    }
+}
+
+// Same rules as criteria, anything that is not a number is a string fact
+pub fn parse_fact_value(value: &str) -> FactValue {
+    value
+        .parse::<f32>()
+        .map(FactValue::Number)
+        .unwrap_or_else(|_| FactValue::text(value))
+}
+
+fn raw_action_to_action(action: &str) -> Result<super::Action, ParseError> {
+    let splits = action.split_whitespace().collect::<Vec<_>>();
+    let Some((name, args)) = splits.split_first() else {
+        return Err(ParseError::UnknownAction(action.to_string()));
+    };
+
+    let (min_args, max_args) = match *name {
+        "RandomText" | "RemoveGlobalFact" | "RemoveEntityFact" => (1, 1),
+        "AddGlobalFact" | "AddEntityFact" | "IncGlobalFact" | "IncEntityFact" | "DecGlobalFact"
+        | "DecEntityFact" => (1, 2),
+        "SetGlobalFact" | "SetEntityFact" => (2, 3),
+        _ => return Err(ParseError::UnknownAction(action.to_string())),
+    };
+    if args.len() < min_args {
+        return Err(invalid_action(action, "missing arguments"));
+    }
+    if args.len() > max_args {
+        return Err(invalid_action(action, "too many arguments"));
+    }
+
+    let key = args[0].to_string();
+    let parsed = match *name {
+        "RandomText" => {
+            super::Action::RandomText(args[0].split(",").map(|s| s.to_owned()).collect())
+        }
+        "AddGlobalFact" => {
+            super::Action::InsertGlobalFact(key, 1.0.into(), parse_expire(action, args.get(1))?)
+        }
+        "AddEntityFact" => {
+            super::Action::InsertEntityFact(key, 1.0.into(), parse_expire(action, args.get(1))?)
+        }
+        "SetGlobalFact" => super::Action::InsertGlobalFact(
+            key,
+            parse_fact_value(args[1]),
+            parse_expire(action, args.get(2))?,
+        ),
+        "SetEntityFact" => super::Action::InsertEntityFact(
+            key,
+            parse_fact_value(args[1]),
+            parse_expire(action, args.get(2))?,
+        ),
+        "IncGlobalFact" => {
+            super::Action::IncrementGlobalFact(key, parse_amount(action, args.get(1))?)
         }
+        "IncEntityFact" => {
+            super::Action::IncrementEntityFact(key, parse_amount(action, args.get(1))?)
+        }
+        "DecGlobalFact" => {
+            super::Action::IncrementGlobalFact(key, -parse_amount(action, args.get(1))?)
+        }
+        "DecEntityFact" => {
+            super::Action::IncrementEntityFact(key, -parse_amount(action, args.get(1))?)
+        }
+        "RemoveGlobalFact" => super::Action::RemoveGlobalFact(key),
+        "RemoveEntityFact" => super::Action::RemoveEntityFact(key),
+        _ => unreachable!(),
+    };
 
-        // Sort rules by criteria count
-        result
-            .rules
-            .sort_by(|a, b| b.criteria.criterion.len().cmp(&a.criteria.criterion.len()));
+    Ok(parsed)
+}
 
-        result
+pub(super) fn raw_action_to_action_set(
+    actions: &[String],
+) -> Result<super::ActionSet, Vec<ParseError>> {
+    let mut result = Vec::new();
+    let mut errors = Vec::new();
+
+    for action in actions {
+        match raw_action_to_action(action) {
+            Ok(action) => result.push(action),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(super::ActionSet::new(result))
+    } else {
+        Err(errors)
     }
 }
 
-pub fn parse_criterion<T: ToString>(criterion: T) -> super::Criterion {
+// Alternatives separated by || match when any one of them does
+pub fn parse_criterion<T: ToString>(criterion: T) -> Result<super::Criterion, ParseError> {
     let criterion = criterion.to_string();
-    let splits = criterion.split(" ").collect::<Vec<_>>();
 
-    match splits.len() {
-        1 => {
-            let key = splits[0].to_string();
-            let fa = 1.0;
-            let fb = 1.0;
+    let any = criterion
+        .split("||")
+        .map(|test| parse_fact_test(test.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(super::Criterion { any })
+}
+
+fn parse_compare_op(operator: &str) -> Option<CompareOp> {
+    let op = match operator {
+        "=" => CompareOp::Eq,
+        "!=" => CompareOp::Ne,
+        "<" => CompareOp::Lt,
+        "<=" => CompareOp::Le,
+        ">" => CompareOp::Gt,
+        ">=" => CompareOp::Ge,
+        _ => return None,
+    };
+
+    Some(op)
+}
+
+// start..end or start..=end
+fn parse_range(range: &str) -> Option<(f32, f32, bool)> {
+    let (start, end) = range.split_once("..")?;
+    let (end, inclusive) = match end.strip_prefix('=') {
+        Some(end) => (end, true),
+        None => (end, false),
+    };
+
+    Some((start.parse().ok()?, end.parse().ok()?, inclusive))
+}
+
+// $Name always refers to a fact, a bare word is a fact when ordering and
+// text when checking equality
+fn parse_operand(op: CompareOp, operand: &str) -> Operand {
+    if let Some(fact) = operand.strip_prefix('$') {
+        return Operand::Fact(fact.to_string());
+    }
 
-            super::Criterion { key, fa, fb }
+    match operand.parse::<f32>() {
+        Ok(value) => Operand::Value(FactValue::Number(value)),
+        Err(_) if matches!(op, CompareOp::Eq | CompareOp::Ne) => {
+            Operand::Value(FactValue::text(operand))
         }
+        Err(_) => Operand::Fact(operand.to_string()),
+    }
+}
+
+fn parse_fact_test(criterion: &str) -> Result<FactTest, ParseError> {
+    let splits = criterion.split_whitespace().collect::<Vec<_>>();
+
+    let unknown_operator = |operator: &str| ParseError::UnknownOperator {
+        operator: operator.to_string(),
+        criterion: criterion.to_string(),
+    };
+    let invalid_criterion = || ParseError::InvalidCriterion(criterion.to_string());
+
+    match splits.len() {
+        1 => Ok(FactTest::Compare {
+            key: splits[0].to_string(),
+            op: CompareOp::Eq,
+            operand: Operand::Value(FactValue::Number(1.0)),
+        }),
         2 => {
             let key = splits[0].to_string();
             let operator = splits[1];
 
-            let (fa, fb) = match operator {
-                "!" => (0.0, 0.0),
-                _ => panic!("Invalid operator: {}", operator),
-            };
+            if operator != "!" {
+                return Err(unknown_operator(operator));
+            }
 
-            super::Criterion { key, fa, fb }
+            Ok(FactTest::Compare {
+                key,
+                op: CompareOp::Eq,
+                operand: Operand::Value(FactValue::Number(0.0)),
+            })
         }
         3 => {
             let key = splits[0].to_string();
             let operator = splits[1];
 
-            if let Ok(value) = splits[2].parse::<f32>() {
-                let (fa, fb) = match operator {
-                    "<" => (f32::MIN, value),
-                    ">" => (value, f32::MAX),
-                    "=" => (value, value),
-                    _ => panic!("Invalid operator: {}", operator),
-                };
-
-                super::Criterion { key, fa, fb }
-            } else {
-                // handle string
-                if operator != "=" {
-                    panic!("Invalid operator: {} for string", operator);
-                }
+            if operator == "in" {
+                let (start, end, inclusive) =
+                    parse_range(splits[2]).ok_or_else(invalid_criterion)?;
 
-                let hash = fact_str_hash(splits[2]);
-                super::Criterion {
+                return Ok(FactTest::Range {
                     key,
-                    fa: hash,
-                    fb: hash,
-                }
+                    start,
+                    end,
+                    inclusive,
+                });
             }
-        }
-        _ => panic!("Invalid fact: {}", criterion),
-    }
-}
 
-fn generate_combinations_with_core_vec<T: Clone + std::fmt::Debug>(
-    alts: Vec<Vec<T>>,
-    core: Vec<T>,
-) -> Vec<Vec<T>> {
-    let mut combinations = vec![];
-
-    // Recursive function to generate combinations
-    fn recurse<T: Clone + std::fmt::Debug>(
-        alts: &[Vec<T>],
-        core: &Vec<T>,
-        current: Vec<T>,
-        combinations: &mut Vec<Vec<T>>,
-    ) {
-        if alts.is_empty() {
-            // Once there are no more alternative groups, append the core items to the current combination
-            let mut final_combination = current.clone();
-            final_combination.extend(core.clone());
-            combinations.push(final_combination);
-        } else {
-            // Iterate over the first group of alternatives
-            let first_group = &alts[0];
-            let rest_groups = &alts[1..];
-
-            for item in first_group {
-                let mut new_current = current.clone();
-                new_current.push(item.clone());
-                recurse(rest_groups, core, new_current, combinations);
-            }
+            let op = parse_compare_op(operator).ok_or_else(|| unknown_operator(operator))?;
+            let operand = parse_operand(op, splits[2]);
+
+            Ok(FactTest::Compare { key, op, operand })
         }
+        _ => Err(invalid_criterion()),
     }
-
-    // Start the recursive generation
-    recurse(&alts, &core, vec![], &mut combinations);
-
-    combinations
 }
 
-fn parse_criteria(criteria: &Criteria) -> Vec<super::Criteria> {
-    let mut alts = Vec::new();
-    let mut core = Vec::new();
+fn parse_criteria(criteria: &Criteria) -> Result<super::Criteria, Vec<ParseError>> {
+    let mut criterion = Vec::new();
+    let mut errors = Vec::new();
 
     for fact in &criteria.facts {
-        // It's an Or
-        if fact.contains("||") {
-            let mut alt_set = Vec::new();
-            let splits = fact.split(" || ").collect::<Vec<_>>();
-            for split in splits {
-                alt_set.push(parse_criterion(split));
-            }
-            alts.push(alt_set);
-        } else {
-            core.push(parse_criterion(fact));
+        match parse_criterion(fact) {
+            Ok(parsed) => criterion.push(parsed),
+            Err(error) => errors.push(error),
         }
     }
 
-    if alts.is_empty() {
-        return vec![super::Criteria {
-            concept: criteria.concept,
-            criterion: core,
-        }];
-    }
-
-    let mut permutations = generate_combinations_with_core_vec(alts, core);
-
-    let mut result = Vec::new();
-    for perm in &mut permutations {
-        result.push(super::Criteria {
-            concept: criteria.concept,
-            criterion: perm.clone(),
-        });
+    if !errors.is_empty() {
+        return Err(errors);
     }
 
-    result
+    Ok(super::Criteria {
+        concept: criteria.concept,
+        criterion,
+    })
 }
 
 #[derive(Debug, Resource)]
@@ -0,0 +1,339 @@
+use core::fmt;
+
+use shared_deps::ron;
+
+use crate::{
+    parse::{ParseError, RawRuleSet},
+    Concept, RuleSet,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DialogueId {
+    Rule(String),
+    Response(String),
+}
+
+impl fmt::Display for DialogueId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DialogueId::Rule(id) => write!(f, "rule `{}`", id),
+            DialogueId::Response(id) => write!(f, "response `{}`", id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DialogueProblem {
+    Syntax(String),
+    UnknownConcept(String),
+    Parse(ParseError),
+    MissingResponse(String),
+    InvalidCooldown,
+    UnusedResponse,
+    DuplicateRule,
+    DuplicateResponse,
+}
+
+impl fmt::Display for DialogueProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DialogueProblem::Syntax(message) => write!(f, "{}", message),
+            DialogueProblem::UnknownConcept(concept) => write!(f, "unknown concept `{}`", concept),
+            DialogueProblem::Parse(error) => write!(f, "{}", error),
+            DialogueProblem::MissingResponse(response) => {
+                write!(f, "response `{}` does not exist", response)
+            }
+            DialogueProblem::InvalidCooldown => {
+                write!(f, "cooldown must be zero or more seconds")
+            }
+            DialogueProblem::UnusedResponse => write!(f, "not used by any rule"),
+            DialogueProblem::DuplicateRule => write!(f, "rule id is used more than once"),
+            DialogueProblem::DuplicateResponse => write!(f, "response id is used more than once"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialogueError {
+    pub id: Option<DialogueId>,
+    pub line: Option<usize>,
+    pub problem: DialogueProblem,
+    // Index into the rule set entries, only used to find the line
+    pub(crate) entry: Option<usize>,
+}
+
+impl DialogueError {
+    pub(crate) fn new(entry: usize, id: DialogueId, problem: DialogueProblem) -> Self {
+        Self {
+            id: Some(id),
+            line: None,
+            problem,
+            entry: Some(entry),
+        }
+    }
+}
+
+impl fmt::Display for DialogueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, "{}: ", id)?;
+        }
+        write!(f, "{}", self.problem)
+    }
+}
+
+// Checks a dialogue file, every problem found is reported with the line it is on
+pub fn validate_dialogue(source: &str) -> Result<RuleSet, Vec<DialogueError>> {
+    let mut errors = unknown_concepts(source);
+
+    let raw_rule_set: RawRuleSet = match ron::from_str(source) {
+        Ok(raw_rule_set) => raw_rule_set,
+        Err(err) => {
+            // Unknown concepts have already been reported with their rule
+            let is_concept = matches!(
+                &err.code,
+                ron::Error::NoSuchEnumVariant { outer: Some(outer), .. } if outer == "Concept"
+            );
+            if !is_concept || errors.is_empty() {
+                errors.push(DialogueError {
+                    id: None,
+                    line: Some(err.position.line),
+                    problem: DialogueProblem::Syntax(err.code.to_string()),
+                    entry: None,
+                });
+            }
+            return Err(errors);
+        }
+    };
+
+    let (rule_set, build_errors) = raw_rule_set.build();
+    let entry_lines = entry_lines(source);
+    errors.extend(build_errors.into_iter().map(|mut error| {
+        error.line = error
+            .entry
+            .and_then(|entry| locate(source, &entry_lines, entry, &error.problem));
+        error
+    }));
+
+    if errors.is_empty() {
+        Ok(rule_set)
+    } else {
+        Err(errors)
+    }
+}
+
+// Line numbers (1 based) of every Response and Rule entry in order
+fn entry_lines(source: &str) -> Vec<usize> {
+    source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim_start();
+            line.starts_with("Response(") || line.starts_with("Rule(")
+        })
+        .map(|(i, _)| i + 1)
+        .collect()
+}
+
+fn locate(
+    source: &str,
+    entry_lines: &[usize],
+    entry: usize,
+    problem: &DialogueProblem,
+) -> Option<usize> {
+    let start = *entry_lines.get(entry)?;
+    let end = entry_lines.get(entry + 1).copied().unwrap_or(usize::MAX);
+
+    let needle = match problem {
+        DialogueProblem::Parse(error) => error.text(),
+        DialogueProblem::MissingResponse(response) => response,
+        DialogueProblem::InvalidCooldown => "cooldown:",
+        _ => return Some(start),
+    };
+
+    source
+        .lines()
+        .enumerate()
+        .skip(start - 1)
+        .take_while(|(i, _)| i + 1 < end)
+        .find(|(_, line)| line.contains(needle))
+        .map(|(i, _)| i + 1)
+        .or(Some(start))
+}
+
+// Done by hand as ron stops at the first unknown variant
+fn unknown_concepts(source: &str) -> Vec<DialogueError> {
+    let mut errors = Vec::new();
+    let mut rule_id = None;
+
+    for (i, line) in source.lines().enumerate() {
+        if let Some(id) = line.trim_start().strip_prefix("id:") {
+            rule_id = Some(
+                id.trim()
+                    .trim_end_matches(',')
+                    .trim_matches('"')
+                    .to_string(),
+            );
+        }
+
+        let Some(concept) = line.trim_start().strip_prefix("concept:") else {
+            continue;
+        };
+        let concept = concept.trim().trim_end_matches(',');
+        if ron::from_str::<Concept>(concept).is_err() {
+            errors.push(DialogueError {
+                id: rule_id.clone().map(DialogueId::Rule),
+                line: Some(i + 1),
+                problem: DialogueProblem::UnknownConcept(concept.to_string()),
+                entry: None,
+            });
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BROKEN_DIALOGUE: &str = r#"RawRuleSet(
+    entries: [
+        Response(RawResponse(
+            id: "Greet",
+            now: ["RandomText hello", "AddGlobalFact Greeted soon"],
+        )),
+        Rule(RawRule(
+            id: "Greet",
+            criteria: Criteria(
+                concept: ThinkIdle,
+                facts: ["Mood ~ 1.5"]
+            ),
+            response: "Greet",
+        )),
+        Rule(RawRule(
+            id: "Greet",
+            criteria: Criteria(
+                concept: ThinkIdle,
+                facts: ["Mood > 1.5", "IsHappy"]
+            ),
+            response: "Greet",
+        )),
+        Rule(RawRule(
+            id: "Wave",
+            criteria: Criteria(
+                concept: ThinkIdle,
+                facts: ["Mood > 1.5"]
+            ),
+            response: "Waving",
+        )),
+        Response(RawResponse(
+            id: "Unused",
+            now: ["RandomText unused"],
+        )),
+    ],
+)"#;
+
+    #[test]
+    fn test_reports_every_problem() {
+        let errors = validate_dialogue(BROKEN_DIALOGUE).unwrap_err();
+
+        let found = errors
+            .iter()
+            .map(|e| (e.line, e.id.clone(), e.problem.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    Some(5),
+                    Some(DialogueId::Response("Greet".to_string())),
+                    DialogueProblem::Parse(ParseError::InvalidAction {
+                        action: "AddGlobalFact Greeted soon".to_string(),
+                        reason: "invalid expire time",
+                    })
+                ),
+                (
+                    Some(11),
+                    Some(DialogueId::Rule("Greet".to_string())),
+                    DialogueProblem::Parse(ParseError::UnknownOperator {
+                        operator: "~".to_string(),
+                        criterion: "Mood ~ 1.5".to_string(),
+                    })
+                ),
+                (
+                    Some(15),
+                    Some(DialogueId::Rule("Greet".to_string())),
+                    DialogueProblem::DuplicateRule
+                ),
+                (
+                    Some(29),
+                    Some(DialogueId::Rule("Wave".to_string())),
+                    DialogueProblem::MissingResponse("Waving".to_string())
+                ),
+                (
+                    Some(31),
+                    Some(DialogueId::Response("Unused".to_string())),
+                    DialogueProblem::UnusedResponse
+                ),
+            ]
+        );
+
+        assert_eq!(
+            errors[1].to_string(),
+            "line 11: rule `Greet`: unknown operator `~` in `Mood ~ 1.5`"
+        );
+    }
+
+    #[test]
+    fn test_unknown_concepts() {
+        let source = BROKEN_DIALOGUE
+            .replacen("concept: ThinkIdle", "concept: ThinkSleepy", 1)
+            .replace("id: \"Wave\",\n            criteria: Criteria(\n                concept: ThinkIdle", "id: \"Wave\",\n            criteria: Criteria(\n                concept: Dance");
+
+        let errors = validate_dialogue(&source).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                DialogueError {
+                    id: Some(DialogueId::Rule("Greet".to_string())),
+                    line: Some(10),
+                    problem: DialogueProblem::UnknownConcept("ThinkSleepy".to_string()),
+                    entry: None,
+                },
+                DialogueError {
+                    id: Some(DialogueId::Rule("Wave".to_string())),
+                    line: Some(26),
+                    problem: DialogueProblem::UnknownConcept("Dance".to_string()),
+                    entry: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        let errors = validate_dialogue("RawRuleSet(entries: [\n    Rule(\n]").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, Some(3));
+        assert!(matches!(errors[0].problem, DialogueProblem::Syntax(_)));
+    }
+
+    #[test]
+    fn test_valid_dialogue() {
+        let source = BROKEN_DIALOGUE
+            .replace("AddGlobalFact Greeted soon", "AddGlobalFact Greeted 5")
+            .replace("Mood ~ 1.5", "Mood < 1.5")
+            .replace(
+                "id: \"Greet\",\n            criteria: Criteria(\n                concept: ThinkIdle,\n                facts: [\"Mood > 1.5\", \"IsHappy\"]",
+                "id: \"GreetHappy\",\n            criteria: Criteria(\n                concept: ThinkIdle,\n                facts: [\"Mood > 1.5\", \"IsHappy\"]",
+            )
+            .replace("\"Waving\"", "\"Unused\"");
+
+        let rule_set = validate_dialogue(&source).unwrap();
+        assert_eq!(rule_set.rules.len(), 3);
+    }
+}
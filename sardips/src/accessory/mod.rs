@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use fact_db::{Concept, FactDb};
 use sardips_core::{
     accessory_core::{
         AccessoryDiscoveredEntries, AccessoryTemplate, AccessoryTemplateDatabase, AnchorPointSet,
@@ -9,6 +10,8 @@ use serde::{Deserialize, Serialize};
 use shared_deps::moonshine_save::save::Save;
 use view::AccessoryViewPlugin;
 
+use crate::thinking::{Thought, TryThinkEvent};
+
 pub mod view;
 
 pub struct AccessoryPlugin;
@@ -17,7 +20,13 @@ impl Plugin for AccessoryPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Accessory>()
             .add_plugins(AccessoryViewPlugin)
-            .add_systems(Update, add_starting_accessory_discovered_entries);
+            .add_systems(
+                Update,
+                (
+                    add_starting_accessory_discovered_entries,
+                    think_about_new_accessory,
+                ),
+            );
     }
 }
 
@@ -66,3 +75,25 @@ fn add_starting_accessory_discovered_entries(
         }
     }
 }
+
+fn think_about_new_accessory(
+    mut try_think_events: EventWriter<TryThinkEvent>,
+    accessories: Query<(&Accessory, &Parent), Added<Accessory>>,
+    wearers: Query<Ref<Thought>>,
+) {
+    for (accessory, parent) in accessories.iter() {
+        let Ok(thought) = wearers.get(parent.get()) else {
+            continue;
+        };
+
+        // Spawned wearing it, like when a save is loaded
+        if thought.is_added() {
+            continue;
+        }
+
+        let mut fact_db = FactDb::default();
+        fact_db.add_str("Accessory", &accessory.template);
+        try_think_events
+            .send(TryThinkEvent::new(parent.get(), Concept::ThinkNewAccessory).with_facts(fact_db));
+    }
+}
@@ -1,13 +1,14 @@
 use bevy::prelude::*;
 use sardips_core::age_core::Age;
 
-use crate::simulation::SimulationUpdate;
+use crate::simulation::{SimulationCatchUp, SimulationUpdate};
 
 pub struct AgePlugin;
 
 impl Plugin for AgePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(SimulationUpdate, tick_ages);
+        app.add_systems(SimulationUpdate, tick_ages)
+            .add_systems(SimulationCatchUp, tick_ages);
     }
 }
 
@@ -0,0 +1,276 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use sardips_core::{
+    age_core::Age,
+    assets::FontAssets,
+    button_hover::ButtonHover,
+    despawn_all,
+    money_core::Money,
+    name::SpeciesName,
+    text_translation::{warp_recursive_value_key, KeyText},
+    GameState,
+};
+
+use crate::{money::money_display, palettes};
+
+pub struct AwayReportPlugin;
+
+impl Plugin for AwayReportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (show_away_report, dismiss_away_report).run_if(in_state(GameState::ViewScreen)),
+        )
+        .add_systems(OnExit(GameState::ViewScreen), despawn_all::<AwayReportUi>);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AwayStockChange {
+    pub name_key: String,
+    pub start_assets: Money,
+    pub end_assets: Money,
+}
+
+// Filled in by the catch up systems while the game was closed
+#[derive(Resource, Debug, Default)]
+pub struct AwayReport {
+    pub simulated: Duration,
+    pub skipped: Duration,
+    pub starved: Vec<String>,
+    pub evolved: Vec<(String, String)>,
+    pub hatched: Vec<String>,
+    pub poops_spawned: u32,
+    pub quarters_passed: u32,
+    pub stock_changes: Vec<AwayStockChange>,
+    // Company name key and how many of the player's shares went with it
+    pub delisted: Vec<(String, u64)>,
+    pub listed: Vec<String>,
+}
+
+impl AwayReport {
+    pub fn is_empty(&self) -> bool {
+        self.simulated.is_zero() && self.skipped.is_zero()
+    }
+
+    fn lines(&self) -> Vec<KeyText> {
+        let mut lines = vec![KeyText::new().with_value(
+            0,
+            text_keys::AWAY_REPORT_DURATION,
+            &[&Age(self.simulated + self.skipped).lived_for_text()],
+        )];
+
+        if !self.skipped.is_zero() {
+            lines.push(KeyText::new().with_value(
+                0,
+                text_keys::AWAY_REPORT_SKIPPED,
+                &[&Age(self.skipped).lived_for_text()],
+            ));
+        }
+
+        for name in &self.starved {
+            lines.push(KeyText::new().with_value(
+                0,
+                text_keys::AWAY_REPORT_STARVED,
+                &[&warp_recursive_value_key(name)],
+            ));
+        }
+
+        for (name, species) in &self.evolved {
+            lines.push(KeyText::new().with_value(
+                0,
+                text_keys::AWAY_REPORT_EVOLVED,
+                &[
+                    &warp_recursive_value_key(name),
+                    &warp_recursive_value_key(SpeciesName::new(species).name_key()),
+                ],
+            ));
+        }
+
+        for species in &self.hatched {
+            lines.push(KeyText::new().with_value(
+                0,
+                text_keys::AWAY_REPORT_HATCHED,
+                &[&warp_recursive_value_key(
+                    SpeciesName::new(species).name_key(),
+                )],
+            ));
+        }
+
+        if self.poops_spawned > 0 {
+            lines.push(KeyText::new().with_value(
+                0,
+                text_keys::AWAY_REPORT_POOPS,
+                &[&self.poops_spawned.to_string()],
+            ));
+        }
+
+        if self.quarters_passed > 0 {
+            lines.push(KeyText::new().with_value(
+                0,
+                text_keys::AWAY_REPORT_QUARTERS,
+                &[&self.quarters_passed.to_string()],
+            ));
+        }
+
+        for change in &self.stock_changes {
+            lines.push(KeyText::new().with_value(
+                0,
+                text_keys::AWAY_REPORT_STOCK_CHANGE,
+                &[
+                    &warp_recursive_value_key(&change.name_key),
+                    &money_display(change.start_assets),
+                    &money_display(change.end_assets),
+                ],
+            ));
+        }
+
+        for (name_key, shares) in &self.delisted {
+            lines.push(KeyText::new().with_value(
+                0,
+                text_keys::AWAY_REPORT_DELISTED,
+                &[&warp_recursive_value_key(name_key), &shares.to_string()],
+            ));
+        }
+
+        for name_key in &self.listed {
+            lines.push(KeyText::new().with_value(
+                0,
+                text_keys::AWAY_REPORT_LISTED,
+                &[&warp_recursive_value_key(name_key)],
+            ));
+        }
+
+        lines
+    }
+}
+
+#[derive(Component)]
+struct AwayReportUi;
+
+#[derive(Component)]
+struct AwayReportDismissButton;
+
+fn show_away_report(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    report: Res<AwayReport>,
+    existing: Query<Entity, With<AwayReportUi>>,
+) {
+    if !report.is_changed() || report.is_empty() || !existing.is_empty() {
+        return;
+    }
+
+    let text_style = TextStyle {
+        font: fonts.main_font.clone(),
+        font_size: 25.,
+        color: Color::BLACK,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            AwayReportUi,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(85.),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(10.)),
+                        border: UiRect::all(Val::Px(4.)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(palettes::view_screen::TOP_UI),
+                    border_color: BorderColor(palettes::view_screen::TOP_UI_BORDER),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font_size: 40.,
+                                ..text_style.clone()
+                            },
+                        ),
+                        KeyText::new().with(0, text_keys::AWAY_REPORT_TITLE),
+                    ));
+
+                    for line in report.lines() {
+                        parent.spawn((
+                            TextBundle::from_section("", text_style.clone()).with_style(Style {
+                                margin: UiRect::top(Val::Px(5.)),
+                                ..default()
+                            }),
+                            line,
+                        ));
+                    }
+
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(150.),
+                                    height: Val::Px(50.),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    margin: UiRect::top(Val::Px(15.)),
+                                    border: UiRect::all(Val::Px(4.)),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            ButtonHover::default()
+                                .with_background(palettes::ui::BUTTON_SET)
+                                .with_border(palettes::ui::BUTTON_BORDER_SET),
+                            AwayReportDismissButton,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                TextBundle::from_section(
+                                    "",
+                                    TextStyle {
+                                        font_size: 30.,
+                                        ..text_style.clone()
+                                    },
+                                ),
+                                KeyText::new().with(0, text_keys::AWAY_REPORT_DISMISS),
+                            ));
+                        });
+                });
+        });
+}
+
+fn dismiss_away_report(
+    mut commands: Commands,
+    mut report: ResMut<AwayReport>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<AwayReportDismissButton>)>,
+    ui: Query<Entity, With<AwayReportUi>>,
+) {
+    for interaction in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        for entity in &ui {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        *report = AwayReport::default();
+    }
+}
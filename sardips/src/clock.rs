@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use shared_deps::chrono::{DateTime, Utc};
+
+// Every wall clock read in the simulation goes through this so runs can be replayed
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub enum Clock {
+    #[default]
+    System,
+    // Only moves when advanced, pair with a seeded RngPlugin for reproducible runs
+    Manual(DateTime<Utc>),
+}
+
+impl Clock {
+    pub fn manual(start: DateTime<Utc>) -> Self {
+        Self::Manual(start)
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        match self {
+            Self::System => Utc::now(),
+            Self::Manual(now) => *now,
+        }
+    }
+
+    pub fn advance(&mut self, by: Duration) {
+        if let Self::Manual(now) = self {
+            *now += by;
+        }
+    }
+
+    pub fn set(&mut self, to: DateTime<Utc>) {
+        if let Self::Manual(now) = self {
+            *now = to;
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use core::fmt;
+use std::collections::HashSet;
+
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use sardips_core::money_core::Money;
+use sardips_core::text_database::TextDatabase;
+use serde::{Deserialize, Serialize};
+use shared_deps::bevy_common_assets::ron::RonAssetPlugin;
+
+use crate::stock_market::{Company, CompanyHistory, DividendPolicy, Industry, PerformanceRanking};
+
+pub struct CompanyTemplatePlugin;
+
+impl Plugin for CompanyTemplatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<AssetCompanyTemplateSet>::new(&[
+            "companies.ron",
+        ]))
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            load_templates.run_if(
+                not(resource_exists::<CompanyTemplateDatabase>)
+                    .and_then(resource_exists::<TextDatabase>),
+            ),
+        );
+    }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let template_set = CompanyTemplateSetHandle(asset_server.load("stocks/complete.companies.ron"));
+    commands.insert_resource(template_set);
+}
+
+fn load_templates(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    text_db: Res<TextDatabase>,
+    template_handle: Res<CompanyTemplateSetHandle>,
+    mut template_assets: ResMut<Assets<AssetCompanyTemplateSet>>,
+) {
+    // A file that doesn't parse at all leaves the market with nothing new to list rather than
+    // waiting on it forever
+    if let LoadState::Failed(err) = asset_server.load_state(template_handle.0.id()) {
+        error!(
+            "complete.companies.ron failed to load, no companies will be listed: {}",
+            err
+        );
+        commands.insert_resource(CompanyTemplateDatabase { templates: vec![] });
+        return;
+    }
+
+    if let Some(set) = template_assets.remove(template_handle.0.id()) {
+        // Bad templates are left out rather than taking the market down with them
+        let errors = validate_templates(&set.templates);
+        for error in &errors {
+            error!("complete.companies.ron: {}", error);
+        }
+        let templates = set
+            .templates
+            .into_iter()
+            .filter(|template| !errors.iter().any(|error| error.ticker == template.ticker))
+            .collect();
+
+        let db = CompanyTemplateDatabase { templates };
+        for error in db.missing_text(&text_db) {
+            error!("complete.companies.ron: {}", error);
+        }
+
+        commands.insert_resource(db);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompanyTemplate {
+    pub ticker: String,
+    pub industries: Vec<(f32, Industry)>,
+    // Prices are in cents, the rest of the financials in dollars
+    pub stock_price: Money,
+    #[serde(default)]
+    pub market_cap: Money,
+    pub revenue: Money,
+    pub expenses: Money,
+    #[serde(default)]
+    pub dividend_paid: Money,
+    pub assets: Money,
+    pub outstanding_shares: u64,
+    // Held back to list on the market later instead of at the start
+    #[serde(default)]
+    pub ipo: bool,
+}
+
+impl CompanyTemplate {
+    pub fn company(&self, quarter: u32) -> Company {
+        Company {
+            ticker: self.ticker.clone(),
+            existing_shares: self.outstanding_shares,
+            history: vec![CompanyHistory {
+                quarter,
+                assets: self.assets * 100,
+                revenue: self.revenue * 100,
+                expenses: self.expenses * 100,
+                total_shares: self.outstanding_shares,
+                dividend_paid: self.dividend_paid,
+                performance: PerformanceRanking::Average,
+                economic_events: vec![],
+            }],
+            performance_history: vec![],
+            industries: self.industries.clone(),
+            distressed_quarters: 0,
+            dividend_policy: DividendPolicy::implied(
+                self.dividend_paid,
+                self.outstanding_shares,
+                (self.revenue - self.expenses) * 100,
+            ),
+        }
+    }
+
+    pub fn main_industry(&self) -> Industry {
+        self.industries
+            .iter()
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, industry)| *industry)
+            .unwrap_or_default()
+    }
+
+    pub fn name_key(&self) -> String {
+        format!("company.{}.name", self.ticker.to_lowercase())
+    }
+
+    pub fn description_key(&self) -> String {
+        format!("company.{}.description", self.ticker.to_lowercase())
+    }
+}
+
+#[derive(Resource)]
+pub struct CompanyTemplateDatabase {
+    pub templates: Vec<CompanyTemplate>,
+}
+
+impl CompanyTemplateDatabase {
+    pub fn iter(&self) -> impl Iterator<Item = &CompanyTemplate> {
+        self.templates.iter()
+    }
+
+    pub fn get(&self, ticker: &str) -> Option<&CompanyTemplate> {
+        self.templates
+            .iter()
+            .find(|template| template.ticker == ticker)
+    }
+
+    // Listed when a new market is made
+    pub fn starting(&self) -> impl Iterator<Item = &CompanyTemplate> {
+        self.templates.iter().filter(|template| !template.ipo)
+    }
+
+    pub fn ipos(&self) -> impl Iterator<Item = &CompanyTemplate> {
+        self.templates.iter().filter(|template| template.ipo)
+    }
+
+    pub fn missing_text(&self, text_db: &TextDatabase) -> Vec<CompanyTemplateError> {
+        let mut errors = vec![];
+        for template in &self.templates {
+            for key in [template.name_key(), template.description_key()] {
+                if !text_db.exists(&key) {
+                    errors.push(CompanyTemplateError::new(
+                        template,
+                        CompanyTemplateProblem::MissingText(key),
+                    ));
+                }
+            }
+        }
+        errors
+    }
+}
+
+pub const MAX_TICKER_LEN: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompanyTemplateProblem {
+    InvalidTicker,
+    DuplicateTicker,
+    NoIndustries,
+    InvalidIndustryWeights,
+    InvalidStockPrice,
+    NoShares,
+    MissingText(String),
+}
+
+impl fmt::Display for CompanyTemplateProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompanyTemplateProblem::InvalidTicker => write!(
+                f,
+                "ticker must be 1 to {} uppercase letters",
+                MAX_TICKER_LEN
+            ),
+            CompanyTemplateProblem::DuplicateTicker => write!(f, "ticker is used more than once"),
+            CompanyTemplateProblem::NoIndustries => write!(f, "needs at least one industry"),
+            CompanyTemplateProblem::InvalidIndustryWeights => {
+                write!(f, "industry weights must be positive and add up to about 1")
+            }
+            CompanyTemplateProblem::InvalidStockPrice => {
+                write!(f, "stock price must be more than 0")
+            }
+            CompanyTemplateProblem::NoShares => write!(f, "needs outstanding shares"),
+            CompanyTemplateProblem::MissingText(key) => {
+                write!(f, "text `{}` is not in the text database", key)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompanyTemplateError {
+    pub ticker: String,
+    pub problem: CompanyTemplateProblem,
+}
+
+impl CompanyTemplateError {
+    fn new(template: &CompanyTemplate, problem: CompanyTemplateProblem) -> Self {
+        Self {
+            ticker: template.ticker.clone(),
+            problem,
+        }
+    }
+}
+
+impl fmt::Display for CompanyTemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "company `{}`: {}", self.ticker, self.problem)
+    }
+}
+
+// How far off 1 the industry weights can add up to
+const INDUSTRY_WEIGHT_TOLERANCE: f32 = 0.1;
+
+pub fn validate_templates(templates: &[CompanyTemplate]) -> Vec<CompanyTemplateError> {
+    let mut errors = vec![];
+    let mut seen = HashSet::new();
+
+    for template in templates {
+        let mut problem = |problem| errors.push(CompanyTemplateError::new(template, problem));
+
+        let ticker = &template.ticker;
+        if ticker.is_empty()
+            || ticker.len() > MAX_TICKER_LEN
+            || !ticker.chars().all(|c| c.is_ascii_uppercase())
+        {
+            problem(CompanyTemplateProblem::InvalidTicker);
+        }
+        if !seen.insert(ticker.clone()) {
+            problem(CompanyTemplateProblem::DuplicateTicker);
+        }
+
+        if template.industries.is_empty() {
+            problem(CompanyTemplateProblem::NoIndustries);
+        } else {
+            let total: f32 = template.industries.iter().map(|(weight, _)| weight).sum();
+            if template.industries.iter().any(|(weight, _)| *weight <= 0.)
+                || (total - 1.).abs() > INDUSTRY_WEIGHT_TOLERANCE
+            {
+                problem(CompanyTemplateProblem::InvalidIndustryWeights);
+            }
+        }
+
+        if template.stock_price <= 0 {
+            problem(CompanyTemplateProblem::InvalidStockPrice);
+        }
+        if template.outstanding_shares == 0 {
+            problem(CompanyTemplateProblem::NoShares);
+        }
+    }
+
+    errors
+}
+
+#[derive(Asset, Serialize, Deserialize, TypePath)]
+pub struct AssetCompanyTemplateSet {
+    pub templates: Vec<CompanyTemplate>,
+}
+
+#[derive(Debug, Resource)]
+struct CompanyTemplateSetHandle(Handle<AssetCompanyTemplateSet>);
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::path::PathBuf;
+
+    use sardips_core::text_database::Language;
+    use shared_deps::ron;
+
+    use super::*;
+
+    pub(crate) fn load_test_templates() -> CompanyTemplateDatabase {
+        let mut file_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        file_path.push("../run/assets/stocks/complete.companies.ron");
+
+        let data = std::fs::read_to_string(file_path).unwrap();
+        let set: AssetCompanyTemplateSet = ron::from_str(&data).unwrap();
+
+        CompanyTemplateDatabase {
+            templates: set.templates,
+        }
+    }
+
+    #[test]
+    fn test_companies_asset() {
+        let db = load_test_templates();
+
+        let errors = validate_templates(&db.templates);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(db.starting().count() > 0);
+        assert!(db.ipos().count() > 0);
+
+        let mut file_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        file_path.push("../run/assets/text/main.text_database.ron");
+        let data = std::fs::read_to_string(file_path).unwrap();
+        let text_db: TextDatabase = ron::from_str(&data).unwrap();
+        assert!(text_db.values.contains_key(&Language::English));
+
+        let missing = db.missing_text(&text_db);
+        assert!(missing.is_empty(), "{:?}", missing);
+    }
+
+    #[test]
+    fn test_validate_templates() {
+        let mut template = load_test_templates().templates.remove(0);
+        template.ticker = "ok".to_string();
+        template.industries = vec![(0.5, Industry::Tech)];
+        template.stock_price = 0;
+
+        let mut duplicate = template.clone();
+        duplicate.stock_price = 100;
+        duplicate.industries = vec![];
+        duplicate.outstanding_shares = 0;
+
+        let problems = validate_templates(&[template, duplicate])
+            .into_iter()
+            .map(|error| error.problem)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            vec![
+                CompanyTemplateProblem::InvalidTicker,
+                CompanyTemplateProblem::InvalidIndustryWeights,
+                CompanyTemplateProblem::InvalidStockPrice,
+                CompanyTemplateProblem::InvalidTicker,
+                CompanyTemplateProblem::DuplicateTicker,
+                CompanyTemplateProblem::NoIndustries,
+                CompanyTemplateProblem::NoShares,
+            ]
+        );
+    }
+}
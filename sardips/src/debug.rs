@@ -11,13 +11,18 @@ use crate::{
     simulation::SimTimeScale,
 };
 use bevy::prelude::*;
+use fact_db::{
+    parse::parse_fact_value, Concept, EntityFactDatabase, FactDb, FactQuery, GlobalFactDatabase,
+    RuleSet,
+};
 use sardips_core::{
+    age_core::Age,
     food_core::FoodTemplateDatabase,
     money_core::Money,
     name::EntityName,
     particles::SPARKS,
     pet_core::{PetTemplateDatabase, DEFAULT_POOP_TEXTURE},
-    text_database::Language,
+    text_database::{Language, TextDatabase},
     text_translation::SelectedLanguageTag,
     GameState,
 };
@@ -39,6 +44,7 @@ impl Plugin for DebugPlugin {
                     update_sim_time_scale_debug_text,
                     toggle_dev_console,
                     action_dev_console_command,
+                    action_dev_console_fact_command,
                 ),
             )
             .add_systems(OnEnter(DevConsoleState::Open), spawn_dev_console)
@@ -532,6 +538,38 @@ fn dev_console_text_input(
                     dev_console_commands.send(DevConsoleCommand::GiveMoney(100));
                 }
             }
+            DevConsoleCommand::LIST_FACTS_COMMAND => {
+                if splits.len() > 1 {
+                    let prefix = splits.get(2).unwrap_or(&"").to_string();
+                    dev_console_commands
+                        .send(DevConsoleCommand::ListFacts(splits[1].to_string(), prefix));
+                }
+            }
+            DevConsoleCommand::SET_FACT_COMMAND => {
+                if splits.len() > 3 {
+                    dev_console_commands.send(DevConsoleCommand::SetFact(
+                        splits[1].to_string(),
+                        splits[2].to_string(),
+                        splits[3].to_string(),
+                    ));
+                }
+            }
+            DevConsoleCommand::REMOVE_FACT_COMMAND => {
+                if splits.len() > 2 {
+                    dev_console_commands.send(DevConsoleCommand::RemoveFact(
+                        splits[1].to_string(),
+                        splits[2].to_string(),
+                    ));
+                }
+            }
+            DevConsoleCommand::DRY_RUN_QUERY_COMMAND => {
+                if splits.len() > 2 {
+                    dev_console_commands.send(DevConsoleCommand::DryRunQuery(
+                        splits[1].to_string(),
+                        splits[2].to_string(),
+                    ));
+                }
+            }
             _ => {
                 error!("Unknown command: {}", splits[0]);
                 history.push_command_output(format!("Unknown command: \"{}\"", splits[0]));
@@ -558,6 +596,11 @@ enum DevConsoleCommand {
     ChangeLanguage(String),
     DiscoverCompleteDipdex,
     GiveMoney(Money),
+    // Target is "global" or a pet's first name
+    ListFacts(String, String),
+    SetFact(String, String, String),
+    RemoveFact(String, String),
+    DryRunQuery(String, String),
 }
 
 impl DevConsoleCommand {
@@ -572,6 +615,10 @@ impl DevConsoleCommand {
     const DISCOVER_COMPLETE_DIPDEX_COMMAND: &'static str = "discover_complete_dipdex";
     const SPAWN_SPEWER_COMMAND: &'static str = "spawn_spewer";
     const GIVE_MONEY_COMMAND: &'static str = "give_money";
+    const LIST_FACTS_COMMAND: &'static str = "list_facts";
+    const SET_FACT_COMMAND: &'static str = "set_fact";
+    const REMOVE_FACT_COMMAND: &'static str = "remove_fact";
+    const DRY_RUN_QUERY_COMMAND: &'static str = "dry_run_query";
 
     pub const fn command_str(&self) -> &'static str {
         match self {
@@ -586,6 +633,10 @@ impl DevConsoleCommand {
             DevConsoleCommand::DiscoverCompleteDipdex => Self::DISCOVER_COMPLETE_DIPDEX_COMMAND,
             DevConsoleCommand::SpawnSpewer => Self::SPAWN_SPEWER_COMMAND,
             DevConsoleCommand::GiveMoney(_) => Self::GIVE_MONEY_COMMAND,
+            DevConsoleCommand::ListFacts(_, _) => Self::LIST_FACTS_COMMAND,
+            DevConsoleCommand::SetFact(_, _, _) => Self::SET_FACT_COMMAND,
+            DevConsoleCommand::RemoveFact(_, _) => Self::REMOVE_FACT_COMMAND,
+            DevConsoleCommand::DryRunQuery(_, _) => Self::DRY_RUN_QUERY_COMMAND,
         }
     }
 
@@ -647,7 +698,13 @@ fn action_dev_console_command(
             DevConsoleCommand::SpawnPoop => {
                 let x = rng.i32(-200..200) as f32 + rng.f32();
                 let y = rng.i32(-300..300) as f32 + rng.f32();
-                spawn_poop(&mut commands, 1.0, Vec2::new(x, y), DEFAULT_POOP_TEXTURE);
+                spawn_poop(
+                    &mut commands,
+                    &mut *rng,
+                    1.0,
+                    Vec2::new(x, y),
+                    DEFAULT_POOP_TEXTURE,
+                );
                 history.push_command_output(format!("Spawned poop at {},{}", x, y));
             }
             DevConsoleCommand::ClearAllPets => {
@@ -695,6 +752,168 @@ fn action_dev_console_command(
                     wallet.balance += *amount;
                 }
             }
+            // Handled by action_dev_console_fact_command
+            DevConsoleCommand::ListFacts(_, _)
+            | DevConsoleCommand::SetFact(_, _, _)
+            | DevConsoleCommand::RemoveFact(_, _)
+            | DevConsoleCommand::DryRunQuery(_, _) => {}
+        }
+    }
+}
+
+const GLOBAL_FACT_TARGET: &str = "global";
+
+// Pets go by their first name, either the text key or as shown in the selected language
+fn find_pet_by_name(
+    name: &str,
+    pets: &Query<(Entity, &EntityName, Option<&Age>), With<Pet>>,
+    text_db: Option<&TextDatabase>,
+    language: Language,
+) -> Option<Entity> {
+    pets.iter()
+        .find(|(_, pet_name, _)| {
+            let first_name = &pet_name.first_name;
+            first_name.eq_ignore_ascii_case(name)
+                || text_db.is_some_and(|text_db| {
+                    text_db.exists(first_name)
+                        && text_db.get(language, first_name).eq_ignore_ascii_case(name)
+                })
+        })
+        .map(|(entity, _, _)| entity)
+}
+
+fn list_facts(fact_db: &FactDb, prefix: &str) -> String {
+    let mut facts = fact_db
+        .iter()
+        .filter(|(key, _)| key.starts_with(prefix))
+        .map(|(key, value)| format!("{} = {}", key, value))
+        .collect::<Vec<_>>();
+    facts.sort();
+
+    if facts.is_empty() {
+        "none".to_string()
+    } else {
+        facts.join(", ")
+    }
+}
+
+fn action_dev_console_fact_command(
+    mut dev_commands: EventReader<DevConsoleCommand>,
+    mut history: Query<&mut DevConsoleHistory>,
+    text_db: Option<Res<TextDatabase>>,
+    language: Query<&Language, With<SelectedLanguageTag>>,
+    rule_set: Option<Res<RuleSet>>,
+    mut global_fact_db: ResMut<GlobalFactDatabase>,
+    pets: Query<(Entity, &EntityName, Option<&Age>), With<Pet>>,
+    mut entity_fact_dbs: Query<&mut EntityFactDatabase>,
+) {
+    let mut history = match history.get_single_mut() {
+        Ok(history) => history,
+        Err(_) => return,
+    };
+    let language = language.get_single().copied().unwrap_or_default();
+    let find_pet = |name: &str| find_pet_by_name(name, &pets, text_db.as_deref(), language);
+
+    for dev_command in dev_commands.read() {
+        match dev_command {
+            DevConsoleCommand::ListFacts(target, prefix) => {
+                let listing = if target == GLOBAL_FACT_TARGET {
+                    list_facts(&global_fact_db.0, prefix)
+                } else if let Some(fact_db) =
+                    find_pet(target).and_then(|pet| entity_fact_dbs.get(pet).ok())
+                {
+                    list_facts(&fact_db.0, prefix)
+                } else {
+                    history.push_command_output(format!("No pet named: {}", target));
+                    continue;
+                };
+                info!("Facts on {}: {}", target, listing);
+                history.push_command_output(format!("{}: {}", target, listing));
+            }
+            DevConsoleCommand::SetFact(target, key, value) => {
+                let value = parse_fact_value(value);
+                if target == GLOBAL_FACT_TARGET {
+                    global_fact_db.0.set(key, value.clone());
+                } else if let Some(mut fact_db) =
+                    find_pet(target).and_then(|pet| entity_fact_dbs.get_mut(pet).ok())
+                {
+                    fact_db.0.set(key, value.clone());
+                } else {
+                    history.push_command_output(format!("No pet named: {}", target));
+                    continue;
+                }
+                history.push_command_output(format!("Set {} = {} on {}", key, value, target));
+            }
+            DevConsoleCommand::RemoveFact(target, key) => {
+                if target == GLOBAL_FACT_TARGET {
+                    global_fact_db.0.remove(key);
+                } else if let Some(mut fact_db) =
+                    find_pet(target).and_then(|pet| entity_fact_dbs.get_mut(pet).ok())
+                {
+                    fact_db.0.remove(key);
+                } else {
+                    history.push_command_output(format!("No pet named: {}", target));
+                    continue;
+                }
+                history.push_command_output(format!("Removed {} from {}", key, target));
+            }
+            DevConsoleCommand::DryRunQuery(target, concept) => {
+                let Ok(concept) = shared_deps::ron::from_str::<Concept>(concept) else {
+                    history.push_command_output(format!("Unknown concept: {}", concept));
+                    continue;
+                };
+                let Some(rule_set) = &rule_set else {
+                    history.push_command_output("Dialogue is not loaded");
+                    continue;
+                };
+                let Some((pet, age)) = find_pet(target)
+                    .and_then(|pet| pets.get(pet).ok())
+                    .map(|(pet, _, age)| (pet, age))
+                else {
+                    history.push_command_output(format!("No pet named: {}", target));
+                    continue;
+                };
+                let Ok(fact_db) = entity_fact_dbs.get(pet) else {
+                    history.push_command_output(format!("{} has no facts", target));
+                    continue;
+                };
+
+                // Same query handle_thought would make
+                let mut query = FactQuery::new(concept)
+                    .add_fact_db(&global_fact_db.0)
+                    .add_fact_db(&fact_db.0);
+                if let Some(age) = age {
+                    query = query.at_time(age.0.as_secs_f32());
+                }
+
+                let checks = query.check(rule_set);
+                let matched = checks
+                    .iter()
+                    .filter(|check| check.failed.is_empty())
+                    .map(|check| match (check.chosen, check.ready) {
+                        (true, _) => format!("{} (chosen)", check.rule_id),
+                        (false, true) => check.rule_id.to_string(),
+                        (false, false) => format!("{} (resting)", check.rule_id),
+                    })
+                    .collect::<Vec<_>>();
+                let output = format!(
+                    "{:?} on {}: {} rules, matched: {}",
+                    concept,
+                    target,
+                    checks.len(),
+                    matched.join(", ")
+                );
+                info!("{}", output);
+                history.push_command_output(output);
+
+                // Near misses only failed a single criterion
+                for check in checks.iter().filter(|check| check.failed.len() == 1) {
+                    let output = format!("{} failed: {}", check.rule_id, check.failed[0]);
+                    info!("{}", output);
+                    history.push_command_output(output);
+                }
+            }
+            _ => {}
         }
     }
 }
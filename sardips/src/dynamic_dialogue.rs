@@ -1,6 +1,5 @@
 use bevy::prelude::*;
 use fact_db::parse;
-use fact_db::{self, RuleSet};
 use shared_deps::bevy_common_assets::ron::RonAssetPlugin;
 
 pub struct DynamicDialoguePlugin;
@@ -24,7 +23,11 @@ fn parse_rule_sets(
     mut assets: ResMut<Assets<parse::RawRuleSet>>,
 ) {
     if let Some(raw_rule_sets) = assets.remove(handle.0.id()) {
-        let rule_set: RuleSet = raw_rule_sets.into();
+        // Broken rules are left out rather than taking the whole game down
+        let (rule_set, errors) = raw_rule_sets.build();
+        for error in &errors {
+            warn!("main.dialogue.ron: {}", error);
+        }
         commands.insert_resource(rule_set);
     }
 }
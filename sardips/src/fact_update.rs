@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use fact_db::{fact_str_hash, EntityFactDatabase, FactDb, GlobalFactDatabase};
+use fact_db::{EntityFactDatabase, FactDb, GlobalFactDatabase};
 
 use crate::{
     food::Food,
@@ -86,13 +86,13 @@ fn update_species_name(
     mut query: Query<(&mut EntityFactDatabase, &SpeciesName), Changed<SpeciesName>>,
 ) {
     for (mut fact_db, name) in query.iter_mut() {
-        fact_db.0.add("Species", fact_str_hash(&name.0));
+        fact_db.0.add_str("Species", &name.0);
     }
 }
 
 fn update_pet_kind(mut query: Query<(&mut EntityFactDatabase, &PetKind), Changed<PetKind>>) {
     for (mut fact_db, kind) in query.iter_mut() {
-        fact_db.0.add("Kind", fact_str_hash(kind.to_string()));
+        fact_db.0.add_str("Kind", kind);
     }
 }
 
@@ -169,13 +169,13 @@ fn update_names_facts(
     mut query: Query<(&mut EntityFactDatabase, &EntityName), Changed<EntityName>>,
 ) {
     for (mut fact_db, name) in query.iter_mut() {
-        fact_db.0.add("FirstName", fact_str_hash(&name.first_name));
+        fact_db.0.add_str("FirstName", &name.first_name);
         match &name.middle_name {
-            Some(name) => fact_db.0.add("MiddleName", fact_str_hash(name)),
+            Some(name) => fact_db.0.add_str("MiddleName", name),
             None => fact_db.0.remove("MiddleName"),
         }
         match &name.last_name {
-            Some(name) => fact_db.0.add("LastName", fact_str_hash(name)),
+            Some(name) => fact_db.0.add_str("LastName", name),
             None => fact_db.0.remove("LastName"),
         }
     }
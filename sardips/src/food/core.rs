@@ -82,7 +82,8 @@ fn spawn_pending_food(
         let mut rng = RngComponent::from(&mut global_rng);
 
         if let Some(template) = food_db.get(&event.name) {
-            spawn_food(template, &mut commands, random_point_in_game_zone(&mut rng));
+            let location = random_point_in_game_zone(&mut rng);
+            spawn_food(template, &mut commands, &mut rng, location);
         } else {
             error!("No food template found for {}", event.name);
         }
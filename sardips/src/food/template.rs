@@ -3,6 +3,7 @@ use sardips_core::food_core::{FoodFillFactor, FoodSensations, FoodTemplate, Food
 use sardips_core::name::{EntityName, SpeciesName};
 use serde::{Deserialize, Serialize};
 use shared_deps::bevy_common_assets::ron::RonAssetPlugin;
+use shared_deps::bevy_turborand::DelegatedRng;
 
 use crate::layering;
 
@@ -38,7 +39,12 @@ fn load_templates(
     }
 }
 
-pub fn spawn_food(template: &FoodTemplate, commands: &mut Commands, location: Vec2) -> Entity {
+pub fn spawn_food<T: DelegatedRng>(
+    template: &FoodTemplate,
+    commands: &mut Commands,
+    rng: &mut T,
+    location: Vec2,
+) -> Entity {
     let entity_id = commands
         .spawn(FoodBundle {
             sensations: FoodSensations {
@@ -50,7 +56,8 @@ pub fn spawn_food(template: &FoodTemplate, commands: &mut Commands, location: Ve
                 layering::view_screen::FOOD,
             )),
             species_name: SpeciesName::new(&template.name),
-            name: EntityName::new(format!("food.{}", template.name.to_lowercase())),
+            name: EntityName::new(format!("food.{}", template.name.to_lowercase()))
+                .with_id(rng.u32(..)),
             fill_factor: FoodFillFactor(template.fill_factor),
             ..default()
         })
@@ -9,6 +9,9 @@
 pub mod accessory;
 pub mod age;
 pub mod anime;
+pub mod away_report;
+pub mod clock;
+pub mod company_template;
 pub mod debug;
 pub mod dynamic_dialogue;
 pub mod fact_update;
@@ -25,7 +28,10 @@ pub mod player;
 pub mod sardip_save;
 pub mod scenes;
 pub mod simulation;
+pub mod stock_margin;
 pub mod stock_market;
+pub mod stock_portfolio;
+pub mod stock_strategy;
 pub mod stock_ticker;
 pub mod thinking;
 pub mod tools;
@@ -33,7 +39,9 @@ pub mod tools;
 use accessory::AccessoryPlugin;
 use age::AgePlugin;
 use anime::AnimePlugin;
+use away_report::AwayReportPlugin;
 use bevy::{asset::AssetMetaCheck, prelude::*, window::WindowResolution};
+use company_template::CompanyTemplatePlugin;
 use debug::DebugPlugin;
 use dynamic_dialogue::DynamicDialoguePlugin;
 use fact_db::FactsPlugin;
@@ -51,7 +59,7 @@ use shared_deps::bevy_kira_audio::prelude::*;
 use shared_deps::bevy_parallax::ParallaxPlugin;
 use shared_deps::bevy_prototype_lyon::prelude::*;
 use shared_deps::bevy_turborand::prelude::*;
-use simulation::{SimulationPlugin, SimulationState};
+use simulation::{SimulationCatchUp, SimulationPlugin, SimulationState, SimulationUpdate};
 use stock_market::StockMarketPlugin;
 use stock_ticker::StockTickerPlugin;
 use thinking::ThinkingPlugin;
@@ -96,7 +104,7 @@ impl Plugin for GamePlugin {
             shared_deps::avian2d::PhysicsPlugins::new(FixedUpdate),
             shared_deps::avian3d::PhysicsPlugins::new(FixedUpdate),
             shared_deps::avian3d::prelude::PhysicsDebugPlugin::default(),
-            FactsPlugin,
+            FactsPlugin::new(SimulationUpdate).with_expire_schedule(SimulationCatchUp),
             sardips_core::SardipsCorePlugin,
             shared_deps::bevy_rts_camera::RtsCameraPlugin,
             // shared_deps::avian2d::prelude::PhysicsDebugPlugin::default(),
@@ -106,6 +114,7 @@ impl Plugin for GamePlugin {
             PetPlugin,
             SimulationPlugin,
             FoodTemplatePlugin,
+            CompanyTemplatePlugin,
             MinigamePlugin,
             MoneyPlugin,
             PlayerPlugin,
@@ -126,6 +135,7 @@ impl Plugin for GamePlugin {
             AccessoryPlugin,
             InventoryPlugin,
             PetPreviewPlugin,
+            AwayReportPlugin,
         ));
 
         // #[cfg(feature = "dev")]
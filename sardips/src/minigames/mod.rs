@@ -4,6 +4,7 @@
 // Candy Crush clone
 
 use bevy::prelude::*;
+use fact_db::{Concept, FactDb};
 use sardips_core::{
     assets::FontAssets,
     button_hover::ButtonHover,
@@ -27,6 +28,7 @@ use crate::{
         Pet,
     },
     player::Player,
+    thinking::TryThinkEvent,
 };
 use text_keys;
 
@@ -116,13 +118,14 @@ fn mini_game_completed(
     mut mini_game_state: ResMut<NextState<MiniGameState>>,
     mut game_state: ResMut<NextState<GameState>>,
     mut game_completed: EventReader<MiniGameCompleted>,
-    mut playing: Query<(&mut Fun, &MinigamePreferences), With<Playing>>,
+    mut try_think_events: EventWriter<TryThinkEvent>,
+    mut playing: Query<(Entity, &mut Fun, &MinigamePreferences), With<Playing>>,
     mut player_wallet: Query<&mut Wallet, With<Player>>,
 ) {
     for event in game_completed.read() {
         let prize = MiniGamePrize::from_result(&event.game_type, event.result).unwrap();
 
-        if let Ok((mut fun, preferences)) = playing.get_single_mut() {
+        if let Ok((entity, mut fun, preferences)) = playing.get_single_mut() {
             let preference: &MinigamePreference = preferences
                 .0
                 .get(&event.game_type)
@@ -131,6 +134,13 @@ fn mini_game_completed(
             let fun_score = prize.fun * preference.fun_modifier();
 
             fun.add(fun_score);
+
+            let mut fact_db = FactDb::default();
+            fact_db.add_str("MiniGame", format!("{:?}", event.game_type));
+            fact_db.add_str("MiniGameResult", format!("{:?}", event.result));
+            try_think_events.send(
+                TryThinkEvent::new(entity, Concept::ThinkMiniGameFinished).with_facts(fact_db),
+            );
         }
 
         if let Ok(mut wallet) = player_wallet.get_single_mut() {
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use fact_db::{Concept, FactDb};
 use shared_deps::bevy_turborand::{DelegatedRng, GlobalRng, RngComponent};
 
 use sardips_core::{
@@ -10,32 +11,43 @@ use sardips_core::{
     text_database::TextDatabase,
 };
 
-use crate::simulation::{Simulated, SimulationUpdate, EGG_HATCH_ATTEMPT_INTERVAL, MAX_EGG_LIFE};
+use crate::{
+    away_report::AwayReport,
+    simulation::{
+        Simulated, SimulationCatchUp, SimulationUpdate, EGG_HATCH_ATTEMPT_INTERVAL, MAX_EGG_LIFE,
+    },
+    thinking::TryThinkEvent,
+};
 
-use super::template::SpawnPetEvent;
-use shared_deps::rand::prelude::SliceRandom;
+use super::{template::SpawnPetEvent, Pet};
 
 pub struct BreedPlugin;
 
 impl Plugin for BreedPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<BreedEvent>().add_systems(
-            SimulationUpdate,
-            (
-                apply_pending_breeds,
-                tick_breeds,
-                add_ready_to_breed,
-                attempt_hatch,
-                egg_hatch,
-            ),
-        );
+        app.add_event::<BreedEvent>()
+            .add_systems(
+                SimulationUpdate,
+                (
+                    apply_pending_breeds,
+                    tick_breeds,
+                    add_ready_to_breed,
+                    attempt_hatch,
+                    egg_hatch,
+                ),
+            )
+            .add_systems(
+                SimulationCatchUp,
+                (tick_breeds, add_ready_to_breed, catch_up_hatch),
+            );
     }
 }
 
-fn breeding_result(
+fn breeding_result<'a, T: DelegatedRng>(
     mut breeding: [PetKind; 2],
-    pet_db: &PetTemplateDatabase,
-) -> Option<&PetTemplate> {
+    pet_db: &'a PetTemplateDatabase,
+    rng: &mut T,
+) -> Option<&'a PetTemplate> {
     if breeding.contains(&PetKind::Blob) {
         return None;
     }
@@ -70,11 +82,7 @@ fn breeding_result(
         breeder_right
     );
 
-    Some(
-        *possible
-            .choose(&mut shared_deps::rand::thread_rng())
-            .unwrap(),
-    )
+    Some(possible[rng.usize(0..possible.len())])
 }
 
 /*
@@ -156,10 +164,39 @@ fn attempt_hatch(
     }
 }
 
+fn catch_up_hatch(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut report: ResMut<AwayReport>,
+    mut query: Query<(Entity, &Egg, &mut EggHatchAttempt, &mut RngComponent, &Age)>,
+) {
+    for (entity, egg, mut hatch, mut rng, age) in query.iter_mut() {
+        let attempts = hatch
+            .attempt_timer
+            .tick(time.delta())
+            .times_finished_this_tick();
+
+        for _ in 0..attempts {
+            if rng.i32(0..100) > 50 {
+                continue;
+            }
+            hatch.successes += 1;
+        }
+
+        // Overdue eggs attempt every tick so they would have hatched within seconds
+        if hatch.successes >= 3 || age.0 > MAX_EGG_LIFE {
+            commands.entity(entity).remove::<EggHatchAttempt>();
+            report.hatched.push(egg.contains.clone());
+        }
+    }
+}
+
 fn egg_hatch(
     mut commands: Commands,
     mut spawn_pets: EventWriter<SpawnPetEvent>,
+    mut try_think_events: EventWriter<TryThinkEvent>,
     query: Query<(Entity, &Egg, &GlobalTransform), Without<EggHatchAttempt>>,
+    pets: Query<Entity, With<Pet>>,
 ) {
     for (entity, egg, transform) in query.iter() {
         spawn_pets.send(SpawnPetEvent::Blank((
@@ -167,6 +204,14 @@ fn egg_hatch(
             egg.contains.clone(),
         )));
         commands.entity(entity).despawn_recursive();
+
+        // The new pet isn't spawned yet so everyone here is watching
+        for pet in pets.iter() {
+            let mut fact_db = FactDb::default();
+            fact_db.add_str("HatchedSpecies", &egg.contains);
+            try_think_events
+                .send(TryThinkEvent::new(pet, Concept::ThinkSawHatch).with_facts(fact_db));
+        }
     }
 }
 
@@ -260,20 +305,21 @@ fn apply_pending_breeds(
             continue;
         }
 
-        match breeding_result([kinds[0], kinds[1]], &pet_db) {
+        match breeding_result([kinds[0], kinds[1]], &pet_db, &mut *global_rng) {
             Some(template) => {
                 // Spawn egg at the midpoint of the two breeders
                 let midpoint = (query.get(event.breeding[0]).unwrap().3.translation()
                     + query.get(event.breeding[1]).unwrap().3.translation())
                     / 2.0;
 
+                let name = EntityName::random(&text_db, &mut *global_rng);
                 spawn_egg(
                     &template.species_name,
                     &mut commands,
                     &mut global_rng,
                     &game_image_assets,
                     midpoint.xy(),
-                    EntityName::random(&text_db),
+                    name,
                 );
 
                 // reset the breed timer
@@ -293,40 +339,48 @@ fn apply_pending_breeds(
 
 #[cfg(test)]
 mod test {
+    use std::path::PathBuf;
+
     use sardips_core::pet_core::{
         PetTemplate, PetTemplateDatabase, PetTemplateImageSet, PreCalculated, TemplateSize,
         TemplateSpeed, WeightType,
     };
+    use shared_deps::ron;
     use strum::IntoEnumIterator;
 
+    use super::*;
+
+    fn test_template(species_name: impl ToString, kind: PetKind) -> PetTemplate {
+        PetTemplate {
+            species_name: species_name.to_string(),
+            kind,
+            possible_evolutions: vec![],
+            image_set: PetTemplateImageSet::default(),
+            size: TemplateSize::XY(1, 1),
+            weight: WeightType::MiddleWeight,
+            speed: TemplateSpeed::Medium,
+            breeds: false,
+            stomach: None,
+            pooper: None,
+            cleanliness: None,
+            fun: None,
+            money_hungry: None,
+            starter: true,
+            pre_calculated: PreCalculated::default(),
+            anchor_points: Default::default(),
+        }
+    }
+
     #[test]
     fn test_all_combos_exist() {
-        use super::breeding_result;
-        use super::PetKind;
-
         let mut pet_db = PetTemplateDatabase::default();
         // Put one of every kind into the db
         for kind in PetKind::iter() {
-            pet_db.add(PetTemplate {
-                species_name: kind.to_string(),
-                kind,
-                possible_evolutions: vec![],
-                image_set: PetTemplateImageSet::default(),
-                size: TemplateSize::XY(1, 1),
-                weight: WeightType::MiddleWeight,
-                speed: TemplateSpeed::Medium,
-                breeds: false,
-                stomach: None,
-                pooper: None,
-                cleanliness: None,
-                fun: None,
-                money_hungry: None,
-                starter: true,
-                pre_calculated: PreCalculated::default(),
-                anchor_points: Default::default(),
-            });
+            pet_db.add(test_template(kind, kind));
         }
 
+        let mut rng = GlobalRng::with_seed(0);
+
         let breeding_kinds = PetKind::iter()
             .filter(|&kind| kind != PetKind::Blob)
             .collect::<Vec<_>>();
@@ -334,7 +388,7 @@ mod test {
         for kind_left in &breeding_kinds {
             for kind_right in &breeding_kinds {
                 let breeding = [*kind_left, *kind_right];
-                let result = breeding_result(breeding, &pet_db);
+                let result = breeding_result(breeding, &pet_db, &mut rng);
                 assert!(
                     result.is_some(),
                     "No result for {:?} and {:?}",
@@ -344,4 +398,93 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_breeding_replay() {
+        type Hatchling = (
+            String,
+            String,
+            Option<String>,
+            Option<String>,
+            u32,
+            Option<u32>,
+        );
+
+        fn run_breeding(seed: u64) -> Vec<Hatchling> {
+            let mut file_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+            file_path.push("../run/assets/text/main.text_database.ron");
+            let mut text_db: TextDatabase =
+                ron::from_str(&std::fs::read_to_string(file_path).unwrap()).unwrap();
+            text_db.populate_default_name_keys();
+
+            let mut pet_db = PetTemplateDatabase::default();
+            for kind in PetKind::iter() {
+                for i in 0..3 {
+                    pet_db.add(test_template(format!("{}{}", kind, i), kind));
+                }
+            }
+
+            let mut app = App::new();
+            app.insert_resource(GlobalRng::with_seed(seed))
+                .insert_resource(Time::<()>::default())
+                .add_systems(Update, attempt_hatch);
+
+            let pairs = [
+                [PetKind::Object, PetKind::Creature],
+                [PetKind::Creature, PetKind::Creature],
+                [PetKind::Supernatural, PetKind::Object],
+                [PetKind::Supernatural, PetKind::Supernatural],
+            ];
+            let mut eggs = Vec::new();
+            for pair in pairs.iter().cycle().take(8) {
+                let world = app.world_mut();
+                let mut rng = world.resource_mut::<GlobalRng>();
+                let template = breeding_result(*pair, &pet_db, &mut *rng).unwrap();
+                let name = EntityName::random(&text_db, &mut *rng);
+                let egg_rng = RngComponent::from(&mut *rng);
+                let egg = world
+                    .spawn((
+                        Egg {
+                            contains: template.species_name.clone(),
+                        },
+                        EggHatchAttempt::default(),
+                        egg_rng,
+                        Age::default(),
+                        name.clone(),
+                    ))
+                    .id();
+                eggs.push((egg, template.species_name.clone(), name, None));
+            }
+
+            for step in 0..20 {
+                app.world_mut()
+                    .resource_mut::<Time>()
+                    .advance_by(EGG_HATCH_ATTEMPT_INTERVAL);
+                app.update();
+
+                for (egg, _, _, hatched_at) in &mut eggs {
+                    if hatched_at.is_none() && app.world().get::<EggHatchAttempt>(*egg).is_none() {
+                        *hatched_at = Some(step);
+                    }
+                }
+            }
+
+            eggs.into_iter()
+                .map(|(_, species, name, hatched_at)| {
+                    (
+                        species,
+                        name.first_name,
+                        name.middle_name,
+                        name.last_name,
+                        name.id,
+                        hatched_at,
+                    )
+                })
+                .collect()
+        }
+
+        let hatchlings = run_breeding(42);
+        assert!(hatchlings.iter().any(|hatchling| hatchling.5.is_some()));
+        assert_eq!(hatchlings, run_breeding(42));
+    }
 }
@@ -7,7 +7,10 @@ use sardips_core::{
 };
 use shared_deps::bevy_turborand::{DelegatedRng, RngComponent};
 
-use crate::simulation::{SimulationState, SimulationUpdate};
+use crate::{
+    away_report::AwayReport,
+    simulation::{SimulationCatchUp, SimulationState, SimulationUpdate},
+};
 
 use fact_db::{Concept, EntityFactDatabase, FactQuery, GlobalFactDatabase};
 
@@ -17,10 +20,15 @@ pub struct EvolvePlugin;
 
 impl Plugin for EvolvePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(SimulationUpdate, check_evolve).add_systems(
-            PreUpdate,
-            evolve_pending.run_if(in_state(SimulationState::Running)),
-        );
+        app.add_systems(SimulationUpdate, check_evolve)
+            .add_systems(
+                SimulationCatchUp,
+                (check_evolve, report_catch_up_evolutions).chain(),
+            )
+            .add_systems(
+                PreUpdate,
+                evolve_pending.run_if(in_state(SimulationState::Running)),
+            );
     }
 }
 
@@ -92,6 +100,18 @@ fn check_evolve(
     }
 }
 
+fn report_catch_up_evolutions(
+    mut report: ResMut<AwayReport>,
+    evolvers: Query<(&EntityName, &ShouldEvolve), Added<ShouldEvolve>>,
+) {
+    for (entity_name, should_evolve) in evolvers.iter() {
+        report.evolved.push((
+            entity_name.first_name.clone(),
+            should_evolve.species.clone(),
+        ));
+    }
+}
+
 fn evolve_pending(
     mut spawn_pets: EventWriter<SpawnPetEvent>,
     evolvers: Query<
@@ -4,13 +4,14 @@ use bevy::prelude::*;
 
 use sardips_core::{fun_core::Fun, minigames_core::MiniGameType};
 
-use crate::simulation::{SimulationUpdate, FUN_TICK_DOWN};
+use crate::simulation::{SimulationCatchUp, SimulationUpdate, FUN_TICK_DOWN};
 
 pub struct FunPlugin;
 
 impl Plugin for FunPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(SimulationUpdate, tick_down_fun_mood);
+        app.add_systems(SimulationUpdate, tick_down_fun_mood)
+            .add_systems(SimulationCatchUp, catch_up_fun_mood);
     }
 }
 
@@ -40,3 +41,9 @@ fn tick_down_fun_mood(mut fun: Query<&mut Fun>) {
         fun.add(-FUN_TICK_DOWN);
     }
 }
+
+fn catch_up_fun_mood(time: Res<Time>, mut fun: Query<&mut Fun>) {
+    for mut fun in fun.iter_mut() {
+        fun.add(-FUN_TICK_DOWN * time.delta_seconds());
+    }
+}
@@ -1,16 +1,17 @@
 use bevy::prelude::*;
 
 use crate::{
+    away_report::AwayReport,
     food::Food,
     layering,
-    simulation::{SimulationUpdate, HUNGER_TICK_DOWN},
+    simulation::{SimulationCatchUp, SimulationUpdate, HUNGER_TICK_DOWN},
     thinking::TryThinkEvent,
     SimulationState,
 };
 use sardips_core::{
     food_core::FoodFillFactor,
     hunger_core::Hunger,
-    name::SpeciesName,
+    name::{EntityName, SpeciesName},
     sounds::{PlaySoundEffect, SoundEffect},
 };
 
@@ -22,6 +23,7 @@ impl Plugin for HungerPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<EatFoodEvent>()
             .add_systems(SimulationUpdate, tick_hunger)
+            .add_systems(SimulationCatchUp, catch_up_hunger)
             .add_systems(
                 FixedUpdate,
                 (update_starving, begin_eating_food, eating_food)
@@ -42,6 +44,23 @@ fn tick_hunger(mut commands: Commands, mut query: Query<(Entity, &mut Hunger), W
     }
 }
 
+pub(super) fn catch_up_hunger(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut report: ResMut<AwayReport>,
+    mut query: Query<(Entity, &mut Hunger, Option<&EntityName>), Without<Starving>>,
+) {
+    for (entity, mut hunger, name) in query.iter_mut() {
+        hunger.decrease(HUNGER_TICK_DOWN * time.delta_seconds());
+        if hunger.empty() {
+            commands.entity(entity).insert(Starving);
+            if let Some(name) = name {
+                report.starved.push(name.first_name.clone());
+            }
+        }
+    }
+}
+
 fn update_starving(mut commands: Commands, query: Query<(Entity, &Hunger), With<Starving>>) {
     for (entity, hunger) in query.iter() {
         if hunger.value > 0.0 {
@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 use bevy::prelude::*;
+use fact_db::{Concept, FactDb};
 use sardips_core::age_core::Age;
 use sardips_core::hunger_core::Hunger;
 use sardips_core::name::EntityName;
@@ -11,9 +12,14 @@ use shared_deps::moonshine_save::save::Save;
 
 use crate::{
     anime::{AnimeBundle, AnimeIndices, AnimeTimer},
+    away_report::AwayReport,
+    game_zone::random_point_in_game_zone,
     layering,
-    simulation::{Simulated, SimulationUpdate},
+    simulation::{CatchUpConfig, Simulated, SimulationCatchUp, SimulationUpdate},
+    thinking::TryThinkEvent,
 };
+
+use super::{hunger::catch_up_hunger, Pet};
 use sardips_core::{
     assets::GameImageAssets,
     interaction::Clickable,
@@ -27,7 +33,9 @@ pub struct PoopPlugin;
 impl Plugin for PoopPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Poop>()
-            .add_systems(SimulationUpdate, tick_poopers)
+            .add_systems(SimulationUpdate, (tick_poopers, step_in_poop))
+            // Poops while away go by how full the pet was when they left
+            .add_systems(SimulationCatchUp, catch_up_poopers.before(catch_up_hunger))
             .add_systems(
                 Update,
                 spawn_poop_view.run_if(in_state(GameState::ViewScreen)),
@@ -65,13 +73,19 @@ pub struct PoopBundleView {
     pub simulated: Simulated,
 }
 
-pub fn spawn_poop(commands: &mut Commands, scale: f32, location: Vec2, texture: &str) {
+pub fn spawn_poop<T: DelegatedRng>(
+    commands: &mut Commands,
+    rng: &mut T,
+    scale: f32,
+    location: Vec2,
+    texture: &str,
+) {
     commands.spawn(PoopBundle {
         poop: Poop {
             texture_path: texture.to_owned(),
             scale,
         },
-        entity_name: EntityName::new(text_keys::POOP),
+        entity_name: EntityName::new(text_keys::POOP).with_id(rng.u32(..)),
         transform: Transform::from_translation(Vec3::new(
             location.x,
             location.y,
@@ -146,6 +160,50 @@ pub fn spawn_poop_view(
     }
 }
 
+// The poop a pet is standing in so walking across it only counts once
+#[derive(Component)]
+struct StandingInPoop(Entity);
+
+fn step_in_poop(
+    mut commands: Commands,
+    mut try_think_events: EventWriter<TryThinkEvent>,
+    pets: Query<(Entity, &Transform, Option<&StandingInPoop>), With<Pet>>,
+    poops: Query<(Entity, Ref<Poop>, &Transform)>,
+) {
+    for (entity, transform, standing_in) in pets.iter() {
+        let position = transform.translation.xy();
+        let poop = poops.iter().find(|(_, poop, poop_transform)| {
+            const HALF_SIZE: f32 = 32.;
+            position.distance(poop_transform.translation.xy()) < HALF_SIZE * poop.scale
+        });
+
+        match poop {
+            Some((poop_entity, poop, _)) => {
+                if standing_in.is_some_and(|standing_in| standing_in.0 == poop_entity) {
+                    continue;
+                }
+                commands.entity(entity).insert(StandingInPoop(poop_entity));
+
+                // Pooping spawns it right under the pooper, that's not stepping in it
+                if poop.is_added() {
+                    continue;
+                }
+
+                let mut fact_db = FactDb::default();
+                fact_db.add("PoopScale", poop.scale);
+                try_think_events.send(
+                    TryThinkEvent::new(entity, Concept::ThinkSteppedInPoop).with_facts(fact_db),
+                );
+            }
+            None => {
+                if standing_in.is_some() {
+                    commands.entity(entity).remove::<StandingInPoop>();
+                }
+            }
+        }
+    }
+}
+
 pub fn poop_scale(rng: &mut Rng) -> f32 {
     rng.i32(80..100) as f32 / 100.
 }
@@ -181,6 +239,7 @@ fn tick_poopers(
 
                 spawn_poop(
                     &mut commands,
+                    &mut *rng,
                     scale,
                     transform.translation.xy(),
                     &pooper.texture,
@@ -189,3 +248,39 @@ fn tick_poopers(
         }
     }
 }
+
+fn catch_up_poopers(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<CatchUpConfig>,
+    mut report: ResMut<AwayReport>,
+    mut poopers: Query<(
+        &mut Pooper,
+        &mut RngComponent,
+        Option<&Hunger>,
+        Option<&Diarrhea>,
+    )>,
+) {
+    for (mut pooper, mut rng, hunger, diarrhea) in poopers.iter_mut() {
+        if hunger.is_some_and(|hunger| hunger.filled_percent() <= 0.5) {
+            continue;
+        }
+
+        let tick_mul = if diarrhea.is_some() { 1.0 } else { 2.0 };
+
+        let poops = pooper
+            .poop_timer
+            .tick(time.delta().mul_f32(tick_mul))
+            .times_finished_this_tick()
+            .min(config.max_poops);
+
+        // Pets would have wandered around so scatter them over the zone
+        for _ in 0..poops {
+            let scale = poop_scale(&mut rng.fork());
+            let location = random_point_in_game_zone(&mut rng);
+            spawn_poop(&mut commands, &mut *rng, scale, location, &pooper.texture);
+        }
+
+        report.poops_spawned += poops;
+    }
+}
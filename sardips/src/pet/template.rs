@@ -6,6 +6,7 @@ use sardips_core::name::{EntityName, SpeciesName};
 use sardips_core::pet_core::{EvolvingPet, PetTemplate, PetTemplateDatabase};
 use serde::Deserialize;
 use shared_deps::bevy_common_assets::ron::RonAssetPlugin;
+use shared_deps::bevy_turborand::GlobalRng;
 
 use crate::layering;
 use sardips_core::{text_database::TextDatabase, velocity::Speed, GameState};
@@ -143,13 +144,19 @@ fn spawn_pending_pets(
     mut events: EventReader<SpawnPetEvent>,
     pet_template_db: Res<PetTemplateDatabase>,
     text_db: Res<TextDatabase>,
+    mut global_rng: ResMut<GlobalRng>,
 ) {
     for event in events.read() {
         info!("Spawning pet: {:?}", event.species_name());
         if let Some(template) = pet_template_db.get_by_name(event.species_name()) {
             match event {
                 SpawnPetEvent::Blank((pos, _)) => {
-                    spawn_pet(template, &mut commands, *pos, EntityName::random(&text_db));
+                    spawn_pet(
+                        template,
+                        &mut commands,
+                        *pos,
+                        EntityName::random(&text_db, &mut *global_rng),
+                    );
                 }
                 SpawnPetEvent::Evolve((_, evolving)) => {
                     evolve_pet(template, &mut commands, evolving.clone());
@@ -0,0 +1,57 @@
+use std::io::{Read, Write};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+// Same format the web build sends to the server so a blob works in either
+pub fn encode_save_blob(save: &[u8]) -> String {
+    let mut e = ZlibEncoder::new(Vec::new(), Compression::new(9));
+    e.write_all(save).unwrap();
+    let compressed_data = e.finish().unwrap();
+    BASE64_STANDARD.encode(compressed_data)
+}
+
+pub fn decode_save_blob(blob: &str) -> Result<String, String> {
+    // Pasted blobs tend to pick up line breaks
+    let blob = blob.split_whitespace().collect::<String>();
+    if blob.is_empty() {
+        return Err("save blob is empty".to_string());
+    }
+
+    let decoded_data = BASE64_STANDARD
+        .decode(blob)
+        .map_err(|e| format!("save blob is not base64: {}", e))?;
+    let mut e = ZlibDecoder::new(&decoded_data[..]);
+    let mut decompressed_data = String::new();
+    e.read_to_string(&mut decompressed_data)
+        .map_err(|e| format!("save blob is not compressed: {}", e))?;
+
+    Ok(decompressed_data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_blob_round_trip() {
+        let save = "(resources: {}, entities: {})";
+        let blob = encode_save_blob(save.as_bytes());
+        assert_eq!(decode_save_blob(&blob), Ok(save.to_string()));
+
+        let wrapped = blob
+            .as_bytes()
+            .chunks(8)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(decode_save_blob(&wrapped), Ok(save.to_string()));
+    }
+
+    #[test]
+    fn test_bad_blob() {
+        assert!(decode_save_blob("").is_err());
+        assert!(decode_save_blob("not a blob!").is_err());
+        assert!(decode_save_blob(&BASE64_STANDARD.encode("plain text")).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use flate2::Crc;
+use sardips_core::from_mins;
+use shared_deps::chrono::{DateTime, Utc};
+
+const CHECKSUM_PREFIX: &str = "// crc32: ";
+
+#[derive(Resource, Debug, Clone)]
+pub struct SaveFiles {
+    pub path: PathBuf,
+    pub backup_dir: PathBuf,
+    pub backup_count: usize,
+    // Saves happen every few seconds so only some of them are kept as backups
+    pub backup_interval: Duration,
+}
+
+impl SaveFiles {
+    pub fn new(path: impl Into<PathBuf>, backup_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            backup_dir: backup_dir.into(),
+            backup_count: 5,
+            backup_interval: from_mins(10),
+        }
+    }
+
+    pub(super) fn backup_prefix(&self) -> String {
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        format!("{}-", stem)
+    }
+
+    // Newest first
+    pub fn backups(&self) -> Vec<PathBuf> {
+        let prefix = self.backup_prefix();
+        let mut backups = match fs::read_dir(&self.backup_dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().starts_with(&prefix))
+                        .unwrap_or(false)
+                })
+                .collect::<Vec<_>>(),
+            Err(_) => Vec::new(),
+        };
+        // Timestamps are zero padded so they sort by name
+        backups.sort();
+        backups.reverse();
+        backups
+    }
+
+    // Every file a save could be loaded from, best first
+    pub fn load_candidates(&self) -> Vec<PathBuf> {
+        let mut candidates = Vec::new();
+        if self.path.exists() {
+            candidates.push(self.path.clone());
+        }
+        candidates.extend(self.backups());
+        candidates
+    }
+
+    // When the main save was last written, None if there isn't one
+    pub fn saved_at(&self) -> Option<DateTime<Utc>> {
+        fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .map(DateTime::<Utc>::from)
+    }
+
+    pub fn write(&self, save: &str) -> std::io::Result<()> {
+        write_atomic(&self.path, &with_checksum(save))
+    }
+
+    pub fn write_backup(&self, save: &str, now: DateTime<Utc>) -> std::io::Result<()> {
+        fs::create_dir_all(&self.backup_dir)?;
+
+        let name = format!(
+            "{}{}.ron",
+            self.backup_prefix(),
+            now.format("%Y%m%d%H%M%S%3f")
+        );
+        write_atomic(&self.backup_dir.join(name), &with_checksum(save))?;
+
+        for old in self.backups().iter().skip(self.backup_count) {
+            fs::remove_file(old)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Write to a temp file next to the target then rename over it so a crash
+// mid write never leaves a half written save behind
+pub fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+    }
+
+    fs::rename(&tmp, path)
+}
+
+fn checksum(body: &str) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(body.as_bytes());
+    crc.sum()
+}
+
+pub fn with_checksum(body: &str) -> String {
+    format!("{}{:08x}\n{}", CHECKSUM_PREFIX, checksum(body), body)
+}
+
+// Saves from before checksums were added are passed through as is
+pub fn verify_checksum(save: &str) -> Result<&str, String> {
+    let Some(rest) = save.strip_prefix(CHECKSUM_PREFIX) else {
+        return Ok(save);
+    };
+
+    let (expected, body) = rest
+        .split_once('\n')
+        .ok_or_else(|| "save is truncated".to_string())?;
+    let expected = u32::from_str_radix(expected.trim(), 16)
+        .map_err(|_| format!("malformed checksum {}", expected))?;
+
+    let actual = checksum(body);
+    if actual != expected {
+        return Err(format!(
+            "checksum mismatch expected {:08x} got {:08x}",
+            expected, actual
+        ));
+    }
+
+    Ok(body)
+}
+
+pub fn read_verified(path: &Path) -> Result<String, String> {
+    let save = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    verify_checksum(&save).map(|body| body.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sardip_save::test_util::test_files;
+
+    #[test]
+    fn test_checksum_round_trip() {
+        let save = "(resources: {}, entities: {})";
+        assert_eq!(verify_checksum(&with_checksum(save)), Ok(save));
+        assert_eq!(verify_checksum(save), Ok(save));
+
+        let corrupted = with_checksum(save).replace("entities", "entitie5");
+        assert!(verify_checksum(&corrupted).is_err());
+
+        let truncated = &with_checksum(save)[..CHECKSUM_PREFIX.len() + 4];
+        assert!(verify_checksum(truncated).is_err());
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let files = test_files("write_and_read");
+        files.write("first").unwrap();
+        files.write("second").unwrap();
+
+        assert_eq!(read_verified(&files.path), Ok("second".to_string()));
+        assert_eq!(files.load_candidates(), vec![files.path.clone()]);
+
+        fs::remove_dir_all(files.path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_backups_rotate() {
+        let mut files = test_files("backups_rotate");
+        files.backup_count = 3;
+
+        let start = DateTime::UNIX_EPOCH;
+        for i in 0..5 {
+            files
+                .write_backup(&format!("save {}", i), start + from_mins(i * 10))
+                .unwrap();
+        }
+
+        let backups = files.backups();
+        assert_eq!(backups.len(), 3);
+        let contents = backups
+            .iter()
+            .map(|backup| read_verified(backup).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(contents, vec!["save 4", "save 3", "save 2"]);
+
+        // Main save comes before the backups
+        files.write("main").unwrap();
+        assert_eq!(files.load_candidates()[0], files.path);
+        assert_eq!(files.load_candidates()[1..], backups[..]);
+
+        fs::remove_dir_all(files.path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,622 @@
+use std::fmt;
+
+use bevy::reflect::TypePath;
+use shared_deps::ron;
+
+use super::{SaveHeader, CURRENT_SAVE_VERSION};
+use crate::stock_market::{CompleteShareOrderHistory, QuarterManger};
+
+#[derive(Debug, PartialEq)]
+pub enum SaveMigrationError {
+    Parse(String),
+    BadHeader(String),
+    TooNew(u32),
+    MissingMigration(u32),
+    Failed { from: u32, reason: String },
+}
+
+impl fmt::Display for SaveMigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(reason) => write!(f, "unable to parse save: {}", reason),
+            Self::BadHeader(reason) => write!(f, "unable to read save header: {}", reason),
+            Self::TooNew(version) => write!(
+                f,
+                "save version {} is newer than supported version {}",
+                version, CURRENT_SAVE_VERSION
+            ),
+            Self::MissingMigration(version) => {
+                write!(f, "no migration from save version {}", version)
+            }
+            Self::Failed { from, reason } => {
+                write!(f, "migration from version {} failed: {}", from, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SaveMigrationError {}
+
+pub struct SaveMigration {
+    // Migrates a document from this version to the next one
+    pub from: u32,
+    pub migrate: fn(&mut SaveDocument) -> Result<(), String>,
+}
+
+// Add a migration here and bump CURRENT_SAVE_VERSION whenever a saved type changes shape.
+// Fields that can be filled in with #[serde(default)] still get a version, with a migration
+// that does nothing, so there's a fixture showing the old shape still loads
+pub const MIGRATIONS: &[SaveMigration] = &[
+    SaveMigration {
+        from: 0,
+        migrate: v0_add_header,
+    },
+    SaveMigration {
+        from: 1,
+        migrate: v1_stock_market_defaults,
+    },
+    SaveMigration {
+        from: 2,
+        migrate: v2_fact_expiries,
+    },
+];
+
+// Saves before versioning had no header, the header gets written after migrating
+fn v0_add_header(_: &mut SaveDocument) -> Result<(), String> {
+    Ok(())
+}
+
+// The stock market rework mostly added fields that default when missing:
+// StockOrder order_type and expiry, the per company OrderBook, Company distressed_quarters
+// and dividend_policy, CompleteShareOrderHistory dividends, CompanyHistory economic_events,
+// MacroEconomy and ShareHistory candles. OrderHistoryEntry quarter would default to the
+// first quarter so older orders are put in the quarter the save was made in instead
+fn v1_stock_market_defaults(document: &mut SaveDocument) -> Result<(), String> {
+    let quarter = document
+        .resource(QuarterManger::type_path())
+        .and_then(|manager| field(manager, "current_quarter"))
+        .unwrap_or("0")
+        .to_string();
+
+    for history in document.components_mut(CompleteShareOrderHistory::type_path()) {
+        let Some(orders) = field(history, "orders") else {
+            continue;
+        };
+        let orders = list_items(orders)?
+            .into_iter()
+            .map(|order| match field(order, "quarter") {
+                Some(_) => Ok(order.to_string()),
+                None => set_field(order, "quarter", &quarter),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        *history = set_field(history, "orders", &format!("[{}]", orders.join(", ")))?;
+    }
+
+    Ok(())
+}
+
+// FactDb keeps the time left on expiring facts, older saves had none
+fn v2_fact_expiries(_: &mut SaveDocument) -> Result<(), String> {
+    Ok(())
+}
+
+pub fn migrate_save(save: &str) -> Result<String, SaveMigrationError> {
+    migrate_save_with(save, MIGRATIONS, CURRENT_SAVE_VERSION)
+}
+
+pub fn migrate_save_with(
+    save: &str,
+    migrations: &[SaveMigration],
+    target: u32,
+) -> Result<String, SaveMigrationError> {
+    let mut document = SaveDocument::parse(save)?;
+
+    let mut version = document.version()?;
+    if version > target {
+        return Err(SaveMigrationError::TooNew(version));
+    }
+
+    while version < target {
+        let migration = migrations
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or(SaveMigrationError::MissingMigration(version))?;
+
+        (migration.migrate)(&mut document).map_err(|reason| SaveMigrationError::Failed {
+            from: version,
+            reason,
+        })?;
+
+        version += 1;
+    }
+
+    document.set_resource(
+        SaveHeader::type_path(),
+        ron::to_string(&SaveHeader { version }).unwrap(),
+    );
+
+    Ok(document.to_ron())
+}
+
+// The top two levels of a saved scene, values are kept as raw ron so types the
+// migrations don't touch pass through untouched
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SaveDocument {
+    pub resources: Vec<(String, String)>,
+    pub entities: Vec<(u64, Vec<(String, String)>)>,
+}
+
+impl SaveDocument {
+    pub fn parse(save: &str) -> Result<Self, SaveMigrationError> {
+        let parse_error = SaveMigrationError::Parse;
+        let mut document = SaveDocument::default();
+
+        for (field, value) in split_entries(save).map_err(parse_error)? {
+            match field {
+                Some("resources") => document.resources = type_map(value)?,
+                Some("entities") => {
+                    for (id, entity) in split_entries(value).map_err(parse_error)? {
+                        let id = id
+                            .unwrap_or_default()
+                            .parse::<u64>()
+                            .map_err(|e| SaveMigrationError::Parse(e.to_string()))?;
+                        let components = match field_value(entity, "components")? {
+                            Some(components) => type_map(components)?,
+                            None => Vec::new(),
+                        };
+                        document.entities.push((id, components));
+                    }
+                }
+                _ => {
+                    return Err(SaveMigrationError::Parse(format!(
+                        "unknown field {}",
+                        field.unwrap_or_default()
+                    )))
+                }
+            }
+        }
+
+        Ok(document)
+    }
+
+    pub fn to_ron(&self) -> String {
+        let mut out = String::from("(\n  resources: {\n");
+        for (path, value) in &self.resources {
+            out.push_str(&format!("    \"{}\": {},\n", path, value));
+        }
+        out.push_str("  },\n  entities: {\n");
+        for (id, components) in &self.entities {
+            out.push_str(&format!("    {}: (\n      components: {{\n", id));
+            for (path, value) in components {
+                out.push_str(&format!("        \"{}\": {},\n", path, value));
+            }
+            out.push_str("      },\n    ),\n");
+        }
+        out.push_str("  },\n)");
+        out
+    }
+
+    pub fn version(&self) -> Result<u32, SaveMigrationError> {
+        match self.resource(SaveHeader::type_path()) {
+            Some(header) => ron::from_str::<SaveHeader>(header)
+                .map(|header| header.version)
+                .map_err(|e| SaveMigrationError::BadHeader(e.to_string())),
+            None => Ok(0),
+        }
+    }
+
+    pub fn resource(&self, path: &str) -> Option<&str> {
+        self.resources
+            .iter()
+            .find(|(resource, _)| resource == path)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_resource(&mut self, path: &str, value: String) {
+        match self
+            .resources
+            .iter_mut()
+            .find(|(resource, _)| resource == path)
+        {
+            Some((_, existing)) => *existing = value,
+            None => self.resources.push((path.to_string(), value)),
+        }
+    }
+
+    pub fn remove_resource(&mut self, path: &str) {
+        self.resources.retain(|(resource, _)| resource != path);
+    }
+
+    pub fn components_mut<'a>(&'a mut self, path: &'a str) -> impl Iterator<Item = &'a mut String> {
+        self.entities.iter_mut().flat_map(move |(_, components)| {
+            components
+                .iter_mut()
+                .filter(move |(component, _)| component == path)
+                .map(|(_, value)| value)
+        })
+    }
+
+    pub fn remove_component(&mut self, path: &str) {
+        for (_, components) in &mut self.entities {
+            components.retain(|(component, _)| component != path);
+        }
+    }
+
+    // For when a saved type is moved or renamed
+    pub fn rename_type(&mut self, from: &str, to: &str) {
+        let entries = self.resources.iter_mut().chain(
+            self.entities
+                .iter_mut()
+                .flat_map(|(_, components)| components.iter_mut()),
+        );
+        for (path, _) in entries {
+            if path == from {
+                *path = to.to_string();
+            }
+        }
+    }
+}
+
+// Splits a (..), [..] or {..} value into its top level entries, with the key for
+// struct fields and map entries. Only handles what ron writes, no comments or raw strings
+fn split_entries(value: &str) -> Result<Vec<(Option<&str>, &str)>, String> {
+    let value = value.trim();
+    let inner = match (value.chars().next(), value.chars().last()) {
+        (Some('('), Some(')')) | (Some('['), Some(']')) | (Some('{'), Some('}')) => {
+            &value[1..value.len() - 1]
+        }
+        _ => return Err(format!("expected a struct, list or map not {:.20}", value)),
+    };
+
+    let mut entries = Vec::new();
+    let mut push = |start: usize, end: usize, colon: Option<usize>| match colon {
+        Some(colon) => entries.push((
+            Some(inner[start..colon].trim()),
+            inner[colon + 1..end].trim(),
+        )),
+        None if !inner[start..end].trim().is_empty() => {
+            entries.push((None, inner[start..end].trim()))
+        }
+        None => {}
+    };
+
+    let (mut depth, mut start, mut colon) = (0usize, 0, None);
+    let mut chars = inner.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' | '\'' => loop {
+                match chars.next() {
+                    Some((_, '\\')) => {
+                        chars.next();
+                    }
+                    Some((_, quote)) if quote == c => break,
+                    Some(_) => {}
+                    None => return Err("unterminated string".to_string()),
+                }
+            },
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| "unbalanced brackets".to_string())?
+            }
+            ':' if depth == 0 && colon.is_none() => colon = Some(i),
+            ',' if depth == 0 => {
+                push(start, i, colon);
+                start = i + 1;
+                colon = None;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err("unbalanced brackets".to_string());
+    }
+    push(start, inner.len(), colon);
+
+    Ok(entries)
+}
+
+// Map of type path to value, the shape used for both resources and components
+fn type_map(value: &str) -> Result<Vec<(String, String)>, SaveMigrationError> {
+    split_entries(value)
+        .map_err(SaveMigrationError::Parse)?
+        .into_iter()
+        .map(|(path, value)| {
+            let path = ron::from_str::<String>(path.unwrap_or_default())
+                .map_err(|e| SaveMigrationError::Parse(e.to_string()))?;
+            Ok((path, value.to_string()))
+        })
+        .collect()
+}
+
+fn field_value<'a>(value: &'a str, name: &str) -> Result<Option<&'a str>, SaveMigrationError> {
+    Ok(split_entries(value)
+        .map_err(SaveMigrationError::Parse)?
+        .into_iter()
+        .find(|(field, _)| *field == Some(name))
+        .map(|(_, value)| value))
+}
+
+// Helpers for migrations that change values inside a struct, the value passed in is raw ron
+pub fn field<'a>(value: &'a str, name: &str) -> Option<&'a str> {
+    field_value(value, name).ok().flatten()
+}
+
+pub fn set_field(value: &str, name: &str, new_value: &str) -> Result<String, String> {
+    let mut fields = split_entries(value)?
+        .into_iter()
+        .map(|(field, value)| (field.unwrap_or_default(), value))
+        .collect::<Vec<_>>();
+    match fields.iter_mut().find(|(field, _)| *field == name) {
+        Some((_, value)) => *value = new_value,
+        None => fields.push((name, new_value)),
+    }
+
+    let fields = fields
+        .iter()
+        .map(|(field, value)| format!("{}: {}", field, value))
+        .collect::<Vec<_>>();
+    Ok(format!("({})", fields.join(", ")))
+}
+
+pub fn list_items(value: &str) -> Result<Vec<&str>, String> {
+    Ok(split_entries(value)?
+        .into_iter()
+        .map(|(_, item)| item)
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{ecs::entity::EntityHashMap, prelude::*, scene::serde::SceneDeserializer};
+    use fact_db::EntityFactDatabase;
+    use sardips_core::persistent_id::{PersistentId, PersistentIdGenerator};
+    use serde::de::DeserializeSeed;
+
+    use super::*;
+    use crate::{
+        money::Wallet,
+        stock_market::{
+            BuySellOrchestrator, Company, OrderBook, OrderExpiry, OrderType, ShareHistory,
+            SharePortfolio,
+        },
+    };
+
+    const V0_SAVE: &str = include_str!("fixtures/save_v0.ron");
+    const V1_SAVE: &str = include_str!("fixtures/save_v1.ron");
+    const V2_SAVE: &str = include_str!("fixtures/save_v2.ron");
+
+    fn rename_migration(document: &mut SaveDocument) -> Result<(), String> {
+        document.rename_type(
+            "sardips::stock_market::QuarterManger",
+            "sardips::stock_market::QuarterManager",
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let document = SaveDocument::parse(V0_SAVE).unwrap();
+        assert!(!document.resources.is_empty());
+        assert!(!document.entities.is_empty());
+
+        let reparsed = SaveDocument::parse(&document.to_ron()).unwrap();
+        assert_eq!(document, reparsed);
+    }
+
+    #[test]
+    fn test_parse_tricky_values() {
+        let save = r#"(
+  resources: {
+    "a::B": (text: "quote \" and ) brace }", c: ')'),
+  },
+  entities: {
+    7: (
+      components: {
+        "a::C": [1, 2, (3, 4)],
+        "a::D": Some(Average),
+      },
+    ),
+  },
+)"#;
+        let document = SaveDocument::parse(save).unwrap();
+        assert_eq!(
+            document.resource("a::B"),
+            Some(r#"(text: "quote \" and ) brace }", c: ')')"#)
+        );
+        assert_eq!(
+            document.entities,
+            vec![(
+                7,
+                vec![
+                    ("a::C".to_string(), "[1, 2, (3, 4)]".to_string()),
+                    ("a::D".to_string(), "Some(Average)".to_string())
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_migrate_v0_fixture() {
+        let migrated = migrate_save(V0_SAVE).unwrap();
+        let document = SaveDocument::parse(&migrated).unwrap();
+
+        assert_eq!(document.version(), Ok(CURRENT_SAVE_VERSION));
+
+        // Nothing else is touched
+        let original = SaveDocument::parse(V0_SAVE).unwrap();
+        assert_eq!(document.entities, original.entities);
+        for (path, value) in &original.resources {
+            assert_eq!(document.resource(path), Some(value.as_str()));
+        }
+    }
+
+    #[test]
+    fn test_migrate_v1_fixture() {
+        let migrated = migrate_save(V1_SAVE).unwrap();
+        let document = SaveDocument::parse(&migrated).unwrap();
+
+        assert_eq!(document.version(), Ok(CURRENT_SAVE_VERSION));
+
+        // Orders without a quarter are put in the quarter the save was made in
+        let history = document
+            .entities
+            .iter()
+            .flat_map(|(_, components)| components)
+            .find(|(path, _)| path == CompleteShareOrderHistory::type_path())
+            .map(|(_, history)| history)
+            .unwrap();
+        let orders = list_items(field(history, "orders").unwrap()).unwrap();
+        assert_eq!(field(orders[0], "quarter"), Some("3"));
+        assert_eq!(field(orders[0], "price"), Some("100"));
+    }
+
+    #[test]
+    fn test_set_field() {
+        let value = "(kind: Buy, company: (0), text: \"a: b, c\")";
+        assert_eq!(field(value, "text"), Some("\"a: b, c\""));
+        assert_eq!(
+            set_field(value, "company", "(2)").unwrap(),
+            "(kind: Buy, company: (2), text: \"a: b, c\")"
+        );
+        assert_eq!(
+            set_field(value, "quarter", "1").unwrap(),
+            "(kind: Buy, company: (0), text: \"a: b, c\", quarter: 1)"
+        );
+        assert_eq!(list_items("[1, (2, 3), ]").unwrap(), vec!["1", "(2, 3)"]);
+    }
+
+    #[test]
+    fn test_migrations_chain() {
+        let migrations = [
+            SaveMigration {
+                from: 0,
+                migrate: v0_add_header,
+            },
+            SaveMigration {
+                from: 1,
+                migrate: rename_migration,
+            },
+        ];
+
+        let migrated = migrate_save_with(V0_SAVE, &migrations, 2).unwrap();
+        let document = SaveDocument::parse(&migrated).unwrap();
+        assert_eq!(document.version(), Ok(2));
+        assert!(document
+            .resource("sardips::stock_market::QuarterManager")
+            .is_some());
+        assert!(document
+            .resource("sardips::stock_market::QuarterManger")
+            .is_none());
+    }
+
+    #[test]
+    fn test_migrate_errors() {
+        let migrated = migrate_save_with(V0_SAVE, MIGRATIONS, 1).unwrap();
+        assert_eq!(
+            migrate_save_with(&migrated, MIGRATIONS, 0),
+            Err(SaveMigrationError::TooNew(1))
+        );
+        assert_eq!(
+            migrate_save_with(V0_SAVE, &[], 1),
+            Err(SaveMigrationError::MissingMigration(0))
+        );
+        assert!(matches!(
+            migrate_save("not a save"),
+            Err(SaveMigrationError::Parse(_))
+        ));
+    }
+
+    fn load_migrated(save: &str) -> World {
+        let registry = AppTypeRegistry::default();
+        {
+            let mut registry = registry.write();
+            registry.register::<SaveHeader>();
+            registry.register::<OrderBook>();
+            registry.register::<QuarterManger>();
+            registry.register::<BuySellOrchestrator>();
+            registry.register::<PersistentIdGenerator>();
+            registry.register::<PersistentId>();
+            registry.register::<Company>();
+            registry.register::<ShareHistory>();
+            registry.register::<Wallet>();
+            registry.register::<SharePortfolio>();
+            registry.register::<CompleteShareOrderHistory>();
+            registry.register::<EntityFactDatabase>();
+        }
+
+        let migrated = migrate_save(save).unwrap();
+        let mut deserializer = ron::de::Deserializer::from_str(&migrated).unwrap();
+        let scene = SceneDeserializer {
+            type_registry: &registry.read(),
+        }
+        .deserialize(&mut deserializer)
+        .unwrap();
+
+        let mut world = World::new();
+        world.insert_resource(registry);
+        scene
+            .write_to_world(&mut world, &mut EntityHashMap::default())
+            .unwrap();
+        world
+    }
+
+    #[test]
+    fn test_load_migrated_v0_fixture() {
+        let mut world = load_migrated(V0_SAVE);
+
+        assert_eq!(world.resource::<SaveHeader>().version, CURRENT_SAVE_VERSION);
+        assert_eq!(world.resource::<OrderBook>().top_order_id, 1);
+
+        let companies = world
+            .query::<&Company>()
+            .iter(&world)
+            .map(|company| company.ticker.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(companies, vec!["TEST".to_string()]);
+
+        let balances = world
+            .query::<&Wallet>()
+            .iter(&world)
+            .map(|wallet| wallet.balance)
+            .sum::<i64>();
+        assert_eq!(balances, 5000 + 123456);
+    }
+
+    #[test]
+    fn test_load_migrated_v1_fixture() {
+        let mut world = load_migrated(V1_SAVE);
+
+        assert_eq!(world.resource::<SaveHeader>().version, CURRENT_SAVE_VERSION);
+
+        let (company_id, company) = world.query::<(&PersistentId, &Company)>().single(&world);
+        assert_eq!(company.distressed_quarters, 0);
+        assert!(company.dividend_policy.is_none());
+        assert!(company.history[0].economic_events.is_empty());
+
+        let order = world
+            .resource::<OrderBook>()
+            .asks(*company_id)
+            .next()
+            .unwrap();
+        assert_eq!(order.order_type, OrderType::Limit);
+        assert_eq!(order.expiry, OrderExpiry::Timed);
+
+        let history = world.query::<&CompleteShareOrderHistory>().single(&world);
+        assert_eq!(history.orders()[0].quarter, 3);
+        assert!(history.dividends().is_empty());
+    }
+
+    #[test]
+    fn test_load_migrated_v2_fixture() {
+        let mut world = load_migrated(V2_SAVE);
+
+        assert_eq!(world.resource::<SaveHeader>().version, CURRENT_SAVE_VERSION);
+
+        let fact_db = &world.query::<&EntityFactDatabase>().single(&world).0;
+        assert_eq!(fact_db.get_str("Mood"), Some("happy"));
+        assert_eq!(fact_db.get("Hunger"), 3.);
+        assert_eq!(fact_db.expires_in("Mood"), None);
+    }
+}
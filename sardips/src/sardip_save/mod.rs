@@ -0,0 +1,839 @@
+pub mod blob;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod file;
+pub mod migration;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod slot;
+pub mod storage;
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test_util;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod transfer;
+
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use bevy::prelude::*;
+use bevy_http_client::prelude::{HttpTypedRequestTrait, TypedResponse};
+use sardips_core::{persistent_id::PersistentIdGenerator, GameState};
+use serde::{Deserialize, Serialize};
+use shared_deps::moonshine_save::{prelude::*, stream_from_resource, GetStream};
+
+#[cfg(target_arch = "wasm32")]
+use self::blob::{decode_save_blob, encode_save_blob};
+
+#[cfg(target_arch = "wasm32")]
+use bevy_http_client::{
+    prelude::{TypedRequest, TypedResponseError},
+    HttpClient, HttpResponse, HttpResponseError,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use self::file::{read_verified, SaveFiles};
+use self::migration::migrate_save;
+#[cfg(not(target_arch = "wasm32"))]
+use self::slot::{ActiveSaveSlot, SaveSlots};
+#[cfg(not(target_arch = "wasm32"))]
+use self::storage::{
+    HttpStorage, RemoteSave, ResolveSaveConflict, SaveConflict, SaveStorageError, StorageTask,
+    StoredSave,
+};
+#[cfg(target_arch = "wasm32")]
+use self::storage::{LoadResponse, SendSaveRequest};
+use self::storage::{SaveServerConfig, DEFAULT_SAVE_NAME};
+#[cfg(not(target_arch = "wasm32"))]
+use self::transfer::SaveTransferPlugin;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    clock::Clock,
+    money::Wallet,
+    pet::{breeding::Egg, Pet},
+    player::Player,
+};
+use crate::{
+    simulation::SavedSimTime,
+    stock_market::{BuySellOrchestrator, MacroEconomy, OrderBook, QuarterManger},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::scene::serde::SceneDeserializer;
+#[cfg(not(target_arch = "wasm32"))]
+use serde::de::DeserializeSeed;
+#[cfg(not(target_arch = "wasm32"))]
+use shared_deps::{
+    chrono::{DateTime, Utc},
+    ron,
+};
+
+pub struct SardipSavePlugin;
+
+impl Plugin for SardipSavePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_state(SardipLoadingState::default());
+
+        app.register_type::<SaveHeader>()
+            .init_resource::<SaveHeader>()
+            .insert_resource(SaveData::default());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_plugins(SaveTransferPlugin)
+            .add_event::<ResolveSaveConflict>()
+            .insert_resource(SaveFiles::new(SAVE_PATH, BACKUP_DIR))
+            .insert_resource(SaveSlots::new(SAVE_SLOT_DIR))
+            .add_systems(Startup, import_legacy_save)
+            .add_systems(
+                PostUpdate,
+                (
+                    (
+                        write_save_file,
+                        update_slot_meta.run_if(resource_exists::<ActiveSaveSlot>),
+                    )
+                        .run_if(resource_removed::<SaveRequest>()),
+                    sync_remote_save.run_if(
+                        resource_exists::<RemoteSave>
+                            .and_then(not(resource_exists::<SaveConflict>)),
+                    ),
+                    // After the autosave so the remote copy isn't written over
+                    resolve_save_conflict.run_if(
+                        resource_exists::<RemoteSave>.and_then(resource_exists::<SaveConflict>),
+                    ),
+                )
+                    .chain(),
+            );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(config) = SaveServerConfig::from_env() {
+            info!("Syncing saves with {}", config.base_url);
+            app.insert_resource(config);
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        app.init_resource::<SaveServerConfig>();
+
+        app.add_systems(
+            PreUpdate,
+            save_default()
+                .include_resource::<SaveHeader>()
+                .include_resource::<OrderBook>()
+                .include_resource::<QuarterManger>()
+                .include_resource::<MacroEconomy>()
+                .include_resource::<BuySellOrchestrator>()
+                .include_resource::<PersistentIdGenerator>()
+                .include_resource::<SavedSimTime>()
+                .into(stream_from_resource::<SaveRequest>()),
+        );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(OnEnter(SardipLoadingState::Loading), begin_load)
+            .add_systems(Update, trigger_load.run_if(resource_exists::<PendingLoad>));
+
+        #[cfg(target_arch = "wasm32")]
+        app.add_systems(
+            OnEnter(SardipLoadingState::Loading),
+            trigger_load_save_remote,
+        )
+        .add_systems(
+            Update,
+            handle_load_save_response.run_if(in_state(SardipLoadingState::Loading)),
+        );
+
+        app.add_systems(PreUpdate, load(stream_from_resource::<LoadFromStream>()));
+        app.add_systems(
+            Update,
+            post_load.run_if(
+                in_state(SardipLoadingState::Loading)
+                    .and_then(resource_removed::<LoadFromStream>()),
+            ),
+        );
+
+        app.add_systems(Update, trigger_save.run_if(in_state(GameState::ViewScreen)))
+            .add_systems(Update, handle_saved_response);
+
+        #[cfg(target_arch = "wasm32")]
+        app.add_systems(
+            PostUpdate,
+            send_save_data.run_if(resource_removed::<SaveRequest>()),
+        );
+
+        app.register_request_type::<SendSaveResponse>();
+        #[cfg(target_arch = "wasm32")]
+        app.register_request_type::<LoadResponse>();
+    }
+}
+
+#[derive(Resource)]
+struct SaveRequest {
+    pub buffer: WriteBuffer,
+}
+
+impl GetStream for SaveRequest {
+    type Stream = WriteBuffer;
+
+    fn stream(&self) -> Self::Stream {
+        self.buffer.clone()
+    }
+}
+
+#[derive(Clone, Default)]
+struct WriteBuffer {
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Write for WriteBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Resource, Default)]
+struct SaveData {
+    pub buffer: WriteBuffer,
+}
+
+struct SaveTimer {
+    timer: Timer,
+}
+
+impl Default for SaveTimer {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(5., TimerMode::Repeating),
+        }
+    }
+}
+
+fn trigger_save(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut save_timer: Local<SaveTimer>,
+    mut save_data: ResMut<SaveData>,
+) {
+    if save_timer.timer.tick(time.delta()).just_finished() {
+        save_data.buffer = WriteBuffer::default();
+        commands.insert_resource(SaveRequest {
+            buffer: save_data.buffer.clone(),
+        });
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_save_file(
+    clock: Res<Clock>,
+    files: Res<SaveFiles>,
+    save_data: Res<SaveData>,
+    mut last_backup: Local<Option<DateTime<Utc>>>,
+) {
+    let buffer = save_data.buffer.buffer.lock().unwrap();
+    if buffer.is_empty() {
+        return;
+    }
+
+    let save = String::from_utf8_lossy(&buffer);
+    if let Err(err) = files.write(&save) {
+        error!("Failed to write save {}: {}", files.path.display(), err);
+        return;
+    }
+
+    let now = clock.now();
+    let backup_due = match *last_backup {
+        Some(last) => (now - last).to_std().unwrap_or_default() >= files.backup_interval,
+        None => true,
+    };
+    if backup_due {
+        match files.write_backup(&save, now) {
+            Ok(()) => *last_backup = Some(now),
+            Err(err) => error!("Failed to write save backup: {}", err),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn update_slot_meta(
+    clock: Res<Clock>,
+    mut active: ResMut<ActiveSaveSlot>,
+    pets_or_eggs: Query<(), Or<(With<Pet>, With<Egg>)>>,
+    wallet: Query<&Wallet, With<Player>>,
+) {
+    let meta = &mut active.0.meta;
+    meta.pet_count = pets_or_eggs.iter().count();
+    meta.last_played = clock.now();
+    if let Ok(wallet) = wallet.get_single() {
+        meta.money = wallet.balance;
+    }
+
+    if let Err(err) = active.0.write_meta() {
+        error!("Failed to write save slot {}: {}", active.0.id, err);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn import_legacy_save(clock: Res<Clock>, slots: Res<SaveSlots>) {
+    if !slots.list().is_empty() {
+        return;
+    }
+
+    let legacy = SaveFiles::new(SAVE_PATH, BACKUP_DIR);
+    if let Err(err) = slots.import_legacy(&legacy, default_player_name(1), clock.now()) {
+        error!("Unable to move {} into a save slot: {}", SAVE_PATH, err);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn default_player_name(number: usize) -> String {
+    format!("Player {}", number)
+}
+
+// Checks the save will deserialize before handing it to moonshine which
+// has no way to report a failure back
+#[cfg(not(target_arch = "wasm32"))]
+fn validate_save(save: &str, registry: &AppTypeRegistry) -> Result<(), String> {
+    let mut deserializer = ron::de::Deserializer::from_str(save).map_err(|e| e.to_string())?;
+    SceneDeserializer {
+        type_registry: &registry.read(),
+    }
+    .deserialize(&mut deserializer)
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type RemoteLoad = Result<Option<StoredSave>, SaveStorageError>;
+
+// Loading waits on the remote copy when there is one to compare against
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Resource)]
+struct PendingLoad {
+    remote: Option<StorageTask<RemoteLoad>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn begin_load(
+    mut commands: Commands,
+    config: Option<Res<SaveServerConfig>>,
+    active_slot: Option<Res<ActiveSaveSlot>>,
+    remote: Option<Res<RemoteSave>>,
+) {
+    let storage = match (config, remote) {
+        (Some(config), _) => {
+            let name = active_slot
+                .map(|slot| slot.0.id.clone())
+                .unwrap_or(DEFAULT_SAVE_NAME.to_string());
+            let remote = RemoteSave::new(Arc::new(HttpStorage::new(&config, name)));
+            let storage = remote.storage.clone();
+            commands.insert_resource(remote);
+            Some(storage)
+        }
+        (None, Some(remote)) => Some(remote.storage.clone()),
+        (None, None) => None,
+    };
+
+    let remote = storage.map(|storage| {
+        info!("Fetching remote save from {}", storage.describe());
+        StorageTask::spawn(move || storage.load())
+    });
+    commands.insert_resource(PendingLoad { remote });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+enum LoadCandidate {
+    File(std::path::PathBuf),
+    Remote(StoredSave),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl LoadCandidate {
+    fn describe(&self) -> String {
+        match self {
+            Self::File(path) => path.display().to_string(),
+            Self::Remote(save) => format!("remote save from {}", save.saved_at),
+        }
+    }
+
+    fn read(&self) -> Result<String, String> {
+        match self {
+            Self::File(path) => read_verified(path),
+            Self::Remote(save) => Ok(save.save.clone()),
+        }
+    }
+}
+
+// A remote copy newer than the local save wins, otherwise it is only a last resort
+#[cfg(not(target_arch = "wasm32"))]
+fn load_candidates(files: &SaveFiles, remote: Option<StoredSave>) -> Vec<LoadCandidate> {
+    let mut candidates = files
+        .load_candidates()
+        .into_iter()
+        .map(LoadCandidate::File)
+        .collect::<Vec<_>>();
+
+    if let Some(remote) = remote {
+        let local_saved_at = files.saved_at();
+        if local_saved_at.is_none_or(|local| remote.saved_at > local) {
+            if local_saved_at.is_some() {
+                warn!(
+                    "Remote save from {} is newer than the local save, using it",
+                    remote.saved_at
+                );
+            }
+            candidates.insert(0, LoadCandidate::Remote(remote));
+        } else {
+            candidates.push(LoadCandidate::Remote(remote));
+        }
+    }
+
+    candidates
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn trigger_load(
+    mut commands: Commands,
+    pending: Res<PendingLoad>,
+    files: Res<SaveFiles>,
+    registry: Res<AppTypeRegistry>,
+    remote_save: Option<ResMut<RemoteSave>>,
+    mut state: ResMut<NextState<SardipLoadingState>>,
+) {
+    let remote = match pending.remote.as_ref() {
+        Some(task) => match task.poll() {
+            Some(remote) => remote,
+            None => return,
+        },
+        None => Ok(None),
+    };
+    commands.remove_resource::<PendingLoad>();
+
+    let remote = match remote {
+        Ok(remote) => remote,
+        Err(err) => {
+            warn!("Unable to fetch remote save: {}", err);
+            None
+        }
+    };
+    if let Some(mut remote_save) = remote_save {
+        remote_save.base = remote.as_ref().map(|remote| remote.saved_at);
+    }
+
+    let candidates = load_candidates(&files, remote);
+    if candidates.is_empty() {
+        info!("No save to load starting fresh");
+        state.set(SardipLoadingState::Loaded);
+        return;
+    }
+
+    let mut failures = Vec::new();
+    for (i, candidate) in candidates.iter().enumerate() {
+        // Older saves are brought up to the current shape before moonshine sees them
+        let save = candidate
+            .read()
+            .and_then(|save| migrate_save(&save).map_err(|e| e.to_string()))
+            .and_then(|save| validate_save(&save, &registry).map(|_| save));
+
+        match save {
+            Ok(save) => {
+                if i > 0 {
+                    warn!("Recovered save from {}", candidate.describe());
+                }
+                commands.insert_resource(LoadFromStream {
+                    buffer: ReadBuffer {
+                        buffer: Arc::new(Mutex::new(save.into_bytes())),
+                    },
+                });
+                return;
+            }
+            Err(err) => {
+                warn!("Unable to load {}: {}", candidate.describe(), err);
+                failures.push(format!("{}: {}", candidate.describe(), err));
+            }
+        }
+    }
+
+    let reason = failures.join("\n");
+    error!("No valid save found\n{}", reason);
+
+    // Keep the broken save around instead of letting the next autosave replace it
+    if files.path.exists() {
+        let mut corrupt = files.path.as_os_str().to_owned();
+        corrupt.push(".corrupt");
+        if let Err(err) = std::fs::rename(&files.path, &corrupt) {
+            error!("Unable to move aside corrupt save: {}", err);
+        }
+    }
+
+    commands.insert_resource(SaveLoadFailure { reason });
+    state.set(SardipLoadingState::Failed);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type RemoteUpload = Result<DateTime<Utc>, SaveStorageError>;
+
+// Mirrors the latest local save every upload_interval
+#[cfg(not(target_arch = "wasm32"))]
+fn sync_remote_save(
+    mut commands: Commands,
+    clock: Res<Clock>,
+    files: Res<SaveFiles>,
+    mut remote: ResMut<RemoteSave>,
+    mut upload: Local<Option<StorageTask<RemoteUpload>>>,
+    mut last_upload: Local<Option<DateTime<Utc>>>,
+) {
+    if let Some(task) = upload.as_ref() {
+        let Some(result) = task.poll() else {
+            return;
+        };
+        *upload = None;
+
+        match result {
+            Ok(saved_at) => remote.base = Some(saved_at),
+            Err(SaveStorageError::Conflict { remote_saved_at }) => {
+                warn!(
+                    "Not uploading save, {} has a newer copy from {}",
+                    remote.storage.describe(),
+                    remote_saved_at
+                );
+                commands.insert_resource(SaveConflict { remote_saved_at });
+            }
+            Err(err) => warn!("Unable to upload save: {}", err),
+        }
+        return;
+    }
+
+    // Skip the save that was just loaded, the remote already has it or is newer
+    let now = clock.now();
+    let Some(last) = *last_upload else {
+        *last_upload = Some(now);
+        return;
+    };
+    if (now - last).to_std().unwrap_or_default() < remote.upload_interval {
+        return;
+    }
+    *last_upload = Some(now);
+
+    let Ok(save) = read_verified(&files.path) else {
+        return;
+    };
+    let save = StoredSave {
+        save,
+        saved_at: files.saved_at().unwrap_or(now),
+    };
+    let storage = remote.storage.clone();
+    let base = remote.base;
+    *upload = Some(StorageTask::spawn(move || storage.store(&save, base)));
+}
+
+#[allow(clippy::too_many_arguments)]
+#[cfg(not(target_arch = "wasm32"))]
+fn resolve_save_conflict(
+    mut commands: Commands,
+    mut events: EventReader<ResolveSaveConflict>,
+    clock: Res<Clock>,
+    conflict: Res<SaveConflict>,
+    files: Res<SaveFiles>,
+    mut remote: ResMut<RemoteSave>,
+    mut download: Local<Option<StorageTask<RemoteLoad>>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if let Some(task) = download.as_ref() {
+        let Some(result) = task.poll() else {
+            return;
+        };
+        *download = None;
+
+        match result {
+            Ok(Some(stored)) => {
+                // The local save is about to be replaced so keep it as a backup
+                if let Ok(local) = read_verified(&files.path) {
+                    if let Err(err) = files.write_backup(&local, clock.now()) {
+                        error!("Failed to back up local save: {}", err);
+                        return;
+                    }
+                }
+                if let Err(err) = files.write(&stored.save) {
+                    error!("Failed to write remote save: {}", err);
+                    return;
+                }
+
+                info!("Took remote save from {}", stored.saved_at);
+                remote.base = Some(stored.saved_at);
+                // Drop any autosave of the old game still in flight
+                commands.remove_resource::<SaveRequest>();
+                commands.remove_resource::<SaveConflict>();
+                game_state.set(GameState::LoadViewScreen);
+            }
+            // Gone since the conflict so there is nothing to clash with
+            Ok(None) => {
+                remote.base = None;
+                commands.remove_resource::<SaveConflict>();
+            }
+            Err(err) => warn!("Unable to fetch remote save: {}", err),
+        }
+        return;
+    }
+
+    let Some(resolution) = events.read().last().copied() else {
+        return;
+    };
+    match resolution {
+        ResolveSaveConflict::KeepLocal => {
+            info!(
+                "Keeping local save over remote from {}",
+                conflict.remote_saved_at
+            );
+            remote.base = Some(conflict.remote_saved_at);
+            commands.remove_resource::<SaveConflict>();
+        }
+        ResolveSaveConflict::TakeRemote => {
+            let storage = remote.storage.clone();
+            *download = Some(StorageTask::spawn(move || storage.load()));
+        }
+    }
+}
+
+fn post_load(mut state: ResMut<NextState<SardipLoadingState>>) {
+    state.set(SardipLoadingState::Loaded);
+}
+
+#[derive(Debug, States, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SardipLoadingState {
+    #[default]
+    None,
+    Loading,
+    Loaded,
+    Failed,
+}
+
+const SAVE_PATH: &str = "sardip_save.ron";
+#[cfg(not(target_arch = "wasm32"))]
+const BACKUP_DIR: &str = "sardip_backups";
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_SLOT_DIR: &str = "sardip_saves";
+
+// Why the last load ended in SardipLoadingState::Failed
+#[derive(Resource, Debug, Clone)]
+pub struct SaveLoadFailure {
+    pub reason: String,
+}
+
+pub const CURRENT_SAVE_VERSION: u32 = 3;
+
+#[derive(Resource, Debug, Deserialize, Serialize, Clone, Reflect)]
+#[reflect_value(Deserialize, Serialize, Resource)]
+pub struct SaveHeader {
+    pub version: u32,
+}
+
+impl Default for SaveHeader {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_SAVE_VERSION,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct SendSaveResponse {}
+
+#[cfg(target_arch = "wasm32")]
+fn send_save_data(
+    mut save_request: EventWriter<TypedRequest<SendSaveResponse>>,
+    config: Res<SaveServerConfig>,
+    save_data: Res<SaveData>,
+) {
+    let buffer = save_data.buffer.buffer.lock().unwrap();
+
+    if !buffer.is_empty() {
+        let body: SendSaveRequest = SendSaveRequest {
+            name: DEFAULT_SAVE_NAME.to_string(),
+            save_blob: encode_save_blob(&buffer),
+            ..default()
+        };
+
+        save_request.send(
+            HttpClient::new()
+                .post(config.save_url())
+                .json(&body)
+                .with_type::<SendSaveResponse>(),
+        );
+    }
+}
+
+fn handle_saved_response(mut events: ResMut<Events<TypedResponse<SendSaveResponse>>>) {
+    for _ in events.drain() {
+        println!("Saved on remote");
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn trigger_load_save_remote(
+    mut load_request: EventWriter<TypedRequest<LoadResponse>>,
+    config: Res<SaveServerConfig>,
+) {
+    info!("Loading save data from remote");
+    load_request.send(
+        HttpClient::new()
+            .get(config.save_url())
+            .with_type::<LoadResponse>(),
+    );
+}
+
+#[derive(Resource)]
+struct LoadFromStream {
+    pub buffer: ReadBuffer,
+}
+
+#[derive(Clone)]
+struct ReadBuffer {
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+impl std::io::Read for ReadBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut buffer = self.buffer.lock().unwrap();
+        if buffer.is_empty() {
+            Ok(0)
+        } else {
+            let len = std::cmp::min(buf.len(), buffer.len());
+            buf[..len].copy_from_slice(&buffer[..len]);
+            buffer.drain(..len);
+            Ok(len)
+        }
+    }
+}
+
+impl GetStream for LoadFromStream {
+    type Stream = ReadBuffer;
+
+    fn stream(&self) -> Self::Stream {
+        self.buffer.clone()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn handle_load_save_response(
+    mut commands: Commands,
+    mut events: ResMut<Events<TypedResponse<LoadResponse>>>,
+    mut state: ResMut<NextState<SardipLoadingState>>,
+) {
+    for event in events.drain() {
+        let response: LoadResponse = event.into_inner();
+        if response.save_blob.is_empty() {
+            info!("No save data found on remote");
+            state.set(SardipLoadingState::Loaded);
+            continue;
+        }
+
+        // post_load moves on once moonshine has taken the stream
+        match decode_save_blob(&response.save_blob)
+            .and_then(|save| migrate_save(&save).map_err(|e| e.to_string()))
+        {
+            Ok(save) => {
+                info!("Loaded save data from remote");
+                commands.insert_resource(LoadFromStream {
+                    buffer: ReadBuffer {
+                        buffer: Arc::new(Mutex::new(save.into_bytes())),
+                    },
+                });
+            }
+            Err(err) => {
+                error!("Unable to load remote save: {}", err);
+                commands.insert_resource(SaveLoadFailure { reason: err });
+                state.set(SardipLoadingState::Failed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::state::app::StatesPlugin;
+    use sardips_core::from_mins;
+
+    use super::{storage::MemoryStorage, test_util::test_files, *};
+
+    fn conflict_app(name: &str) -> (App, SaveFiles) {
+        let files = test_files(name);
+        files.write("local").unwrap();
+
+        let remote_saved_at = DateTime::UNIX_EPOCH + from_mins(10);
+        let storage = MemoryStorage::with_save(StoredSave {
+            save: "remote".to_string(),
+            saved_at: remote_saved_at,
+        });
+        let mut remote = RemoteSave::new(Arc::new(storage));
+        remote.base = Some(DateTime::UNIX_EPOCH);
+
+        let mut app = App::new();
+        app.add_plugins(StatesPlugin)
+            .init_state::<GameState>()
+            .add_event::<ResolveSaveConflict>()
+            .insert_resource(Clock::manual(DateTime::UNIX_EPOCH))
+            .insert_resource(files.clone())
+            .insert_resource(remote)
+            .insert_resource(SaveConflict { remote_saved_at })
+            .add_systems(
+                Update,
+                resolve_save_conflict.run_if(resource_exists::<SaveConflict>),
+            );
+
+        (app, files)
+    }
+
+    #[test]
+    fn test_keep_local_save() {
+        let (mut app, files) = conflict_app("keep_local_save");
+
+        // Nothing happens until the player picks
+        app.update();
+        assert!(app.world().contains_resource::<SaveConflict>());
+
+        app.world_mut().send_event(ResolveSaveConflict::KeepLocal);
+        app.update();
+
+        assert!(!app.world().contains_resource::<SaveConflict>());
+        assert_eq!(
+            app.world().resource::<RemoteSave>().base,
+            Some(DateTime::UNIX_EPOCH + from_mins(10))
+        );
+        assert_eq!(read_verified(&files.path), Ok("local".to_string()));
+
+        std::fs::remove_dir_all(files.path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_take_remote_save() {
+        let (mut app, files) = conflict_app("take_remote_save");
+
+        app.world_mut().send_event(ResolveSaveConflict::TakeRemote);
+        for _ in 0..100 {
+            app.update();
+            if !app.world().contains_resource::<SaveConflict>() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        app.update();
+
+        assert!(!app.world().contains_resource::<SaveConflict>());
+        assert_eq!(
+            app.world().resource::<RemoteSave>().base,
+            Some(DateTime::UNIX_EPOCH + from_mins(10))
+        );
+        assert_eq!(read_verified(&files.path), Ok("remote".to_string()));
+        // The local copy it replaced is kept
+        let backups = files.backups();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(read_verified(&backups[0]), Ok("local".to_string()));
+        assert_eq!(
+            *app.world().resource::<State<GameState>>().get(),
+            GameState::LoadViewScreen
+        );
+
+        std::fs::remove_dir_all(files.path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use sardips_core::money_core::Money;
+use serde::{Deserialize, Serialize};
+use shared_deps::{
+    chrono::{DateTime, Utc},
+    ron,
+};
+
+use super::file::{write_atomic, SaveFiles};
+
+const SLOT_SAVE: &str = "sardip_save.ron";
+const SLOT_BACKUPS: &str = "backups";
+const SLOT_META: &str = "slot.ron";
+const SLOT_PREFIX: &str = "slot_";
+
+// Shown in the main menu without having to load the whole save
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveSlotMeta {
+    pub player_name: String,
+    pub pet_count: usize,
+    pub last_played: DateTime<Utc>,
+    pub money: Money,
+}
+
+impl SaveSlotMeta {
+    pub fn new(player_name: impl ToString, now: DateTime<Utc>) -> Self {
+        Self {
+            player_name: player_name.to_string(),
+            pet_count: 0,
+            last_played: now,
+            money: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SaveSlot {
+    pub id: String,
+    pub dir: PathBuf,
+    pub meta: SaveSlotMeta,
+}
+
+impl SaveSlot {
+    pub fn files(&self) -> SaveFiles {
+        SaveFiles::new(self.dir.join(SLOT_SAVE), self.dir.join(SLOT_BACKUPS))
+    }
+
+    pub fn write_meta(&self) -> std::io::Result<()> {
+        let meta = ron::ser::to_string_pretty(&self.meta, ron::ser::PrettyConfig::default())
+            .map_err(std::io::Error::other)?;
+        write_atomic(&self.dir.join(SLOT_META), &meta)
+    }
+}
+
+// The slot the running game reads from and writes to
+#[derive(Resource, Debug, Clone)]
+pub struct ActiveSaveSlot(pub SaveSlot);
+
+#[derive(Resource, Debug, Clone)]
+pub struct SaveSlots {
+    pub root: PathBuf,
+}
+
+impl SaveSlots {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    // Most recently played first, slots with unreadable metadata are skipped
+    pub fn list(&self) -> Vec<SaveSlot> {
+        let Ok(entries) = fs::read_dir(&self.root) else {
+            return Vec::new();
+        };
+
+        let mut slots = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .filter_map(|dir| match read_slot(&dir) {
+                Ok(slot) => Some(slot),
+                Err(err) => {
+                    warn!("Skipping save slot {}: {}", dir.display(), err);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        slots.sort_by(|a, b| {
+            b.meta
+                .last_played
+                .cmp(&a.meta.last_played)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        slots
+    }
+
+    fn next_id(&self) -> String {
+        let taken = self
+            .list()
+            .into_iter()
+            .map(|slot| slot.id)
+            .collect::<Vec<_>>();
+        (1..)
+            .map(|i| format!("{}{}", SLOT_PREFIX, i))
+            .find(|id| !taken.contains(id) && !self.root.join(id).exists())
+            .unwrap()
+    }
+
+    pub fn create(
+        &self,
+        player_name: impl ToString,
+        now: DateTime<Utc>,
+    ) -> std::io::Result<SaveSlot> {
+        let id = self.next_id();
+        let dir = self.root.join(&id);
+        fs::create_dir_all(&dir)?;
+
+        let slot = SaveSlot {
+            id,
+            dir,
+            meta: SaveSlotMeta::new(player_name, now),
+        };
+        slot.write_meta()?;
+        Ok(slot)
+    }
+
+    // Backups are not copied, the copy starts its own history
+    pub fn copy(&self, source: &SaveSlot, player_name: impl ToString) -> std::io::Result<SaveSlot> {
+        let mut slot = self.create(player_name, source.meta.last_played)?;
+        slot.meta = SaveSlotMeta {
+            player_name: slot.meta.player_name,
+            ..source.meta.clone()
+        };
+        slot.write_meta()?;
+
+        let source_save = source.dir.join(SLOT_SAVE);
+        if source_save.exists() {
+            fs::copy(source_save, slot.dir.join(SLOT_SAVE))?;
+        }
+
+        Ok(slot)
+    }
+
+    pub fn delete(&self, slot: &SaveSlot) -> std::io::Result<()> {
+        // Never follow a slot outside of the save root
+        if slot.dir.parent() != Some(self.root.as_path()) {
+            return Err(std::io::Error::other(format!(
+                "{} is not a save slot",
+                slot.dir.display()
+            )));
+        }
+        fs::remove_dir_all(&slot.dir)
+    }
+
+    // Saves from before slots existed become the first slot, backups and all
+    pub fn import_legacy(
+        &self,
+        legacy: &SaveFiles,
+        player_name: impl ToString,
+        now: DateTime<Utc>,
+    ) -> std::io::Result<Option<SaveSlot>> {
+        if !legacy.path.exists() {
+            return Ok(None);
+        }
+
+        let slot = self.create(player_name, now)?;
+        let files = slot.files();
+        fs::rename(&legacy.path, &files.path)?;
+
+        let backups = legacy.backups();
+        if !backups.is_empty() {
+            fs::create_dir_all(&files.backup_dir)?;
+        }
+        // Renamed to the slot's prefix so they're still found as backups of its save
+        let legacy_prefix = legacy.backup_prefix();
+        for backup in backups {
+            let Some(name) = backup
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+            let suffix = name.strip_prefix(&legacy_prefix).unwrap_or(&name);
+            fs::rename(
+                &backup,
+                files
+                    .backup_dir
+                    .join(format!("{}{}", files.backup_prefix(), suffix)),
+            )?;
+        }
+        // Only goes if nothing else was kept in there
+        let _ = fs::remove_dir(&legacy.backup_dir);
+
+        info!("Moved {} into save slot {}", legacy.path.display(), slot.id);
+        Ok(Some(slot))
+    }
+}
+
+fn read_slot(dir: &Path) -> Result<SaveSlot, String> {
+    let id = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| "slot has no name".to_string())?;
+    let meta = fs::read_to_string(dir.join(SLOT_META)).map_err(|e| e.to_string())?;
+    let meta = ron::from_str(&meta).map_err(|e| e.to_string())?;
+
+    Ok(SaveSlot {
+        id,
+        dir: dir.to_path_buf(),
+        meta,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sardip_save::{file::read_verified, test_util::test_slots};
+    use sardips_core::from_mins;
+
+    #[test]
+    fn test_create_copy_delete() {
+        let slots = test_slots("slots_create_copy_delete");
+        let start = DateTime::UNIX_EPOCH;
+
+        let first = slots.create("Alice", start).unwrap();
+        let mut second = slots.create("Bob", start + from_mins(5)).unwrap();
+        assert_ne!(first.id, second.id);
+
+        second.meta.pet_count = 3;
+        second.meta.money = 1200;
+        second.write_meta().unwrap();
+        second.files().write("bob's tank").unwrap();
+
+        // Most recently played first
+        let listed = slots.list();
+        assert_eq!(listed, vec![second.clone(), first.clone()]);
+
+        let copy = slots.copy(&second, "Carol").unwrap();
+        assert_eq!(copy.meta.player_name, "Carol");
+        assert_eq!(copy.meta.pet_count, 3);
+        assert_eq!(copy.meta.money, 1200);
+        assert_eq!(
+            fs::read_to_string(copy.files().path).unwrap(),
+            fs::read_to_string(second.files().path).unwrap()
+        );
+
+        slots.delete(&second).unwrap();
+        let ids = slots.list().into_iter().map(|s| s.id).collect::<Vec<_>>();
+        assert_eq!(ids.len(), 2);
+        assert!(!ids.contains(&second.id));
+
+        // Deleted ids are reused
+        let third = slots.create("Dave", start).unwrap();
+        assert_eq!(third.id, second.id);
+
+        fs::remove_dir_all(&slots.root).unwrap();
+    }
+
+    #[test]
+    fn test_import_legacy() {
+        let slots = test_slots("slots_import_legacy");
+        fs::create_dir_all(&slots.root).unwrap();
+        let legacy = SaveFiles::new(
+            slots.root.join("legacy_save.ron"),
+            slots.root.join("legacy_backups"),
+        );
+
+        assert_eq!(
+            slots
+                .import_legacy(&legacy, "Alice", DateTime::UNIX_EPOCH)
+                .unwrap(),
+            None
+        );
+
+        fs::write(&legacy.path, "old save").unwrap();
+        for i in 0..2 {
+            legacy
+                .write_backup(
+                    &format!("old backup {}", i),
+                    DateTime::UNIX_EPOCH + from_mins(i * 10),
+                )
+                .unwrap();
+        }
+        let slot = slots
+            .import_legacy(&legacy, "Alice", DateTime::UNIX_EPOCH)
+            .unwrap()
+            .unwrap();
+        assert!(!legacy.path.exists());
+        assert!(!legacy.backup_dir.exists());
+        assert_eq!(fs::read_to_string(slot.files().path).unwrap(), "old save");
+
+        let backups = slot
+            .files()
+            .backups()
+            .iter()
+            .map(|backup| read_verified(backup).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(backups, vec!["old backup 1", "old backup 0"]);
+        assert_eq!(slots.list(), vec![slot]);
+
+        fs::remove_dir_all(&slots.root).unwrap();
+    }
+}
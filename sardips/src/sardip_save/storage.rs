@@ -0,0 +1,536 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use sardips_core::from_mins;
+use serde::{Deserialize, Serialize};
+use shared_deps::chrono::{DateTime, Utc};
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::blob::{decode_save_blob, encode_save_blob};
+#[cfg(not(target_arch = "wasm32"))]
+use super::file::{read_verified, write_atomic, SaveFiles};
+
+pub const SAVE_ENDPOINT: &str = "/api/user/sardips/save";
+pub const DEFAULT_SAVE_NAME: &str = "sardips_save";
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_URL_ENV: &str = "SARDIPS_SAVE_URL";
+
+// Where the save server lives, the web build talks to the host it was served from
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct SaveServerConfig {
+    pub base_url: String,
+}
+
+impl SaveServerConfig {
+    pub fn new(base_url: impl ToString) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+        }
+    }
+
+    // Desktop builds only sync when pointed at a server
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_env() -> Option<Self> {
+        std::env::var(SAVE_URL_ENV)
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .map(Self::new)
+    }
+
+    pub fn save_url(&self) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), SAVE_ENDPOINT)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SendSaveRequest {
+    pub name: String,
+    pub save_blob: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_at: Option<DateTime<Utc>>,
+    // The server copy this save was based on, used to spot another device having saved since
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_saved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct LoadResponse {
+    pub save_blob: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SaveConflictResponse {
+    pub saved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredSave {
+    pub save: String,
+    pub saved_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveStorageError {
+    Io(String),
+    Http { status: u16, message: String },
+    Invalid(String),
+    // Something else saved after the copy this game is based on
+    Conflict { remote_saved_at: DateTime<Utc> },
+}
+
+impl fmt::Display for SaveStorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Http { status, message } => write!(f, "server replied {} {}", status, message),
+            Self::Invalid(err) => write!(f, "stored save is invalid: {}", err),
+            Self::Conflict { remote_saved_at } => {
+                write!(
+                    f,
+                    "stored save is newer ({}) than this one",
+                    remote_saved_at
+                )
+            }
+        }
+    }
+}
+
+pub trait SaveStorage: Send + Sync {
+    fn describe(&self) -> String;
+
+    fn load(&self) -> Result<Option<StoredSave>, SaveStorageError>;
+
+    // `base` is the stored copy this save descends from, None if there wasn't one.
+    // Returns the time the storage recorded for the new copy
+    fn store(
+        &self,
+        save: &StoredSave,
+        base: Option<DateTime<Utc>>,
+    ) -> Result<DateTime<Utc>, SaveStorageError>;
+}
+
+pub fn check_conflict(
+    current: Option<DateTime<Utc>>,
+    base: Option<DateTime<Utc>>,
+) -> Result<(), SaveStorageError> {
+    match (current, base) {
+        (Some(current), Some(base)) if current <= base => Ok(()),
+        (Some(current), _) => Err(SaveStorageError::Conflict {
+            remote_saved_at: current,
+        }),
+        (None, _) => Ok(()),
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryStorage {
+    save: Mutex<Option<StoredSave>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_save(save: StoredSave) -> Self {
+        Self {
+            save: Mutex::new(Some(save)),
+        }
+    }
+}
+
+impl SaveStorage for MemoryStorage {
+    fn describe(&self) -> String {
+        "memory".to_string()
+    }
+
+    fn load(&self) -> Result<Option<StoredSave>, SaveStorageError> {
+        Ok(self.save.lock().unwrap().clone())
+    }
+
+    fn store(
+        &self,
+        save: &StoredSave,
+        base: Option<DateTime<Utc>>,
+    ) -> Result<DateTime<Utc>, SaveStorageError> {
+        let mut stored = self.save.lock().unwrap();
+        check_conflict(stored.as_ref().map(|stored| stored.saved_at), base)?;
+        *stored = Some(save.clone());
+        Ok(save.saved_at)
+    }
+}
+
+// A save file somewhere else on disk, like a synced folder
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileStorage {
+    pub files: SaveFiles,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+    pub fn new(files: SaveFiles) -> Self {
+        Self { files }
+    }
+
+    fn saved_at_path(&self) -> std::path::PathBuf {
+        let mut path = self.files.path.as_os_str().to_owned();
+        path.push(".saved_at");
+        path.into()
+    }
+
+    // The saved_at of the stored copy, file mtimes are too coarse on some
+    // file systems and get mangled by sync tools so they're only a fallback
+    fn saved_at(&self) -> Option<DateTime<Utc>> {
+        std::fs::read_to_string(self.saved_at_path())
+            .ok()
+            .and_then(|saved_at| DateTime::parse_from_rfc3339(saved_at.trim()).ok())
+            .map(|saved_at| saved_at.with_timezone(&Utc))
+            .or_else(|| self.files.saved_at())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SaveStorage for FileStorage {
+    fn describe(&self) -> String {
+        self.files.path.display().to_string()
+    }
+
+    fn load(&self) -> Result<Option<StoredSave>, SaveStorageError> {
+        if !self.files.path.exists() {
+            return Ok(None);
+        }
+        let saved_at = self.saved_at().unwrap_or(DateTime::UNIX_EPOCH);
+
+        let save = read_verified(&self.files.path).map_err(SaveStorageError::Invalid)?;
+        Ok(Some(StoredSave { save, saved_at }))
+    }
+
+    fn store(
+        &self,
+        save: &StoredSave,
+        base: Option<DateTime<Utc>>,
+    ) -> Result<DateTime<Utc>, SaveStorageError> {
+        check_conflict(self.saved_at(), base)?;
+        if let Some(parent) = self.files.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| SaveStorageError::Io(e.to_string()))?;
+        }
+        self.files
+            .write(&save.save)
+            .map_err(|e| SaveStorageError::Io(e.to_string()))?;
+        write_atomic(&self.saved_at_path(), &save.saved_at.to_rfc3339())
+            .map_err(|e| SaveStorageError::Io(e.to_string()))?;
+
+        Ok(save.saved_at)
+    }
+}
+
+// Blocking, run it off the main thread
+#[cfg(not(target_arch = "wasm32"))]
+pub struct HttpStorage {
+    pub url: String,
+    pub name: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HttpStorage {
+    pub fn new(config: &SaveServerConfig, name: impl ToString) -> Self {
+        Self {
+            url: config.save_url(),
+            name: name.to_string(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn http_error(response: &ehttp::Response) -> SaveStorageError {
+    SaveStorageError::Http {
+        status: response.status,
+        message: response.text().unwrap_or(&response.status_text).to_string(),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SaveStorage for HttpStorage {
+    fn describe(&self) -> String {
+        format!("{} ({})", self.url, self.name)
+    }
+
+    fn load(&self) -> Result<Option<StoredSave>, SaveStorageError> {
+        let request = ehttp::Request::get(format!("{}?name={}", self.url, self.name));
+        let response = ehttp::fetch_blocking(&request).map_err(SaveStorageError::Io)?;
+        if response.status == 404 {
+            return Ok(None);
+        }
+        if !response.ok {
+            return Err(http_error(&response));
+        }
+
+        let body: LoadResponse = response
+            .json()
+            .map_err(|e| SaveStorageError::Invalid(e.to_string()))?;
+        if body.save_blob.is_empty() {
+            return Ok(None);
+        }
+
+        let save = decode_save_blob(&body.save_blob).map_err(SaveStorageError::Invalid)?;
+        Ok(Some(StoredSave {
+            save,
+            // Servers that predate sync never win against a local save
+            saved_at: body.saved_at.unwrap_or(DateTime::UNIX_EPOCH),
+        }))
+    }
+
+    fn store(
+        &self,
+        save: &StoredSave,
+        base: Option<DateTime<Utc>>,
+    ) -> Result<DateTime<Utc>, SaveStorageError> {
+        let body = SendSaveRequest {
+            name: self.name.clone(),
+            save_blob: encode_save_blob(save.save.as_bytes()),
+            saved_at: Some(save.saved_at),
+            base_saved_at: base,
+        };
+        let request = ehttp::Request::json(&self.url, &body)
+            .map_err(|e| SaveStorageError::Invalid(e.to_string()))?;
+        let response = ehttp::fetch_blocking(&request).map_err(SaveStorageError::Io)?;
+
+        if response.status == 409 {
+            let conflict: SaveConflictResponse = response.json().unwrap_or_default();
+            return Err(SaveStorageError::Conflict {
+                remote_saved_at: conflict.saved_at.unwrap_or(save.saved_at),
+            });
+        }
+        if !response.ok {
+            return Err(http_error(&response));
+        }
+
+        let stored: SaveConflictResponse = response.json().unwrap_or_default();
+        Ok(stored.saved_at.unwrap_or(save.saved_at))
+    }
+}
+
+// Bevy is built without multi_threaded so blocking storage calls get their own thread
+#[cfg(not(target_arch = "wasm32"))]
+pub struct StorageTask<T>(Arc<Mutex<Option<T>>>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + 'static> StorageTask<T> {
+    pub fn spawn(work: impl FnOnce() -> T + Send + 'static) -> Self {
+        let result = Arc::new(Mutex::new(None));
+        let output = result.clone();
+        std::thread::spawn(move || {
+            *output.lock().unwrap() = Some(work());
+        });
+        Self(result)
+    }
+
+    pub fn poll(&self) -> Option<T> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+// Mirrors the local save somewhere else
+#[derive(Resource, Clone)]
+pub struct RemoteSave {
+    pub storage: Arc<dyn SaveStorage>,
+    pub upload_interval: Duration,
+    // Time of the stored copy this game last loaded or uploaded
+    pub base: Option<DateTime<Utc>>,
+}
+
+impl RemoteSave {
+    pub fn new(storage: Arc<dyn SaveStorage>) -> Self {
+        Self {
+            storage,
+            upload_interval: from_mins(1),
+            base: None,
+        }
+    }
+}
+
+// Uploads stop until the player picks which copy to keep
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct SaveConflict {
+    pub remote_saved_at: DateTime<Utc>,
+}
+
+// The player's answer to a SaveConflict
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveSaveConflict {
+    // Overwrite the remote copy on the next upload
+    KeepLocal,
+    // Replace the local save with the remote copy and reload it
+    TakeRemote,
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::sardip_save::test_util::test_files;
+
+    // Just enough of the save server to exercise HttpStorage
+    struct MockSaveServer {
+        config: SaveServerConfig,
+    }
+
+    impl MockSaveServer {
+        fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let config =
+                SaveServerConfig::new(format!("http://{}", listener.local_addr().unwrap()));
+
+            std::thread::spawn(move || {
+                let mut stored: Option<SendSaveRequest> = None;
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    handle_request(stream, &mut stored);
+                }
+            });
+
+            Self { config }
+        }
+    }
+
+    fn handle_request(mut stream: TcpStream, stored: &mut Option<SendSaveRequest>) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        let (status, response) = if request_line.starts_with("GET") {
+            match stored {
+                Some(save) => (
+                    "200 OK",
+                    serde_json::to_string(&LoadResponse {
+                        save_blob: save.save_blob.clone(),
+                        saved_at: save.saved_at,
+                    })
+                    .unwrap(),
+                ),
+                None => ("404 Not Found", String::new()),
+            }
+        } else {
+            let upload: SendSaveRequest = serde_json::from_slice(&body).unwrap();
+            let current = stored.as_ref().and_then(|save| save.saved_at);
+            match check_conflict(current, upload.base_saved_at) {
+                Ok(()) => {
+                    let response = SaveConflictResponse {
+                        saved_at: upload.saved_at,
+                    };
+                    *stored = Some(upload);
+                    ("200 OK", serde_json::to_string(&response).unwrap())
+                }
+                Err(_) => (
+                    "409 Conflict",
+                    serde_json::to_string(&SaveConflictResponse { saved_at: current }).unwrap(),
+                ),
+            }
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            response.len(),
+            response
+        )
+        .unwrap();
+    }
+
+    fn save_at(save: &str, minutes: u64) -> StoredSave {
+        StoredSave {
+            save: save.to_string(),
+            saved_at: DateTime::UNIX_EPOCH + from_mins(minutes),
+        }
+    }
+
+    // Two devices sharing one storage, the second one has to notice the first
+    fn check_storage(storage: &dyn SaveStorage) {
+        assert_eq!(storage.load(), Ok(None));
+
+        let first = storage.store(&save_at("desktop", 1), None).unwrap();
+        assert_eq!(storage.load().unwrap().unwrap().save, "desktop");
+
+        let second = storage
+            .store(&save_at("desktop 2", 2), Some(first))
+            .unwrap();
+
+        // Laptop last saw the first copy
+        assert_eq!(
+            storage.store(&save_at("laptop", 3), Some(first)),
+            Err(SaveStorageError::Conflict {
+                remote_saved_at: second
+            })
+        );
+        assert_eq!(
+            storage.store(&save_at("laptop", 3), None),
+            Err(SaveStorageError::Conflict {
+                remote_saved_at: second
+            })
+        );
+        assert_eq!(storage.load().unwrap().unwrap().save, "desktop 2");
+
+        storage.store(&save_at("laptop", 3), Some(second)).unwrap();
+        assert_eq!(storage.load().unwrap().unwrap().save, "laptop");
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        check_storage(&MemoryStorage::new());
+    }
+
+    #[test]
+    fn test_file_storage() {
+        let files = test_files("file_storage");
+        let dir = files.path.parent().unwrap().to_path_buf();
+
+        check_storage(&FileStorage::new(files));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_http_storage() {
+        let server = MockSaveServer::start();
+        check_storage(&HttpStorage::new(&server.config, DEFAULT_SAVE_NAME));
+    }
+
+    #[test]
+    fn test_save_url() {
+        assert_eq!(SaveServerConfig::default().save_url(), SAVE_ENDPOINT);
+        assert_eq!(
+            SaveServerConfig::new("http://localhost:8080/").save_url(),
+            "http://localhost:8080/api/user/sardips/save"
+        );
+    }
+}
@@ -0,0 +1,20 @@
+use std::{fs, path::PathBuf};
+
+use super::{file::SaveFiles, slot::SaveSlots};
+
+// Empty dir under the system temp dir, unique to the test name and process
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sardips_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn test_files(name: &str) -> SaveFiles {
+    let dir = test_dir(name);
+    SaveFiles::new(dir.join("sardip_save.ron"), dir.join("backups"))
+}
+
+pub fn test_slots(name: &str) -> SaveSlots {
+    SaveSlots::new(test_dir(name))
+}
@@ -0,0 +1,284 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use arboard::Clipboard;
+use bevy::prelude::*;
+use shared_deps::chrono::{DateTime, Utc};
+
+use super::{
+    blob::{decode_save_blob, encode_save_blob},
+    file::read_verified,
+    migration::migrate_save,
+    slot::{SaveSlot, SaveSlots},
+    validate_save,
+};
+use crate::clock::Clock;
+
+pub struct SaveTransferPlugin;
+
+impl Plugin for SaveTransferPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ExportSave>()
+            .add_event::<ImportSave>()
+            .add_event::<SaveTransferResult>()
+            .add_systems(Update, (export_save, import_save));
+    }
+}
+
+const EXPORT_DIR: &str = "sardip_exports";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveTransfer {
+    File(PathBuf),
+    Clipboard,
+}
+
+impl SaveTransfer {
+    pub fn export_file(slot: &SaveSlot) -> Self {
+        Self::File(PathBuf::from(EXPORT_DIR).join(format!("{}.txt", slot.id)))
+    }
+
+    // The last save exported to a file, for bringing a tank back without the clipboard
+    pub fn latest_export_file() -> Option<Self> {
+        latest_export_in(Path::new(EXPORT_DIR)).map(Self::File)
+    }
+}
+
+fn latest_export_in(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .max_by_key(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct ExportSave {
+    pub slot: SaveSlot,
+    pub to: SaveTransfer,
+}
+
+// Imports always go into a fresh slot so a bad blob can never touch an existing tank
+#[derive(Event, Debug, Clone)]
+pub struct ImportSave {
+    pub from: SaveTransfer,
+    pub player_name: String,
+}
+
+#[derive(Event, Debug, Clone)]
+pub enum SaveTransferResult {
+    Exported(SaveTransfer),
+    Imported(SaveSlot),
+    Failed(String),
+}
+
+pub fn export_blob(slot: &SaveSlot) -> Result<String, String> {
+    let save = read_verified(&slot.files().path)?;
+    Ok(encode_save_blob(save.as_bytes()))
+}
+
+// Nothing is written until the blob has been decoded, migrated and deserialized
+pub fn import_blob(
+    slots: &SaveSlots,
+    blob: &str,
+    player_name: &str,
+    now: DateTime<Utc>,
+    registry: &AppTypeRegistry,
+) -> Result<SaveSlot, String> {
+    let save = decode_save_blob(blob)?;
+    let save = migrate_save(&save).map_err(|e| e.to_string())?;
+    validate_save(&save, registry)?;
+
+    let slot = slots.create(player_name, now).map_err(|e| e.to_string())?;
+    if let Err(err) = slot.files().write(&save) {
+        let _ = slots.delete(&slot);
+        return Err(err.to_string());
+    }
+
+    Ok(slot)
+}
+
+fn clipboard(clipboard: &mut Option<Clipboard>) -> Result<&mut Clipboard, String> {
+    if clipboard.is_none() {
+        *clipboard = Some(Clipboard::new().map_err(|e| e.to_string())?);
+    }
+    Ok(clipboard.as_mut().unwrap())
+}
+
+// The clipboard is kept alive as some platforms drop the contents with it
+fn export_save(
+    mut events: EventReader<ExportSave>,
+    mut results: EventWriter<SaveTransferResult>,
+    mut held_clipboard: Local<Option<Clipboard>>,
+) {
+    for event in events.read() {
+        let exported = export_blob(&event.slot).and_then(|blob| match &event.to {
+            SaveTransfer::File(path) => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                fs::write(path, blob).map_err(|e| e.to_string())
+            }
+            SaveTransfer::Clipboard => clipboard(&mut held_clipboard)?
+                .set_text(blob)
+                .map_err(|e| e.to_string()),
+        });
+
+        match exported {
+            Ok(()) => {
+                info!("Exported save slot {} to {:?}", event.slot.id, event.to);
+                results.send(SaveTransferResult::Exported(event.to.clone()));
+            }
+            Err(err) => {
+                error!("Unable to export save slot {}: {}", event.slot.id, err);
+                results.send(SaveTransferResult::Failed(err));
+            }
+        }
+    }
+}
+
+fn import_save(
+    mut events: EventReader<ImportSave>,
+    mut results: EventWriter<SaveTransferResult>,
+    mut held_clipboard: Local<Option<Clipboard>>,
+    clock: Res<Clock>,
+    slots: Res<SaveSlots>,
+    registry: Res<AppTypeRegistry>,
+) {
+    for event in events.read() {
+        let imported = match &event.from {
+            SaveTransfer::File(path) => fs::read_to_string(path).map_err(|e| e.to_string()),
+            SaveTransfer::Clipboard => clipboard(&mut held_clipboard)
+                .and_then(|clipboard| clipboard.get_text().map_err(|e| e.to_string())),
+        }
+        .and_then(|blob| import_blob(&slots, &blob, &event.player_name, clock.now(), &registry));
+
+        match imported {
+            Ok(slot) => {
+                info!("Imported save into slot {}", slot.id);
+                results.send(SaveTransferResult::Imported(slot));
+            }
+            Err(err) => {
+                error!("Unable to import save: {}", err);
+                results.send(SaveTransferResult::Failed(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sardip_save::{
+        test_util::{test_dir, test_slots},
+        SaveHeader, CURRENT_SAVE_VERSION,
+    };
+
+    fn test_registry() -> AppTypeRegistry {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<SaveHeader>();
+        registry
+    }
+
+    #[test]
+    fn test_export_import() {
+        let slots = test_slots("transfer_export_import");
+        let registry = test_registry();
+        let now = DateTime::UNIX_EPOCH;
+
+        let save = format!(
+            "(resources: {{\"sardips::sardip_save::SaveHeader\": (version: {})}}, entities: {{}})",
+            CURRENT_SAVE_VERSION
+        );
+        let source = slots.create("Alice", now).unwrap();
+        source.files().write(&save).unwrap();
+
+        let blob = export_blob(&source).unwrap();
+        let imported = import_blob(&slots, &blob, "Bob", now, &registry).unwrap();
+        assert_ne!(imported.id, source.id);
+        assert_eq!(imported.meta.player_name, "Bob");
+        assert_eq!(
+            read_verified(&imported.files().path),
+            Ok(migrate_save(&save).unwrap())
+        );
+
+        fs::remove_dir_all(&slots.root).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_import_leaves_no_slot() {
+        let slots = test_slots("transfer_invalid_import");
+        let registry = test_registry();
+        let now = DateTime::UNIX_EPOCH;
+
+        assert!(import_blob(&slots, "garbage", "Bob", now, &registry).is_err());
+
+        let not_a_save = encode_save_blob(b"(resources: {\"Unknown\": ()}, entities: {})");
+        assert!(import_blob(&slots, &not_a_save, "Bob", now, &registry).is_err());
+
+        assert!(slots.list().is_empty());
+    }
+
+    #[test]
+    fn test_latest_export_in() {
+        let dir = test_dir("exports");
+        assert_eq!(latest_export_in(&dir.join("missing")), None);
+        assert_eq!(latest_export_in(&dir), None);
+
+        let older = dir.join("older.txt");
+        let newer = dir.join("newer.txt");
+        for (path, age) in [(&older, 60), (&newer, 0)] {
+            let file = fs::File::create(path).unwrap();
+            file.set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(age))
+                .unwrap();
+        }
+        fs::write(dir.join("notes.md"), "").unwrap();
+
+        assert_eq!(latest_export_in(&dir), Some(newer));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_from_file() {
+        let slots = test_slots("transfer_import_file");
+        let source = slots.create("Alice", DateTime::UNIX_EPOCH).unwrap();
+        let save = format!(
+            "(resources: {{\"sardips::sardip_save::SaveHeader\": (version: {})}}, entities: {{}})",
+            CURRENT_SAVE_VERSION
+        );
+        source.files().write(&save).unwrap();
+        let path = slots.root.join("export.txt");
+        fs::write(&path, export_blob(&source).unwrap()).unwrap();
+
+        let mut app = App::new();
+        app.insert_resource(Clock::manual(DateTime::UNIX_EPOCH));
+        app.insert_resource(slots.clone());
+        app.insert_resource(test_registry());
+        app.add_event::<ImportSave>();
+        app.add_event::<SaveTransferResult>();
+        app.add_systems(Update, import_save);
+
+        app.world_mut().send_event(ImportSave {
+            from: SaveTransfer::File(path),
+            player_name: "Bob".to_string(),
+        });
+        app.update();
+
+        let results = app
+            .world_mut()
+            .resource_mut::<Events<SaveTransferResult>>()
+            .drain()
+            .collect::<Vec<_>>();
+        let [SaveTransferResult::Imported(imported)] = results.as_slice() else {
+            panic!("Expected an import, got {:?}", results);
+        };
+        assert_eq!(imported.meta.player_name, "Bob");
+        assert_eq!(slots.list().len(), 2);
+
+        fs::remove_dir_all(&slots.root).unwrap();
+    }
+}
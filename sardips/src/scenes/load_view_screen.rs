@@ -5,7 +5,7 @@ use crate::pet::template::SpawnPetEvent;
 
 use crate::{
     pet::{breeding::Egg, Pet},
-    sardip_save::SardipLoadingState,
+    sardip_save::{SardipLoadingState, SaveLoadFailure},
 };
 use sardips_core::GameState;
 
@@ -32,9 +32,16 @@ impl Plugin for LoadViewScreenPlugin {
 
 fn setup_new_game(
     #[cfg(not(feature = "dev"))] mut spawn_pets: EventWriter<SpawnPetEvent>,
-    mut game_state: ResMut<NextState<GameState>>,
+    failure: Option<Res<SaveLoadFailure>>,
     mut loading_state: ResMut<NextState<SardipLoadingState>>,
 ) {
+    if let Some(failure) = failure {
+        warn!(
+            "Starting a new game, save could not be loaded:\n{}",
+            failure.reason
+        );
+    }
+
     #[cfg(not(feature = "dev"))]
     for _ in 0..2 {
         spawn_pets.send(SpawnPetEvent::Blank((
@@ -43,8 +50,8 @@ fn setup_new_game(
         )));
     }
 
-    game_state.set(GameState::ViewScreen);
-    loading_state.set(SardipLoadingState::None);
+    // Run the usual post load setup so the player and market exist
+    loading_state.set(SardipLoadingState::Loaded);
 }
 
 fn setup(mut loading_state: ResMut<NextState<SardipLoadingState>>) {
@@ -52,12 +59,17 @@ fn setup(mut loading_state: ResMut<NextState<SardipLoadingState>>) {
 }
 
 fn setup_complete(
+    mut commands: Commands,
+    failure: Option<Res<SaveLoadFailure>>,
     pets_or_eggs: Query<Entity, Or<(With<Pet>, With<Egg>)>>,
     mut game_state: ResMut<NextState<GameState>>,
     mut loading_state: ResMut<NextState<SardipLoadingState>>,
     mut spawn_pets: EventWriter<SpawnPetEvent>,
 ) {
-    if pets_or_eggs.is_empty() {
+    // A failed load has already sent its starting pets
+    if failure.is_some() {
+        commands.remove_resource::<SaveLoadFailure>();
+    } else if pets_or_eggs.is_empty() {
         spawn_pets.send(SpawnPetEvent::Blank((
             Vec2::new(0., 0.),
             "Blob".to_string(),
@@ -5,6 +5,18 @@ use shared_deps::bevy_parallax::{
 };
 
 use crate::palettes;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    clock::Clock,
+    money::money_display,
+    sardip_save::{
+        default_player_name,
+        slot::{ActiveSaveSlot, SaveSlot, SaveSlots},
+        transfer::{ExportSave, ImportSave, SaveTransfer, SaveTransferResult},
+    },
+};
+#[cfg(not(target_arch = "wasm32"))]
+use sardips_core::age_core::Age;
 use sardips_core::autoscroll::AutoScroll;
 use sardips_core::{
     assets::{self, FontAssets},
@@ -21,7 +33,26 @@ impl Plugin for MainMenuPlugin {
         app.add_systems(OnEnter(GameState::MainMenu), (setup_ui, setup_background));
         app.add_systems(OnExit(GameState::MainMenu), teardown);
 
+        #[cfg(target_arch = "wasm32")]
         app.add_systems(Update, (play_button).run_if(in_state(GameState::MainMenu)));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_event::<SlotsChanged>().add_systems(
+            Update,
+            (
+                new_slot_button,
+                play_slot_button,
+                copy_slot_button,
+                export_slot_button,
+                import_slot_button,
+                import_file_button,
+                imported_slot,
+                delete_slot_button,
+                rebuild_slot_list,
+            )
+                .chain()
+                .run_if(in_state(GameState::MainMenu)),
+        );
     }
 }
 
@@ -56,10 +87,91 @@ fn setup_background(mut commands: Commands, mut create_parallax: EventWriter<Cre
 #[derive(Component)]
 struct MainMenuUiItem;
 
+#[cfg(target_arch = "wasm32")]
 #[derive(Component)]
 struct PlayButton;
 
-fn setup_ui(mut commands: Commands, fonts: Res<FontAssets>) {
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Component)]
+struct SlotList;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Component)]
+struct NewSlotButton;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Component)]
+struct PlaySlotButton(SaveSlot);
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Component)]
+struct CopySlotButton(SaveSlot);
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Component)]
+struct ExportSlotButton(SaveSlot);
+
+// Reads a blob from the clipboard into a new slot
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Component)]
+struct ImportSlotButton;
+
+// Reads the newest blob in the exports folder into a new slot
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Component)]
+struct ImportFileButton;
+
+// Needs a second press before the slot is removed
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Component)]
+struct DeleteSlotButton {
+    slot: SaveSlot,
+    armed: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Event)]
+struct SlotsChanged;
+
+fn menu_button(width: f32, height: f32) -> (ButtonBundle, ButtonHover) {
+    (
+        ButtonBundle {
+            style: Style {
+                width: Val::Px(width),
+                height: Val::Px(height),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                margin: UiRect::all(Val::Px(5.)),
+                border: UiRect::all(Val::Px(5.)),
+                ..default()
+            },
+            ..default()
+        },
+        ButtonHover::default()
+            .with_background(palettes::ui::BUTTON_SET)
+            .with_border(palettes::ui::BUTTON_BORDER_SET),
+    )
+}
+
+fn button_text(fonts: &FontAssets, font_size: f32, text: KeyText) -> (TextBundle, KeyText) {
+    (
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: fonts.main_font.clone(),
+                font_size,
+                color: Color::BLACK,
+            },
+        ),
+        text,
+    )
+}
+
+fn setup_ui(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    #[cfg(not(target_arch = "wasm32"))] mut slots_changed: EventWriter<SlotsChanged>,
+) {
     commands
         .spawn((
             NodeBundle {
@@ -90,6 +202,63 @@ fn setup_ui(mut commands: Commands, fonts: Res<FontAssets>) {
                 Label,
             ));
 
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            margin: UiRect::top(Val::Px(40.)),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    SlotList,
+                ));
+
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent
+                            .spawn((menu_button(210., 60.), NewSlotButton))
+                            .with_children(|parent| {
+                                parent.spawn(button_text(
+                                    &fonts,
+                                    35.,
+                                    KeyText::new().with(0, text_keys::MAIN_MENU_NEW_SLOT),
+                                ));
+                            });
+
+                        parent
+                            .spawn((menu_button(210., 60.), ImportSlotButton))
+                            .with_children(|parent| {
+                                parent.spawn(button_text(
+                                    &fonts,
+                                    35.,
+                                    KeyText::new().with(0, text_keys::MAIN_MENU_SLOT_IMPORT),
+                                ));
+                            });
+
+                        parent
+                            .spawn((menu_button(210., 60.), ImportFileButton))
+                            .with_children(|parent| {
+                                parent.spawn(button_text(
+                                    &fonts,
+                                    35.,
+                                    KeyText::new().with(0, text_keys::MAIN_MENU_SLOT_IMPORT_FILE),
+                                ));
+                            });
+                    });
+            }
+
+            #[cfg(target_arch = "wasm32")]
             parent
                 .spawn((
                     ButtonBundle {
@@ -142,6 +311,9 @@ fn setup_ui(mut commands: Commands, fonts: Res<FontAssets>) {
         }),
         MainMenuUiItem,
     ));
+
+    #[cfg(not(target_arch = "wasm32"))]
+    slots_changed.send(SlotsChanged);
 }
 
 fn teardown(
@@ -160,6 +332,7 @@ fn teardown(
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 fn play_button(
     mut game_state: ResMut<NextState<GameState>>,
     button: Query<&Interaction, (Changed<Interaction>, With<PlayButton>)>,
@@ -170,3 +343,265 @@ fn play_button(
         game_state.set(GameState::LoadViewScreen);
     }
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+fn rebuild_slot_list(
+    mut commands: Commands,
+    mut events: EventReader<SlotsChanged>,
+    fonts: Res<FontAssets>,
+    clock: Res<Clock>,
+    slots: Res<SaveSlots>,
+    list: Query<Entity, With<SlotList>>,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+
+    let Ok(list) = list.get_single() else {
+        return;
+    };
+
+    let now = clock.now();
+    commands
+        .entity(list)
+        .despawn_descendants()
+        .with_children(|parent| {
+            for slot in slots.list() {
+                let since = (now - slot.meta.last_played).to_std().unwrap_or_default();
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            margin: UiRect::bottom(Val::Px(10.)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent
+                            .spawn((menu_button(440., 55.), PlaySlotButton(slot.clone())))
+                            .with_children(|parent| {
+                                parent.spawn(button_text(
+                                    &fonts,
+                                    25.,
+                                    KeyText::new().with_value(
+                                        0,
+                                        text_keys::MAIN_MENU_SLOT_SUMMARY,
+                                        &[
+                                            &slot.meta.player_name,
+                                            &slot.meta.pet_count.to_string(),
+                                            &money_display(slot.meta.money),
+                                            &Age(since).lived_for_text(),
+                                        ],
+                                    ),
+                                ));
+                            });
+
+                        parent
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::Row,
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent
+                                    .spawn((menu_button(135., 40.), CopySlotButton(slot.clone())))
+                                    .with_children(|parent| {
+                                        parent.spawn(button_text(
+                                            &fonts,
+                                            20.,
+                                            KeyText::new().with(0, text_keys::MAIN_MENU_SLOT_COPY),
+                                        ));
+                                    });
+
+                                parent
+                                    .spawn((menu_button(135., 40.), ExportSlotButton(slot.clone())))
+                                    .with_children(|parent| {
+                                        parent.spawn(button_text(
+                                            &fonts,
+                                            20.,
+                                            KeyText::new()
+                                                .with(0, text_keys::MAIN_MENU_SLOT_EXPORT),
+                                        ));
+                                    });
+
+                                parent
+                                    .spawn((
+                                        menu_button(135., 40.),
+                                        DeleteSlotButton {
+                                            slot: slot.clone(),
+                                            armed: false,
+                                        },
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn(button_text(
+                                            &fonts,
+                                            20.,
+                                            KeyText::new()
+                                                .with(0, text_keys::MAIN_MENU_SLOT_DELETE),
+                                        ));
+                                    });
+                            });
+                    });
+            }
+        });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn new_slot_button(
+    clock: Res<Clock>,
+    slots: Res<SaveSlots>,
+    mut slots_changed: EventWriter<SlotsChanged>,
+    button: Query<&Interaction, (Changed<Interaction>, With<NewSlotButton>)>,
+) {
+    if let Ok(Interaction::Pressed) = button.get_single() {
+        let name = default_player_name(slots.list().len() + 1);
+        match slots.create(name, clock.now()) {
+            Ok(_) => {
+                slots_changed.send(SlotsChanged);
+            }
+            Err(err) => error!("Unable to create save slot: {}", err),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn play_slot_button(
+    mut commands: Commands,
+    mut game_state: ResMut<NextState<GameState>>,
+    buttons: Query<(&Interaction, &PlaySlotButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        info!("Playing save slot {}", button.0.id);
+        commands.insert_resource(button.0.files());
+        commands.insert_resource(ActiveSaveSlot(button.0.clone()));
+        game_state.set(GameState::LoadViewScreen);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn copy_slot_button(
+    slots: Res<SaveSlots>,
+    mut slots_changed: EventWriter<SlotsChanged>,
+    buttons: Query<(&Interaction, &CopySlotButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let name = format!("{} 2", button.0.meta.player_name);
+        match slots.copy(&button.0, name) {
+            Ok(_) => {
+                slots_changed.send(SlotsChanged);
+            }
+            Err(err) => error!("Unable to copy save slot {}: {}", button.0.id, err),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn export_slot_button(
+    mut export: EventWriter<ExportSave>,
+    buttons: Query<(&Interaction, &ExportSlotButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        for to in [
+            SaveTransfer::export_file(&button.0),
+            SaveTransfer::Clipboard,
+        ] {
+            export.send(ExportSave {
+                slot: button.0.clone(),
+                to,
+            });
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn import_slot_button(
+    slots: Res<SaveSlots>,
+    mut import: EventWriter<ImportSave>,
+    button: Query<&Interaction, (Changed<Interaction>, With<ImportSlotButton>)>,
+) {
+    if let Ok(Interaction::Pressed) = button.get_single() {
+        import.send(ImportSave {
+            from: SaveTransfer::Clipboard,
+            player_name: default_player_name(slots.list().len() + 1),
+        });
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn import_file_button(
+    slots: Res<SaveSlots>,
+    mut import: EventWriter<ImportSave>,
+    button: Query<&Interaction, (Changed<Interaction>, With<ImportFileButton>)>,
+) {
+    if let Ok(Interaction::Pressed) = button.get_single() {
+        let Some(from) = SaveTransfer::latest_export_file() else {
+            warn!("No exported saves to import");
+            return;
+        };
+
+        import.send(ImportSave {
+            from,
+            player_name: default_player_name(slots.list().len() + 1),
+        });
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn imported_slot(
+    mut results: EventReader<SaveTransferResult>,
+    mut slots_changed: EventWriter<SlotsChanged>,
+) {
+    for result in results.read() {
+        if let SaveTransferResult::Imported(_) = result {
+            slots_changed.send(SlotsChanged);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn delete_slot_button(
+    slots: Res<SaveSlots>,
+    mut slots_changed: EventWriter<SlotsChanged>,
+    mut buttons: Query<(&Interaction, &mut DeleteSlotButton, &Children), Changed<Interaction>>,
+    mut texts: Query<&mut KeyText>,
+) {
+    for (interaction, mut button, children) in &mut buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        if !button.armed {
+            button.armed = true;
+            for child in children.iter() {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.set(0, text_keys::MAIN_MENU_SLOT_DELETE_CONFIRM);
+                }
+            }
+            continue;
+        }
+
+        match slots.delete(&button.slot) {
+            Ok(()) => {
+                slots_changed.send(SlotsChanged);
+            }
+            Err(err) => error!("Unable to delete save slot {}: {}", button.slot.id, err),
+        }
+    }
+}
@@ -5,6 +5,8 @@ pub mod info_panel;
 pub mod load_view_screen;
 pub mod main_menu;
 pub mod minigame_scene;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod save_conflict;
 pub mod stock_scene;
 pub mod template_scene;
 pub mod view_screen;
@@ -35,5 +37,8 @@ impl Plugin for GameScenePlugin {
             StockScenePlugin,
             BuyAccessoryScenePlugin,
         ));
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_plugins(save_conflict::SaveConflictPlugin);
     }
 }
@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use sardips_core::{
+    assets::FontAssets, button_hover::ButtonHover, despawn_all, text_translation::KeyText,
+};
+
+use crate::{
+    palettes,
+    sardip_save::storage::{ResolveSaveConflict, SaveConflict},
+};
+
+// Asks which copy to keep when another device has saved over the remote copy
+pub struct SaveConflictPlugin;
+
+impl Plugin for SaveConflictPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                setup_prompt.run_if(resource_added::<SaveConflict>),
+                prompt_button.run_if(resource_exists::<SaveConflict>),
+                despawn_all::<SaveConflictPrompt>.run_if(resource_removed::<SaveConflict>()),
+            ),
+        );
+    }
+}
+
+#[derive(Component)]
+struct SaveConflictPrompt;
+
+#[derive(Component, Clone, Copy)]
+struct PromptButton(ResolveSaveConflict);
+
+fn setup_prompt(mut commands: Commands, fonts: Res<FontAssets>, conflict: Res<SaveConflict>) {
+    let text_style = TextStyle {
+        font: fonts.main_font.clone(),
+        font_size: 30.,
+        color: Color::BLACK,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                z_index: ZIndex::Global(100),
+                ..default()
+            },
+            SaveConflictPrompt,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(20.)),
+                        border: UiRect::all(Val::Px(5.)),
+                        ..default()
+                    },
+                    background_color: palettes::OFF_WHITE.into(),
+                    border_color: palettes::LIGHT_PINK.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section("", text_style.clone()),
+                        KeyText::new().with_value(
+                            0,
+                            text_keys::SAVE_CONFLICT_TITLE,
+                            &[&conflict
+                                .remote_saved_at
+                                .format("%Y-%m-%d %H:%M")
+                                .to_string()],
+                        ),
+                    ));
+
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                margin: UiRect::top(Val::Px(20.)),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for (resolution, key) in [
+                                (
+                                    ResolveSaveConflict::KeepLocal,
+                                    text_keys::SAVE_CONFLICT_KEEP_LOCAL,
+                                ),
+                                (
+                                    ResolveSaveConflict::TakeRemote,
+                                    text_keys::SAVE_CONFLICT_TAKE_REMOTE,
+                                ),
+                            ] {
+                                parent
+                                    .spawn((
+                                        ButtonBundle {
+                                            style: Style {
+                                                width: Val::Px(220.),
+                                                height: Val::Px(60.),
+                                                justify_content: JustifyContent::Center,
+                                                align_items: AlignItems::Center,
+                                                margin: UiRect::all(Val::Px(5.)),
+                                                border: UiRect::all(Val::Px(5.)),
+                                                ..default()
+                                            },
+                                            ..default()
+                                        },
+                                        ButtonHover::default()
+                                            .with_background(palettes::ui::BUTTON_SET)
+                                            .with_border(palettes::ui::BUTTON_BORDER_SET),
+                                        PromptButton(resolution),
+                                    ))
+                                    .with_children(|parent| {
+                                        parent.spawn((
+                                            TextBundle::from_section("", text_style.clone()),
+                                            KeyText::new().with(0, key),
+                                        ));
+                                    });
+                            }
+                        });
+                });
+        });
+}
+
+fn prompt_button(
+    mut resolve: EventWriter<ResolveSaveConflict>,
+    buttons: Query<(&Interaction, &PromptButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction == Interaction::Pressed {
+            resolve.send(button.0);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::cmp::Ordering;
+use std::collections::HashMap as StdHashMap;
 
 use bevy::{prelude::*, utils::HashMap};
 use strum::IntoEnumIterator;
@@ -9,10 +10,18 @@ use crate::{
     palettes,
     player::Player,
     simulation::SimulationState,
+    stock_margin::{MarginAccount, MarginStatus, MAINTENANCE_MARGIN},
     stock_market::{
-        Company, CompanyPerformance, CompanyRank, OrderBook, OrderBrief, OrderKind, ShareHistory,
-        SharePortfolio, StockOrder,
+        CancelStockOrder, Candle, CandleResolution, Company, CompanyPerformance, CompanyRank,
+        CompleteShareOrderHistory, OrderBook, OrderBrief, OrderExpiry, OrderFilled, OrderKind,
+        OrderType, QuarterManger, ShareHistory, SharePortfolio, StockOrder,
     },
+    stock_portfolio::{equal_weight_index_return, PortfolioAnalytics},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    sardip_save::slot::ActiveSaveSlot,
+    stock_portfolio::{export_ledger, LEDGER_FILE},
 };
 use sardips_core::{
     assets::FontAssets,
@@ -21,26 +30,48 @@ use sardips_core::{
     money_core::Money,
     persistent_id::PersistentId,
     rgb_to_color,
-    text_translation::{warp_recursive_value_key, KeyText},
+    text_translation::{warp_recursive_value_key, KeyString, KeyText},
     ui_utils::spawn_back_button,
     GameState,
 };
 use text_keys::{
     STOCK_BUY_SCENE_BUY_EXISTING_BUY_LINE, STOCK_BUY_SCENE_BUY_MODE,
-    STOCK_BUY_SCENE_BUY_REMOVE_ORDER_BUTTON, STOCK_BUY_SCENE_EXPAND,
-    STOCK_BUY_SCENE_FEATURE_BUY_BUTTON, STOCK_BUY_SCENE_FEATURE_BUY_OPEN,
-    STOCK_BUY_SCENE_FEATURE_BUY_OPEN_NONE, STOCK_BUY_SCENE_FEATURE_EARNINGS,
+    STOCK_BUY_SCENE_BUY_REMOVE_ORDER_BUTTON, STOCK_BUY_SCENE_EXPAND, STOCK_BUY_SCENE_EXPIRY_GTC,
+    STOCK_BUY_SCENE_EXPIRY_GTQ, STOCK_BUY_SCENE_EXPIRY_TIMED, STOCK_BUY_SCENE_FEATURE_BUY_BUTTON,
+    STOCK_BUY_SCENE_FEATURE_BUY_OPEN, STOCK_BUY_SCENE_FEATURE_BUY_OPEN_NONE,
+    STOCK_BUY_SCENE_FEATURE_CHART_CANDLES, STOCK_BUY_SCENE_FEATURE_CHART_EMPTY,
+    STOCK_BUY_SCENE_FEATURE_CHART_LINE, STOCK_BUY_SCENE_FEATURE_CHART_MINUTE,
+    STOCK_BUY_SCENE_FEATURE_CHART_QUARTER, STOCK_BUY_SCENE_FEATURE_CHART_RANGE,
+    STOCK_BUY_SCENE_FEATURE_CHART_YEAR, STOCK_BUY_SCENE_FEATURE_CHART_ZOOM_IN,
+    STOCK_BUY_SCENE_FEATURE_CHART_ZOOM_OUT, STOCK_BUY_SCENE_FEATURE_EARNINGS,
     STOCK_BUY_SCENE_FEATURE_INDUSTRY_HEADER, STOCK_BUY_SCENE_FEATURE_INDUSTRY_PERCENT,
     STOCK_BUY_SCENE_FEATURE_MARKET_CAP, STOCK_BUY_SCENE_FEATURE_NET_ASSETS,
-    STOCK_BUY_SCENE_FEATURE_ONE_Q_CHANGE, STOCK_BUY_SCENE_FEATURE_ONE_YEAR_CHANGE,
-    STOCK_BUY_SCENE_FEATURE_PB_RATIO, STOCK_BUY_SCENE_FEATURE_PEG_RATIO,
-    STOCK_BUY_SCENE_FEATURE_PERCENTILE, STOCK_BUY_SCENE_FEATURE_PE_RATIO,
-    STOCK_BUY_SCENE_FEATURE_REVENUE, STOCK_BUY_SCENE_FEATURE_SELL_BUTTON,
-    STOCK_BUY_SCENE_FEATURE_SELL_OPEN, STOCK_BUY_SCENE_FEATURE_SELL_OPEN_NONE,
-    STOCK_BUY_SCENE_FEATURE_STOCK_PRICE, STOCK_BUY_SCENE_MARKET_CAP_HEADER,
-    STOCK_BUY_SCENE_ONE_Q_CHANGE_HEADER, STOCK_BUY_SCENE_OWN_HEADER, STOCK_BUY_SCENE_SELL_MODE,
-    STOCK_BUY_SCENE_STOCK_PRICE, STOCK_BUY_SCENE_STOCK_PRICE_HEADER, STOCK_BUY_SCENE_TICKER_HEADER,
-    STOCK_BUY_SCENE_TITLE,
+    STOCK_BUY_SCENE_FEATURE_NEWS_HEADER, STOCK_BUY_SCENE_FEATURE_ONE_Q_CHANGE,
+    STOCK_BUY_SCENE_FEATURE_ONE_YEAR_CHANGE, STOCK_BUY_SCENE_FEATURE_PB_RATIO,
+    STOCK_BUY_SCENE_FEATURE_PEG_RATIO, STOCK_BUY_SCENE_FEATURE_PERCENTILE,
+    STOCK_BUY_SCENE_FEATURE_PE_RATIO, STOCK_BUY_SCENE_FEATURE_REVENUE,
+    STOCK_BUY_SCENE_FEATURE_SELL_BUTTON, STOCK_BUY_SCENE_FEATURE_SELL_OPEN,
+    STOCK_BUY_SCENE_FEATURE_SELL_OPEN_NONE, STOCK_BUY_SCENE_FEATURE_STOCK_PRICE,
+    STOCK_BUY_SCENE_LIMIT_ORDER, STOCK_BUY_SCENE_MARGIN_BORROW, STOCK_BUY_SCENE_MARGIN_CALL,
+    STOCK_BUY_SCENE_MARGIN_EQUITY, STOCK_BUY_SCENE_MARGIN_LOAN, STOCK_BUY_SCENE_MARGIN_OK,
+    STOCK_BUY_SCENE_MARGIN_OPEN, STOCK_BUY_SCENE_MARGIN_RATIO, STOCK_BUY_SCENE_MARGIN_RATIO_NONE,
+    STOCK_BUY_SCENE_MARGIN_REPAY, STOCK_BUY_SCENE_MARGIN_SHORTS, STOCK_BUY_SCENE_MARKET_CAP_HEADER,
+    STOCK_BUY_SCENE_MARKET_ORDER, STOCK_BUY_SCENE_ONE_Q_CHANGE_HEADER, STOCK_BUY_SCENE_OWN_HEADER,
+    STOCK_BUY_SCENE_PORTFOLIO_AVG_COST_HEADER, STOCK_BUY_SCENE_PORTFOLIO_BUTTON,
+    STOCK_BUY_SCENE_PORTFOLIO_DELISTED, STOCK_BUY_SCENE_PORTFOLIO_DIVIDENDS,
+    STOCK_BUY_SCENE_PORTFOLIO_DIVIDENDS_HEADER, STOCK_BUY_SCENE_PORTFOLIO_EMPTY,
+    STOCK_BUY_SCENE_PORTFOLIO_INDEX, STOCK_BUY_SCENE_PORTFOLIO_INDEX_NONE,
+    STOCK_BUY_SCENE_PORTFOLIO_INVESTED, STOCK_BUY_SCENE_PORTFOLIO_REALISED,
+    STOCK_BUY_SCENE_PORTFOLIO_REALISED_HEADER, STOCK_BUY_SCENE_PORTFOLIO_RETURN,
+    STOCK_BUY_SCENE_PORTFOLIO_SHARES_HEADER, STOCK_BUY_SCENE_PORTFOLIO_TITLE,
+    STOCK_BUY_SCENE_PORTFOLIO_UNREALISED, STOCK_BUY_SCENE_PORTFOLIO_UNREALISED_HEADER,
+    STOCK_BUY_SCENE_PORTFOLIO_VALUE, STOCK_BUY_SCENE_SELL_MODE, STOCK_BUY_SCENE_STOCK_PRICE,
+    STOCK_BUY_SCENE_STOCK_PRICE_HEADER, STOCK_BUY_SCENE_TICKER_HEADER, STOCK_BUY_SCENE_TITLE,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use text_keys::{
+    STOCK_BUY_SCENE_PORTFOLIO_EXPORT, STOCK_BUY_SCENE_PORTFOLIO_EXPORTED,
+    STOCK_BUY_SCENE_PORTFOLIO_EXPORT_FAILED,
 };
 
 pub struct StockScenePlugin;
@@ -48,6 +79,7 @@ pub struct StockScenePlugin;
 impl Plugin for StockScenePlugin {
     fn build(&self, app: &mut App) {
         app.insert_state(StockBuySceneState::default())
+            .init_resource::<PriceChartSettings>()
             .add_systems(
                 OnEnter(GameState::StockBuy),
                 (
@@ -68,9 +100,32 @@ impl Plugin for StockScenePlugin {
             )
             .add_systems(
                 Update,
-                (exit_scene, expand_button_pressed, update_select_rows)
+                (
+                    exit_scene,
+                    expand_button_pressed,
+                    update_select_rows,
+                    open_portfolio_pressed,
+                )
                     .run_if(in_state(StockBuySceneState::SelectingCompany)),
             )
+            .add_systems(
+                OnEnter(StockBuySceneState::Portfolio),
+                setup_portfolio_screen,
+            )
+            .add_systems(
+                OnExit(StockBuySceneState::Portfolio),
+                despawn_all::<PortfolioScreen>,
+            )
+            .add_systems(
+                Update,
+                (
+                    portfolio_back_pressed,
+                    open_margin_account_pressed,
+                    margin_loan_pressed,
+                    update_margin_section,
+                )
+                    .run_if(in_state(StockBuySceneState::Portfolio)),
+            )
             .add_systems(
                 OnEnter(StockBuySceneState::FeatureCompany),
                 setup_company_focus_screen,
@@ -85,7 +140,18 @@ impl Plugin for StockScenePlugin {
             )
             .add_systems(
                 Update,
-                feature_back_pressed.run_if(in_state(StockBuySceneState::FeatureCompany)),
+                (
+                    feature_back_pressed,
+                    (price_chart_button_pressed, draw_price_chart).chain(),
+                )
+                    .run_if(in_state(StockBuySceneState::FeatureCompany)),
+            )
+            .add_systems(
+                Update,
+                leave_delisted_company.run_if(
+                    in_state(StockBuySceneState::FeatureCompany)
+                        .or_else(in_state(StockBuySceneState::BuySell)),
+                ),
             )
             .add_systems(OnEnter(StockBuySceneState::BuySell), setup_buy_screen)
             .add_systems(
@@ -108,12 +174,22 @@ impl Plugin for StockScenePlugin {
                     disable_buy_button,
                     remove_order_button,
                     toggle_buy_sell_mode,
+                    toggle_order_type,
+                    toggle_order_expiry,
                     update_but_sell_select_mode_text,
                     update_buy_sell_button_text,
                     update_order_rows,
                 )
-                    .run_if(in_state(StockBuySceneState::BuySell)),
+                    .run_if(in_state(StockBuySceneState::BuySell))
+                    .run_if(selected_company_listed),
             );
+
+        // The ledger is written next to the save slot which only exists on desktop
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(
+            Update,
+            export_ledger_pressed.run_if(in_state(StockBuySceneState::Portfolio)),
+        );
     }
 }
 
@@ -132,12 +208,33 @@ enum StockBuySceneState {
     SelectingCompany,
     FeatureCompany,
     BuySell,
+    Portfolio,
 }
 
 fn setup_state(mut state: ResMut<NextState<StockBuySceneState>>) {
     state.set(StockBuySceneState::SelectingCompany);
 }
 
+fn selected_company_listed(
+    selected: Query<&SelectedExpandedCompany>,
+    companies: Query<(), With<Company>>,
+) -> bool {
+    selected
+        .get_single()
+        .is_ok_and(|selected| companies.contains(selected.0))
+}
+
+// Kicks the player back to the company list if the company they were looking at went bankrupt
+fn leave_delisted_company(
+    selected: Query<&SelectedExpandedCompany>,
+    companies: Query<(), With<Company>>,
+    mut state: ResMut<NextState<StockBuySceneState>>,
+) {
+    if !selected_company_listed(selected, companies) {
+        state.set(StockBuySceneState::SelectingCompany);
+    }
+}
+
 fn setup_selecting_entity(mut commands: Commands, companies: Query<Entity, With<Company>>) {
     let company = companies.iter().next().unwrap();
 
@@ -566,6 +663,13 @@ fn setup_selecting_ui(
                     });
             }
 
+            spawn_order_select_button(
+                parent,
+                &fonts,
+                STOCK_BUY_SCENE_PORTFOLIO_BUTTON,
+                OpenPortfolioButton,
+            );
+
             spawn_back_button::<ExitScene>(
                 parent,
                 &fonts,
@@ -622,6 +726,11 @@ fn update_select_rows(
         {
             let mut text = text.get_mut(select_row.ticker_text).unwrap();
             text.sections[0].value = company.ticker.clone();
+            text.sections[0].style.color = if company.distressed() {
+                BAD_COLOR
+            } else {
+                Color::BLACK
+            };
         }
 
         // Update Own
@@ -746,6 +855,7 @@ fn setup_company_focus_screen(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
     order_book: Res<OrderBook>,
+    chart_settings: Res<PriceChartSettings>,
     selected: Query<&SelectedExpandedCompany>,
     companies: Query<(&PersistentId, &Company, &ShareHistory)>,
     company_per_id: Query<&PersistentId, With<Company>>,
@@ -876,6 +986,36 @@ fn setup_company_focus_screen(
                     }
                 });
 
+            // What the wider economy did to the company last quarter
+            if !last_history.economic_events.is_empty() {
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: font_assets.monospace.clone(),
+                            font_size: STATS_SIZE + 3.,
+                            color: Color::BLACK,
+                        },
+                    ),
+                    KeyText::new().with(0, STOCK_BUY_SCENE_FEATURE_NEWS_HEADER),
+                ));
+                for event in &last_history.economic_events {
+                    let mut key_text = KeyText::new();
+                    key_text.set_section(0, event.headline());
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font: font_assets.monospace.clone(),
+                                font_size: STATS_SIZE,
+                                color: Color::BLACK,
+                            },
+                        ),
+                        key_text,
+                    ));
+                }
+            }
+
             parent.spawn(divider.clone());
 
             const STATS_SIZE: f32 = 25.0;
@@ -1005,6 +1145,69 @@ fn setup_company_focus_screen(
                         });
                 });
 
+            // Price chart
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_order_select_button(
+                        parent,
+                        &font_assets,
+                        chart_settings.resolution_key(),
+                        PriceChartButton::Resolution,
+                    );
+                    spawn_order_select_button(
+                        parent,
+                        &font_assets,
+                        chart_settings.style.key(),
+                        PriceChartButton::Style,
+                    );
+                    spawn_order_select_button(
+                        parent,
+                        &font_assets,
+                        STOCK_BUY_SCENE_FEATURE_CHART_ZOOM_IN,
+                        PriceChartButton::ZoomIn,
+                    );
+                    spawn_order_select_button(
+                        parent,
+                        &font_assets,
+                        STOCK_BUY_SCENE_FEATURE_CHART_ZOOM_OUT,
+                        PriceChartButton::ZoomOut,
+                    );
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "",
+                            TextStyle {
+                                font: font_assets.monospace.clone(),
+                                font_size: STATS_SIZE,
+                                color: Color::BLACK,
+                            },
+                        ),
+                        KeyText::new(),
+                        PriceChartRange,
+                    ));
+                });
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(90.0),
+                        height: Val::Px(CHART_HEIGHT),
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(CHART_BACKGROUND_COLOR),
+                    border_color: BorderColor(Color::BLACK),
+                    ..default()
+                },
+                PriceChart,
+            ));
+
             parent.spawn(divider.clone());
 
             // Key figures
@@ -1257,25 +1460,20 @@ fn setup_company_focus_screen(
 
             let top_buy_order = {
                 let mut found: Option<OrderBrief> = None;
-                for buy_order in &order_book.buy_orders {
-                    if buy_order.company == company_per_id {
-                        match &mut found {
-                            Some(prev) => match buy_order.price.cmp(&prev.price) {
-                                Ordering::Less => {}
-                                Ordering::Equal => {
-                                    prev.quantity += buy_order.remaining_quantity;
-                                }
-                                Ordering::Greater => {
-                                    prev.price = buy_order.price;
-                                    prev.quantity = buy_order.remaining_quantity;
-                                }
-                            },
-                            None => {
-                                found = Some(OrderBrief {
-                                    price: buy_order.price,
-                                    quantity: buy_order.remaining_quantity,
-                                });
+                for buy_order in order_book.bids(company_per_id) {
+                    match &mut found {
+                        Some(prev) => {
+                            if buy_order.price != prev.price {
+                                break;
                             }
+
+                            prev.quantity += buy_order.remaining_quantity;
+                        }
+                        None => {
+                            found = Some(OrderBrief {
+                                price: buy_order.price,
+                                quantity: buy_order.remaining_quantity,
+                            });
                         }
                     }
                 }
@@ -1367,8 +1565,7 @@ fn setup_company_focus_screen(
             // Sell order
             let top_sell_order = {
                 let mut found: Option<OrderBrief> = None;
-                let fuck = order_book.get_sell_orders(company_per_id);
-                for sell_order in fuck {
+                for sell_order in order_book.asks(company_per_id) {
                     match &mut found {
                         Some(prev) => {
                             if sell_order.price != prev.price {
@@ -1460,6 +1657,246 @@ fn setup_company_focus_screen(
         .push_children(&[root]);
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ChartStyle {
+    #[default]
+    Candles,
+    Line,
+}
+
+impl ChartStyle {
+    fn key(&self) -> &'static str {
+        match self {
+            ChartStyle::Candles => STOCK_BUY_SCENE_FEATURE_CHART_CANDLES,
+            ChartStyle::Line => STOCK_BUY_SCENE_FEATURE_CHART_LINE,
+        }
+    }
+}
+
+// Candles shown across the chart at each zoom level
+const CHART_ZOOM_LEVELS: [usize; 5] = [10, 20, 40, 80, 160];
+const CHART_HEIGHT: f32 = 150.0;
+const CHART_BACKGROUND_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.6);
+
+// Kept between visits so the chart opens the way the player left it
+#[derive(Resource)]
+struct PriceChartSettings {
+    resolution: CandleResolution,
+    style: ChartStyle,
+    zoom: usize,
+}
+
+impl Default for PriceChartSettings {
+    fn default() -> Self {
+        Self {
+            resolution: CandleResolution::default(),
+            style: ChartStyle::default(),
+            zoom: 2,
+        }
+    }
+}
+
+impl PriceChartSettings {
+    fn resolution_key(&self) -> &'static str {
+        match self.resolution {
+            CandleResolution::Minute => STOCK_BUY_SCENE_FEATURE_CHART_MINUTE,
+            CandleResolution::Quarter => STOCK_BUY_SCENE_FEATURE_CHART_QUARTER,
+            CandleResolution::Year => STOCK_BUY_SCENE_FEATURE_CHART_YEAR,
+        }
+    }
+
+    fn visible_candles(&self) -> usize {
+        CHART_ZOOM_LEVELS[self.zoom]
+    }
+}
+
+#[derive(Component)]
+struct PriceChart;
+
+#[derive(Component)]
+struct PriceChartRange;
+
+#[derive(Component, Clone, Copy)]
+enum PriceChartButton {
+    Resolution,
+    Style,
+    ZoomIn,
+    ZoomOut,
+}
+
+fn price_chart_button_pressed(
+    mut settings: ResMut<PriceChartSettings>,
+    buttons: Query<(&Interaction, &PriceChartButton, &Children), Changed<Interaction>>,
+    mut text: Query<&mut KeyText>,
+) {
+    for (interaction, button, children) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let key = match button {
+            PriceChartButton::Resolution => {
+                settings.resolution = match settings.resolution {
+                    CandleResolution::Minute => CandleResolution::Quarter,
+                    CandleResolution::Quarter => CandleResolution::Year,
+                    CandleResolution::Year => CandleResolution::Minute,
+                };
+                Some(settings.resolution_key())
+            }
+            PriceChartButton::Style => {
+                settings.style = match settings.style {
+                    ChartStyle::Candles => ChartStyle::Line,
+                    ChartStyle::Line => ChartStyle::Candles,
+                };
+                Some(settings.style.key())
+            }
+            PriceChartButton::ZoomIn => {
+                settings.zoom = settings.zoom.saturating_sub(1);
+                None
+            }
+            PriceChartButton::ZoomOut => {
+                settings.zoom = (settings.zoom + 1).min(CHART_ZOOM_LEVELS.len() - 1);
+                None
+            }
+        };
+
+        if let Some(key) = key {
+            if let Ok(mut text) = text.get_mut(children[0]) {
+                *text = KeyText::new().with(0, key);
+            }
+        }
+    }
+}
+
+fn chart_node(left: f32, top: f32, width: Val, height: Val, color: Color) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(left),
+            top: Val::Percent(top),
+            width,
+            height,
+            ..default()
+        },
+        background_color: BackgroundColor(color),
+        ..default()
+    }
+}
+
+fn draw_price_chart(
+    mut commands: Commands,
+    settings: Res<PriceChartSettings>,
+    selected: Query<&SelectedExpandedCompany>,
+    companies: Query<Ref<ShareHistory>, With<Company>>,
+    charts: Query<(Entity, Ref<PriceChart>)>,
+    mut range_text: Query<&mut KeyText, With<PriceChartRange>>,
+) {
+    let Ok((chart, chart_added)) = charts.get_single() else {
+        return;
+    };
+    let Ok(share_history) = companies.get(selected.single().0) else {
+        return;
+    };
+    if !settings.is_changed() && !share_history.is_changed() && !chart_added.is_added() {
+        return;
+    }
+
+    let visible = settings.visible_candles();
+    let candles = share_history.candles.get(settings.resolution);
+    let candles: &[Candle] = &candles[candles.len().saturating_sub(visible)..];
+
+    commands.entity(chart).despawn_descendants();
+
+    if candles.is_empty() {
+        for mut text in &mut range_text {
+            *text = KeyText::new().with(0, STOCK_BUY_SCENE_FEATURE_CHART_EMPTY);
+        }
+        return;
+    }
+
+    let high = candles.iter().map(|candle| candle.high).max().unwrap();
+    let low = candles.iter().map(|candle| candle.low).min().unwrap();
+    for mut text in &mut range_text {
+        *text = KeyText::new().with_value(
+            0,
+            STOCK_BUY_SCENE_FEATURE_CHART_RANGE,
+            &[money_display(high).as_str(), money_display(low).as_str()],
+        );
+    }
+
+    let range = (high - low).max(1) as f32;
+    let y = |price: Money| (high - price) as f32 / range * 100.;
+    let slot = 100. / visible as f32;
+    // Newest candle sits on the right edge
+    let x = |i: usize| (visible - candles.len() + i) as f32 * slot;
+
+    commands
+        .entity(chart)
+        .with_children(|parent| match settings.style {
+            ChartStyle::Candles => {
+                for (i, candle) in candles.iter().enumerate() {
+                    let color = if candle.close >= candle.open {
+                        GOOD_COLOR
+                    } else {
+                        BAD_COLOR
+                    };
+
+                    parent.spawn(chart_node(
+                        x(i) + slot / 2.,
+                        y(candle.high),
+                        Val::Px(1.0),
+                        Val::Percent(y(candle.low) - y(candle.high)),
+                        color,
+                    ));
+                    parent.spawn(chart_node(
+                        x(i) + slot * 0.15,
+                        y(candle.open.max(candle.close)),
+                        Val::Percent(slot * 0.7),
+                        Val::Percent(
+                            (y(candle.open.min(candle.close)) - y(candle.open.max(candle.close)))
+                                .max(1.),
+                        ),
+                        color,
+                    ));
+                }
+            }
+            ChartStyle::Line => {
+                let mut last: Option<(f32, f32)> = None;
+                for (i, candle) in candles.iter().enumerate() {
+                    let (point_x, point_y) = (x(i) + slot / 2., y(candle.close));
+                    match last {
+                        Some((last_x, last_y)) => {
+                            parent.spawn(chart_node(
+                                last_x,
+                                last_y,
+                                Val::Percent(point_x - last_x),
+                                Val::Px(2.0),
+                                Color::BLACK,
+                            ));
+                            parent.spawn(chart_node(
+                                point_x,
+                                last_y.min(point_y),
+                                Val::Px(2.0),
+                                Val::Percent((point_y - last_y).abs()),
+                                Color::BLACK,
+                            ));
+                        }
+                        None => {
+                            parent.spawn(chart_node(
+                                point_x,
+                                point_y,
+                                Val::Px(2.0),
+                                Val::Px(2.0),
+                                Color::BLACK,
+                            ));
+                        }
+                    }
+                    last = Some((point_x, point_y));
+                }
+            }
+        });
+}
+
 #[derive(Component, Default)]
 struct FeatureBack;
 
@@ -1513,66 +1950,669 @@ fn open_buy_button_interacted(
 }
 
 #[derive(Component)]
-struct BuyScreen;
+struct OpenPortfolioButton;
 
-fn setup_buy_screen(
+fn open_portfolio_pressed(
+    mut state: ResMut<NextState<StockBuySceneState>>,
+    buttons: Query<&Interaction, (With<OpenPortfolioButton>, Changed<Interaction>)>,
+) {
+    for interaction in &buttons {
+        if *interaction == Interaction::Pressed {
+            state.set(StockBuySceneState::Portfolio);
+        }
+    }
+}
+
+#[derive(Component)]
+struct PortfolioScreen;
+
+#[derive(Component, Default)]
+struct PortfolioBack;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Component)]
+struct ExportLedgerButton;
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Component)]
+struct ExportLedgerStatus;
+
+const PORTFOLIO_TEXT_SIZE: f32 = 25.0;
+const PORTFOLIO_COLUMN_WIDTH: Val = Val::Px(110.0);
+
+fn signed_money_set(money: Money) -> (String, Color) {
+    let (color, symbol) = match money.cmp(&0) {
+        Ordering::Greater => (GOOD_COLOR, "+"),
+        Ordering::Less => (BAD_COLOR, "-"),
+        Ordering::Equal => (Color::BLACK, ""),
+    };
+    (format!("{}${}", symbol, money_display(money.abs())), color)
+}
+
+// Text is shown as is unless a key is given
+fn spawn_portfolio_text(
+    parent: &mut ChildBuilder,
+    font_assets: &FontAssets,
+    width: Val,
+    color: Color,
+    text: String,
+    key: Option<KeyText>,
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style { width, ..default() },
+            ..default()
+        })
+        .with_children(|parent| {
+            let mut entity = parent.spawn(TextBundle::from_section(
+                text,
+                TextStyle {
+                    font: font_assets.monospace.clone(),
+                    font_size: PORTFOLIO_TEXT_SIZE,
+                    color,
+                },
+            ));
+            if let Some(key) = key {
+                entity.insert(key);
+            }
+        });
+}
+
+fn setup_portfolio_screen(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
-    selected_company: Query<&SelectedExpandedCompany>,
-    player: Query<(&Wallet, &SharePortfolio), With<Player>>,
-    companies: Query<(&Company, &ShareHistory)>,
-    company_per_id: Query<&PersistentId, With<Company>>,
+    player: Query<&CompleteShareOrderHistory, With<Player>>,
+    companies: Query<(&PersistentId, &Company, &ShareHistory)>,
 ) {
-    let selected = selected_company.single().0;
-    let company_per_id = *company_per_id.get(selected).unwrap();
-
-    let (wallet, share_portfolio) = player.single();
+    let history = player.single();
+    let prices = companies
+        .iter()
+        .map(|(per_id, _, share_history)| (*per_id, share_history.cached_price))
+        .collect::<StdHashMap<_, _>>();
+    let tickers = companies
+        .iter()
+        .map(|(per_id, company, _)| (*per_id, company.ticker.clone()))
+        .collect::<StdHashMap<_, _>>();
 
-    let (company, share_history) = companies.get(selected).unwrap();
+    let analytics = PortfolioAnalytics::new(history, &prices);
+    let index_return = analytics.first_quarter.and_then(|quarter| {
+        equal_weight_index_return(quarter, companies.iter().map(|(_, _, history)| history))
+    });
 
     commands
         .spawn((
             NodeBundle {
                 style: Style {
-                    width: Val::Percent(90.0),
-                    height: Val::Percent(90.0),
-                    padding: UiRect::all(Val::Percent(5.0)),
-                    margin: UiRect::all(Val::Percent(5.0)),
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
                     flex_direction: FlexDirection::Column,
                     align_items: AlignItems::Center,
                     justify_content: JustifyContent::Center,
-                    border: UiRect::all(Val::Px(2.0)),
                     ..default()
                 },
-                background_color: BackgroundColor(FOCUS_BACKGROUND_COLOR),
-                border_color: BorderColor(Color::BLACK),
                 ..default()
             },
-            BuyScreen,
+            PortfolioScreen,
             StockScene,
         ))
         .with_children(|parent| {
-            const HEADING_SIZE: f32 = 50.0;
-            const PLAYER_INFO_SIZE: f32 = 30.0;
-
             parent.spawn((
                 TextBundle::from_section(
                     "",
                     TextStyle {
                         font: font_assets.monospace.clone(),
-                        font_size: HEADING_SIZE,
+                        font_size: 50.0,
                         color: Color::BLACK,
                     },
                 ),
-                KeyText::new().with(0, company.name_key()),
+                KeyText::new().with(0, STOCK_BUY_SCENE_PORTFOLIO_TITLE),
             ));
 
-            // Money Section
-            parent.spawn((
+            // Summary
+            let market_value: Money = analytics
+                .holdings
+                .iter()
+                .map(|holding| holding.market_value())
+                .sum();
+            let (realised, realised_color) = signed_money_set(analytics.realised());
+            let (unrealised, unrealised_color) = signed_money_set(analytics.unrealised());
+            let total_return = analytics.total_return() * 100.;
+            let index = match index_return {
+                Some(index_return) => KeyText::new().with_value(
+                    0,
+                    STOCK_BUY_SCENE_PORTFOLIO_INDEX,
+                    &[&format!("{:.2}", index_return * 100.)],
+                ),
+                None => KeyText::new().with(0, STOCK_BUY_SCENE_PORTFOLIO_INDEX_NONE),
+            };
+
+            for (color, key) in [
                 (
-                    TextBundle::from_section(
-                        "",
-                        TextStyle {
+                    Color::BLACK,
+                    KeyText::new().with_value(
+                        0,
+                        STOCK_BUY_SCENE_PORTFOLIO_INVESTED,
+                        &[&money_display(analytics.invested)],
+                    ),
+                ),
+                (
+                    Color::BLACK,
+                    KeyText::new().with_value(
+                        0,
+                        STOCK_BUY_SCENE_PORTFOLIO_VALUE,
+                        &[&money_display(market_value)],
+                    ),
+                ),
+                (
+                    realised_color,
+                    KeyText::new().with_value(0, STOCK_BUY_SCENE_PORTFOLIO_REALISED, &[&realised]),
+                ),
+                (
+                    unrealised_color,
+                    KeyText::new().with_value(
+                        0,
+                        STOCK_BUY_SCENE_PORTFOLIO_UNREALISED,
+                        &[&unrealised],
+                    ),
+                ),
+                (
+                    Color::BLACK,
+                    KeyText::new().with_value(
+                        0,
+                        STOCK_BUY_SCENE_PORTFOLIO_DIVIDENDS,
+                        &[&money_display(analytics.dividend_income())],
+                    ),
+                ),
+                (
+                    if total_return >= 0. {
+                        GOOD_COLOR
+                    } else {
+                        BAD_COLOR
+                    },
+                    KeyText::new().with_value(
+                        0,
+                        STOCK_BUY_SCENE_PORTFOLIO_RETURN,
+                        &[&format!("{:.2}", total_return)],
+                    ),
+                ),
+                (Color::BLACK, index),
+            ] {
+                spawn_portfolio_text(
+                    parent,
+                    &font_assets,
+                    Val::Auto,
+                    color,
+                    String::new(),
+                    Some(key),
+                );
+            }
+
+            parent.spawn(NodeBundle {
+                style: Style {
+                    height: Val::Px(30.0),
+                    ..default()
+                },
+                ..default()
+            });
+
+            if analytics.holdings.is_empty() {
+                spawn_portfolio_text(
+                    parent,
+                    &font_assets,
+                    Val::Auto,
+                    Color::BLACK,
+                    String::new(),
+                    Some(KeyText::new().with(0, STOCK_BUY_SCENE_PORTFOLIO_EMPTY)),
+                );
+            } else {
+                let row = NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::SpaceAround,
+                        ..default()
+                    },
+                    ..default()
+                };
+
+                parent.spawn(row.clone()).with_children(|parent| {
+                    for key in [
+                        STOCK_BUY_SCENE_TICKER_HEADER,
+                        STOCK_BUY_SCENE_PORTFOLIO_SHARES_HEADER,
+                        STOCK_BUY_SCENE_PORTFOLIO_AVG_COST_HEADER,
+                        STOCK_BUY_SCENE_STOCK_PRICE_HEADER,
+                        STOCK_BUY_SCENE_PORTFOLIO_UNREALISED_HEADER,
+                        STOCK_BUY_SCENE_PORTFOLIO_REALISED_HEADER,
+                        STOCK_BUY_SCENE_PORTFOLIO_DIVIDENDS_HEADER,
+                    ] {
+                        spawn_portfolio_text(
+                            parent,
+                            &font_assets,
+                            PORTFOLIO_COLUMN_WIDTH,
+                            Color::BLACK,
+                            String::new(),
+                            Some(KeyText::new().with(0, key)),
+                        );
+                    }
+                });
+
+                for holding in &analytics.holdings {
+                    parent.spawn(row.clone()).with_children(|parent| {
+                        match tickers.get(&holding.company) {
+                            Some(ticker) => spawn_portfolio_text(
+                                parent,
+                                &font_assets,
+                                PORTFOLIO_COLUMN_WIDTH,
+                                Color::BLACK,
+                                ticker.clone(),
+                                None,
+                            ),
+                            None => spawn_portfolio_text(
+                                parent,
+                                &font_assets,
+                                PORTFOLIO_COLUMN_WIDTH,
+                                BAD_COLOR,
+                                String::new(),
+                                Some(KeyText::new().with(0, STOCK_BUY_SCENE_PORTFOLIO_DELISTED)),
+                            ),
+                        }
+
+                        let price = holding.price.map_or("-".to_string(), |price| {
+                            format!("${}", money_display(price))
+                        });
+                        let (unrealised, unrealised_color) = signed_money_set(holding.unrealised());
+                        let (realised, realised_color) = signed_money_set(holding.realised);
+
+                        for (color, text) in [
+                            (Color::BLACK, holding.shares.to_string()),
+                            (
+                                Color::BLACK,
+                                format!("${}", money_display(holding.average_cost())),
+                            ),
+                            (Color::BLACK, price),
+                            (unrealised_color, unrealised),
+                            (realised_color, realised),
+                            (
+                                Color::BLACK,
+                                format!("${}", money_display(holding.dividends)),
+                            ),
+                        ] {
+                            spawn_portfolio_text(
+                                parent,
+                                &font_assets,
+                                PORTFOLIO_COLUMN_WIDTH,
+                                color,
+                                text,
+                                None,
+                            );
+                        }
+                    });
+                }
+            }
+
+            // Margin, shown once the account is open
+            spawn_order_select_button(
+                parent,
+                &font_assets,
+                STOCK_BUY_SCENE_MARGIN_OPEN,
+                OpenMarginAccountButton,
+            );
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            display: Display::None,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    MarginDetails,
+                ))
+                .with_children(|parent| {
+                    for text in MarginText::iter() {
+                        parent.spawn((
+                            TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font: font_assets.monospace.clone(),
+                                    font_size: PORTFOLIO_TEXT_SIZE,
+                                    color: Color::BLACK,
+                                },
+                            ),
+                            KeyText::new(),
+                            text,
+                        ));
+                    }
+
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            spawn_order_select_button(
+                                parent,
+                                &font_assets,
+                                STOCK_BUY_SCENE_MARGIN_BORROW,
+                                MarginLoanButton::Borrow,
+                            );
+                            spawn_order_select_button(
+                                parent,
+                                &font_assets,
+                                STOCK_BUY_SCENE_MARGIN_REPAY,
+                                MarginLoanButton::Repay,
+                            );
+                        });
+                });
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                spawn_order_select_button(
+                    parent,
+                    &font_assets,
+                    STOCK_BUY_SCENE_PORTFOLIO_EXPORT,
+                    ExportLedgerButton,
+                );
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: font_assets.monospace.clone(),
+                            font_size: PORTFOLIO_TEXT_SIZE,
+                            color: Color::BLACK,
+                        },
+                    ),
+                    KeyText::new(),
+                    ExportLedgerStatus,
+                ));
+            }
+
+            spawn_back_button::<PortfolioBack>(
+                parent,
+                &font_assets,
+                &palettes::ui::BUTTON_SET,
+                &palettes::ui::BUTTON_BORDER_SET,
+            );
+        });
+}
+
+fn portfolio_back_pressed(
+    mut state: ResMut<NextState<StockBuySceneState>>,
+    query: Query<&Interaction, (Changed<Interaction>, With<PortfolioBack>)>,
+) {
+    for interaction in &query {
+        if *interaction == Interaction::Pressed {
+            state.set(StockBuySceneState::SelectingCompany);
+        }
+    }
+}
+
+// Writes the ledger next to the save so it's easy to find
+#[cfg(not(target_arch = "wasm32"))]
+fn export_ledger_pressed(
+    active_slot: Option<Res<ActiveSaveSlot>>,
+    player: Query<&CompleteShareOrderHistory, With<Player>>,
+    companies: Query<(&PersistentId, &Company)>,
+    buttons: Query<&Interaction, (With<ExportLedgerButton>, Changed<Interaction>)>,
+    mut status: Query<&mut KeyText, With<ExportLedgerStatus>>,
+) {
+    for interaction in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let tickers = companies
+            .iter()
+            .map(|(per_id, company)| (*per_id, company.ticker.clone()))
+            .collect::<StdHashMap<_, _>>();
+
+        let result = match &active_slot {
+            Some(slot) => export_ledger(&slot.0.dir, player.single(), &tickers)
+                .map(|_| slot.0.dir.join(LEDGER_FILE).display().to_string()),
+            None => Err(std::io::Error::other("no active save slot")),
+        };
+        if let Err(err) = &result {
+            error!("Failed to export trade ledger: {}", err);
+        }
+
+        for mut status in &mut status {
+            *status = match &result {
+                Ok(path) => KeyText::new().with_value(
+                    0,
+                    STOCK_BUY_SCENE_PORTFOLIO_EXPORTED,
+                    &[path.as_str()],
+                ),
+                Err(_) => KeyText::new().with(0, STOCK_BUY_SCENE_PORTFOLIO_EXPORT_FAILED),
+            };
+        }
+    }
+}
+
+#[derive(Component)]
+struct OpenMarginAccountButton;
+
+#[derive(Component)]
+struct MarginDetails;
+
+#[derive(Component, EnumIter, Clone, Copy)]
+enum MarginText {
+    Loan,
+    Shorts,
+    Equity,
+    Ratio,
+    Call,
+}
+
+#[derive(Component, Clone, Copy)]
+enum MarginLoanButton {
+    Borrow,
+    Repay,
+}
+
+const MARGIN_LOAN_STEP: Money = 10000;
+
+fn open_margin_account_pressed(
+    mut commands: Commands,
+    player: Query<Entity, (With<Player>, Without<MarginAccount>)>,
+    buttons: Query<&Interaction, (With<OpenMarginAccountButton>, Changed<Interaction>)>,
+) {
+    for interaction in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        if let Ok(player) = player.get_single() {
+            commands.entity(player).insert(MarginAccount::default());
+        }
+    }
+}
+
+fn margin_loan_pressed(
+    mut player: Query<(&mut MarginAccount, &mut Wallet, Option<&MarginStatus>), With<Player>>,
+    buttons: Query<(&Interaction, &MarginLoanButton), Changed<Interaction>>,
+) {
+    let Ok((mut account, mut wallet, status)) = player.get_single_mut() else {
+        return;
+    };
+
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            MarginLoanButton::Borrow => {
+                // Nothing to borrow against until the market has looked at the account
+                if let Some(status) = status.filter(|status| !status.margin_call) {
+                    let amount = MARGIN_LOAN_STEP.min(account.cash_capacity(status));
+                    account.borrow_cash(&mut wallet, amount, status);
+                }
+            }
+            MarginLoanButton::Repay => {
+                account.repay_cash(&mut wallet, MARGIN_LOAN_STEP);
+            }
+        }
+    }
+}
+
+fn update_margin_section(
+    player: Query<(Option<&MarginAccount>, Option<&MarginStatus>), With<Player>>,
+    mut open_button: Query<&mut Style, (With<OpenMarginAccountButton>, Without<MarginDetails>)>,
+    mut details: Query<&mut Style, (With<MarginDetails>, Without<OpenMarginAccountButton>)>,
+    mut texts: Query<(&mut KeyText, &mut Text, &MarginText)>,
+) {
+    let Ok((account, status)) = player.get_single() else {
+        return;
+    };
+
+    let (open_display, details_display) = match account {
+        Some(_) => (Display::None, Display::Flex),
+        None => (Display::Flex, Display::None),
+    };
+    for mut style in &mut open_button {
+        if style.display != open_display {
+            style.display = open_display;
+        }
+    }
+    for mut style in &mut details {
+        if style.display != details_display {
+            style.display = details_display;
+        }
+    }
+
+    let Some(account) = account else {
+        return;
+    };
+    let status = status.copied().unwrap_or_default();
+
+    for (mut key_text, mut text, margin_text) in &mut texts {
+        let (key, color) = match margin_text {
+            MarginText::Loan => (
+                KeyString::value(
+                    STOCK_BUY_SCENE_MARGIN_LOAN.to_string(),
+                    &[money_display(account.borrowed_cash)],
+                ),
+                Color::BLACK,
+            ),
+            MarginText::Shorts => (
+                KeyString::value(
+                    STOCK_BUY_SCENE_MARGIN_SHORTS.to_string(),
+                    &[money_display(status.short_value)],
+                ),
+                Color::BLACK,
+            ),
+            MarginText::Equity => (
+                KeyString::value(
+                    STOCK_BUY_SCENE_MARGIN_EQUITY.to_string(),
+                    &[money_display(status.equity)],
+                ),
+                if status.equity >= 0 {
+                    Color::BLACK
+                } else {
+                    BAD_COLOR
+                },
+            ),
+            MarginText::Ratio => match status.ratio() {
+                Some(ratio) => (
+                    KeyString::value(
+                        STOCK_BUY_SCENE_MARGIN_RATIO.to_string(),
+                        &[
+                            format!("{:.1}", ratio * 100.),
+                            format!("{:.0}", MAINTENANCE_MARGIN * 100.),
+                        ],
+                    ),
+                    if status.below_maintenance() {
+                        BAD_COLOR
+                    } else {
+                        Color::BLACK
+                    },
+                ),
+                None => (
+                    KeyString::direct(STOCK_BUY_SCENE_MARGIN_RATIO_NONE),
+                    Color::BLACK,
+                ),
+            },
+            MarginText::Call => match status.margin_call {
+                true => (KeyString::direct(STOCK_BUY_SCENE_MARGIN_CALL), BAD_COLOR),
+                false => (KeyString::direct(STOCK_BUY_SCENE_MARGIN_OK), GOOD_COLOR),
+            },
+        };
+
+        if key_text.keys.get(&0) != Some(&key) {
+            key_text.set_section(0, key);
+        }
+        if text.sections[0].style.color != color {
+            text.sections[0].style.color = color;
+        }
+    }
+}
+
+#[derive(Component)]
+struct BuyScreen;
+
+fn setup_buy_screen(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    selected_company: Query<&SelectedExpandedCompany>,
+    player: Query<(&Wallet, &SharePortfolio), With<Player>>,
+    companies: Query<(&Company, &ShareHistory)>,
+    company_per_id: Query<&PersistentId, With<Company>>,
+) {
+    let selected = selected_company.single().0;
+    let company_per_id = *company_per_id.get(selected).unwrap();
+
+    let (wallet, share_portfolio) = player.single();
+
+    let (company, share_history) = companies.get(selected).unwrap();
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(90.0),
+                    height: Val::Percent(90.0),
+                    padding: UiRect::all(Val::Percent(5.0)),
+                    margin: UiRect::all(Val::Percent(5.0)),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    border: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(FOCUS_BACKGROUND_COLOR),
+                border_color: BorderColor(Color::BLACK),
+                ..default()
+            },
+            BuyScreen,
+            StockScene,
+        ))
+        .with_children(|parent| {
+            const HEADING_SIZE: f32 = 50.0;
+            const PLAYER_INFO_SIZE: f32 = 30.0;
+
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font_assets.monospace.clone(),
+                        font_size: HEADING_SIZE,
+                        color: Color::BLACK,
+                    },
+                ),
+                KeyText::new().with(0, company.name_key()),
+            ));
+
+            // Money Section
+            parent.spawn((
+                (
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
                             font: font_assets.monospace.clone(),
                             font_size: PLAYER_INFO_SIZE,
                             color: Color::BLACK,
@@ -1655,6 +2695,31 @@ fn setup_buy_screen(
                             ));
                         });
 
+                    // Order type and expiry selectors
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            spawn_order_select_button(
+                                parent,
+                                &font_assets,
+                                STOCK_BUY_SCENE_LIMIT_ORDER,
+                                OrderTypeSelectButton::default(),
+                            );
+                            spawn_order_select_button(
+                                parent,
+                                &font_assets,
+                                STOCK_BUY_SCENE_EXPIRY_TIMED,
+                                OrderExpirySelectButton::default(),
+                            );
+                        });
+
                     let price_input_id = parent
                         .spawn((
                             TextBundle::from_section(
@@ -2342,15 +3407,20 @@ fn disable_stock_quantity_input_buttons(
     without_interaction: Query<Entity, (With<StockQuantityInputButton>, Without<Interaction>)>,
     input: Query<&StockQuantityInput>,
     buttons: Query<(Entity, &StockPriceInputKind, &StockQuantityInputButton)>,
-    player_share_portfolio: Query<&SharePortfolio, With<Player>>,
+    player_share_portfolio: Query<(&SharePortfolio, Option<&MarginStatus>), With<Player>>,
     selected: Query<&SelectedExpandedCompany>,
-    company_per_id: Query<&PersistentId, With<Company>>,
+    company: Query<(&PersistentId, &ShareHistory), With<Company>>,
     buy_sell_mode: Query<&BuySellModeSelectButton>,
 ) {
     let buy_mode = buy_sell_mode.single();
     let selected = selected.single().0;
-    let company_per_id = *company_per_id.get(selected).unwrap();
-    let player_portfolio = player_share_portfolio.single();
+    let (company_per_id, share_history) = company.get(selected).unwrap();
+    let (player_portfolio, margin) = player_share_portfolio.single();
+    let sellable = sellable_shares(
+        player_portfolio.get_count(company_per_id),
+        margin,
+        share_history.cached_price,
+    );
 
     for (entity, kind, button) in &buttons {
         let current = input.get(button.for_quantity).unwrap().current;
@@ -2364,9 +3434,7 @@ fn disable_stock_quantity_input_buttons(
                 }
             }
             StockPriceInputKind::Higher | StockPriceInputKind::MuchHigher => {
-                if *buy_mode == BuySellModeSelectButton::Sell
-                    && player_portfolio.get_count(&company_per_id) < current + 1
-                {
+                if *buy_mode == BuySellModeSelectButton::Sell && sellable < current + 1 {
                     enabled = false;
                 }
             }
@@ -2415,12 +3483,12 @@ const ORDER_LIST_BODY_SIZE: f32 = 30.;
 
 #[derive(Default)]
 struct UpdateOrderLocal {
-    buy_order_len: usize,
-    sell_order_len: usize,
+    order_len: usize,
 }
 
 fn update_order_rows(
     mut local: Local<UpdateOrderLocal>,
+    mut filled: EventReader<OrderFilled>,
     order_book: Res<OrderBook>,
     selected: Query<&SelectedExpandedCompany>,
     companies_per_id: Query<&PersistentId, With<Company>>,
@@ -2429,25 +3497,20 @@ fn update_order_rows(
     mut order_row_text: Query<&mut KeyText, With<OrderRowText>>,
     mut order_row_remove_buttons: Query<(&mut Visibility, &mut RemoveOrderButton)>,
 ) {
-    if local.buy_order_len == order_book.buy_orders.len()
-        && local.sell_order_len == order_book.sell_orders.len()
-    {
+    // Partial fills leave the number of orders alone so need checking too
+    let any_filled = filled.read().count() > 0;
+    if !any_filled && local.order_len == order_book.len() {
         return;
     }
 
-    local.buy_order_len = order_book.buy_orders.len();
-    local.sell_order_len = order_book.sell_orders.len();
+    local.order_len = order_book.len();
 
     let selected = selected.single().0;
     let company_per_id = *companies_per_id.get(selected).unwrap();
     let player_per_id = *player.single();
 
     // Reallocate the id's for all the rows
-    let open_buy_orders: Vec<_> = order_book
-        .buy_orders
-        .iter()
-        .filter(|order| order.company == company_per_id)
-        .collect();
+    let open_buy_orders: Vec<_> = order_book.bids(company_per_id).collect();
 
     let mut non_player_buy_orders: HashMap<Money, u64> = HashMap::new();
     let mut player_buy_orders = vec![];
@@ -2473,7 +3536,7 @@ fn update_order_rows(
     let mut non_player_sell_orders: Vec<OrderBrief> = vec![];
     let mut player_sell_orders = vec![];
     {
-        for sell_order in order_book.get_sell_orders(company_per_id) {
+        for sell_order in order_book.asks(company_per_id) {
             if sell_order.owner == player_per_id {
                 player_sell_orders.push(sell_order)
             } else {
@@ -2483,7 +3546,10 @@ fn update_order_rows(
                         continue;
                     }
                 }
-                non_player_sell_orders.push(OrderBrief::new(sell_order.quantity, sell_order.price));
+                non_player_sell_orders.push(OrderBrief::new(
+                    sell_order.remaining_quantity,
+                    sell_order.price,
+                ));
             }
         }
     }
@@ -2571,12 +3637,23 @@ struct BuySellStockButton {
 fn buy_stock_button_interacted(
     mut order_book: ResMut<OrderBook>,
     selected: Query<&SelectedExpandedCompany>,
-    company_per_id: Query<&PersistentId, With<Company>>,
+    company: Query<(&PersistentId, &ShareHistory), With<Company>>,
     price_input: Query<&StockPriceInput>,
     quantity_input: Query<&StockQuantityInput>,
-    mut player: Query<(&PersistentId, &mut Wallet, &mut SharePortfolio), With<Player>>,
+    mut player: Query<
+        (
+            &PersistentId,
+            &mut Wallet,
+            &mut SharePortfolio,
+            Option<&mut MarginAccount>,
+            Option<&MarginStatus>,
+        ),
+        With<Player>,
+    >,
     buttons: Query<(&Interaction, &BuySellStockButton), Changed<Interaction>>,
     buy_sell_mode: Query<&BuySellModeSelectButton>,
+    order_type: Query<&OrderTypeSelectButton>,
+    order_expiry: Query<&OrderExpirySelectButton>,
 ) {
     let (interaction, buy_button) = match buttons.get_single() {
         Ok(button) => button,
@@ -2592,13 +3669,33 @@ fn buy_stock_button_interacted(
         .get(buy_button.quantity_input)
         .unwrap()
         .current;
-    let (player_entity, mut player_wallet, mut share_portfolio) = player.single_mut();
+    if quantity == 0 {
+        return;
+    }
+    let (player_entity, mut player_wallet, mut share_portfolio, margin_account, margin_status) =
+        player.single_mut();
 
     let selected = selected.single().0;
-    let company_per_id = *company_per_id.get(selected).unwrap();
+    let (company_per_id, share_history) = company.get(selected).unwrap();
+    let company_per_id = *company_per_id;
+    let order_type = order_type.single().0;
+    let expiry = order_expiry.single().0;
+
+    // Selling more than is owned borrows the rest to sell short
+    let owned = share_portfolio.get_count(&company_per_id);
+    let short = match *buy_sell_mode {
+        BuySellModeSelectButton::Sell => quantity.saturating_sub(owned),
+        BuySellModeSelectButton::Buy => 0,
+    };
+    if short > 0
+        && (margin_account.is_none()
+            || sellable_shares(owned, margin_status, share_history.cached_price) < quantity)
+    {
+        return;
+    }
 
-    match *buy_sell_mode {
-        BuySellModeSelectButton::Buy => {
+    let order = match (*buy_sell_mode, order_type) {
+        (BuySellModeSelectButton::Buy, OrderType::Limit) => {
             let total_price = price * quantity as i64;
             if total_price > player_wallet.balance {
                 return;
@@ -2606,24 +3703,29 @@ fn buy_stock_button_interacted(
 
             player_wallet.balance -= total_price;
 
-            order_book.add(StockOrder::new_buy(
-                company_per_id,
-                quantity,
-                price,
-                *player_entity,
-            ));
+            StockOrder::new_buy(company_per_id, quantity, price, *player_entity)
         }
-        BuySellModeSelectButton::Sell => {
-            share_portfolio.remove_shares(company_per_id, quantity);
+        // Paid for as it fills
+        (BuySellModeSelectButton::Buy, OrderType::Market) => {
+            StockOrder::new_market_buy(company_per_id, quantity, *player_entity)
+        }
+        (BuySellModeSelectButton::Sell, OrderType::Limit) => {
+            StockOrder::new_sell(company_per_id, quantity, price, *player_entity)
+        }
+        (BuySellModeSelectButton::Sell, OrderType::Market) => {
+            StockOrder::new_market_sell(company_per_id, quantity, *player_entity)
+        }
+    };
 
-            order_book.add(StockOrder::new_sell(
-                company_per_id,
-                quantity,
-                price,
-                *player_entity,
-            ));
+    // Only borrowed now the order is certain to go in
+    if *buy_sell_mode == BuySellModeSelectButton::Sell {
+        if let Some(mut account) = margin_account.filter(|_| short > 0) {
+            account.borrow_shares(&mut share_portfolio, company_per_id, short);
         }
+        share_portfolio.remove_shares(company_per_id, quantity);
     }
+
+    order_book.add(order.with_expiry(expiry));
 }
 
 fn disable_buy_button(
@@ -2631,17 +3733,19 @@ fn disable_buy_button(
     price_input: Query<&StockPriceInput>,
     quantity_input: Query<&StockQuantityInput>,
     player_wallet: Query<&Wallet, With<Player>>,
-    share_portfolio: Query<&SharePortfolio, With<Player>>,
+    share_portfolio: Query<(&SharePortfolio, Option<&MarginStatus>), With<Player>>,
     selected: Query<&SelectedExpandedCompany>,
-    company_per_id: Query<&PersistentId, With<Company>>,
+    company: Query<(&PersistentId, &ShareHistory), With<Company>>,
     buttons: Query<(Entity, &BuySellStockButton)>,
     without_interaction: Query<Entity, (With<BuySellStockButton>, Without<Interaction>)>,
     with_interaction: Query<Entity, (With<BuySellStockButton>, With<Interaction>)>,
     current_mode: Query<&BuySellModeSelectButton>,
+    order_type: Query<&OrderTypeSelectButton>,
 ) {
     let current_mode = current_mode.single();
+    let order_type = order_type.single().0;
     let selected = selected.single().0;
-    let company_per_id = *company_per_id.get(selected).unwrap();
+    let (company_per_id, share_history) = company.get(selected).unwrap();
     let (entity, buy_button) = buttons.single();
     let quantity = quantity_input
         .get(buy_button.quantity_input)
@@ -2650,8 +3754,12 @@ fn disable_buy_button(
 
     match *current_mode {
         BuySellModeSelectButton::Sell => {
-            let player_share_portfolio = share_portfolio.single();
-            let count = player_share_portfolio.get_count(&company_per_id);
+            let (player_share_portfolio, margin) = share_portfolio.single();
+            let count = sellable_shares(
+                player_share_portfolio.get_count(company_per_id),
+                margin,
+                share_history.cached_price,
+            );
             if (quantity == 0 || count < quantity) && with_interaction.get(entity).is_ok() {
                 commands.entity(entity).remove::<Interaction>();
             } else if count > quantity && without_interaction.get(entity).is_ok() {
@@ -2660,7 +3768,11 @@ fn disable_buy_button(
         }
         BuySellModeSelectButton::Buy => {
             let player_wallet = player_wallet.single();
-            let price = price_input.get(buy_button.price_input).unwrap().current;
+            // Market orders buy what they can afford so only need some money
+            let price = match order_type {
+                OrderType::Limit => price_input.get(buy_button.price_input).unwrap().current,
+                OrderType::Market => 0,
+            };
 
             let total_price = price * quantity as i64;
 
@@ -2673,11 +3785,20 @@ fn disable_buy_button(
     }
 }
 
+// Owned shares plus what the margin account would lend to sell short
+fn sellable_shares(owned: u64, margin: Option<&MarginStatus>, price: Money) -> u64 {
+    match margin {
+        Some(status) if !status.margin_call => owned + status.short_capacity(price),
+        _ => owned,
+    }
+}
+
 #[derive(Component)]
 struct RemoveOrderButton(u64);
 
 fn remove_order_button(
-    mut order_book: ResMut<OrderBook>,
+    mut cancel_order: EventWriter<CancelStockOrder>,
+    player: Query<&PersistentId, With<Player>>,
     buttons: Query<(&Interaction, &RemoveOrderButton), Changed<Interaction>>,
 ) {
     for (interaction, button) in &buttons {
@@ -2685,7 +3806,101 @@ fn remove_order_button(
             continue;
         }
 
-        order_book.remove_order(button.0);
+        cancel_order.send(CancelStockOrder::new(button.0, *player.single()));
+    }
+}
+
+#[derive(Component, Default)]
+struct OrderTypeSelectButton(OrderType);
+
+#[derive(Component, Default)]
+struct OrderExpirySelectButton(OrderExpiry);
+
+fn spawn_order_select_button(
+    parent: &mut ChildBuilder,
+    font_assets: &FontAssets,
+    key: &str,
+    button: impl Component,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    border: UiRect::all(Val::Px(2.0)),
+                    margin: UiRect::all(Val::Px(5.0)),
+                    align_content: AlignContent::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            button,
+            ButtonHover::default()
+                .with_background(palettes::ui::BUTTON_SET)
+                .with_border(palettes::ui::BUTTON_BORDER_SET),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font_assets.monospace.clone(),
+                        font_size: BODY_SIZE,
+                        color: Color::BLACK,
+                    },
+                ),
+                KeyText::new().with(0, key),
+            ));
+        });
+}
+
+fn toggle_order_type(
+    mut buttons: Query<(&Interaction, &mut OrderTypeSelectButton, &Children), Changed<Interaction>>,
+    mut text: Query<&mut KeyText>,
+) {
+    for (interaction, mut button, children) in &mut buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let (next, key) = match button.0 {
+            OrderType::Limit => (OrderType::Market, STOCK_BUY_SCENE_MARKET_ORDER),
+            OrderType::Market => (OrderType::Limit, STOCK_BUY_SCENE_LIMIT_ORDER),
+        };
+        button.0 = next;
+
+        if let Ok(mut text) = text.get_mut(children[0]) {
+            *text = KeyText::new().with(0, key);
+        }
+    }
+}
+
+fn toggle_order_expiry(
+    quarter_manager: Res<QuarterManger>,
+    mut buttons: Query<
+        (&Interaction, &mut OrderExpirySelectButton, &Children),
+        Changed<Interaction>,
+    >,
+    mut text: Query<&mut KeyText>,
+) {
+    for (interaction, mut button, children) in &mut buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let (next, key) = match button.0 {
+            OrderExpiry::Timed => (OrderExpiry::GoodTillCancelled, STOCK_BUY_SCENE_EXPIRY_GTC),
+            OrderExpiry::GoodTillCancelled => (
+                OrderExpiry::GoodTillQuarter(quarter_manager.current_quarter()),
+                STOCK_BUY_SCENE_EXPIRY_GTQ,
+            ),
+            OrderExpiry::GoodTillQuarter(_) => (OrderExpiry::Timed, STOCK_BUY_SCENE_EXPIRY_TIMED),
+        };
+        button.0 = next;
+
+        if let Ok(mut text) = text.get_mut(children[0]) {
+            *text = KeyText::new().with(0, key);
+        }
     }
 }
 
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use fact_db::Concept;
 use sardips_core::{
     button_hover::{ButtonHover, Selected},
     name::NameTag,
@@ -14,6 +15,7 @@ use crate::{
     pet::view::PetView,
     player::Player,
     simulation::{SimulationState, SimulationViewState},
+    thinking::TryThinkEvent,
     tools::poop_scooper::{create_poop_scooper, PoopScooper},
 };
 use sardips_core::{
@@ -41,7 +43,10 @@ impl Plugin for ViewScreenPlugin {
         );
         app.add_systems(
             Update,
-            (info_panel_handle_click, toggle_interactions)
+            (
+                (pet_selected_pet, info_panel_handle_click).chain(),
+                toggle_interactions,
+            )
                 .run_if(in_state(GameState::ViewScreen).and_then(in_state(VSSubState::None))),
         );
         app.add_systems(
@@ -316,6 +321,28 @@ fn teardown(
     sim_view_state.set(SimulationViewState::Invisible);
 }
 
+// Clicking a pet that is already selected pets it
+fn pet_selected_pet(
+    mut try_think_events: EventWriter<TryThinkEvent>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    selected_pet: Query<&SelectedPet>,
+    pets: Query<&EntityView, (With<PetView>, With<Hovering>)>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let Ok(selected_pet) = selected_pet.get_single() else {
+        return;
+    };
+
+    for view in pets.iter() {
+        if selected_pet.entity == Some(view.entity) {
+            try_think_events.send(TryThinkEvent::new(view.entity, Concept::ThinkPetted));
+        }
+    }
+}
+
 fn info_panel_handle_click(
     mut selected_pet: Query<&mut SelectedPet>,
     mut update_info_panel: EventWriter<InfoPanelUpdate>,
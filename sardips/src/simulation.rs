@@ -6,12 +6,17 @@ use bevy::{
     prelude::*,
 };
 use sardips_core::{from_days, from_hours, from_mins};
+use serde::{Deserialize, Serialize};
 use shared_deps::chrono::{DateTime, Utc};
+
+use crate::{away_report::AwayReport, clock::Clock, sardip_save::SardipLoadingState};
+
 pub struct SimulationPlugin;
 
 impl Plugin for SimulationPlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<Simulated>();
+        app.register_type::<Simulated>()
+            .register_type::<SavedSimTime>();
 
         app.insert_state(SimulationState::default())
             .insert_state(SimulationViewState::default())
@@ -25,10 +30,21 @@ impl Plugin for SimulationPlugin {
                 sim_entities_visibility,
             );
 
-        app.insert_resource(Time::<SimTime>::default())
+        app.init_resource::<Clock>()
+            .insert_resource(Time::<SimTime>::default())
+            .insert_resource(CatchUpConfig::default())
+            .init_resource::<SavedSimTime>()
+            .init_resource::<AwayReport>()
             .init_schedule(RunSimulationUpdate)
             .init_schedule(SimulationUpdate)
-            .add_systems(RunSimulationUpdate, run_simulation_schedule);
+            .init_schedule(SimulationCatchUp)
+            .add_systems(PreStartup, start_sim_time)
+            .add_systems(RunSimulationUpdate, run_simulation_schedule)
+            .add_systems(OnEnter(SardipLoadingState::Loaded), restore_sim_time)
+            .add_systems(
+                Update,
+                sync_saved_sim_time.run_if(in_state(SimulationState::Running)),
+            );
 
         app.world_mut()
             .resource_mut::<MainScheduleOrder>()
@@ -42,6 +58,10 @@ pub const MONEY_MOOD_UPDATE: Duration = from_hours(2);
 pub const CLEANLINESS_MOOD_UPDATE: Duration = from_mins(2);
 pub const EGG_HATCH_ATTEMPT_INTERVAL: Duration = from_mins(30);
 pub const MAX_EGG_LIFE: Duration = from_days(2);
+// Gaps bigger than this are fast forwarded instead of replayed second by second
+pub const CATCH_UP_THRESHOLD: Duration = from_mins(10);
+pub const MAX_CATCH_UP: Duration = from_days(7);
+pub const MAX_CATCH_UP_POOPS: u32 = 10;
 
 // Tick down rates one point per seconds
 // One point per minute
@@ -116,6 +136,12 @@ pub trait SimTimeTrait {
     fn last_run(&self) -> DateTime<Utc>;
 
     fn set_last_run(&mut self, last_run: DateTime<Utc>);
+
+    fn overstep(&self) -> Duration;
+
+    fn discard_overstep(&mut self);
+
+    fn fast_forward(&mut self, max: Duration) -> (Duration, Duration);
 }
 
 impl SimTimeTrait for Time<SimTime> {
@@ -132,8 +158,8 @@ impl SimTimeTrait for Time<SimTime> {
 
     fn accumulate(&mut self, now: DateTime<Utc>, scale: f32) {
         let last_run = self.context().last_run;
-        let delta = now - last_run;
-        let delta: Duration = Duration::from_nanos(delta.num_nanoseconds().unwrap() as u64);
+        // A clock set backwards accumulates nothing
+        let delta = (now - last_run).to_std().unwrap_or_default();
         self.context_mut().overstep += delta.mul_f32(scale);
         self.context_mut().last_run = now;
     }
@@ -145,14 +171,82 @@ impl SimTimeTrait for Time<SimTime> {
     fn set_last_run(&mut self, last_run: DateTime<Utc>) {
         self.context_mut().last_run = last_run;
     }
+
+    fn overstep(&self) -> Duration {
+        self.context().overstep
+    }
+
+    fn discard_overstep(&mut self) {
+        self.context_mut().overstep = Duration::ZERO;
+    }
+
+    // Consumes every whole timestep in one go, returns (simulated, skipped over max)
+    fn fast_forward(&mut self, max: Duration) -> (Duration, Duration) {
+        let timestep = self.context().timestep;
+        let overstep = self.context().overstep;
+        let partial = Duration::from_nanos((overstep.as_nanos() % timestep.as_nanos()) as u64);
+        let whole = overstep - partial;
+
+        let simulated = whole.min(max);
+        self.context_mut().overstep = partial;
+        self.advance_by(simulated);
+
+        (simulated, whole - simulated)
+    }
 }
 
 #[derive(Resource)]
 pub struct SimTimeScale(pub f32);
 
+#[derive(Resource, Clone)]
+pub struct CatchUpConfig {
+    pub threshold: Duration,
+    pub max: Duration,
+    pub max_poops: u32,
+}
+
+impl Default for CatchUpConfig {
+    fn default() -> Self {
+        Self {
+            threshold: CATCH_UP_THRESHOLD,
+            max: MAX_CATCH_UP,
+            max_poops: MAX_CATCH_UP_POOPS,
+        }
+    }
+}
+
+#[derive(Resource, Default, Deserialize, Serialize, Clone, Reflect)]
+#[reflect_value(Deserialize, Serialize, Resource)]
+pub struct SavedSimTime {
+    last_run: Option<i64>,
+}
+
+fn start_sim_time(clock: Res<Clock>, mut sim_time: ResMut<Time<SimTime>>) {
+    sim_time.set_last_run(clock.now());
+}
+
+fn sync_saved_sim_time(sim_time: Res<Time<SimTime>>, mut saved: ResMut<SavedSimTime>) {
+    saved.last_run = Some(sim_time.last_run().timestamp_millis());
+}
+
+fn restore_sim_time(saved: Res<SavedSimTime>, mut sim_time: ResMut<Time<SimTime>>) {
+    let last_run = match saved.last_run.and_then(DateTime::from_timestamp_millis) {
+        Some(last_run) => last_run,
+        None => return,
+    };
+
+    // Time spent before the save was loaded is covered by the saved timestamp
+    sim_time.discard_overstep();
+    sim_time.set_last_run(last_run);
+}
+
 #[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct SimulationUpdate;
 
+// Runs once with the whole skipped duration as the delta
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SimulationCatchUp;
+
 #[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
 struct RunSimulationUpdate;
 
@@ -162,11 +256,12 @@ pub fn run_simulation_schedule(world: &mut World) {
     }
 
     let time_scale = world.resource::<SimTimeScale>().0;
+    let now = world.resource::<Clock>().now();
 
     // Continue to accumulate time even if sim is not running
     world
         .resource_mut::<Time<SimTime>>()
-        .accumulate(Utc::now(), time_scale);
+        .accumulate(now, time_scale);
 
     if matches!(
         **world.resource::<State<SimulationState>>(),
@@ -175,6 +270,20 @@ pub fn run_simulation_schedule(world: &mut World) {
         return;
     }
 
+    let catch_up = world.resource::<CatchUpConfig>().clone();
+    if world.resource::<Time<SimTime>>().overstep() > catch_up.threshold {
+        let (simulated, skipped) = world
+            .resource_mut::<Time<SimTime>>()
+            .fast_forward(catch_up.max);
+
+        *world.resource_mut::<Time>() = world.resource::<Time<SimTime>>().as_generic();
+        world.run_schedule(SimulationCatchUp);
+
+        let mut report = world.resource_mut::<AwayReport>();
+        report.simulated += simulated;
+        report.skipped += skipped;
+    }
+
     // Run the schedule until we run out of accumulated time
     let _ = world.try_schedule_scope(SimulationUpdate, |world, schedule| {
         while world.resource_mut::<Time<SimTime>>().expend() {
@@ -185,3 +294,192 @@ pub fn run_simulation_schedule(world: &mut World) {
 
     *world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::state::app::StatesPlugin;
+    use sardips_core::{
+        age_core::Age,
+        fun_core::Fun,
+        hunger_core::Hunger,
+        name::EntityName,
+        pet_core::{Pooper, DEFAULT_POOP_TEXTURE},
+        sounds::PlaySoundEffect,
+        GameState,
+    };
+    use shared_deps::bevy_turborand::RngComponent;
+
+    use super::*;
+    use crate::{
+        pet::{
+            breeding::{BreedPlugin, Egg, EggHatchAttempt},
+            fun::FunPlugin,
+            hunger::{HungerPlugin, Starving},
+            poop::{Poop, PoopPlugin},
+        },
+        thinking::TryThinkEvent,
+    };
+
+    #[derive(Resource, Default)]
+    struct CaughtUp(Vec<Duration>);
+
+    fn record_catch_up(time: Res<Time>, mut caught_up: ResMut<CaughtUp>) {
+        caught_up.0.push(time.delta());
+    }
+
+    fn sim_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, SimulationPlugin))
+            .insert_resource(Clock::manual(DateTime::UNIX_EPOCH))
+            .init_resource::<CaughtUp>()
+            .add_systems(SimulationCatchUp, record_catch_up);
+        app
+    }
+
+    fn catch_up_app(away: Duration) -> App {
+        run_away(sim_app(), away)
+    }
+
+    // The pet plugins with whatever setup spawns, away for a whole number of seconds so
+    // nothing is left over for SimulationUpdate
+    fn catch_up_pets_app(away: Duration, setup: impl FnOnce(&mut World)) -> App {
+        let mut app = sim_app();
+        app.add_plugins((HungerPlugin, FunPlugin, PoopPlugin, BreedPlugin))
+            .init_state::<GameState>()
+            .add_event::<TryThinkEvent>()
+            .add_event::<PlaySoundEffect>();
+        setup(app.world_mut());
+        run_away(app, away)
+    }
+
+    fn spawn_pet(world: &mut World, name: &str, hunger: f32) -> Entity {
+        world
+            .spawn((
+                EntityName::new(name),
+                Hunger {
+                    value: hunger,
+                    max: 1000.,
+                },
+                Fun { value: 100. },
+                Pooper::new(from_hours(1), DEFAULT_POOP_TEXTURE),
+                RngComponent::with_seed(0),
+            ))
+            .id()
+    }
+
+    fn poop_count(app: &mut App) -> usize {
+        app.world_mut()
+            .query_filtered::<(), With<Poop>>()
+            .iter(app.world())
+            .count()
+    }
+
+    fn run_away(mut app: App, away: Duration) -> App {
+        app.world_mut()
+            .resource_mut::<NextState<SimulationState>>()
+            .set(SimulationState::Running);
+        // First update starts the clock and applies the state transition
+        app.update();
+
+        app.world_mut().resource_mut::<Clock>().advance(away);
+        app.update();
+
+        app
+    }
+
+    #[test]
+    fn test_fast_forward() {
+        let mut time = Time::<SimTime>::default();
+        time.context_mut().overstep = Duration::from_millis(5500);
+
+        let (simulated, skipped) = time.fast_forward(Duration::from_secs(3));
+        assert_eq!(simulated, Duration::from_secs(3));
+        assert_eq!(skipped, Duration::from_secs(2));
+        assert_eq!(time.overstep(), Duration::from_millis(500));
+        assert_eq!(time.elapsed(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_catch_up_long_absence() {
+        let app = catch_up_app(from_hours(5));
+
+        let caught_up = app.world().resource::<CaughtUp>();
+        assert_eq!(caught_up.0, vec![from_hours(5)]);
+
+        let report = app.world().resource::<AwayReport>();
+        assert_eq!(report.simulated, from_hours(5));
+        assert!(report.skipped.is_zero());
+        assert!(app.world().resource::<Time<SimTime>>().overstep().is_zero());
+    }
+
+    #[test]
+    fn test_catch_up_capped() {
+        let app = catch_up_app(from_days(30));
+
+        let report = app.world().resource::<AwayReport>();
+        assert_eq!(report.simulated, MAX_CATCH_UP);
+        assert_eq!(report.skipped, from_days(23));
+    }
+
+    #[test]
+    fn test_catch_up_pets() {
+        let mut pet = Entity::PLACEHOLDER;
+        let mut hungry = Entity::PLACEHOLDER;
+        let mut egg = Entity::PLACEHOLDER;
+        let mut app = catch_up_pets_app(from_hours(2), |world| {
+            pet = spawn_pet(world, "Alice", 1000.);
+            hungry = spawn_pet(world, "Bob", 100.);
+            egg = world
+                .spawn((
+                    Egg {
+                        contains: "Blob".to_string(),
+                    },
+                    EggHatchAttempt::default(),
+                    RngComponent::with_seed(0),
+                    // Overdue so it hatches whatever the rng says
+                    Age(MAX_EGG_LIFE + from_mins(1)),
+                ))
+                .id();
+        });
+
+        // 7200 seconds at a point a minute and one point every 120 seconds
+        let hunger = app.world().get::<Hunger>(pet).unwrap().value;
+        assert!((hunger - (1000. - 240.)).abs() < 0.01, "{}", hunger);
+        let fun = app.world().get::<Fun>(pet).unwrap().value;
+        assert!((fun - (100. - 60.)).abs() < 0.01, "{}", fun);
+        assert!(app.world().get::<Starving>(pet).is_none());
+        assert_eq!(app.world().get::<Hunger>(hungry).unwrap().value, 0.);
+        assert!(app.world().get::<Starving>(hungry).is_some());
+        assert!(app.world().get::<EggHatchAttempt>(egg).is_none());
+
+        // Only the full pet poops, an hour interval ticks twice as fast when healthy
+        assert_eq!(poop_count(&mut app), 4);
+
+        let report = app.world().resource::<AwayReport>();
+        assert_eq!(report.simulated, from_hours(2));
+        assert_eq!(report.poops_spawned, 4);
+        assert_eq!(report.starved, vec!["Bob".to_string()]);
+        assert_eq!(report.hatched, vec!["Blob".to_string()]);
+    }
+
+    #[test]
+    fn test_catch_up_poops_before_hunger() {
+        // Over half full when they left but under half by the time catch up is done
+        let mut pet = Entity::PLACEHOLDER;
+        let mut app = catch_up_pets_app(from_hours(2), |world| {
+            pet = spawn_pet(world, "Alice", 550.);
+        });
+
+        assert!(app.world().get::<Hunger>(pet).unwrap().filled_percent() <= 0.5);
+        assert_eq!(poop_count(&mut app), 4);
+        assert_eq!(app.world().resource::<AwayReport>().poops_spawned, 4);
+    }
+
+    #[test]
+    fn test_short_absence_not_caught_up() {
+        let app = catch_up_app(from_mins(5));
+
+        assert!(app.world().resource::<CaughtUp>().0.is_empty());
+        assert!(app.world().resource::<AwayReport>().is_empty());
+    }
+}
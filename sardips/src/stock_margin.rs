@@ -0,0 +1,509 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::prelude::*;
+use sardips_core::money_core::Money;
+use sardips_core::persistent_id::PersistentId;
+use serde::{Deserialize, Serialize};
+
+use crate::money::Wallet;
+use crate::stock_market::{
+    Company, OrderBook, OrderKind, OrderType, QuarterReaders, QuarterStepped, ShareHistory,
+    SharePortfolio, StockOrder,
+};
+
+// Share of the position the player has to put up themselves when borrowing
+pub const INITIAL_MARGIN: f32 = 0.5;
+// Positions are liquidated once equity falls under this share of them
+pub const MAINTENANCE_MARGIN: f32 = 0.25;
+// Charged each quarter on the cash loan
+pub const CASH_INTEREST_RATE: f32 = 0.02;
+// Charged each quarter on the value of borrowed shares
+pub const SHORT_FEE_RATE: f32 = 0.01;
+
+// Opt in account that lends the player cash and shares to sell short
+#[derive(Debug, Default, Component, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Component, Serialize, Deserialize)]
+pub struct MarginAccount {
+    pub borrowed_cash: Money,
+    pub borrowed_shares: BTreeMap<PersistentId, u64>,
+}
+
+impl MarginAccount {
+    pub fn owed_shares(&self, company: &PersistentId) -> u64 {
+        *self.borrowed_shares.get(company).unwrap_or(&0)
+    }
+
+    // Cash that can still be borrowed if all of it went into shares without breaking the
+    // initial margin
+    pub fn cash_capacity(&self, status: &MarginStatus) -> Money {
+        let limit = status.equity as f64 * (1. / INITIAL_MARGIN as f64 - 1.);
+        (limit as Money - self.borrowed_cash - status.short_value).max(0)
+    }
+
+    pub fn borrow_cash(
+        &mut self,
+        wallet: &mut Wallet,
+        amount: Money,
+        status: &MarginStatus,
+    ) -> bool {
+        if amount <= 0 || amount > self.cash_capacity(status) {
+            return false;
+        }
+
+        self.borrowed_cash += amount;
+        wallet.balance += amount;
+        true
+    }
+
+    pub fn repay_cash(&mut self, wallet: &mut Wallet, amount: Money) -> Money {
+        let repaid = amount.min(self.borrowed_cash).min(wallet.balance).max(0);
+        self.borrowed_cash -= repaid;
+        wallet.balance -= repaid;
+        repaid
+    }
+
+    // Borrowed shares land in the portfolio so they can be sold like any other
+    pub fn borrow_shares(
+        &mut self,
+        portfolio: &mut SharePortfolio,
+        company: PersistentId,
+        quantity: u64,
+    ) {
+        *self.borrowed_shares.entry(company).or_insert(0) += quantity;
+        portfolio.add_shares(company, quantity);
+    }
+
+    // Gives back any borrowed shares sitting in the portfolio
+    pub fn return_shares(&mut self, portfolio: &mut SharePortfolio) {
+        for (company, owed) in self.borrowed_shares.iter_mut() {
+            let returned = portfolio.get_count(company).min(*owed);
+            portfolio.remove_shares(*company, returned);
+            *owed -= returned;
+        }
+        self.borrowed_shares.retain(|_, owed| *owed > 0);
+    }
+
+    pub fn quarterly_charge(&self, prices: &HashMap<PersistentId, Money>) -> Money {
+        let interest = self.borrowed_cash as f64 * CASH_INTEREST_RATE as f64;
+        let fees = short_value(self, prices) as f64 * SHORT_FEE_RATE as f64;
+        (interest + fees).ceil() as Money
+    }
+}
+
+#[derive(Debug, Default, Component, Clone, Copy, PartialEq)]
+pub struct MarginStatus {
+    pub equity: Money,
+    pub long_value: Money,
+    pub short_value: Money,
+    pub margin_call: bool,
+}
+
+impl MarginStatus {
+    pub fn new(
+        owner: PersistentId,
+        account: &MarginAccount,
+        wallet: &Wallet,
+        portfolio: &SharePortfolio,
+        order_book: &OrderBook,
+        prices: &HashMap<PersistentId, Money>,
+    ) -> Self {
+        let price = |company: &PersistentId| *prices.get(company).unwrap_or(&0);
+
+        // Shares and cash tied up in open orders still belong to the player
+        let mut long_value: Money = portfolio
+            .owned_shares
+            .iter()
+            .map(|(company, count)| price(company) * *count as Money)
+            .sum();
+        let mut escrow = 0;
+        for order in order_book.orders().filter(|order| order.owner == owner) {
+            match (order.kind, order.order_type) {
+                (OrderKind::Sell, _) => {
+                    long_value += price(&order.company) * order.remaining_quantity as Money
+                }
+                (OrderKind::Buy, OrderType::Limit) => {
+                    escrow += order.price * order.remaining_quantity as Money
+                }
+                (OrderKind::Buy, OrderType::Market) => {}
+            }
+        }
+
+        let short_value = short_value(account, prices);
+
+        Self {
+            equity: wallet.balance + escrow + long_value - account.borrowed_cash - short_value,
+            long_value,
+            short_value,
+            margin_call: false,
+        }
+    }
+
+    pub fn exposure(&self) -> Money {
+        self.long_value + self.short_value
+    }
+
+    pub fn ratio(&self) -> Option<f32> {
+        if self.exposure() <= 0 {
+            return None;
+        }
+        Some(self.equity as f32 / self.exposure() as f32)
+    }
+
+    pub fn below_maintenance(&self) -> bool {
+        self.ratio().is_some_and(|ratio| ratio < MAINTENANCE_MARGIN)
+    }
+
+    // Shares that can be sold short at the price without breaking the initial margin
+    pub fn short_capacity(&self, price: Money) -> u64 {
+        let limit = self.equity as f64 / INITIAL_MARGIN as f64 - self.exposure() as f64;
+        (limit / price.max(1) as f64).max(0.) as u64
+    }
+}
+
+fn short_value(account: &MarginAccount, prices: &HashMap<PersistentId, Money>) -> Money {
+    account
+        .borrowed_shares
+        .iter()
+        .map(|(company, owed)| *prices.get(company).unwrap_or(&0) * *owed as Money)
+        .sum()
+}
+
+fn company_prices(
+    companies: &Query<(&PersistentId, &ShareHistory), With<Company>>,
+) -> HashMap<PersistentId, Money> {
+    companies
+        .iter()
+        .map(|(per_id, share_history)| (*per_id, share_history.cached_price))
+        .collect()
+}
+
+pub(crate) fn charge_margin_interest(
+    stepped: Res<Events<QuarterStepped>>,
+    mut readers: ResMut<QuarterReaders>,
+    companies: Query<(&PersistentId, &ShareHistory), With<Company>>,
+    mut accounts: Query<&mut MarginAccount>,
+) {
+    let quarters = readers.margin_interest.read(&stepped).count();
+    if quarters == 0 {
+        return;
+    }
+
+    let prices = company_prices(&companies);
+    for mut account in &mut accounts {
+        for _ in 0..quarters {
+            let charge = account.quarterly_charge(&prices);
+            account.borrowed_cash += charge;
+        }
+    }
+}
+
+// Sells everything and buys back every borrowed share through the book once equity runs too
+// low, paying down the loan with whatever cash the shorts don't need
+pub(crate) fn check_margin(
+    mut commands: Commands,
+    mut order_book: ResMut<OrderBook>,
+    companies: Query<(&PersistentId, &ShareHistory), With<Company>>,
+    mut accounts: Query<(
+        Entity,
+        &PersistentId,
+        &mut MarginAccount,
+        &mut Wallet,
+        &mut SharePortfolio,
+        Option<&MarginStatus>,
+    )>,
+) {
+    let prices = company_prices(&companies);
+
+    for (entity, owner, mut account, mut wallet, mut portfolio, last_status) in &mut accounts {
+        // Positions in delisted companies are gone, borrowed shares included
+        if account
+            .borrowed_shares
+            .keys()
+            .any(|company| !prices.contains_key(company))
+        {
+            account
+                .borrowed_shares
+                .retain(|company, _| prices.contains_key(company));
+        }
+
+        let mut status =
+            MarginStatus::new(*owner, &account, &wallet, &portfolio, &order_book, &prices);
+        let was_called = last_status.is_some_and(|status| status.margin_call);
+        status.margin_call = status.below_maintenance() || (was_called && status.exposure() > 0);
+
+        if status.margin_call {
+            if !was_called {
+                info!("Margin call, liquidating positions for {:?}", owner);
+            }
+
+            // Shares that will go back to the lender aren't sold
+            for (company, count) in portfolio.owned_shares.clone() {
+                let count = count.saturating_sub(account.owed_shares(&company));
+                if count == 0 || !prices.contains_key(&company) {
+                    continue;
+                }
+                portfolio.remove_shares(company, count);
+                order_book.add(StockOrder::new_market_sell(company, count, *owner));
+            }
+
+            // Only buy back what isn't already held or waiting on an order from an earlier tick
+            let buy_backs = account
+                .borrowed_shares
+                .iter()
+                .map(|(company, owed)| {
+                    let pending: u64 = order_book
+                        .orders()
+                        .filter(|order| {
+                            order.owner == *owner
+                                && order.kind == OrderKind::Buy
+                                && order.company == *company
+                        })
+                        .map(|order| order.remaining_quantity)
+                        .sum();
+                    let needed = owed.saturating_sub(portfolio.get_count(company) + pending);
+                    (*company, needed)
+                })
+                .filter(|(_, needed)| *needed > 0)
+                .collect::<Vec<_>>();
+            for (company, needed) in buy_backs {
+                order_book.add(StockOrder::new_market_buy(company, needed, *owner));
+            }
+
+            // Cash needed to buy back the shorts stays put
+            let spare = wallet.balance - status.short_value;
+            account.repay_cash(&mut wallet, spare);
+        }
+
+        commands.entity(entity).insert(status);
+    }
+}
+
+pub(crate) fn settle_borrowed_shares(
+    mut accounts: Query<(&mut MarginAccount, &mut SharePortfolio)>,
+) {
+    for (mut account, mut portfolio) in &mut accounts {
+        if account.borrowed_shares.is_empty() {
+            continue;
+        }
+        account.return_shares(&mut portfolio);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sardips_core::persistent_id::PersistentIdGenerator;
+
+    use super::*;
+    use crate::simulation::{SimulationCatchUp, SimulationUpdate};
+
+    fn status(equity: Money, long_value: Money, short_value: Money) -> MarginStatus {
+        MarginStatus {
+            equity,
+            long_value,
+            short_value,
+            margin_call: false,
+        }
+    }
+
+    #[test]
+    fn test_margin_account() {
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let owner = per_id_gen.next_id();
+        let company = per_id_gen.next_id();
+        let prices = HashMap::from([(company, 100)]);
+
+        let mut account = MarginAccount::default();
+        let mut wallet = Wallet { balance: 1000 };
+        let mut portfolio = SharePortfolio::default();
+        let order_book = OrderBook::default();
+
+        // Short 10 shares and sell them
+        account.borrow_shares(&mut portfolio, company, 10);
+        portfolio.remove_shares(company, 10);
+        wallet.balance += 10 * 100;
+
+        let current = MarginStatus::new(owner, &account, &wallet, &portfolio, &order_book, &prices);
+        assert_eq!(current, status(1000, 0, 1000));
+        assert_eq!(current.short_capacity(100), 10);
+        assert_eq!(account.cash_capacity(&current), 0);
+
+        // The price doubling wipes out the margin
+        let prices = HashMap::from([(company, 200)]);
+        let current = MarginStatus::new(owner, &account, &wallet, &portfolio, &order_book, &prices);
+        assert_eq!(current.equity, 0);
+        assert!(current.below_maintenance());
+
+        assert_eq!(account.quarterly_charge(&prices), 20);
+        account.borrowed_cash = 500;
+        assert_eq!(account.quarterly_charge(&prices), 30);
+        assert_eq!(account.repay_cash(&mut wallet, 10000), 500);
+        assert_eq!(wallet.balance, 1500);
+
+        // Buying the shares back returns them
+        portfolio.add_shares(company, 12);
+        account.return_shares(&mut portfolio);
+        assert_eq!(account.owed_shares(&company), 0);
+        assert_eq!(portfolio.get_count(&company), 2);
+    }
+
+    #[test]
+    fn test_borrow_cash() {
+        let mut account = MarginAccount::default();
+        let mut wallet = Wallet { balance: 1000 };
+
+        assert!(!account.borrow_cash(&mut wallet, 1001, &status(1000, 0, 0)));
+        assert!(account.borrow_cash(&mut wallet, 600, &status(1000, 0, 0)));
+        assert_eq!(wallet.balance, 1600);
+        assert_eq!(account.cash_capacity(&status(1000, 0, 0)), 400);
+        assert_eq!(account.cash_capacity(&status(1000, 600, 300)), 100);
+    }
+
+    #[test]
+    fn test_charge_margin_interest_once_after_catch_up() {
+        let mut app = App::new();
+        app.init_resource::<QuarterReaders>();
+        app.add_event::<QuarterStepped>();
+        app.add_systems(SimulationCatchUp, charge_margin_interest);
+        app.add_systems(SimulationUpdate, charge_margin_interest);
+
+        let account = app
+            .world_mut()
+            .spawn(MarginAccount {
+                borrowed_cash: 1000,
+                ..default()
+            })
+            .id();
+
+        app.world_mut().send_event(QuarterStepped { quarter: 1 });
+        app.world_mut().run_schedule(SimulationCatchUp);
+        app.world_mut().run_schedule(SimulationUpdate);
+
+        assert_eq!(
+            app.world()
+                .get::<MarginAccount>(account)
+                .unwrap()
+                .borrowed_cash,
+            1020
+        );
+    }
+
+    #[test]
+    fn test_check_margin_liquidates() {
+        let mut app = App::new();
+        app.insert_resource(OrderBook::default());
+        app.add_systems(Update, check_margin);
+
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let owner = per_id_gen.next_id();
+        let held = per_id_gen.next_id();
+        let shorted = per_id_gen.next_id();
+
+        app.world_mut()
+            .spawn((Company::default(), held, ShareHistory::new(100)));
+        app.world_mut()
+            .spawn((Company::default(), shorted, ShareHistory::new(300)));
+
+        let mut portfolio = SharePortfolio::default();
+        portfolio.add_shares(held, 10);
+        let account = MarginAccount {
+            borrowed_cash: 500,
+            borrowed_shares: BTreeMap::from([(shorted, 2)]),
+        };
+        // Equity is 100 + 1000 - 500 - 600 against 1600 of positions
+        let player = app
+            .world_mut()
+            .spawn((owner, account, Wallet { balance: 100 }, portfolio))
+            .id();
+
+        app.update();
+
+        let world = app.world();
+        let status = world.get::<MarginStatus>(player).unwrap();
+        assert!(status.margin_call);
+        assert_eq!(
+            world
+                .get::<SharePortfolio>(player)
+                .unwrap()
+                .get_count(&held),
+            0
+        );
+        assert_eq!(
+            world.get::<MarginAccount>(player).unwrap().borrowed_cash,
+            500
+        );
+        assert_eq!(world.get::<Wallet>(player).unwrap().balance, 100);
+
+        let order_book = world.resource::<OrderBook>();
+        let sells = order_book.asks(held).collect::<Vec<_>>();
+        assert_eq!(sells.len(), 1);
+        assert_eq!(sells[0].remaining_quantity, 10);
+        assert_eq!(sells[0].order_type, OrderType::Market);
+        let buys = order_book.bids(shorted).collect::<Vec<_>>();
+        assert_eq!(buys.len(), 1);
+        assert_eq!(buys[0].remaining_quantity, 2);
+
+        // Stays called while the shorts are open and pays the loan from the sales
+        app.insert_resource(OrderBook::default());
+        app.world_mut().get_mut::<Wallet>(player).unwrap().balance = 1100;
+        app.update();
+
+        let world = app.world();
+        assert!(world.get::<MarginStatus>(player).unwrap().margin_call);
+        assert_eq!(world.get::<MarginAccount>(player).unwrap().borrowed_cash, 0);
+        assert_eq!(world.get::<Wallet>(player).unwrap().balance, 600);
+    }
+
+    #[test]
+    fn test_check_margin_doesnt_pile_up_orders() {
+        let mut app = App::new();
+        app.insert_resource(OrderBook::default());
+        app.add_systems(Update, check_margin);
+
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let owner = per_id_gen.next_id();
+        let held = per_id_gen.next_id();
+        let shorted = per_id_gen.next_id();
+
+        app.world_mut()
+            .spawn((Company::default(), held, ShareHistory::new(100)));
+        app.world_mut()
+            .spawn((Company::default(), shorted, ShareHistory::new(300)));
+
+        // One of the shorted shares has already been bought back
+        let mut portfolio = SharePortfolio::default();
+        portfolio.add_shares(held, 10);
+        portfolio.add_shares(shorted, 1);
+        let account = MarginAccount {
+            borrowed_cash: 500,
+            borrowed_shares: BTreeMap::from([(shorted, 3)]),
+        };
+        let player = app
+            .world_mut()
+            .spawn((owner, account, Wallet { balance: 0 }, portfolio))
+            .id();
+
+        // Nothing fills between the ticks
+        app.update();
+        app.update();
+        app.update();
+
+        let world = app.world();
+        assert!(world.get::<MarginStatus>(player).unwrap().margin_call);
+        assert_eq!(
+            world
+                .get::<SharePortfolio>(player)
+                .unwrap()
+                .get_count(&shorted),
+            1
+        );
+
+        let order_book = world.resource::<OrderBook>();
+        let sells = order_book.asks(held).collect::<Vec<_>>();
+        assert_eq!(sells.len(), 1);
+        assert_eq!(sells[0].remaining_quantity, 10);
+        assert_eq!(order_book.asks(shorted).count(), 0);
+        let buys = order_book.bids(shorted).collect::<Vec<_>>();
+        assert_eq!(buys.len(), 1);
+        assert_eq!(buys[0].remaining_quantity, 2);
+    }
+}
@@ -1,17 +1,33 @@
-use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::time::Duration;
 
-use crate::{money::Wallet, sardip_save::SardipLoadingState, simulation::SimulationUpdate};
+use crate::{
+    away_report::{AwayReport, AwayStockChange},
+    clock::Clock,
+    company_template::{CompanyTemplate, CompanyTemplateDatabase},
+    money::Wallet,
+    pet::Pet,
+    player::Player,
+    sardip_save::SardipLoadingState,
+    simulation::{SimulationCatchUp, SimulationUpdate},
+    stock_margin::{charge_margin_interest, check_margin, settle_borrowed_shares, MarginAccount},
+    stock_strategy::{jitter, Quote, TraderMix, TraderStrategy, TradingStrategy},
+    thinking::TryThinkEvent,
+};
+use bevy::ecs::event::ManualEventReader;
 use bevy::prelude::*;
+use fact_db::{Concept, FactDb};
 use sardips_core::money_core::Money;
 use sardips_core::persistent_id::{PersistentId, PersistentIdMapping};
 use sardips_core::rand_utils::{gen_f32_range, gen_f64_range, NewBuilder, WalkerTable};
+use sardips_core::text_translation::{warp_recursive_value_key, KeyString};
 use sardips_core::wrapped_vec::WrappingVec;
 use serde::{Deserialize, Serialize};
 use shared_deps::bevy_turborand::{DelegatedRng, GlobalRng, RngComponent};
 use shared_deps::chrono::{DateTime, Utc};
 use shared_deps::moonshine_save::save::Save;
+use strum::IntoEnumIterator;
 
 pub struct StockMarketPlugin;
 
@@ -22,29 +38,72 @@ impl Plugin for StockMarketPlugin {
             .register_type::<SharePortfolio>()
             .register_type::<OrderBook>()
             .register_type::<QuarterManger>()
+            .register_type::<MacroEconomy>()
             .register_type::<BuySellOrchestrator>()
             .register_type::<StockMarketAI>()
             .register_type::<ShareHistory>()
             .register_type::<CompleteShareOrderHistory>()
+            .register_type::<MarginAccount>()
+            .register_type::<TraderStrategy>()
+            .init_resource::<TraderMix>()
+            .init_resource::<QuarterReaders>()
+            .add_event::<CancelStockOrder>()
+            .add_event::<OrderFilled>()
+            .add_event::<QuarterStepped>()
+            .add_event::<CompanyDelisted>()
+            .add_event::<CompanyListed>()
             .add_systems(
                 OnEnter(SardipLoadingState::Loaded),
                 (
                     create_order_book.run_if(not(resource_exists::<OrderBook>)),
                     create_quarter_manager.run_if(not(resource_exists::<QuarterManger>)),
+                    create_macro_economy.run_if(not(resource_exists::<MacroEconomy>)),
                     create_buy_sell_orchestrator
                         .run_if(not(resource_exists::<BuySellOrchestrator>)),
-                    spawn_companies,
                     spawn_ghosts,
                 ),
             )
-            .add_systems(Update, (add_rng_to_stock_stuff, allocate_stocks))
+            .add_systems(
+                Update,
+                (
+                    // Templates may still be loading when the save finishes
+                    spawn_companies.run_if(
+                        in_state(SardipLoadingState::Loaded)
+                            .and_then(resource_exists::<CompanyTemplateDatabase>),
+                    ),
+                    add_rng_to_stock_stuff,
+                    allocate_stocks,
+                ),
+            )
             .add_systems(
                 SimulationUpdate,
                 (
                     tick_quarter,
+                    pay_dividends,
+                    delist_bankrupt_companies,
+                    list_new_companies,
+                    float_ipos,
                     update_company_price_cache,
+                    charge_margin_interest,
+                    check_margin,
                     generate_buy_sell_activity,
                     process_orders,
+                    settle_borrowed_shares,
+                    think_about_portfolio,
+                    think_about_delisting,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                SimulationCatchUp,
+                (
+                    catch_up_quarters,
+                    pay_dividends,
+                    charge_margin_interest,
+                    delist_bankrupt_companies,
+                    list_new_companies,
+                    update_company_price_cache,
+                    report_listing_changes,
                 )
                     .chain(),
             );
@@ -54,7 +113,7 @@ impl Plugin for StockMarketPlugin {
 #[derive(Default, Component, Reflect)]
 #[reflect(Component)]
 pub struct SharePortfolio {
-    pub owned_shares: HashMap<PersistentId, u64>,
+    pub owned_shares: BTreeMap<PersistentId, u64>,
 }
 
 impl SharePortfolio {
@@ -81,7 +140,20 @@ impl SharePortfolio {
     }
 }
 
-#[derive(Default, Deserialize, Serialize, Clone, Copy, IntoStaticStr, Reflect)]
+#[derive(
+    Debug,
+    Default,
+    Deserialize,
+    Serialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    IntoStaticStr,
+    EnumIter,
+    Reflect,
+)]
 #[reflect_value(Deserialize, Serialize)]
 pub enum Industry {
     #[default]
@@ -116,6 +188,9 @@ pub struct CompanyHistory {
     pub total_shares: u64,
     pub dividend_paid: Money,
     pub performance: PerformanceRanking,
+    // Macro events that were pushing the company around this quarter
+    #[serde(default)]
+    pub economic_events: Vec<EconomicEvent>,
 }
 
 #[derive(Default, Deserialize, Serialize, Clone, Copy, Reflect)]
@@ -132,6 +207,8 @@ pub enum PerformanceRanking {
 }
 
 const REDUCTION_RATE: f64 = 1.0;
+// How far a bias of 1 scales the chance of the best and worst outcomes
+const BIAS_STRENGTH: f32 = 2.0;
 
 impl PerformanceRanking {
     const TRANSITIONS: [PerformanceRanking; 7] = [
@@ -178,31 +255,47 @@ impl PerformanceRanking {
         (range.start * REDUCTION_RATE)..(range.end * REDUCTION_RATE)
     }
 
+    fn transition_weights(&self) -> [u32; 7] {
+        match self {
+            Self::Extraordinary => [50, 100, 100, 250, 250, 200, 50],
+            Self::Excellent => [20, 200, 350, 350, 50, 22, 8],
+            Self::Good => [10, 100, 500, 350, 30, 5, 5],
+            Self::Average => [1, 37, 100, 800, 50, 11, 1],
+            Self::Poor => [5, 5, 30, 350, 500, 100, 10],
+            Self::Terrible => [8, 22, 50, 350, 350, 200, 20],
+            Self::Horrific => [10, 20, 20, 100, 25, 25, 800],
+        }
+    }
+
     pub fn next<T: DelegatedRng>(&self, rng: &mut T) -> Self {
         lazy_static! {
-            static ref EXTRAORDINARY_TRANS: WalkerTable =
-                WalkerTable::new(&[50, 100, 100, 250, 250, 200, 50]);
-            static ref EXCELLENT_TRANS: WalkerTable =
-                WalkerTable::new(&[20, 200, 350, 350, 50, 22, 8]);
-            static ref GOOD_TRANS: WalkerTable = WalkerTable::new(&[10, 100, 500, 350, 30, 5, 5]);
-            static ref AVERAGE_TRANS: WalkerTable = WalkerTable::new(&[1, 37, 100, 800, 50, 11, 1]);
-            static ref POOR_TRANS: WalkerTable = WalkerTable::new(&[5, 5, 30, 350, 500, 100, 10]);
-            static ref TERRIBLE_TRANS: WalkerTable =
-                WalkerTable::new(&[8, 22, 50, 350, 350, 200, 20]);
-            static ref HORRIFIC_TRANS: WalkerTable =
-                WalkerTable::new(&[10, 20, 20, 100, 25, 25, 800]);
-        }
-        let table = match *self {
-            PerformanceRanking::Extraordinary => &*EXTRAORDINARY_TRANS,
-            PerformanceRanking::Excellent => &*EXCELLENT_TRANS,
-            PerformanceRanking::Good => &*GOOD_TRANS,
-            PerformanceRanking::Average => &*AVERAGE_TRANS,
-            PerformanceRanking::Poor => &*POOR_TRANS,
-            PerformanceRanking::Terrible => &*TERRIBLE_TRANS,
-            PerformanceRanking::Horrific => &*HORRIFIC_TRANS,
-        };
+            static ref TRANSITION_TABLES: Vec<WalkerTable> = PerformanceRanking::TRANSITIONS
+                .iter()
+                .map(|ranking| WalkerTable::new(&ranking.transition_weights()[..]))
+                .collect();
+        }
+
+        PerformanceRanking::TRANSITIONS[TRANSITION_TABLES[*self as usize].next_rng(rng)]
+    }
+
+    // Bias from -1 to 1 shifts weight towards worse or better outcomes
+    pub fn next_biased<T: DelegatedRng>(&self, rng: &mut T, bias: f32) -> Self {
+        if bias == 0. {
+            return self.next(rng);
+        }
+
+        let weights = self
+            .transition_weights()
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| {
+                // 1 for Extraordinary down to -1 for Horrific
+                let goodness = (3. - i as f32) / 3.;
+                *weight as f32 * (1. + BIAS_STRENGTH * bias * goodness).max(0.05)
+            })
+            .collect::<Vec<_>>();
 
-        PerformanceRanking::TRANSITIONS[table.next_rng(rng)]
+        PerformanceRanking::TRANSITIONS[WalkerTable::new(&weights[..]).next_rng(rng)]
     }
 }
 
@@ -212,6 +305,8 @@ pub struct ShareHistory {
     pub history: WrappingVec<OrderBrief, 300>,
     pub cached_price: Money,
     pub dirty_price: bool,
+    #[serde(default)]
+    pub candles: PriceCandles,
 }
 
 impl ShareHistory {
@@ -223,6 +318,7 @@ impl ShareHistory {
             history,
             cached_price: starting_price,
             dirty_price: false,
+            candles: PriceCandles::default(),
         }
     }
 
@@ -231,6 +327,11 @@ impl ShareHistory {
         self.dirty_price = true;
     }
 
+    pub fn add_trade(&mut self, price: Money, volume: u64, timestamp: i64, quarter: u32) {
+        self.add_entry(price, volume);
+        self.candles.record(timestamp, quarter, price, volume);
+    }
+
     pub fn update_cached_price(&mut self) {
         if !self.dirty_price {
             return;
@@ -261,6 +362,97 @@ impl ShareHistory {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, EnumIter)]
+pub enum CandleResolution {
+    #[default]
+    Minute,
+    Quarter,
+    Year,
+}
+
+impl CandleResolution {
+    pub fn period(&self, timestamp: i64, quarter: u32) -> i64 {
+        match self {
+            CandleResolution::Minute => timestamp.div_euclid(60),
+            CandleResolution::Quarter => quarter as i64,
+            // Matches the financial years shown by QuarterManger
+            CandleResolution::Year => quarter.saturating_sub(1) as i64 / 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Candle {
+    pub period: i64,
+    pub open: Money,
+    pub high: Money,
+    pub low: Money,
+    pub close: Money,
+    pub volume: u64,
+}
+
+impl Candle {
+    fn new(period: i64, price: Money, volume: u64) -> Self {
+        Self {
+            period,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume,
+        }
+    }
+
+    fn add(&mut self, price: Money, volume: u64) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume += volume;
+    }
+}
+
+// A day of minutes, the coarser resolutions are small enough to keep forever
+pub const MAX_MINUTE_CANDLES: usize = 60 * 24;
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct PriceCandles {
+    minute: Vec<Candle>,
+    quarter: Vec<Candle>,
+    year: Vec<Candle>,
+}
+
+impl PriceCandles {
+    pub fn get(&self, resolution: CandleResolution) -> &[Candle] {
+        match resolution {
+            CandleResolution::Minute => &self.minute,
+            CandleResolution::Quarter => &self.quarter,
+            CandleResolution::Year => &self.year,
+        }
+    }
+
+    pub fn record(&mut self, timestamp: i64, quarter: u32, price: Money, volume: u64) {
+        for resolution in CandleResolution::iter() {
+            let period = resolution.period(timestamp, quarter);
+            let candles = match resolution {
+                CandleResolution::Minute => &mut self.minute,
+                CandleResolution::Quarter => &mut self.quarter,
+                CandleResolution::Year => &mut self.year,
+            };
+
+            match candles.last_mut() {
+                // Trades stamped out of order are folded into the latest candle
+                Some(last) if last.period >= period => last.add(price, volume),
+                _ => candles.push(Candle::new(period, price, volume)),
+            }
+        }
+
+        if self.minute.len() > MAX_MINUTE_CANDLES {
+            let excess = self.minute.len() - MAX_MINUTE_CANDLES;
+            self.minute.drain(..excess);
+        }
+    }
+}
+
 #[derive(Default, Deserialize, Serialize, Clone, Reflect, PartialEq)]
 #[reflect_value(Deserialize, Serialize)]
 pub struct CompanyPerformance {
@@ -313,7 +505,7 @@ impl CompanyPerformance {
             pb_ratio,
             pe_ratio,
             peg_ratio,
-            dividend_yield: 0.0,
+            dividend_yield: history.dividend_paid as f32 / share_price.max(1) as f32,
             stock_price: share_price,
         }
     }
@@ -323,6 +515,7 @@ pub struct CompanyRank {
     pub pe_percentile: f32,
     pub pb_percentile: f32,
     pub peg_percentile: f32,
+    pub dividend_percentile: f32,
 }
 
 impl CompanyRank {
@@ -362,17 +555,34 @@ impl CompanyRank {
                 .unwrap()
         });
 
+        let mut dividend_ranking = company_lookup.clone().into_iter().collect::<Vec<_>>();
+        dividend_ranking.sort_by(|a, b| {
+            companies[*a]
+                .1
+                .dividend_yield
+                .total_cmp(&companies[*b].1.dividend_yield)
+                .reverse()
+        });
+
         let mut company_rankings = HashMap::new();
         for (i, company_index) in company_lookup.iter().enumerate() {
             let pe_rank = pe_ranking.iter().position(|x| *x == i).unwrap();
             let pb_rank = pb_ranking.iter().position(|x| *x == i).unwrap();
             let peg_rank = peg_ranking.iter().position(|x| *x == i).unwrap();
+            // Not paying anything is as bad as it gets
+            let dividend_percentile = if companies[i].1.dividend_yield > 0. {
+                let dividend_rank = dividend_ranking.iter().position(|x| *x == i).unwrap();
+                (dividend_rank + 1) as f32 / companies.len() as f32
+            } else {
+                1.
+            };
             company_rankings.insert(
                 companies[*company_index].0,
                 CompanyRank {
                     pe_percentile: (pe_rank + 1) as f32 / companies.len() as f32,
                     pb_percentile: (pb_rank + 1) as f32 / companies.len() as f32,
                     peg_percentile: (peg_rank + 1) as f32 / companies.len() as f32,
+                    dividend_percentile,
                 },
             );
         }
@@ -389,9 +599,54 @@ pub struct Company {
     pub history: Vec<CompanyHistory>,
     pub performance_history: Vec<CompanyPerformance>,
     pub industries: Vec<(f32, Industry)>,
+    // Quarters in a row the company has ended with negative assets
+    #[serde(default)]
+    pub distressed_quarters: u32,
+    #[serde(default)]
+    pub dividend_policy: Option<DividendPolicy>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Reflect, PartialEq)]
+#[reflect_value(Deserialize, Serialize)]
+pub struct DividendPolicy {
+    // Share of each quarter's profit paid out to shareholders
+    pub payout_ratio: f32,
+}
+
+impl DividendPolicy {
+    // The payout ratio a real world dividend works out to
+    pub fn implied(dividend_per_share: Money, shares: u64, profit: Money) -> Option<Self> {
+        if dividend_per_share <= 0 {
+            return None;
+        }
+
+        let payout_ratio = if profit > 0 {
+            (dividend_per_share as f64 * shares as f64 / profit as f64) as f32
+        } else {
+            DEFAULT_PAYOUT_RATIO
+        };
+
+        Some(Self {
+            payout_ratio: payout_ratio.clamp(MIN_PAYOUT_RATIO, MAX_PAYOUT_RATIO),
+        })
+    }
 }
 
+const MIN_PAYOUT_RATIO: f32 = 0.05;
+const MAX_PAYOUT_RATIO: f32 = 0.9;
+const DEFAULT_PAYOUT_RATIO: f32 = 0.3;
+// Chance a profitable company without a policy starts paying dividends each quarter
+const DECLARE_DIVIDEND_CHANCE: f32 = 0.1;
+
 impl Company {
+    pub fn distressed(&self) -> bool {
+        self.distressed_quarters > 0
+    }
+
+    pub fn bankrupt(&self) -> bool {
+        self.distressed_quarters >= QUARTERS_TO_BANKRUPTCY
+    }
+
     pub fn book_value(&self) -> Money {
         self.history.last().unwrap().assets
     }
@@ -478,22 +733,49 @@ impl BuyThreshold {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Reflect, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Reflect, PartialEq, Eq)]
 #[reflect_value(Deserialize, Serialize)]
 pub enum OrderKind {
     Buy,
     Sell,
 }
 
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, Reflect, PartialEq, Eq)]
+#[reflect_value(Deserialize, Serialize)]
+pub enum OrderType {
+    #[default]
+    Limit,
+    // Takes whatever price is on the book, anything left unfilled is pulled
+    Market,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, Reflect, PartialEq, Eq)]
+#[reflect_value(Deserialize, Serialize)]
+pub enum OrderExpiry {
+    // Pulled after ORDER_PULL_TIME
+    #[default]
+    Timed,
+    GoodTillCancelled,
+    // Pulled once the quarter it was placed in is over
+    GoodTillQuarter(u32),
+}
+
+const ORDER_PULL_TIME: Duration = Duration::from_secs(60);
+
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Reflect)]
 #[reflect_value(Deserialize, Serialize)]
 pub struct StockOrder {
     pub id: u64,
     pub kind: OrderKind,
+    #[serde(default)]
+    pub order_type: OrderType,
+    #[serde(default)]
+    pub expiry: OrderExpiry,
     pub lifetime: Duration,
     pub company: PersistentId,
     pub quantity: u64,
     pub remaining_quantity: u64,
+    // Market orders have no price, they take the other side's
     pub price: Money,
     pub owner: PersistentId,
 }
@@ -508,6 +790,8 @@ impl StockOrder {
         Self {
             id: 0,
             kind: OrderKind::Buy,
+            order_type: OrderType::Limit,
+            expiry: OrderExpiry::Timed,
             lifetime: Duration::ZERO,
             company,
             quantity,
@@ -526,6 +810,8 @@ impl StockOrder {
         Self {
             id: 0,
             kind: OrderKind::Sell,
+            order_type: OrderType::Limit,
+            expiry: OrderExpiry::Timed,
             lifetime: Duration::ZERO,
             company,
             quantity,
@@ -534,6 +820,53 @@ impl StockOrder {
             owner: seller,
         }
     }
+
+    // Paid for as it fills so nothing is taken up front
+    pub fn new_market_buy(company: PersistentId, quantity: u64, buyer: PersistentId) -> Self {
+        Self {
+            order_type: OrderType::Market,
+            ..Self::new_buy(company, quantity, 0, buyer)
+        }
+    }
+
+    // The shares still come out of the seller's portfolio up front
+    pub fn new_market_sell(company: PersistentId, quantity: u64, seller: PersistentId) -> Self {
+        Self {
+            order_type: OrderType::Market,
+            ..Self::new_sell(company, quantity, 0, seller)
+        }
+    }
+
+    pub fn with_expiry(mut self, expiry: OrderExpiry) -> Self {
+        self.expiry = expiry;
+        self
+    }
+
+    // Checked after matching so market orders get one go at the book
+    pub fn expired(&self, current_quarter: u32) -> bool {
+        if self.order_type == OrderType::Market {
+            return true;
+        }
+
+        match self.expiry {
+            OrderExpiry::Timed => self.lifetime > ORDER_PULL_TIME,
+            OrderExpiry::GoodTillCancelled => false,
+            OrderExpiry::GoodTillQuarter(quarter) => quarter != current_quarter,
+        }
+    }
+}
+
+// Pulls an open order, refunded the same way as when it expires
+#[derive(Event)]
+pub struct CancelStockOrder {
+    pub id: u64,
+    pub owner: PersistentId,
+}
+
+impl CancelStockOrder {
+    pub fn new(id: u64, owner: PersistentId) -> Self {
+        Self { id, owner }
+    }
 }
 
 impl PartialOrd for StockOrder {
@@ -561,12 +894,59 @@ impl OrderBrief {
     }
 }
 
+// Bids are sorted highest first and asks lowest first, with ties going to the older (lower id)
+// order. Market orders sort ahead of any price.
+#[derive(Default, Clone)]
+struct CompanyBook {
+    bids: BTreeMap<(Reverse<Money>, u64), StockOrder>,
+    asks: BTreeMap<(Money, u64), StockOrder>,
+}
+
+impl CompanyBook {
+    fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+}
+
+fn book_price(order: &StockOrder) -> Money {
+    match (order.order_type, &order.kind) {
+        (OrderType::Limit, _) => order.price,
+        (OrderType::Market, OrderKind::Buy) => Money::MAX,
+        (OrderType::Market, OrderKind::Sell) => Money::MIN,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trade {
+    pub company: PersistentId,
+    pub buy_id: u64,
+    pub buyer: PersistentId,
+    pub sell_id: u64,
+    pub seller: PersistentId,
+    pub price: Money,
+    pub quantity: u64,
+}
+
+// Sent for each side of every trade, remaining_quantity is 0 once the order is filled
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct OrderFilled {
+    pub id: u64,
+    pub owner: PersistentId,
+    pub company: PersistentId,
+    pub kind: OrderKind,
+    pub price: Money,
+    pub quantity: u64,
+    pub remaining_quantity: u64,
+}
+
 #[derive(Default, Reflect, Clone, Serialize, Deserialize, Resource)]
 #[reflect_value(Deserialize, Serialize, Resource)]
+#[serde(from = "SavedOrderBook", into = "SavedOrderBook")]
 pub struct OrderBook {
     pub top_order_id: u64,
-    pub buy_orders: Vec<StockOrder>,
-    pub sell_orders: HashMap<PersistentId, Vec<StockOrder>>,
+    books: BTreeMap<PersistentId, CompanyBook>,
+    // Where each open order is kept so lookups don't need to scan every book
+    index: BTreeMap<u64, (PersistentId, OrderKind, Money)>,
 }
 
 impl OrderBook {
@@ -576,72 +956,239 @@ impl OrderBook {
         id
     }
 
-    pub fn add(&mut self, mut order: StockOrder) {
+    pub fn add(&mut self, mut order: StockOrder) -> u64 {
         order.id = self.get_next_order_id();
+        let id = order.id;
+        self.insert(order);
+        id
+    }
 
+    fn insert(&mut self, order: StockOrder) {
+        let price = book_price(&order);
+        self.index
+            .insert(order.id, (order.company, order.kind, price));
+
+        let book = self.books.entry(order.company).or_default();
         match order.kind {
             OrderKind::Buy => {
-                self.buy_orders.push(order);
+                book.bids.insert((Reverse(price), order.id), order);
             }
             OrderKind::Sell => {
-                let sell_orders = self.sell_orders.entry(order.company).or_default();
-
-                let index = match sell_orders.binary_search_by(|x| x.price.cmp(&order.price)) {
-                    Ok(i) => i,
-                    Err(i) => i,
-                };
-
-                sell_orders.insert(index, order);
+                book.asks.insert((price, order.id), order);
             }
         }
     }
 
-    pub fn get_sell_order_by_id(&self, id: u64) -> Option<&StockOrder> {
-        for (_, orders) in self.sell_orders.iter() {
-            for order in orders {
-                if order.id == id {
-                    return Some(order);
-                }
-            }
+    pub fn get_order(&self, id: u64) -> Option<&StockOrder> {
+        let (company, kind, price) = self.index.get(&id)?;
+        let book = self.books.get(company)?;
+        match kind {
+            OrderKind::Buy => book.bids.get(&(Reverse(*price), id)),
+            OrderKind::Sell => book.asks.get(&(*price, id)),
         }
+    }
+
+    pub fn remove_order(&mut self, id: u64) -> Option<StockOrder> {
+        let (company, kind, price) = self.index.remove(&id)?;
+        let book = self.books.get_mut(&company)?;
+        let order = match kind {
+            OrderKind::Buy => book.bids.remove(&(Reverse(price), id)),
+            OrderKind::Sell => book.asks.remove(&(price, id)),
+        };
+        if book.is_empty() {
+            self.books.remove(&company);
+        }
+        order
+    }
+
+    // Best price first
+    pub fn bids(&self, company: PersistentId) -> impl Iterator<Item = &StockOrder> {
+        self.books
+            .get(&company)
+            .into_iter()
+            .flat_map(|book| book.bids.values())
+    }
+
+    // Best price first
+    pub fn asks(&self, company: PersistentId) -> impl Iterator<Item = &StockOrder> {
+        self.books
+            .get(&company)
+            .into_iter()
+            .flat_map(|book| book.asks.values())
+    }
+
+    pub fn orders(&self) -> impl Iterator<Item = &StockOrder> {
+        self.books
+            .values()
+            .flat_map(|book| book.bids.values().chain(book.asks.values()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    // Pulls every order for the company
+    pub fn remove_company(&mut self, company: PersistentId) -> Vec<StockOrder> {
+        let book = match self.books.remove(&company) {
+            Some(book) => book,
+            None => return vec![],
+        };
 
-        None
+        let orders = book
+            .bids
+            .into_values()
+            .chain(book.asks.into_values())
+            .collect::<Vec<_>>();
+        for order in &orders {
+            self.index.remove(&order.id);
+        }
+        orders
     }
 
-    pub fn get_buy_order_by_id(&self, id: u64) -> Option<&StockOrder> {
-        self.buy_orders.iter().find(|x| x.id == id)
+    pub fn total_sell_orders(&self) -> usize {
+        self.books.values().map(|book| book.asks.len()).sum()
     }
 
-    pub fn remove_order(&mut self, id: u64) {
-        for i in 0..self.buy_orders.len() {
-            if self.buy_orders[i].id == id {
-                self.buy_orders.remove(i);
-                return;
+    fn tick(&mut self, delta: Duration) {
+        for book in self.books.values_mut() {
+            for order in book.bids.values_mut().chain(book.asks.values_mut()) {
+                order.lifetime += delta;
             }
         }
+    }
+
+    // Removes every order `keep` returns false for
+    pub fn retain(&mut self, mut keep: impl FnMut(&StockOrder) -> bool) {
+        let index = &mut self.index;
+        self.books.retain(|_, book| {
+            book.bids.retain(|_, order| {
+                let kept = keep(order);
+                if !kept {
+                    index.remove(&order.id);
+                }
+                kept
+            });
+            book.asks.retain(|_, order| {
+                let kept = keep(order);
+                if !kept {
+                    index.remove(&order.id);
+                }
+                kept
+            });
+            !book.is_empty()
+        });
+    }
+
+    // Crosses every book in price-time priority. Trades happen at the price of whichever order
+    // was on the book first. `settle` is given the bid, the ask, the price and the most that
+    // can trade and returns how many shares actually changed hands.
+    pub fn match_orders(
+        &mut self,
+        mut settle: impl FnMut(&StockOrder, &StockOrder, Money, u64) -> u64,
+    ) -> Vec<Trade> {
+        let mut trades = vec![];
+
+        for book in self.books.values_mut() {
+            for bid in book.bids.values_mut() {
+                let market_bid = bid.order_type == OrderType::Market;
+
+                for ask in book.asks.values_mut() {
+                    if bid.remaining_quantity == 0 {
+                        break;
+                    }
+
+                    let market_ask = ask.order_type == OrderType::Market;
+                    // Asks are sorted so no later one will cross either
+                    if !market_bid && !market_ask && bid.price < ask.price {
+                        break;
+                    }
+                    // Two market orders have no price to agree on
+                    if ask.remaining_quantity == 0 || (market_bid && market_ask) {
+                        continue;
+                    }
+
+                    let price = if market_bid {
+                        ask.price
+                    } else if market_ask || bid.id < ask.id {
+                        bid.price
+                    } else {
+                        ask.price
+                    };
 
-        for (_, orders) in self.sell_orders.iter_mut() {
-            for i in 0..orders.len() {
-                if orders[i].id == id {
-                    orders.remove(i);
-                    return;
+                    let quantity = bid.remaining_quantity.min(ask.remaining_quantity);
+                    let quantity = settle(bid, ask, price, quantity).min(quantity);
+                    if quantity == 0 {
+                        continue;
+                    }
+
+                    bid.remaining_quantity -= quantity;
+                    ask.remaining_quantity -= quantity;
+
+                    trades.push(Trade {
+                        company: bid.company,
+                        buy_id: bid.id,
+                        buyer: bid.owner,
+                        sell_id: ask.id,
+                        seller: ask.owner,
+                        price,
+                        quantity,
+                    });
                 }
             }
         }
+
+        self.retain(|order| order.remaining_quantity > 0);
+
+        trades
     }
+}
 
-    pub fn get_sell_orders(&self, company: PersistentId) -> &Vec<StockOrder> {
-        lazy_static! {
-            static ref EMPTY: Vec<StockOrder> = Vec::new();
-        }
+// Saved in the same shape as the old flat book so existing saves still load
+#[derive(Serialize, Deserialize)]
+struct SavedOrderBook {
+    top_order_id: u64,
+    buy_orders: Vec<StockOrder>,
+    sell_orders: BTreeMap<PersistentId, Vec<StockOrder>>,
+}
 
-        self.sell_orders
-            .get(&company)
-            .map_or(&(*EMPTY), |orders| orders)
+impl From<SavedOrderBook> for OrderBook {
+    fn from(saved: SavedOrderBook) -> Self {
+        let mut order_book = OrderBook {
+            top_order_id: saved.top_order_id,
+            ..default()
+        };
+        for order in saved
+            .buy_orders
+            .into_iter()
+            .chain(saved.sell_orders.into_values().flatten())
+        {
+            order_book.top_order_id = order_book.top_order_id.max(order.id + 1);
+            order_book.insert(order);
+        }
+        order_book
     }
+}
 
-    pub fn total_sell_orders(&self) -> usize {
-        self.sell_orders.values().map(|x| x.len()).sum()
+impl From<OrderBook> for SavedOrderBook {
+    fn from(order_book: OrderBook) -> Self {
+        let mut saved = SavedOrderBook {
+            top_order_id: order_book.top_order_id,
+            buy_orders: vec![],
+            sell_orders: BTreeMap::new(),
+        };
+        for (company, book) in order_book.books {
+            saved.buy_orders.extend(book.bids.into_values());
+            if !book.asks.is_empty() {
+                saved
+                    .sell_orders
+                    .insert(company, book.asks.into_values().collect());
+            }
+        }
+        saved
     }
 }
 
@@ -653,6 +1200,10 @@ fn create_quarter_manager(mut commands: Commands) {
     commands.insert_resource(QuarterManger::default());
 }
 
+fn create_macro_economy(mut commands: Commands) {
+    commands.insert_resource(MacroEconomy::default());
+}
+
 fn create_buy_sell_orchestrator(mut commands: Commands) {
     commands.insert_resource(BuySellOrchestrator::default());
 }
@@ -662,196 +1213,17 @@ struct StocksToAllocate {
     pub to_allocate: u64,
 }
 
-fn spawn_companies(mut commands: Commands, existing_companies: Query<Entity, With<Company>>) {
-    struct CompanyTemplate {
-        pub ticker: &'static str,
-        pub industries: Vec<(f32, Industry)>,
-        pub stock_price: Money,
-        #[allow(dead_code)]
-        pub market_cap: Money,
-        pub revenue: Money,
-        pub expenses: Money,
-        pub dividend_paid: Money,
-        pub assets: Money,
-        pub outstanding_shares: u64,
-    }
-
-    let company_templates = vec![
-        CompanyTemplate {
-            ticker: "GROUP",
-            industries: vec![(1., Industry::Tech)],
-            stock_price: 28400,
-            market_cap: 11700000000000,
-            revenue: 730000000000,
-            expenses: 747000000000,
-            dividend_paid: 0,
-            assets: 190000000000,
-            outstanding_shares: 261147000,
-        },
-        CompanyTemplate {
-            ticker: "TSFT",
-            industries: vec![
-                (0.9, Industry::Tech),
-                (0.08, Industry::Entertainment),
-                (0.02, Industry::Retail),
-            ],
-            stock_price: 40400,
-            market_cap: 473800000000000,
-            revenue: 39838000000000,
-            expenses: 22569000000000,
-            dividend_paid: 481,
-            assets: 47266000000000,
-            outstanding_shares: 7435000000,
-        },
-        CompanyTemplate {
-            ticker: "CENT",
-            industries: vec![(0.9, Industry::Tech), (0.1, Industry::Entertainment)],
-            stock_price: 18100,
-            market_cap: 3435000000000,
-            revenue: 53288000000000,
-            expenses: 34392642014200,
-            dividend_paid: 2000,
-            assets: 70111550000000,
-            outstanding_shares: 12343000000,
-        },
-        CompanyTemplate {
-            ticker: "COBA",
-            industries: vec![
-                (0.67, Industry::Retail),
-                (0.25, Industry::Tech),
-                (0.04, Industry::Healthcare),
-                (0.03, Industry::Food),
-                (0.1, Industry::Entertainment),
-            ],
-            stock_price: 33600,
-            market_cap: 3560000000000,
-            revenue: 972570000000,
-            expenses: 864220000000,
-            dividend_paid: 00,
-            assets: 460310000000,
-            outstanding_shares: 10501000000,
-        },
-        CompanyTemplate {
-            ticker: "NFLM",
-            industries: vec![(1.0, Industry::Entertainment)],
-            stock_price: 155000,
-            market_cap: 663110000000,
-            revenue: 59330000000,
-            expenses: 59330000000 - 16230000000,
-            dividend_paid: 0,
-            assets: 39820000000,
-            outstanding_shares: 428239000,
-        },
-        CompanyTemplate {
-            ticker: "USBNK",
-            industries: vec![(1.0, Industry::Finance)],
-            stock_price: 15500,
-            market_cap: 260810000000,
-            revenue: 27100000000,
-            expenses: 27100000000 - 14340000000,
-            dividend_paid: 416,
-            assets: 78740000000,
-            outstanding_shares: 1690000000,
-        },
-        CompanyTemplate {
-            ticker: "CBANK",
-            industries: vec![(1.0, Industry::Finance)],
-            stock_price: 119,
-            market_cap: 328050000000,
-            revenue: 130940000000,
-            expenses: 60990000000,
-            dividend_paid: 3,
-            assets: 660120000000,
-            outstanding_shares: 300852631579,
-        },
-        CompanyTemplate {
-            ticker: "TELCO",
-            industries: vec![(1.0, Industry::Telecommunications)],
-            stock_price: 416,
-            market_cap: 47660000000,
-            revenue: 22360000000,
-            expenses: 22360000000 - 2860000000,
-            dividend_paid: 16,
-            assets: 18690000000,
-            outstanding_shares: 11543000000,
-        },
-        CompanyTemplate {
-            ticker: "KTEL",
-            industries: vec![(1.0, Industry::Telecommunications)],
-            stock_price: 2763,
-            market_cap: 13580000000,
-            revenue: 29510000000,
-            expenses: 29510000000 - 1730000000,
-            dividend_paid: 287,
-            assets: 23590000000,
-            outstanding_shares: 491651550,
-        },
-        CompanyTemplate {
-            ticker: "RR",
-            industries: vec![(1.0, Industry::Mining)],
-            stock_price: 9751,
-            market_cap: 161480000000,
-            revenue: 87010000000,
-            expenses: 87010000000 - 25340000000,
-            dividend_paid: 700,
-            assets: 93330000000,
-            outstanding_shares: 1621400000,
-        },
-        CompanyTemplate {
-            ticker: "PPCP",
-            industries: vec![(1.0, Industry::Mining)],
-            stock_price: 7806,
-            market_cap: 197530000000,
-            revenue: 86650000000,
-            expenses: 86650000000 - 37920000000,
-            dividend_paid: 470,
-            assets: 79090000000,
-            outstanding_shares: 2532000000,
-        },
-        CompanyTemplate {
-            ticker: "EFARM",
-            industries: vec![
-                (0.7, Industry::Retail),
-                (0.25, Industry::Manufacturing),
-                (0.05, Industry::Healthcare),
-            ],
-            stock_price: 7416,
-            market_cap: 84170000000,
-            revenue: 43410000000,
-            expenses: 43410000000 - 3640000000,
-            dividend_paid: 198,
-            assets: 9250000000,
-            outstanding_shares: 1132000000,
-        },
-        CompanyTemplate {
-            ticker: "GAMGO",
-            industries: vec![(1.0, Industry::Retail)],
-            stock_price: 4032,
-            market_cap: 18010000000,
-            revenue: 6940000000,
-            expenses: 6940000000 + 3460000,
-            dividend_paid: 15,
-            assets: 7730000000,
-            outstanding_shares: 437400000,
-        },
-        // CompanyTemplate {
-        //     ticker: "TEMP",
-        //     industries: vec![(1.0, Industry::Entertainment)],
-        //     stock_price: 0,
-        //     market_cap: 0,
-        //     revenue: 0,
-        //     expenses: 0,
-        //     dividend_paid: 0,
-        //     assets: 0,
-        //     outstanding_shares: 0,
-        // },
-    ];
-
+fn spawn_companies(
+    mut commands: Commands,
+    mut global_rng: ResMut<GlobalRng>,
+    templates: Res<CompanyTemplateDatabase>,
+    existing_companies: Query<Entity, With<Company>>,
+) {
     if existing_companies.iter().next().is_some() {
         return;
     }
 
-    for template in company_templates {
+    for template in templates.starting() {
         let entity = commands.spawn_empty().id();
 
         let shares_to_allocate = if template.outstanding_shares > 10000000 {
@@ -862,27 +1234,13 @@ fn spawn_companies(mut commands: Commands, existing_companies: Query<Entity, Wit
 
         commands.entity(entity).insert((
             CompanyBundle {
-                company: Company {
-                    ticker: template.ticker.to_string(),
-                    existing_shares: template.outstanding_shares,
-                    history: vec![CompanyHistory {
-                        quarter: 0,
-                        assets: template.assets * 100,
-                        revenue: template.revenue * 100,
-                        expenses: template.expenses * 100,
-                        total_shares: template.outstanding_shares,
-                        dividend_paid: template.dividend_paid,
-                        performance: PerformanceRanking::Average,
-                    }],
-                    performance_history: vec![],
-                    industries: template.industries,
-                },
+                company: template.company(0),
                 share_history: ShareHistory::new(template.stock_price),
                 wallet: Wallet::default(),
                 share_portfolio: SharePortfolio {
-                    owned_shares: hashmap! {},
+                    owned_shares: BTreeMap::new(),
                 },
-                rng: RngComponent::new(),
+                rng: RngComponent::from(&mut global_rng),
                 save: Save,
             },
             StocksToAllocate {
@@ -894,6 +1252,7 @@ fn spawn_companies(mut commands: Commands, existing_companies: Query<Entity, Wit
 
 fn add_rng_to_stock_stuff(
     mut commands: Commands,
+    mut global_rng: ResMut<GlobalRng>,
     query: Query<
         Entity,
         (
@@ -903,7 +1262,9 @@ fn add_rng_to_stock_stuff(
     >,
 ) {
     for entity in query.iter() {
-        commands.entity(entity).insert(RngComponent::new());
+        commands
+            .entity(entity)
+            .insert(RngComponent::from(&mut global_rng));
     }
 }
 
@@ -950,6 +1311,7 @@ fn allocate_stocks(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn step_company_quarter<T: DelegatedRng>(
     quarter: u32,
     company_per_id: PersistentId,
@@ -957,11 +1319,15 @@ pub fn step_company_quarter<T: DelegatedRng>(
     share_history: &ShareHistory,
     wallet: &mut Wallet,
     order_book: &mut OrderBook,
+    economy: &MacroEconomy,
     rng: &mut T,
 ) {
     let last = company.history.last().unwrap();
 
-    let next_perf = last.performance.next(rng);
+    let next_perf = last
+        .performance
+        .next_biased(rng, economy.bias(&company.industries));
+    let economic_events = economy.events_for(&company.industries);
 
     let mut change_func = |last: Money, range: std::ops::Range<f64>| {
         let change = ((last as f64) * gen_f64_range(rng, &range)) as Money;
@@ -1009,12 +1375,32 @@ pub fn step_company_quarter<T: DelegatedRng>(
         ));
     }
 
+    // Profitable companies may start paying out, a loss suspends it
+    company.dividend_policy = match company.dividend_policy {
+        Some(_) if profit <= 0 => None,
+        None if profit > 0 && rng.f32() < DECLARE_DIVIDEND_CHANCE => Some(DividendPolicy {
+            payout_ratio: gen_f32_range(rng, &(MIN_PAYOUT_RATIO..MAX_PAYOUT_RATIO)),
+        }),
+        policy => policy,
+    };
+
+    // Per share, paid out to holders by pay_dividends
+    let next_dividend_paid = match company.dividend_policy {
+        Some(policy) if company.existing_shares > 0 => {
+            (profit as f64 * policy.payout_ratio as f64 / company.existing_shares as f64) as Money
+        }
+        _ => 0,
+    };
+    let next_assets = next_assets
+        .saturating_sub(next_dividend_paid.saturating_mul(company.existing_shares as Money));
+
+    // Delisted by delist_bankrupt_companies once this hits QUARTERS_TO_BANKRUPTCY
     if next_assets < 0 {
-        // TODO handle bankruptcy
+        company.distressed_quarters += 1;
+    } else {
+        company.distressed_quarters = 0;
     }
 
-    let next_dividend_paid = 0;
-
     wallet.balance = 0;
 
     company.history.push(CompanyHistory {
@@ -1025,6 +1411,7 @@ pub fn step_company_quarter<T: DelegatedRng>(
         total_shares: last.total_shares,
         dividend_paid: next_dividend_paid,
         performance: next_perf,
+        economic_events,
     });
 
     update_company_performance(company, share_history);
@@ -1051,6 +1438,10 @@ pub struct QuarterManger {
 }
 
 impl QuarterManger {
+    pub fn current_quarter(&self) -> u32 {
+        self.current_quarter
+    }
+
     pub fn percent_complete(&self) -> f32 {
         self.quarter_timer.elapsed().as_secs_f32() / self.quarter_timer.duration().as_secs_f32()
     }
@@ -1083,28 +1474,198 @@ impl Default for QuarterManger {
     }
 }
 
-fn tick_quarter(
-    time: Res<Time>,
-    mut quarter_manager: ResMut<QuarterManger>,
-    mut order_book: ResMut<OrderBook>,
-    mut companies: Query<(
-        &PersistentId,
-        &mut Company,
-        &ShareHistory,
-        &mut SharePortfolio,
-        &mut Wallet,
-        &mut RngComponent,
-    )>,
-) {
-    if quarter_manager
-        .quarter_timer
-        .tick(time.delta())
-        .just_finished()
-        || quarter_manager.current_quarter == 0
-    {
-        quarter_manager.current_quarter += 1;
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect_value(Deserialize, Serialize)]
+pub enum EconomicEvent {
+    Boom,
+    Recession,
+    IndustryBoom(Industry),
+    IndustryCrisis(Industry),
+}
 
-        for (per_id, mut company, share_history, mut _portfolio, mut wallet, rng) in
+impl EconomicEvent {
+    // How hard it pushes the performance of a company fully in the affected industry
+    fn bias(&self) -> f32 {
+        match self {
+            Self::Boom => 0.3,
+            Self::Recession => -0.3,
+            Self::IndustryBoom(_) => 0.5,
+            Self::IndustryCrisis(_) => -0.5,
+        }
+    }
+
+    fn industry(&self) -> Option<Industry> {
+        match self {
+            Self::Boom | Self::Recession => None,
+            Self::IndustryBoom(industry) | Self::IndustryCrisis(industry) => Some(*industry),
+        }
+    }
+
+    // Share of a company with these industries that the event applies to
+    fn exposure(&self, industries: &[(f32, Industry)]) -> f32 {
+        match self.industry() {
+            Some(affected) => industries
+                .iter()
+                .filter(|(_, industry)| *industry == affected)
+                .map(|(weight, _)| weight)
+                .sum(),
+            None => 1.,
+        }
+    }
+
+    pub fn headline(&self) -> KeyString {
+        match self {
+            Self::Boom => KeyString::direct(text_keys::STOCK_NEWS_BOOM),
+            Self::Recession => KeyString::direct(text_keys::STOCK_NEWS_RECESSION),
+            Self::IndustryBoom(industry) => KeyString::value(
+                text_keys::STOCK_NEWS_INDUSTRY_BOOM.to_string(),
+                &[warp_recursive_value_key(industry.name_key())],
+            ),
+            Self::IndustryCrisis(industry) => KeyString::value(
+                text_keys::STOCK_NEWS_INDUSTRY_CRISIS.to_string(),
+                &[warp_recursive_value_key(industry.name_key())],
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct ActiveEconomicEvent {
+    pub event: EconomicEvent,
+    pub quarters_left: u32,
+}
+
+// Chance each quarter of the market tipping into a boom or recession when there isn't one
+const MARKET_CYCLE_CHANCE: f32 = 0.08;
+const MARKET_CYCLE_QUARTERS: std::ops::RangeInclusive<u32> = 4..=12;
+const INDUSTRY_SHOCK_CHANCE: f32 = 0.1;
+const INDUSTRY_SHOCK_QUARTERS: std::ops::RangeInclusive<u32> = 2..=6;
+
+// Market cycles and industry shocks that bias every company they touch
+#[derive(Resource, Default, Deserialize, Serialize, Clone, Reflect)]
+#[reflect_value(Deserialize, Serialize, Resource)]
+pub struct MacroEconomy {
+    events: Vec<ActiveEconomicEvent>,
+}
+
+impl MacroEconomy {
+    pub fn events(&self) -> impl Iterator<Item = &ActiveEconomicEvent> {
+        self.events.iter()
+    }
+
+    pub fn start(&mut self, event: EconomicEvent, quarters: u32) {
+        self.events.push(ActiveEconomicEvent {
+            event,
+            quarters_left: quarters,
+        });
+    }
+
+    // Ages out finished events and maybe starts new ones, returning what started
+    pub fn step<T: DelegatedRng>(&mut self, rng: &mut T) -> Vec<EconomicEvent> {
+        for active in &mut self.events {
+            active.quarters_left = active.quarters_left.saturating_sub(1);
+        }
+        self.events.retain(|active| active.quarters_left > 0);
+
+        let mut started = vec![];
+
+        let in_cycle = self
+            .events
+            .iter()
+            .any(|active| active.event.industry().is_none());
+        if !in_cycle && rng.f32() < MARKET_CYCLE_CHANCE {
+            let event = if rng.bool() {
+                EconomicEvent::Boom
+            } else {
+                EconomicEvent::Recession
+            };
+            self.start(event, rng.u32(MARKET_CYCLE_QUARTERS));
+            started.push(event);
+        }
+
+        if rng.f32() < INDUSTRY_SHOCK_CHANCE {
+            let calm = Industry::iter()
+                .filter(|industry| {
+                    !self
+                        .events
+                        .iter()
+                        .any(|active| active.event.industry() == Some(*industry))
+                })
+                .collect::<Vec<_>>();
+            if !calm.is_empty() {
+                let industry = calm[rng.usize(0..calm.len())];
+                let event = if rng.bool() {
+                    EconomicEvent::IndustryBoom(industry)
+                } else {
+                    EconomicEvent::IndustryCrisis(industry)
+                };
+                self.start(event, rng.u32(INDUSTRY_SHOCK_QUARTERS));
+                started.push(event);
+            }
+        }
+
+        started
+    }
+
+    pub fn bias(&self, industries: &[(f32, Industry)]) -> f32 {
+        self.events
+            .iter()
+            .map(|active| active.event.bias() * active.event.exposure(industries))
+            .sum::<f32>()
+            .clamp(-0.9, 0.9)
+    }
+
+    pub fn events_for(&self, industries: &[(f32, Industry)]) -> Vec<EconomicEvent> {
+        self.events
+            .iter()
+            .filter(|active| active.event.exposure(industries) > 0.)
+            .map(|active| active.event)
+            .collect()
+    }
+}
+
+// Sent after every company has been stepped into `quarter`
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuarterStepped {
+    pub quarter: u32,
+}
+
+// Consumers of QuarterStepped run in both simulation schedules. Their place is kept here
+// rather than in each system so a quarter stepped during catch up isn't read again by the
+// update that runs straight after it.
+#[derive(Resource, Default)]
+pub(crate) struct QuarterReaders {
+    pub dividends: ManualEventReader<QuarterStepped>,
+    pub margin_interest: ManualEventReader<QuarterStepped>,
+    pub listings: ManualEventReader<QuarterStepped>,
+}
+
+fn tick_quarter(
+    time: Res<Time>,
+    mut quarter_manager: ResMut<QuarterManger>,
+    mut order_book: ResMut<OrderBook>,
+    mut economy: ResMut<MacroEconomy>,
+    mut global_rng: ResMut<GlobalRng>,
+    mut stepped: EventWriter<QuarterStepped>,
+    mut companies: Query<(
+        &PersistentId,
+        &mut Company,
+        &ShareHistory,
+        &mut SharePortfolio,
+        &mut Wallet,
+        &mut RngComponent,
+    )>,
+) {
+    if quarter_manager
+        .quarter_timer
+        .tick(time.delta())
+        .just_finished()
+        || quarter_manager.current_quarter == 0
+    {
+        quarter_manager.current_quarter += 1;
+        economy.step(&mut *global_rng);
+
+        for (per_id, mut company, share_history, mut _portfolio, mut wallet, rng) in
             companies.iter_mut()
         {
             let rng = rng.into_inner();
@@ -1116,9 +1677,384 @@ fn tick_quarter(
                 share_history,
                 &mut wallet,
                 &mut order_book,
+                &economy,
+                rng,
+            );
+        }
+
+        stepped.send(QuarterStepped {
+            quarter: quarter_manager.current_quarter,
+        });
+    }
+}
+
+// How much the player's shares have to move in a quarter for pets to notice
+const PORTFOLIO_SWING: f64 = 0.2;
+
+// Count and price of every holding when the quarter was last looked at
+type PortfolioSnapshot = HashMap<PersistentId, (u64, Money)>;
+
+// Only the shares held at the snapshot count so buying or selling doesn't read as a swing
+fn portfolio_price_change(
+    last: &PortfolioSnapshot,
+    prices: &HashMap<PersistentId, Money>,
+) -> Option<f64> {
+    let (moved, held) = last
+        .iter()
+        .fold((0, 0), |(moved, held), (id, (count, then))| {
+            // A company that's gone is worth nothing
+            let now = prices.get(id).copied().unwrap_or(0);
+            let count = *count as Money;
+            (moved + count * (now - then), held + count * then)
+        });
+
+    if held <= 0 {
+        return None;
+    }
+
+    Some(moved as f64 / held as f64)
+}
+
+fn think_about_portfolio(
+    mut last: Local<Option<(u32, PortfolioSnapshot)>>,
+    mut try_think_events: EventWriter<TryThinkEvent>,
+    quarter_manager: Res<QuarterManger>,
+    player: Query<&SharePortfolio, With<Player>>,
+    companies: Query<(&PersistentId, &ShareHistory), With<Company>>,
+    pets: Query<Entity, With<Pet>>,
+) {
+    let quarter = quarter_manager.current_quarter();
+    if last
+        .as_ref()
+        .is_some_and(|(last_quarter, _)| *last_quarter == quarter)
+    {
+        return;
+    }
+
+    let Ok(portfolio) = player.get_single() else {
+        return;
+    };
+    let prices: HashMap<PersistentId, Money> = companies
+        .iter()
+        .map(|(id, history)| (*id, history.cached_price))
+        .collect();
+    let snapshot: PortfolioSnapshot = prices
+        .iter()
+        .filter_map(|(id, price)| {
+            let count = portfolio.get_count(id);
+            (count > 0).then_some((*id, (count, *price)))
+        })
+        .collect();
+
+    let Some((_, last_snapshot)) = last.replace((quarter, snapshot)) else {
+        return;
+    };
+    let Some(change) = portfolio_price_change(&last_snapshot, &prices) else {
+        return;
+    };
+    let concept = if change >= PORTFOLIO_SWING {
+        Concept::ThinkPortfolioRising
+    } else if change <= -PORTFOLIO_SWING {
+        Concept::ThinkPortfolioCrashing
+    } else {
+        return;
+    };
+
+    for pet in pets.iter() {
+        let mut fact_db = FactDb::default();
+        fact_db.add("PortfolioChange", change as f32);
+        try_think_events.send(TryThinkEvent::new(pet, concept).with_facts(fact_db));
+    }
+}
+
+// Only steps the quarters, trading while away would just be noise
+fn catch_up_quarters(
+    time: Res<Time>,
+    mut report: ResMut<AwayReport>,
+    mut quarter_manager: ResMut<QuarterManger>,
+    mut order_book: ResMut<OrderBook>,
+    mut economy: ResMut<MacroEconomy>,
+    mut global_rng: ResMut<GlobalRng>,
+    mut stepped: EventWriter<QuarterStepped>,
+    player: Query<&SharePortfolio, With<Player>>,
+    mut companies: Query<
+        (
+            &PersistentId,
+            &mut Company,
+            &ShareHistory,
+            &mut Wallet,
+            &mut RngComponent,
+        ),
+        Without<Player>,
+    >,
+) {
+    let quarters = quarter_manager
+        .quarter_timer
+        .tick(time.delta())
+        .times_finished_this_tick();
+    if quarters == 0 {
+        return;
+    }
+
+    let held = |per_id: &PersistentId| {
+        player
+            .iter()
+            .any(|portfolio| portfolio.get_count(per_id) > 0)
+    };
+
+    let start_assets: HashMap<PersistentId, Money> = companies
+        .iter()
+        .filter(|(per_id, ..)| held(per_id))
+        .map(|(per_id, company, ..)| (*per_id, company.book_value()))
+        .collect();
+
+    for _ in 0..quarters {
+        quarter_manager.current_quarter += 1;
+        economy.step(&mut *global_rng);
+
+        for (per_id, mut company, share_history, mut wallet, rng) in companies.iter_mut() {
+            let rng = rng.into_inner();
+
+            step_company_quarter(
+                quarter_manager.current_quarter,
+                *per_id,
+                &mut company,
+                share_history,
+                &mut wallet,
+                &mut order_book,
+                &economy,
                 rng,
             );
         }
+
+        stepped.send(QuarterStepped {
+            quarter: quarter_manager.current_quarter,
+        });
+    }
+
+    report.quarters_passed += quarters;
+
+    for (per_id, company, ..) in companies.iter() {
+        if let Some(start_assets) = start_assets.get(per_id) {
+            report.stock_changes.push(AwayStockChange {
+                name_key: company.name_key(),
+                start_assets: *start_assets,
+                end_assets: company.book_value(),
+            });
+        }
+    }
+}
+
+// Sent when a bankrupt company is removed, player_shares is how many the player lost with it
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct CompanyDelisted {
+    pub company: PersistentId,
+    pub ticker: String,
+    pub name_key: String,
+    pub player_shares: u64,
+}
+
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct CompanyListed {
+    pub ticker: String,
+    pub name_key: String,
+}
+
+const QUARTERS_TO_BANKRUPTCY: u32 = 4;
+// Chance each quarter of a new company listing
+const IPO_CHANCE: f64 = 0.15;
+// Below this a company lists every quarter
+const MIN_COMPANIES: usize = 12;
+const MAX_COMPANIES: usize = 20;
+// Most shares a company puts up for sale when it lists
+const MAX_IPO_FLOAT: u64 = 1000000;
+
+fn delist_bankrupt_companies(
+    mut commands: Commands,
+    mut order_book: ResMut<OrderBook>,
+    per_id_map: Res<PersistentIdMapping>,
+    companies: Query<(Entity, &PersistentId, &Company)>,
+    mut portfolios: Query<(&mut SharePortfolio, Has<Player>)>,
+    mut wallets: Query<&mut Wallet>,
+    mut delisted: EventWriter<CompanyDelisted>,
+) {
+    for (entity, per_id, company) in &companies {
+        if !company.bankrupt() {
+            continue;
+        }
+
+        // Shares for sale are worthless now but buyers get their money back
+        for order in order_book.remove_company(*per_id) {
+            if order.kind != OrderKind::Buy {
+                continue;
+            }
+            if let Ok(mut wallet) = wallets.get_mut(per_id_map.get(order.owner)) {
+                wallet.balance += order.remaining_quantity as i64 * order.price;
+            }
+        }
+
+        let mut player_shares = 0;
+        for (mut portfolio, is_player) in &mut portfolios {
+            let shares = portfolio.owned_shares.remove(per_id).unwrap_or(0);
+            if is_player {
+                player_shares += shares;
+            }
+        }
+
+        info!("Delisting bankrupt company {}", company.ticker);
+
+        commands.entity(entity).despawn_recursive();
+        delisted.send(CompanyDelisted {
+            company: *per_id,
+            ticker: company.ticker.clone(),
+            name_key: company.name_key(),
+            player_shares,
+        });
+    }
+}
+
+// Picks the template whose main industry has the least weight on the market
+fn pick_ipo_template<'a>(
+    templates: impl Iterator<Item = &'a CompanyTemplate>,
+    listed: &HashSet<String>,
+    weights: &HashMap<Industry, f32>,
+) -> Option<&'a CompanyTemplate> {
+    templates
+        .filter(|template| !listed.contains(&template.ticker))
+        .min_by(|a, b| {
+            let a = weights.get(&a.main_industry()).copied().unwrap_or(0.);
+            let b = weights.get(&b.main_industry()).copied().unwrap_or(0.);
+            a.total_cmp(&b)
+        })
+}
+
+#[derive(Component)]
+struct PendingIpo {
+    shares: u64,
+    price: Money,
+}
+
+fn list_new_companies(
+    mut commands: Commands,
+    mut global_rng: ResMut<GlobalRng>,
+    stepped: Res<Events<QuarterStepped>>,
+    mut readers: ResMut<QuarterReaders>,
+    mut listed_events: EventWriter<CompanyListed>,
+    templates: Option<Res<CompanyTemplateDatabase>>,
+    companies: Query<&Company>,
+) {
+    let stepped = readers.listings.read(&stepped).copied().collect::<Vec<_>>();
+    // Templates are still loading, those quarters go by without an IPO
+    let Some(templates) = templates else {
+        return;
+    };
+    if stepped.is_empty() {
+        return;
+    }
+
+    let mut listed = HashSet::new();
+    let mut weights: HashMap<Industry, f32> = Industry::iter().map(|i| (i, 0.)).collect();
+    for company in &companies {
+        listed.insert(company.ticker.clone());
+        for (weight, industry) in &company.industries {
+            *weights.entry(*industry).or_default() += weight;
+        }
+    }
+
+    for stepped in stepped {
+        if listed.len() >= MAX_COMPANIES
+            || (listed.len() >= MIN_COMPANIES && global_rng.f64() > IPO_CHANCE)
+        {
+            continue;
+        }
+
+        let template = match pick_ipo_template(templates.ipos(), &listed, &weights) {
+            Some(template) => template,
+            None => break,
+        };
+
+        let mut company = template.company(stepped.quarter);
+        let share_history = ShareHistory::new(template.stock_price);
+        update_company_performance(&mut company, &share_history);
+
+        listed.insert(company.ticker.clone());
+        for (weight, industry) in &company.industries {
+            *weights.entry(*industry).or_default() += weight;
+        }
+        listed_events.send(CompanyListed {
+            ticker: company.ticker.clone(),
+            name_key: company.name_key(),
+        });
+
+        info!("Listing new company {}", company.ticker);
+
+        commands.spawn((
+            CompanyBundle {
+                company,
+                share_history,
+                wallet: Wallet::default(),
+                share_portfolio: SharePortfolio::default(),
+                rng: RngComponent::from(&mut global_rng),
+                save: Save,
+            },
+            PendingIpo {
+                shares: template.outstanding_shares.min(MAX_IPO_FLOAT),
+                price: template.stock_price,
+            },
+        ));
+    }
+}
+
+// Waits for the new company to have a PersistentId before putting its shares up for sale. The
+// float lasts the quarter it goes up in, which is later than the listing when caught up.
+fn float_ipos(
+    mut commands: Commands,
+    mut order_book: ResMut<OrderBook>,
+    quarter_manager: Res<QuarterManger>,
+    pending: Query<(Entity, &PersistentId, &PendingIpo)>,
+) {
+    for (entity, per_id, ipo) in &pending {
+        order_book.add(
+            StockOrder::new_sell(*per_id, ipo.shares, ipo.price, *per_id).with_expiry(
+                OrderExpiry::GoodTillQuarter(quarter_manager.current_quarter()),
+            ),
+        );
+        commands.entity(entity).remove::<PendingIpo>();
+    }
+}
+
+fn think_about_delisting(
+    mut delisted: EventReader<CompanyDelisted>,
+    mut try_think_events: EventWriter<TryThinkEvent>,
+    pets: Query<Entity, With<Pet>>,
+) {
+    for delisted in delisted.read() {
+        if delisted.player_shares == 0 {
+            continue;
+        }
+
+        for pet in pets.iter() {
+            let mut fact_db = FactDb::default();
+            fact_db.add_str("DelistedTicker", &delisted.ticker);
+            fact_db.add("SharesLost", delisted.player_shares as f32);
+            try_think_events
+                .send(TryThinkEvent::new(pet, Concept::ThinkCompanyDelisted).with_facts(fact_db));
+        }
+    }
+}
+
+fn report_listing_changes(
+    mut report: ResMut<AwayReport>,
+    mut delisted: EventReader<CompanyDelisted>,
+    mut listed: EventReader<CompanyListed>,
+) {
+    for delisted in delisted.read() {
+        report
+            .delisted
+            .push((delisted.name_key.clone(), delisted.player_shares));
+    }
+    for listed in listed.read() {
+        report.listed.push(listed.name_key.clone());
     }
 }
 
@@ -1140,24 +2076,28 @@ pub struct StockMarketGhostBundle {
     pub rng: RngComponent,
     pub save: Save,
     pub ai: StockMarketAI,
+    pub strategy: TraderStrategy,
 }
 
 fn spawn_ghosts(
     mut commands: Commands,
     mut global_rng: ResMut<GlobalRng>,
+    mix: Res<TraderMix>,
     existing_ghosts: Query<Entity, With<StockMarketGhost>>,
 ) {
     if existing_ghosts.iter().next().is_some() {
         return;
     }
 
-    for _ in 0..100 {
+    for _ in 0..mix.ghosts {
         let mut rng = RngComponent::with_seed(global_rng.u64(u64::MIN..u64::MAX));
+        let kind = mix.pick(&mut rng);
         commands.spawn(StockMarketGhostBundle {
             wallet: Wallet {
                 balance: rng.i64(10000000..100000000),
             },
             ai: StockMarketAI::new_from_rng(&mut rng),
+            strategy: TraderStrategy::new_from_rng(kind, &mut rng),
             rng,
             ..default()
         });
@@ -1184,6 +2124,8 @@ pub struct StockMarketAI {
     pe_weight: f32,
     pb_weight: f32,
     peg_weight: f32,
+    #[serde(default)]
+    dividend_weight: f32,
 }
 
 impl StockMarketAI {
@@ -1192,9 +2134,20 @@ impl StockMarketAI {
             pe_weight,
             pb_weight,
             peg_weight,
+            dividend_weight: 0.,
         }
     }
 
+    // Takes the dividend weight out of the others so the score range stays the same
+    pub fn with_dividend_weight(mut self, dividend_weight: f32) -> Self {
+        let scale = 1. - dividend_weight;
+        self.pe_weight *= scale;
+        self.pb_weight *= scale;
+        self.peg_weight *= scale;
+        self.dividend_weight = dividend_weight;
+        self
+    }
+
     pub fn new_from_rng<T: DelegatedRng>(rng: &mut T) -> Self {
         lazy_static! {
             static ref WEIGHT_TABLES: WalkerTable = WalkerTable::new(&[5, 10, 85]);
@@ -1205,12 +2158,14 @@ impl StockMarketAI {
 
         let weights = POSSIBLE_WEIGHTS[WEIGHT_TABLES.next_rng(rng)];
 
-        match rng.i8(0..=2) {
+        let ai = match rng.i8(0..=2) {
             0 => Self::new(weights[0], weights[1], weights[2]),
             1 => Self::new(weights[0], weights[2], weights[1]),
             2 => Self::new(weights[1], weights[0], weights[2]),
             _ => unreachable!(),
-        }
+        };
+
+        ai.with_dividend_weight(gen_f32_range(rng, &(0.0..0.4)))
     }
 
     fn get_buy_threshold(&self, rank: &CompanyRank) -> BuyThreshold {
@@ -1218,8 +2173,32 @@ impl StockMarketAI {
         let pe_score = (1. - rank.pe_percentile) * MAX_SCORE_PART * self.pe_weight;
         let pb_score = (1. - rank.pb_percentile) * MAX_SCORE_PART * self.pb_weight;
         let peg_score = (1. - rank.peg_percentile) * MAX_SCORE_PART * self.peg_weight;
+        let dividend_score =
+            (1. - rank.dividend_percentile) * MAX_SCORE_PART * self.dividend_weight;
+
+        BuyThreshold::from_score(pe_score + pb_score + peg_score + dividend_score)
+    }
+}
 
-        BuyThreshold::from_score(pe_score + pb_score + peg_score)
+impl TradingStrategy for StockMarketAI {
+    fn bid(&self, quote: &Quote, rng: &mut RngComponent) -> Option<Money> {
+        let buy_threshold = self.get_buy_threshold(quote.rank);
+        if rng.f32() > buy_threshold.buy_prob() {
+            return None;
+        }
+
+        let price = (quote.price() as f32 * buy_threshold.price_modifier()) as Money;
+        Some(jitter(price, &(-0.05..0.05), rng))
+    }
+
+    fn ask(&self, quote: &Quote, rng: &mut RngComponent) -> Option<Money> {
+        let sell_threshold = self.get_buy_threshold(quote.rank).invert();
+        if rng.f32() < sell_threshold.buy_prob() - (sell_threshold.buy_prob() * 0.5) {
+            return None;
+        }
+
+        let price = (quote.price() as f32 * sell_threshold.price_modifier()) as Money;
+        Some(jitter(price, &(0.01..0.05), rng))
     }
 }
 
@@ -1256,12 +2235,18 @@ fn generate_buy_sell_activity(
         &mut Wallet,
         &mut RngComponent,
         &StockMarketAI,
+        Option<&TraderStrategy>,
     )>,
     companies: Query<(&PersistentId, &Company, &ShareHistory)>,
 ) {
     const MAX_MODULO: u64 = 20;
 
-    if local.last_update.tick(time.delta()).just_finished() || local.ranking.is_none() {
+    // Companies come and go so rank again whenever the set changes
+    let ranked = local
+        .ranking
+        .as_ref()
+        .is_some_and(|ranking| ranking.len() == companies.iter().len());
+    if local.last_update.tick(time.delta()).just_finished() || !ranked {
         local.ranking = Some(CompanyRank::new_ranking(
             &companies
                 .iter()
@@ -1281,27 +2266,29 @@ fn generate_buy_sell_activity(
 
     const MAX_ORDER_SIZE: u64 = 100;
 
-    for (per_id, mut portfolio, mut wallet, rng, ai) in buyer_sellers.iter_mut() {
+    for (per_id, mut portfolio, mut wallet, rng, ai, strategy) in buyer_sellers.iter_mut() {
         if per_id.value() % MAX_MODULO != local.modulo {
             continue;
         }
 
         let rng = rng.into_inner();
+        let strategy = strategy.copied().unwrap_or_default();
+        let strategy = strategy.strategy(ai);
 
-        for (company_per_id, _, share_history) in &companies {
-            let company_rank = company_rankings.get(company_per_id).unwrap();
-
-            let buy_threshold = ai.get_buy_threshold(company_rank);
+        for (company_per_id, company, share_history) in &companies {
+            let rank = match company_rankings.get(company_per_id) {
+                Some(rank) => rank,
+                None => continue,
+            };
+            let quote = Quote {
+                rank,
+                company,
+                share_history,
+            };
 
-            if rng.f32() > buy_threshold.buy_prob() {
+            let Some(price) = strategy.bid(&quote, rng) else {
                 continue;
-            }
-
-            let price =
-                (share_history.cached_price as f32 * buy_threshold.price_modifier()) as Money;
-            let price = (price
-                + (price as f32 * gen_f32_range(rng, &(-0.05..0.05))).floor() as Money)
-                .max(1);
+            };
 
             // Don't spend more than 1% of wallet
             let money_available = (wallet.balance as f32 * 0.01).floor() as Money;
@@ -1328,7 +2315,7 @@ fn generate_buy_sell_activity(
             ));
         }
 
-        for (company_per_id, _, share_history) in &companies {
+        for (company_per_id, company, share_history) in &companies {
             let shares = match portfolio.owned_shares.get(company_per_id) {
                 Some(shares) => *shares,
                 None => continue,
@@ -1338,20 +2325,20 @@ fn generate_buy_sell_activity(
                 continue;
             }
 
-            let rank = company_rankings.get(company_per_id).unwrap();
+            let rank = match company_rankings.get(company_per_id) {
+                Some(rank) => rank,
+                None => continue,
+            };
 
-            let sell_threshold = ai.get_buy_threshold(rank).invert();
+            let quote = Quote {
+                rank,
+                company,
+                share_history,
+            };
 
-            if rng.f32() < sell_threshold.buy_prob() - (sell_threshold.buy_prob() * 0.5) {
+            let Some(price) = strategy.ask(&quote, rng) else {
                 continue;
-            }
-
-            let price =
-                (share_history.cached_price as f32 * sell_threshold.price_modifier()) as Money;
-
-            let price = (price
-                + (price as f32 * gen_f32_range(rng, &(0.01..0.05))).floor() as Money)
-                .max(1);
+            };
 
             let max_sell = MAX_ORDER_SIZE.min(shares);
 
@@ -1376,7 +2363,11 @@ fn generate_buy_sell_activity(
 
 fn process_orders(
     time: Res<Time>,
+    clock: Res<Clock>,
+    quarter_manager: Res<QuarterManger>,
     order_book: ResMut<OrderBook>,
+    mut cancels: EventReader<CancelStockOrder>,
+    mut filled: EventWriter<OrderFilled>,
     per_id_map: Res<PersistentIdMapping>,
     mut share_history: Query<&mut ShareHistory>,
     mut wallets: Query<&mut Wallet>,
@@ -1384,142 +2375,171 @@ fn process_orders(
     mut share_portfolios: Query<&mut SharePortfolio>,
 ) {
     let order_book = order_book.into_inner();
+    let current_quarter = quarter_manager.current_quarter();
+
+    order_book.tick(time.delta());
+
+    // Cancelled orders come out before matching so they can't fill in the tick they're pulled
+    let cancelled = cancels
+        .read()
+        .map(|cancel| (cancel.id, cancel.owner))
+        .collect::<HashSet<_>>();
+    pull_orders(
+        order_book,
+        &per_id_map,
+        &mut wallets,
+        &mut share_portfolios,
+        |order| cancelled.contains(&(order.id, order.owner)),
+    );
+
+    let trades = order_book.match_orders(|buy_order, sell_order, price, quantity| {
+        let seller = per_id_map.get(sell_order.owner);
+        let buyer = per_id_map.get(buy_order.owner);
+
+        if wallets.get(seller).is_err() {
+            return 0;
+        }
 
-    {
-        let buy_orders = &mut order_book.buy_orders;
-        let sell_orders = &mut order_book.sell_orders;
-        for buy_order in buy_orders.iter_mut() {
-            buy_order.lifetime += time.delta();
-
-            let sell_orders = match sell_orders.get_mut(&buy_order.company) {
-                Some(orders) => orders,
-                None => continue,
-            };
-
-            for sell_order in sell_orders {
-                // Since all sell orders are sorted by price, we can break if the the sell order is more costly
-                if buy_order.price < sell_order.price {
-                    break;
-                }
-
-                if sell_order.remaining_quantity == 0 {
-                    continue;
-                }
-
-                let quantity = buy_order
-                    .remaining_quantity
-                    .min(sell_order.remaining_quantity);
-
-                let mut seller_wallet = match wallets.get_mut(per_id_map.get(sell_order.owner)) {
-                    Ok(wallet) => wallet,
-                    Err(_) => continue,
-                };
-
-                let mut buyer_portfolio =
-                    match share_portfolios.get_mut(per_id_map.get(buy_order.owner)) {
-                        Ok(portfolio) => portfolio,
-                        Err(_) => break,
-                    };
-
-                let order_action_time = shared_deps::chrono::Utc::now();
-                buyer_portfolio.add_shares(sell_order.company, quantity);
-                seller_wallet.balance += quantity as i64 * sell_order.price;
-
-                buy_order.remaining_quantity -= quantity;
-                sell_order.remaining_quantity -= quantity;
-
-                if let Ok(mut share_history) =
-                    share_history.get_mut(per_id_map.get(sell_order.company))
-                {
-                    share_history.add_entry(sell_order.price, quantity);
-                }
-
-                // Update history for entities
-                if let Ok(mut share_order_history) =
-                    history.get_mut(per_id_map.get(buy_order.owner))
-                {
-                    share_order_history.orders.push(OrderHistoryEntry::new(
-                        OrderKind::Buy,
-                        sell_order.company,
-                        sell_order.price,
-                        quantity,
-                        order_action_time,
-                    ));
-                }
+        // Market buys pay as they go so can only take what they can afford
+        let market_buy = buy_order.order_type == OrderType::Market;
+        let quantity = if market_buy {
+            let balance = wallets.get(buyer).map_or(0, |wallet| wallet.balance);
+            quantity.min((balance.max(0) / price.max(1)) as u64)
+        } else {
+            quantity
+        };
+        if quantity == 0 {
+            return 0;
+        }
 
-                if let Ok(mut share_order_history) =
-                    history.get_mut(per_id_map.get(sell_order.owner))
-                {
-                    share_order_history.orders.push(OrderHistoryEntry::new(
-                        OrderKind::Sell,
-                        sell_order.company,
-                        sell_order.price,
-                        quantity,
-                        order_action_time,
-                    ));
-                }
+        let mut buyer_portfolio = match share_portfolios.get_mut(buyer) {
+            Ok(portfolio) => portfolio,
+            Err(_) => return 0,
+        };
 
-                if buy_order.remaining_quantity == 0 {
-                    break;
-                }
+        let order_action_time = clock.now();
+        buyer_portfolio.add_shares(sell_order.company, quantity);
+        if let Ok(mut buyer_wallet) = wallets.get_mut(buyer) {
+            if market_buy {
+                buyer_wallet.balance -= quantity as i64 * price;
+            } else {
+                // Limit buys paid their own price up front so get back any improvement
+                buyer_wallet.balance += quantity as i64 * (buy_order.price - price);
             }
         }
-    }
-
-    // Update sell order lifetimes
-    for orders in order_book.sell_orders.values_mut() {
-        for order in orders {
-            order.lifetime += time.delta();
+        if let Ok(mut seller_wallet) = wallets.get_mut(seller) {
+            seller_wallet.balance += quantity as i64 * price;
         }
-    }
 
-    const PULL_TIME: Duration = Duration::from_secs(60);
+        if let Ok(mut share_history) = share_history.get_mut(per_id_map.get(sell_order.company)) {
+            share_history.add_trade(
+                price,
+                quantity,
+                order_action_time.timestamp(),
+                current_quarter,
+            );
+        }
 
-    order_book.buy_orders.retain(|order| {
-        if order.remaining_quantity == 0 {
-            return false;
+        // Update history for entities
+        if let Ok(mut share_order_history) = history.get_mut(buyer) {
+            share_order_history.record_order(
+                OrderKind::Buy,
+                sell_order.company,
+                price,
+                quantity,
+                order_action_time,
+                current_quarter,
+            );
         }
 
-        if order.lifetime > PULL_TIME || share_portfolios.get(per_id_map.get(order.owner)).is_err()
-        {
-            // Attempt to refund the buyer
-            if let Ok(mut wallet) = wallets.get_mut(per_id_map.get(order.owner)) {
-                wallet.balance += order.remaining_quantity as i64 * order.price;
-            }
-            return false;
+        if let Ok(mut share_order_history) = history.get_mut(seller) {
+            share_order_history.record_order(
+                OrderKind::Sell,
+                sell_order.company,
+                price,
+                quantity,
+                order_action_time,
+                current_quarter,
+            );
         }
 
-        true
+        quantity
     });
 
-    order_book.sell_orders.retain(|_, orders| {
-        orders.retain(|order| {
-            if order.remaining_quantity == 0 {
-                return false;
-            }
+    for trade in trades {
+        for (id, owner, kind) in [
+            (trade.buy_id, trade.buyer, OrderKind::Buy),
+            (trade.sell_id, trade.seller, OrderKind::Sell),
+        ] {
+            filled.send(OrderFilled {
+                id,
+                owner,
+                company: trade.company,
+                kind,
+                price: trade.price,
+                quantity: trade.quantity,
+                remaining_quantity: order_book
+                    .get_order(id)
+                    .map_or(0, |order| order.remaining_quantity),
+            });
+        }
+    }
+
+    pull_orders(
+        order_book,
+        &per_id_map,
+        &mut wallets,
+        &mut share_portfolios,
+        |order| order.expired(current_quarter),
+    );
+}
+
+// Takes out pulled orders and those whose owner is gone, handing back what they held
+fn pull_orders(
+    order_book: &mut OrderBook,
+    per_id_map: &PersistentIdMapping,
+    wallets: &mut Query<&mut Wallet>,
+    share_portfolios: &mut Query<&mut SharePortfolio>,
+    pulled: impl Fn(&StockOrder) -> bool,
+) {
+    order_book.retain(|order| {
+        let owner = per_id_map.get(order.owner);
+        let pulled = pulled(order);
 
-            if order.lifetime > PULL_TIME || wallets.get(per_id_map.get(order.owner)).is_err() {
-                // return shares to seller
-                if let Ok(mut portfolio) = share_portfolios.get_mut(per_id_map.get(order.owner)) {
-                    portfolio.add_shares(order.company, order.remaining_quantity);
+        match order.kind {
+            OrderKind::Buy => {
+                if pulled || share_portfolios.get(owner).is_err() {
+                    // Attempt to refund the buyer
+                    if let Ok(mut wallet) = wallets.get_mut(owner) {
+                        wallet.balance += order.remaining_quantity as i64 * order.price;
+                    }
+                    return false;
                 }
-                return false;
             }
+            OrderKind::Sell => {
+                if pulled || wallets.get(owner).is_err() {
+                    // return shares to seller
+                    if let Ok(mut portfolio) = share_portfolios.get_mut(owner) {
+                        portfolio.add_shares(order.company, order.remaining_quantity);
+                    }
+                    return false;
+                }
+            }
+        }
 
-            true
-        });
-
-        !orders.is_empty()
+        true
     });
 }
 
-#[derive(Serialize, Deserialize, Reflect)]
-struct OrderHistoryEntry {
+#[derive(Debug, Clone, Serialize, Deserialize, Reflect)]
+pub struct OrderHistoryEntry {
     pub kind: OrderKind,
     pub company: PersistentId,
     pub price: Money,
     pub quantity: u64,
     pub timestamp: i64,
+    #[serde(default)]
+    pub quarter: u32,
 }
 
 impl OrderHistoryEntry {
@@ -1529,6 +2549,7 @@ impl OrderHistoryEntry {
         price: Money,
         quantity: u64,
         time: DateTime<Utc>,
+        quarter: u32,
     ) -> Self {
         Self {
             kind,
@@ -1536,24 +2557,146 @@ impl OrderHistoryEntry {
             price,
             quantity,
             timestamp: time.timestamp(),
+            quarter,
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Reflect)]
+pub struct DividendHistoryEntry {
+    pub company: PersistentId,
+    pub quarter: u32,
+    pub per_share: Money,
+    pub shares: u64,
+    pub timestamp: i64,
+}
+
 #[derive(Default, Reflect, Serialize, Deserialize, Component)]
 #[reflect(Component, Serialize, Deserialize)]
 pub struct CompleteShareOrderHistory {
     orders: Vec<OrderHistoryEntry>,
+    #[serde(default)]
+    dividends: Vec<DividendHistoryEntry>,
+}
+
+impl CompleteShareOrderHistory {
+    pub fn orders(&self) -> &[OrderHistoryEntry] {
+        &self.orders
+    }
+
+    pub fn dividends(&self) -> &[DividendHistoryEntry] {
+        &self.dividends
+    }
+
+    pub fn record_order(
+        &mut self,
+        kind: OrderKind,
+        company: PersistentId,
+        price: Money,
+        quantity: u64,
+        time: DateTime<Utc>,
+        quarter: u32,
+    ) {
+        self.orders.push(OrderHistoryEntry::new(
+            kind, company, price, quantity, time, quarter,
+        ));
+    }
+
+    pub fn record_dividend(
+        &mut self,
+        company: PersistentId,
+        quarter: u32,
+        per_share: Money,
+        shares: u64,
+        time: DateTime<Utc>,
+    ) {
+        self.dividends.push(DividendHistoryEntry {
+            company,
+            quarter,
+            per_share,
+            shares,
+            timestamp: time.timestamp(),
+        });
+    }
+
+    pub fn dividend_income(&self) -> Money {
+        self.dividends
+            .iter()
+            .map(|dividend| dividend.per_share * dividend.shares as Money)
+            .sum()
+    }
+}
+
+// Pays each holder the dividend declared for every quarter just stepped
+fn pay_dividends(
+    clock: Res<Clock>,
+    stepped: Res<Events<QuarterStepped>>,
+    mut readers: ResMut<QuarterReaders>,
+    companies: Query<(&PersistentId, &Company)>,
+    mut holders: Query<(
+        &PersistentId,
+        &SharePortfolio,
+        &mut Wallet,
+        Option<&MarginAccount>,
+        Option<&mut CompleteShareOrderHistory>,
+    )>,
+) {
+    for stepped in readers.dividends.read(&stepped) {
+        for (company_per_id, company) in &companies {
+            let per_share = company
+                .history
+                .iter()
+                .rev()
+                .find(|history| history.quarter == stepped.quarter)
+                .map_or(0, |history| history.dividend_paid);
+            if per_share <= 0 {
+                continue;
+            }
+
+            for (per_id, portfolio, mut wallet, margin, history) in &mut holders {
+                // Companies don't pay themselves for shares they hold
+                if per_id == company_per_id {
+                    continue;
+                }
+
+                // Short sellers pay the lender the dividend on the shares they borrowed
+                let shares = portfolio.get_count(company_per_id);
+                let owed = margin.map_or(0, |account| account.owed_shares(company_per_id));
+                if shares == 0 && owed == 0 {
+                    continue;
+                }
+
+                wallet.balance += per_share * (shares as Money - owed as Money);
+
+                if let Some(mut history) = history {
+                    for (per_share, shares) in [(per_share, shares), (-per_share, owed)] {
+                        if shares > 0 {
+                            history.record_dividend(
+                                *company_per_id,
+                                stepped.quarter,
+                                per_share,
+                                shares,
+                                clock.now(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use std::collections::HashSet;
 
+    use proptest::prelude::*;
     use sardips_core::persistent_id::{PersistentIdGenerator, PersistentIdPlugin};
     use shared_deps::bevy_turborand::{prelude::RngPlugin, GlobalRng};
 
     use super::*;
+    use crate::company_template::test::load_test_templates;
+    use crate::stock_strategy::TraderKind;
 
     #[test]
     fn test_tick_quarter() {
@@ -1572,6 +2715,7 @@ mod test {
             app.insert_resource(PersistentIdMapping::default());
             app.insert_resource(PersistentIdGenerator::default());
             app.insert_resource(time);
+            app.add_event::<QuarterStepped>();
 
             fn spawn_test_companies(mut commands: Commands, mut global_rng: ResMut<GlobalRng>) {
                 let company = Company {
@@ -1585,9 +2729,12 @@ mod test {
                         total_shares: 1000,
                         dividend_paid: 0,
                         performance: PerformanceRanking::Average,
+                        economic_events: vec![],
                     }],
                     performance_history: vec![],
                     industries: vec![(1., Industry::Tech)],
+                    distressed_quarters: 0,
+                    dividend_policy: None,
                 };
 
                 commands.spawn(CompanyBundle {
@@ -1605,6 +2752,7 @@ mod test {
                 (
                     create_order_book,
                     create_quarter_manager,
+                    create_macro_economy,
                     create_buy_sell_orchestrator,
                     spawn_test_companies,
                 )
@@ -1711,11 +2859,15 @@ mod test {
         app.insert_resource(PersistentIdGenerator::default());
         app.insert_resource(PersistentIdMapping::default());
         app.insert_resource(time);
+        app.insert_resource(load_test_templates());
+        app.init_resource::<TraderMix>();
+        app.add_event::<QuarterStepped>();
         app.add_systems(
             Startup,
             (
                 create_order_book,
                 create_quarter_manager,
+                create_macro_economy,
                 create_buy_sell_orchestrator,
                 spawn_companies,
                 spawn_ghosts,
@@ -1737,7 +2889,249 @@ mod test {
         // Check book validate for tests
         let order_book = app.world().get_resource::<OrderBook>().unwrap();
 
-        assert!(!order_book.buy_orders.is_empty());
+        assert!(order_book
+            .orders()
+            .any(|order| order.kind == OrderKind::Buy));
+    }
+
+    #[derive(Component)]
+    struct ReplayTrader;
+
+    fn spawn_replay_traders(mut commands: Commands) {
+        for _ in 0..3 {
+            commands.spawn((
+                ReplayTrader,
+                Wallet { balance: 0 },
+                SharePortfolio::default(),
+                Save,
+            ));
+        }
+    }
+
+    // Market buys for more than they can afford in every company so which books are
+    // matched first decides what they end up with
+    fn place_replay_orders(
+        mut order_book: ResMut<OrderBook>,
+        companies: Query<&PersistentId, With<Company>>,
+        mut traders: Query<(&PersistentId, &mut Wallet, &mut SharePortfolio), With<ReplayTrader>>,
+    ) {
+        for (per_id, mut wallet, mut portfolio) in &mut traders {
+            wallet.balance += 50000;
+
+            for (company, count) in portfolio.owned_shares.clone() {
+                let quantity = count / 2;
+                if quantity > 0 {
+                    portfolio.remove_shares(company, quantity);
+                    order_book.add(StockOrder::new_market_sell(company, quantity, *per_id));
+                }
+            }
+
+            for company in &companies {
+                order_book.add(StockOrder::new_market_buy(*company, 1000, *per_id));
+            }
+        }
+    }
+
+    type MarketReplay = (
+        Vec<(String, Vec<Money>, Money)>,
+        Vec<(Money, Vec<(PersistentId, u64)>)>,
+    );
+
+    #[test]
+    fn test_market_replay() {
+        fn run_market(seed: u64) -> MarketReplay {
+            let mut time = Time::<()>::default();
+            time.advance_by(Duration::from_secs(1));
+
+            let mut app = App::new();
+            app.add_plugins(PersistentIdPlugin);
+            app.insert_resource(GlobalRng::with_seed(seed));
+            app.insert_resource(Clock::manual(DateTime::UNIX_EPOCH));
+            app.insert_resource(PersistentIdGenerator::default());
+            app.insert_resource(PersistentIdMapping::default());
+            app.insert_resource(time);
+            app.insert_resource(load_test_templates());
+            app.init_resource::<TraderMix>();
+            app.add_event::<CancelStockOrder>();
+            app.add_event::<OrderFilled>();
+            app.add_event::<QuarterStepped>();
+            app.add_systems(
+                Startup,
+                (
+                    create_order_book,
+                    create_quarter_manager,
+                    create_macro_economy,
+                    create_buy_sell_orchestrator,
+                    spawn_companies,
+                    spawn_ghosts,
+                    spawn_replay_traders,
+                )
+                    .chain(),
+            );
+            app.add_systems(
+                Update,
+                (
+                    add_rng_to_stock_stuff,
+                    allocate_stocks,
+                    tick_quarter,
+                    update_company_price_cache,
+                    generate_buy_sell_activity,
+                    place_replay_orders,
+                    process_orders,
+                )
+                    .chain(),
+            );
+
+            for _ in 0..50 {
+                app.update();
+                app.world_mut()
+                    .resource_mut::<Clock>()
+                    .advance(Duration::from_secs(1));
+            }
+
+            let mut companies = app
+                .world_mut()
+                .query::<(&Company, &ShareHistory)>()
+                .iter(app.world())
+                .map(|(company, share_history)| {
+                    (
+                        company.ticker.clone(),
+                        company
+                            .history
+                            .iter()
+                            .map(|history| history.assets)
+                            .collect(),
+                        share_history.price(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            companies.sort_by(|a, b| a.0.cmp(&b.0));
+
+            let mut traders = app
+                .world_mut()
+                .query_filtered::<(&PersistentId, &Wallet, &SharePortfolio), With<ReplayTrader>>()
+                .iter(app.world())
+                .map(|(per_id, wallet, portfolio)| {
+                    (
+                        *per_id,
+                        wallet.balance,
+                        portfolio
+                            .owned_shares
+                            .iter()
+                            .map(|(company, count)| (*company, *count))
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            traders.sort_by_key(|trader| trader.0);
+            let traders = traders
+                .into_iter()
+                .map(|(_, balance, shares)| (balance, shares))
+                .collect();
+
+            (companies, traders)
+        }
+
+        let (companies, traders) = run_market(42);
+        assert!(companies.len() > 1);
+        assert!(traders.iter().all(|(_, shares)| shares.len() > 1));
+        assert_eq!((companies, traders), run_market(42));
+    }
+
+    #[test]
+    fn test_trader_mix_keeps_prices_sane() {
+        const QUARTER_COUNT: u32 = 200;
+        const STEP: Duration = Duration::from_secs(15);
+
+        let mut time = Time::<()>::default();
+        time.advance_by(STEP);
+
+        let mut app = App::new();
+        app.add_plugins(PersistentIdPlugin);
+        app.insert_resource(GlobalRng::with_seed(7));
+        app.insert_resource(Clock::manual(DateTime::UNIX_EPOCH));
+        app.insert_resource(PersistentIdGenerator::default());
+        app.insert_resource(PersistentIdMapping::default());
+        app.insert_resource(time);
+        app.insert_resource(load_test_templates());
+        app.init_resource::<TraderMix>();
+        app.init_resource::<QuarterReaders>();
+        app.add_event::<CancelStockOrder>();
+        app.add_event::<OrderFilled>();
+        app.add_event::<QuarterStepped>();
+        app.add_event::<CompanyDelisted>();
+        app.add_event::<CompanyListed>();
+        app.add_systems(
+            Startup,
+            (
+                create_order_book,
+                create_quarter_manager,
+                create_macro_economy,
+                create_buy_sell_orchestrator,
+                spawn_companies,
+                spawn_ghosts,
+            )
+                .chain(),
+        );
+        app.add_systems(
+            Update,
+            (
+                add_rng_to_stock_stuff,
+                allocate_stocks,
+                tick_quarter,
+                pay_dividends,
+                delist_bankrupt_companies,
+                list_new_companies,
+                float_ipos,
+                update_company_price_cache,
+                generate_buy_sell_activity,
+                process_orders,
+            )
+                .chain(),
+        );
+
+        loop {
+            app.update();
+            if app.world().resource::<QuarterManger>().current_quarter() >= QUARTER_COUNT {
+                break;
+            }
+            app.world_mut().resource_mut::<Clock>().advance(STEP);
+            app.world_mut().resource_mut::<Time>().advance_by(STEP);
+        }
+
+        let kinds = app
+            .world_mut()
+            .query::<&TraderStrategy>()
+            .iter(app.world())
+            .map(|strategy| strategy.kind())
+            .collect::<HashSet<_>>();
+        assert_eq!(kinds.len(), TraderKind::iter().count());
+
+        // Every company's move since its first quarter of trading
+        let mut ratios = vec![];
+        for (company, share_history) in app
+            .world_mut()
+            .query::<(&Company, &ShareHistory)>()
+            .iter(app.world())
+        {
+            let quarters = share_history.candles.get(CandleResolution::Quarter);
+            let Some(first) = quarters.first() else {
+                continue;
+            };
+            let ratio = share_history.cached_price as f32 / first.open as f32;
+            assert!(
+                (0.01..100.).contains(&ratio),
+                "Company: {}, price moved {}x",
+                company.ticker,
+                ratio
+            );
+            ratios.push(ratio);
+        }
+        ratios.sort_by(f32::total_cmp);
+
+        assert!(!ratios.is_empty());
+        let median = ratios[ratios.len() / 2];
+        assert!((0.2..5.).contains(&median), "{:?}", ratios);
     }
 
     #[test]
@@ -1747,11 +3141,15 @@ mod test {
         let mut app = App::new();
         app.add_plugins(RngPlugin::default());
         app.insert_resource(time);
+        app.insert_resource(Clock::manual(DateTime::UNIX_EPOCH));
+        app.add_event::<CancelStockOrder>();
+        app.add_event::<OrderFilled>();
         app.add_systems(
             Startup,
             (
                 create_order_book,
                 create_quarter_manager,
+                create_macro_economy,
                 create_buy_sell_orchestrator,
             )
                 .chain(),
@@ -1780,9 +3178,12 @@ mod test {
                                 total_shares: 1000,
                                 dividend_paid: 0,
                                 performance: PerformanceRanking::Average,
+                                economic_events: vec![],
                             }],
                             performance_history: vec![],
                             industries: vec![(1., Industry::Tech)],
+                            distressed_quarters: 0,
+                            dividend_policy: None,
                         },
                         share_history: ShareHistory::new(100),
                         wallet: Wallet::default(),
@@ -1855,12 +3256,247 @@ mod test {
         // Check book validate for tests
         let order_book = app.world().get_resource::<OrderBook>().unwrap();
 
-        assert_eq!(order_book.buy_orders.len(), 1, "Buy orders missing");
-        assert_eq!(order_book.buy_orders[0].remaining_quantity, 500,);
-        order_book.sell_orders.values().for_each(|orders| {
-            assert_eq!(orders.len(), 1);
-            assert_eq!(orders[0].price, 200);
-        });
+        let company = order_book.orders().next().unwrap().company;
+        let bids = order_book.bids(company).collect::<Vec<_>>();
+        assert_eq!(bids.len(), 1, "Buy orders missing");
+        assert_eq!(bids[0].remaining_quantity, 500,);
+        let asks = order_book.asks(company).collect::<Vec<_>>();
+        assert_eq!(asks.len(), 1);
+        assert_eq!(asks[0].price, 200);
+
+        let share_history = app.world_mut().query::<&ShareHistory>().single(app.world());
+        let candles = share_history.candles.get(CandleResolution::Quarter);
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].close, 100);
+        assert_eq!(candles[0].volume, 500);
+    }
+
+    #[test]
+    fn test_price_candles() {
+        let mut candles = PriceCandles::default();
+        candles.record(0, 1, 100, 10);
+        candles.record(30, 1, 120, 5);
+        candles.record(45, 1, 90, 5);
+        candles.record(60, 2, 110, 1);
+        candles.record(120, 5, 130, 1);
+
+        let minute = candles.get(CandleResolution::Minute);
+        assert_eq!(minute.len(), 3);
+        assert_eq!(
+            minute[0],
+            Candle {
+                period: 0,
+                open: 100,
+                high: 120,
+                low: 90,
+                close: 90,
+                volume: 20,
+            }
+        );
+
+        let quarter = candles.get(CandleResolution::Quarter);
+        assert_eq!(
+            quarter.iter().map(|c| c.period).collect::<Vec<_>>(),
+            vec![1, 2, 5]
+        );
+
+        // Quarters 1-4 make up the first financial year
+        let year = candles.get(CandleResolution::Year);
+        assert_eq!(year.len(), 2);
+        assert_eq!((year[0].open, year[0].close), (100, 110));
+        assert_eq!((year[0].high, year[0].low), (120, 90));
+
+        for minute in 0..(MAX_MINUTE_CANDLES as i64 + 10) {
+            candles.record(minute * 60 + 600, 5, 100, 1);
+        }
+        assert_eq!(
+            candles.get(CandleResolution::Minute).len(),
+            MAX_MINUTE_CANDLES
+        );
+        assert_eq!(candles.get(CandleResolution::Quarter).len(), 3);
+    }
+
+    #[test]
+    fn test_order_types_and_cancel() {
+        let mut app = App::new();
+        app.insert_resource(Time::<()>::default());
+        app.insert_resource(Clock::manual(DateTime::UNIX_EPOCH));
+        app.insert_resource(OrderBook::default());
+        app.insert_resource(QuarterManger::default());
+        app.add_event::<CancelStockOrder>();
+        app.add_event::<OrderFilled>();
+        app.add_systems(Update, process_orders);
+
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let mut per_id_mapping = PersistentIdMapping::default();
+        let company = per_id_gen.next_id();
+        let seller = per_id_gen.next_id();
+        let buyer = per_id_gen.next_id();
+        for per_id in [seller, buyer] {
+            let entity = app
+                .world_mut()
+                .spawn((Wallet { balance: 1000 }, SharePortfolio::default(), per_id))
+                .id();
+            per_id_mapping.insert(entity, per_id);
+        }
+        let company_entity = app
+            .world_mut()
+            .spawn((ShareHistory::new(100), company))
+            .id();
+        per_id_mapping.insert(company_entity, company);
+        let buyer_entity = per_id_mapping.get(buyer);
+        let seller_entity = per_id_mapping.get(seller);
+        app.insert_resource(per_id_mapping);
+
+        // Market buy takes the cheapest first and only what it can afford
+        {
+            let mut order_book = app.world_mut().resource_mut::<OrderBook>();
+            order_book.add(StockOrder::new_sell(company, 5, 100, seller));
+            order_book.add(
+                StockOrder::new_sell(company, 10, 200, seller)
+                    .with_expiry(OrderExpiry::GoodTillCancelled),
+            );
+            order_book.add(StockOrder::new_market_buy(company, 100, buyer));
+        }
+        app.update();
+
+        let wallet = app.world().get::<Wallet>(buyer_entity).unwrap();
+        assert_eq!(wallet.balance, 100);
+        let portfolio = app.world().get::<SharePortfolio>(buyer_entity).unwrap();
+        assert_eq!(portfolio.get_count(&company), 7);
+        let order_book = app.world().resource::<OrderBook>();
+        assert_eq!(
+            order_book.bids(company).count(),
+            0,
+            "Market order not pulled"
+        );
+        assert_eq!(
+            order_book.asks(company).next().unwrap().remaining_quantity,
+            8
+        );
+
+        // GTC outlives the pull time, GTQ goes at the end of the quarter
+        let (gtc, gtq) = {
+            let mut order_book = app.world_mut().resource_mut::<OrderBook>();
+            let gtc = order_book.add(
+                StockOrder::new_buy(company, 1, 10, buyer)
+                    .with_expiry(OrderExpiry::GoodTillCancelled),
+            );
+            let gtq = order_book.add(
+                StockOrder::new_buy(company, 1, 20, buyer)
+                    .with_expiry(OrderExpiry::GoodTillQuarter(0)),
+            );
+            (gtc, gtq)
+        };
+        app.world_mut()
+            .resource_mut::<Time>()
+            .advance_by(ORDER_PULL_TIME * 2);
+        app.update();
+        assert_eq!(app.world().resource::<OrderBook>().bids(company).count(), 2);
+
+        app.world_mut()
+            .resource_mut::<QuarterManger>()
+            .current_quarter += 1;
+        app.update();
+        let order_book = app.world().resource::<OrderBook>();
+        assert!(order_book.get_order(gtq).is_none());
+        assert!(order_book.get_order(gtc).is_some());
+        assert_eq!(
+            app.world().get::<Wallet>(buyer_entity).unwrap().balance,
+            120,
+            "GTQ order not refunded"
+        );
+
+        // Cancelling needs the right owner and refunds like an expiry
+        let sell = app
+            .world()
+            .resource::<OrderBook>()
+            .asks(company)
+            .next()
+            .unwrap()
+            .id;
+        app.world_mut()
+            .send_event(CancelStockOrder::new(gtc, seller));
+        app.update();
+        assert!(app.world().resource::<OrderBook>().get_order(gtc).is_some());
+
+        app.world_mut()
+            .send_event(CancelStockOrder::new(gtc, buyer));
+        app.world_mut()
+            .send_event(CancelStockOrder::new(sell, seller));
+        app.update();
+        let order_book = app.world().resource::<OrderBook>();
+        assert!(order_book.is_empty());
+        assert_eq!(
+            app.world().get::<Wallet>(buyer_entity).unwrap().balance,
+            130
+        );
+        assert_eq!(
+            app.world()
+                .get::<SharePortfolio>(seller_entity)
+                .unwrap()
+                .get_count(&company),
+            8
+        );
+    }
+
+    #[test]
+    fn test_cancel_before_match() {
+        let mut app = App::new();
+        app.insert_resource(Time::<()>::default());
+        app.insert_resource(Clock::manual(DateTime::UNIX_EPOCH));
+        app.insert_resource(OrderBook::default());
+        app.insert_resource(QuarterManger::default());
+        app.add_event::<CancelStockOrder>();
+        app.add_event::<OrderFilled>();
+        app.add_systems(Update, process_orders);
+
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let mut per_id_mapping = PersistentIdMapping::default();
+        let company = per_id_gen.next_id();
+        let seller = per_id_gen.next_id();
+        let buyer = per_id_gen.next_id();
+        // The buyer has already paid for their limit order
+        for (per_id, balance) in [(seller, 1000), (buyer, 500)] {
+            let entity = app
+                .world_mut()
+                .spawn((Wallet { balance }, SharePortfolio::default(), per_id))
+                .id();
+            per_id_mapping.insert(entity, per_id);
+        }
+        let company_entity = app
+            .world_mut()
+            .spawn((ShareHistory::new(100), company))
+            .id();
+        per_id_mapping.insert(company_entity, company);
+        let buyer_entity = per_id_mapping.get(buyer);
+        app.insert_resource(per_id_mapping);
+
+        let (sell, buy) = {
+            let mut order_book = app.world_mut().resource_mut::<OrderBook>();
+            let sell = order_book.add(StockOrder::new_sell(company, 5, 100, seller));
+            let buy = order_book.add(StockOrder::new_buy(company, 5, 100, buyer));
+            (sell, buy)
+        };
+        app.world_mut()
+            .send_event(CancelStockOrder::new(buy, buyer));
+        app.update();
+
+        assert!(app.world().resource::<Events<OrderFilled>>().is_empty());
+        assert_eq!(
+            app.world()
+                .get::<SharePortfolio>(buyer_entity)
+                .unwrap()
+                .get_count(&company),
+            0
+        );
+        assert_eq!(
+            app.world().get::<Wallet>(buyer_entity).unwrap().balance,
+            1000
+        );
+        let order_book = app.world().resource::<OrderBook>();
+        assert!(order_book.get_order(buy).is_none());
+        assert_eq!(order_book.get_order(sell).unwrap().remaining_quantity, 5);
     }
 
     #[test]
@@ -1876,9 +3512,12 @@ mod test {
                 total_shares: 11543000000,
                 dividend_paid: 18,
                 performance: PerformanceRanking::Average,
+                economic_events: vec![],
             }],
             performance_history: vec![],
             industries: vec![(1., Industry::Tech)],
+            distressed_quarters: 0,
+            dividend_policy: None,
         };
         let share_history = ShareHistory::new(414);
 
@@ -1894,6 +3533,7 @@ mod test {
             pe_percentile: 0.01,
             pb_percentile: 0.01,
             peg_percentile: 0.01,
+            dividend_percentile: 1.,
         };
 
         let buy_threshold = ai.get_buy_threshold(&rank);
@@ -1902,28 +3542,793 @@ mod test {
     }
 
     #[test]
-    fn test_order_book_sell_ordered() {
+    fn test_order_book_price_time_priority() {
         let mut id_gen = PersistentIdGenerator::default();
         let company_per_id = id_gen.next_id();
-        let seller_per_id = id_gen.next_id();
+        let owner_per_id = id_gen.next_id();
 
         let mut order_book = OrderBook::default();
 
-        let mut prices = vec![5, 2, 7, 2, 9, 10, 3];
-        for price in &prices {
+        let prices = [5, 2, 7, 2, 9, 10, 3];
+        for price in prices {
             order_book.add(StockOrder::new_buy(
                 company_per_id,
                 100,
-                *price,
-                seller_per_id,
+                price,
+                owner_per_id,
+            ));
+            order_book.add(StockOrder::new_sell(
+                company_per_id,
+                100,
+                price,
+                owner_per_id,
             ));
         }
+        let market_buy =
+            order_book.add(StockOrder::new_market_buy(company_per_id, 1, owner_per_id));
+        let market_sell =
+            order_book.add(StockOrder::new_market_sell(company_per_id, 1, owner_per_id));
+
+        let bids = order_book.bids(company_per_id).collect::<Vec<_>>();
+        assert_eq!(bids[0].id, market_buy);
+        for pair in bids[1..].windows(2) {
+            assert!(
+                pair[0].price > pair[1].price
+                    || (pair[0].price == pair[1].price && pair[0].id < pair[1].id)
+            );
+        }
+
+        let asks = order_book.asks(company_per_id).collect::<Vec<_>>();
+        assert_eq!(asks[0].id, market_sell);
+        for pair in asks[1..].windows(2) {
+            assert!(
+                pair[0].price < pair[1].price
+                    || (pair[0].price == pair[1].price && pair[0].id < pair[1].id)
+            );
+        }
+
+        assert_eq!(order_book.len(), prices.len() * 2 + 2);
+        let removed = order_book.remove_order(market_sell).unwrap();
+        assert_eq!(removed.id, market_sell);
+        assert!(order_book.get_order(market_sell).is_none());
+        assert_eq!(order_book.get_order(market_buy).unwrap().id, market_buy);
+    }
+
+    #[test]
+    fn test_order_book_trades_at_resting_price() {
+        let mut id_gen = PersistentIdGenerator::default();
+        let company = id_gen.next_id();
+        let owner = id_gen.next_id();
+
+        let mut order_book = OrderBook::default();
+        let ask = order_book.add(StockOrder::new_sell(company, 10, 90, owner));
+        let bid = order_book.add(StockOrder::new_buy(company, 4, 100, owner));
+        let trades = order_book.match_orders(|_, _, _, quantity| quantity);
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].price, 90);
+        assert_eq!(trades[0].buy_id, bid);
+        assert_eq!(trades[0].sell_id, ask);
+        assert!(order_book.get_order(bid).is_none());
+        assert_eq!(order_book.get_order(ask).unwrap().remaining_quantity, 6);
+
+        // The bid was there first this time so sets the price
+        order_book.remove_order(ask);
+        let bid = order_book.add(StockOrder::new_buy(company, 4, 100, owner));
+        order_book.add(StockOrder::new_sell(company, 2, 95, owner));
+        order_book.add(StockOrder::new_sell(company, 2, 90, owner));
+        let trades = order_book.match_orders(|_, _, _, quantity| quantity);
+        assert_eq!(
+            trades.iter().map(|trade| trade.price).collect::<Vec<_>>(),
+            vec![100, 100]
+        );
+        assert!(order_book.get_order(bid).is_none());
+        assert!(order_book.is_empty());
+    }
+
+    #[test]
+    fn test_order_book_save_shape() {
+        let mut id_gen = PersistentIdGenerator::default();
+        let company = id_gen.next_id();
+        let owner = id_gen.next_id();
+
+        let mut order_book = OrderBook::default();
+        order_book.add(StockOrder::new_buy(company, 4, 100, owner));
+        order_book.add(StockOrder::new_sell(company, 2, 120, owner));
+
+        let saved = shared_deps::ron::to_string(&order_book).unwrap();
+        assert!(saved.contains("buy_orders"));
+        let loaded: OrderBook = shared_deps::ron::from_str(&saved).unwrap();
+        assert_eq!(loaded.top_order_id, 2);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.get_order(1).unwrap().price, 120);
+    }
+
+    #[test]
+    fn test_delist_bankrupt_company() {
+        let mut app = App::new();
+        app.insert_resource(OrderBook::default());
+        app.add_event::<CompanyDelisted>();
+        app.add_systems(Update, delist_bankrupt_companies);
+
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let mut per_id_mapping = PersistentIdMapping::default();
+        let company = per_id_gen.next_id();
+        let healthy = per_id_gen.next_id();
+        let player = per_id_gen.next_id();
+
+        let templates = load_test_templates();
+        let mut bankrupt_company = templates.get("CLOUD").unwrap().company(0);
+        bankrupt_company.distressed_quarters = QUARTERS_TO_BANKRUPTCY;
+        let mut healthy_company = templates.get("LEND").unwrap().company(0);
+        healthy_company.distressed_quarters = QUARTERS_TO_BANKRUPTCY - 1;
+
+        let company_entity = app
+            .world_mut()
+            .spawn((bankrupt_company, ShareHistory::new(100), company))
+            .id();
+        per_id_mapping.insert(company_entity, company);
+        let healthy_entity = app
+            .world_mut()
+            .spawn((healthy_company, ShareHistory::new(100), healthy))
+            .id();
+        per_id_mapping.insert(healthy_entity, healthy);
+
+        let mut portfolio = SharePortfolio::default();
+        portfolio.owned_shares.insert(company, 10);
+        portfolio.owned_shares.insert(healthy, 3);
+        let player_entity = app
+            .world_mut()
+            .spawn((Player, Wallet { balance: 0 }, portfolio, player))
+            .id();
+        per_id_mapping.insert(player_entity, player);
+        app.insert_resource(per_id_mapping);
+
+        {
+            let mut order_book = app.world_mut().resource_mut::<OrderBook>();
+            order_book.add(StockOrder::new_buy(company, 5, 100, player));
+            order_book.add(StockOrder::new_sell(company, 2, 120, player));
+            order_book.add(StockOrder::new_buy(healthy, 1, 100, player));
+        }
+        app.update();
+
+        assert!(app.world().get_entity(company_entity).is_none());
+        assert!(app.world().get_entity(healthy_entity).is_some());
+
+        let portfolio = app.world().get::<SharePortfolio>(player_entity).unwrap();
+        assert_eq!(portfolio.get_count(&company), 0);
+        assert_eq!(portfolio.get_count(&healthy), 3);
+        // Only the buy order for the bankrupt company is refunded
+        let wallet = app.world().get::<Wallet>(player_entity).unwrap();
+        assert_eq!(wallet.balance, 500);
+        let order_book = app.world().resource::<OrderBook>();
+        assert_eq!(order_book.len(), 1);
+        assert_eq!(order_book.bids(healthy).count(), 1);
+
+        let events = app.world().resource::<Events<CompanyDelisted>>();
+        let delisted = events
+            .get_reader()
+            .read(events)
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delisted,
+            vec![CompanyDelisted {
+                company,
+                ticker: "CLOUD".to_string(),
+                name_key: "company.cloud.name".to_string(),
+                player_shares: 10,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_pay_dividends() {
+        let mut app = App::new();
+        app.insert_resource(Clock::manual(DateTime::UNIX_EPOCH));
+        app.init_resource::<QuarterReaders>();
+        app.add_event::<QuarterStepped>();
+        app.add_systems(Update, pay_dividends);
+
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let company = per_id_gen.next_id();
+        let player = per_id_gen.next_id();
+        let ghost = per_id_gen.next_id();
+
+        let mut template_company = load_test_templates().get("CLOUD").unwrap().company(0);
+        for (quarter, dividend_paid) in [(1, 10), (2, 0)] {
+            let mut history = template_company.history[0].clone();
+            history.quarter = quarter;
+            history.dividend_paid = dividend_paid;
+            template_company.history.push(history);
+        }
+
+        let mut company_portfolio = SharePortfolio::default();
+        company_portfolio.add_shares(company, 2);
+        let company_entity = app
+            .world_mut()
+            .spawn((
+                template_company,
+                company,
+                company_portfolio,
+                Wallet::default(),
+            ))
+            .id();
+
+        let mut player_portfolio = SharePortfolio::default();
+        player_portfolio.add_shares(company, 5);
+        let player_entity = app
+            .world_mut()
+            .spawn((
+                player,
+                player_portfolio,
+                Wallet::default(),
+                CompleteShareOrderHistory::default(),
+            ))
+            .id();
+
+        let mut ghost_portfolio = SharePortfolio::default();
+        ghost_portfolio.add_shares(company, 3);
+        let ghost_entity = app
+            .world_mut()
+            .spawn((StockMarketGhost, ghost, ghost_portfolio, Wallet::default()))
+            .id();
+
+        app.world_mut().send_event(QuarterStepped { quarter: 1 });
+        app.world_mut().send_event(QuarterStepped { quarter: 2 });
+        app.update();
+
+        let balance = |app: &App, entity| app.world().get::<Wallet>(entity).unwrap().balance;
+        assert_eq!(balance(&app, player_entity), 50);
+        assert_eq!(balance(&app, ghost_entity), 30);
+        assert_eq!(balance(&app, company_entity), 0);
+
+        let history = app
+            .world()
+            .get::<CompleteShareOrderHistory>(player_entity)
+            .unwrap();
+        assert_eq!(history.dividends.len(), 1);
+        assert_eq!(history.dividend_income(), 50);
+    }
+
+    #[test]
+    fn test_pay_dividends_once_after_catch_up() {
+        let mut app = App::new();
+        app.insert_resource(Clock::manual(DateTime::UNIX_EPOCH));
+        app.init_resource::<QuarterReaders>();
+        app.add_event::<QuarterStepped>();
+        app.add_systems(SimulationCatchUp, pay_dividends);
+        app.add_systems(SimulationUpdate, pay_dividends);
+
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let company = per_id_gen.next_id();
+        let player = per_id_gen.next_id();
+
+        let mut template_company = load_test_templates().get("CLOUD").unwrap().company(0);
+        let mut history = template_company.history[0].clone();
+        history.quarter = 1;
+        history.dividend_paid = 10;
+        template_company.history.push(history);
+        app.world_mut().spawn((template_company, company));
+
+        let mut portfolio = SharePortfolio::default();
+        portfolio.add_shares(company, 5);
+        let player_entity = app
+            .world_mut()
+            .spawn((player, portfolio, Wallet::default()))
+            .id();
+
+        // Catch up steps the quarter then the regular update runs in the same frame
+        app.world_mut().send_event(QuarterStepped { quarter: 1 });
+        app.world_mut().run_schedule(SimulationCatchUp);
+        app.world_mut().run_schedule(SimulationUpdate);
+        app.update();
+        app.world_mut().run_schedule(SimulationUpdate);
+
+        assert_eq!(
+            app.world().get::<Wallet>(player_entity).unwrap().balance,
+            50
+        );
+    }
+
+    #[test]
+    fn test_think_about_portfolio_ignores_trades() {
+        let mut app = App::new();
+        app.add_event::<TryThinkEvent>();
+        app.insert_resource(QuarterManger {
+            current_quarter: 1,
+            ..default()
+        });
+        app.add_systems(Update, think_about_portfolio);
+
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let company = per_id_gen.next_id();
+
+        let template_company = load_test_templates().get("CLOUD").unwrap().company(0);
+        let company_entity = app
+            .world_mut()
+            .spawn((template_company, ShareHistory::new(100), company))
+            .id();
+        let mut portfolio = SharePortfolio::default();
+        portfolio.add_shares(company, 1);
+        let player = app.world_mut().spawn((Player, portfolio)).id();
+        app.world_mut().spawn(Pet);
+
+        let step = |app: &mut App| {
+            app.world_mut()
+                .resource_mut::<QuarterManger>()
+                .current_quarter += 1;
+            app.update();
+            app.world_mut()
+                .resource_mut::<Events<TryThinkEvent>>()
+                .drain()
+                .map(|event| event.concept)
+                .collect::<Vec<_>>()
+        };
+
+        app.update();
+
+        // Buying ten times the shares isn't the market rising
+        app.world_mut()
+            .get_mut::<SharePortfolio>(player)
+            .unwrap()
+            .add_shares(company, 9);
+        assert_eq!(step(&mut app), vec![]);
+
+        app.world_mut()
+            .get_mut::<ShareHistory>(company_entity)
+            .unwrap()
+            .cached_price = 125;
+        assert_eq!(step(&mut app), vec![Concept::ThinkPortfolioRising]);
+
+        // Nor is selling it all off a crash
+        app.world_mut()
+            .get_mut::<SharePortfolio>(player)
+            .unwrap()
+            .remove_shares(company, 10);
+        assert_eq!(step(&mut app), vec![]);
+    }
+
+    #[test]
+    fn test_portfolio_price_change() {
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let a = per_id_gen.next_id();
+        let b = per_id_gen.next_id();
+
+        let last = HashMap::from([(a, (10, 100)), (b, (5, 200))]);
+        let prices = HashMap::from([(a, 150), (b, 100)]);
+        assert_eq!(portfolio_price_change(&last, &prices), Some(0.));
+
+        // Delisted companies count as a total loss
+        let prices = HashMap::from([(a, 100)]);
+        assert_eq!(portfolio_price_change(&last, &prices), Some(-0.5));
+
+        assert_eq!(portfolio_price_change(&HashMap::new(), &prices), None);
+    }
+
+    #[test]
+    fn test_short_sellers_pay_dividends() {
+        let mut app = App::new();
+        app.insert_resource(Clock::manual(DateTime::UNIX_EPOCH));
+        app.init_resource::<QuarterReaders>();
+        app.add_event::<QuarterStepped>();
+        app.add_systems(Update, pay_dividends);
+
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let company = per_id_gen.next_id();
+        let buyer = per_id_gen.next_id();
+        let short_seller = per_id_gen.next_id();
+
+        let mut template_company = load_test_templates().get("CLOUD").unwrap().company(0);
+        let mut history = template_company.history[0].clone();
+        history.quarter = 1;
+        history.dividend_paid = 10;
+        template_company.history.push(history);
+        app.world_mut().spawn((template_company, company));
+
+        // The buyer holds the shares the short seller borrowed and sold them
+        let mut portfolio = SharePortfolio::default();
+        portfolio.add_shares(company, 5);
+        let buyer_entity = app
+            .world_mut()
+            .spawn((buyer, portfolio, Wallet::default()))
+            .id();
+        let short_entity = app
+            .world_mut()
+            .spawn((
+                short_seller,
+                SharePortfolio::default(),
+                Wallet { balance: 100 },
+                MarginAccount {
+                    borrowed_shares: BTreeMap::from([(company, 5)]),
+                    ..default()
+                },
+                CompleteShareOrderHistory::default(),
+            ))
+            .id();
+
+        app.world_mut().send_event(QuarterStepped { quarter: 1 });
+        app.update();
+
+        let world = app.world();
+        assert_eq!(world.get::<Wallet>(buyer_entity).unwrap().balance, 50);
+        assert_eq!(world.get::<Wallet>(short_entity).unwrap().balance, 50);
+        assert_eq!(
+            world
+                .get::<CompleteShareOrderHistory>(short_entity)
+                .unwrap()
+                .dividend_income(),
+            -50
+        );
+    }
+
+    #[test]
+    fn test_macro_economy() {
+        let mut economy = MacroEconomy::default();
+        economy.start(EconomicEvent::Boom, 1);
+        economy.start(EconomicEvent::IndustryCrisis(Industry::Energy), 2);
+
+        assert_eq!(economy.bias(&[(1., Industry::Energy)]), 0.3 - 0.5);
+        assert_eq!(
+            economy.bias(&[(0.5, Industry::Energy), (0.5, Industry::Tech)]),
+            0.3 - 0.25
+        );
+        assert_eq!(
+            economy.events_for(&[(1., Industry::Tech)]),
+            vec![EconomicEvent::Boom]
+        );
+
+        let mut rng = RngComponent::with_seed(7);
+        let started = economy.step(&mut rng);
+        let crisis = economy
+            .events()
+            .find(|active| active.event == EconomicEvent::IndustryCrisis(Industry::Energy))
+            .unwrap();
+        assert_eq!(crisis.quarters_left, 1);
+        assert!(economy.events().all(|active| active.quarters_left > 0));
+        assert_eq!(economy.events().count(), 1 + started.len());
+
+        // Pushing the bias moves where companies end up
+        let better_than_average = |bias: f32| {
+            let mut rng = RngComponent::with_seed(7);
+            (0..2000)
+                .filter(|_| {
+                    (PerformanceRanking::Average.next_biased(&mut rng, bias) as usize)
+                        < PerformanceRanking::Average as usize
+                })
+                .count()
+        };
+        assert!(better_than_average(0.9) > better_than_average(0.) * 3 / 2);
+        assert!(better_than_average(-0.9) < better_than_average(0.) * 2 / 3);
+    }
+
+    #[test]
+    fn test_list_new_companies_once_after_catch_up() {
+        let mut app = App::new();
+        app.add_plugins(PersistentIdPlugin);
+        app.insert_resource(GlobalRng::with_seed(1));
+        app.insert_resource(PersistentIdGenerator::default());
+        app.insert_resource(PersistentIdMapping::default());
+        app.insert_resource(load_test_templates());
+        app.init_resource::<QuarterReaders>();
+        app.insert_resource(OrderBook::default());
+        app.insert_resource(QuarterManger {
+            current_quarter: 3,
+            ..default()
+        });
+        app.add_event::<QuarterStepped>();
+        app.add_event::<CompanyListed>();
+        app.add_systems(SimulationCatchUp, list_new_companies);
+        app.add_systems(SimulationUpdate, (list_new_companies, float_ipos).chain());
+
+        // Under MIN_COMPANIES so every stepped quarter lists one
+        app.world_mut().send_event(QuarterStepped { quarter: 2 });
+        app.world_mut().run_schedule(SimulationCatchUp);
+        app.world_mut().run_schedule(SimulationUpdate);
+        app.update();
+        app.world_mut().run_schedule(SimulationUpdate);
+
+        let companies = app
+            .world_mut()
+            .query::<(&PersistentId, &Company)>()
+            .iter(app.world())
+            .map(|(per_id, _)| *per_id)
+            .collect::<Vec<_>>();
+        assert_eq!(companies.len(), 1);
+
+        let order_book = app.world().resource::<OrderBook>();
+        let float = order_book.asks(companies[0]).collect::<Vec<_>>();
+        assert_eq!(float.len(), 1);
+        assert_eq!(float[0].expiry, OrderExpiry::GoodTillQuarter(3));
+        assert!(!float[0].expired(3));
+    }
+
+    #[test]
+    fn test_list_new_companies_without_templates() {
+        let mut app = App::new();
+        app.insert_resource(GlobalRng::with_seed(1));
+        app.init_resource::<QuarterReaders>();
+        app.add_event::<QuarterStepped>();
+        app.add_event::<CompanyListed>();
+        app.add_systems(Update, list_new_companies);
+
+        app.world_mut().send_event(QuarterStepped { quarter: 1 });
+        app.update();
+        assert_eq!(
+            app.world_mut()
+                .query::<&Company>()
+                .iter(app.world())
+                .count(),
+            0
+        );
+
+        // Quarters missed while loading aren't listed late
+        app.insert_resource(load_test_templates());
+        app.update();
+        assert_eq!(
+            app.world_mut()
+                .query::<&Company>()
+                .iter(app.world())
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_pick_ipo_template() {
+        let templates = load_test_templates();
+        let mut weights: HashMap<Industry, f32> = Industry::iter().map(|i| (i, 1.)).collect();
+        weights.insert(Industry::Energy, 0.);
+        let mut listed = HashSet::new();
+
+        let picked = pick_ipo_template(templates.ipos(), &listed, &weights).unwrap();
+        assert_eq!(picked.ticker, "BOLT");
+
+        listed.insert("BOLT".to_string());
+        let picked = pick_ipo_template(templates.ipos(), &listed, &weights).unwrap();
+        assert_eq!(picked.ticker, "SOLR");
+
+        listed.insert("SOLR".to_string());
+        let picked = pick_ipo_template(templates.ipos(), &listed, &weights).unwrap();
+        assert_ne!(picked.main_industry(), Industry::Energy);
+
+        let listed = templates
+            .ipos()
+            .map(|template| template.ticker.clone())
+            .collect();
+        assert!(pick_ipo_template(templates.ipos(), &listed, &weights).is_none());
+    }
+
+    #[derive(Debug, Clone)]
+    enum MarketOp {
+        Place {
+            owner: usize,
+            kind: OrderKind,
+            order_type: OrderType,
+            expiry: OrderExpiry,
+            price: Money,
+            quantity: u64,
+        },
+        Cancel {
+            owner: usize,
+            id: u64,
+        },
+        Tick {
+            secs: u64,
+        },
+        EndQuarter,
+    }
+
+    fn market_op() -> impl Strategy<Value = MarketOp> {
+        let order_type = prop_oneof![Just(OrderType::Limit), Just(OrderType::Market)];
+        let expiry = prop_oneof![
+            Just(OrderExpiry::Timed),
+            Just(OrderExpiry::GoodTillCancelled),
+            Just(OrderExpiry::GoodTillQuarter(0)),
+        ];
+        let kind = prop_oneof![Just(OrderKind::Buy), Just(OrderKind::Sell)];
+        prop_oneof![
+            6 => (0..TRADERS, kind, order_type, expiry, 1..50 as Money, 1..30u64).prop_map(
+                |(owner, kind, order_type, expiry, price, quantity)| MarketOp::Place {
+                    owner,
+                    kind,
+                    order_type,
+                    expiry,
+                    price,
+                    quantity,
+                }
+            ),
+            1 => (0..TRADERS, 0..64u64).prop_map(|(owner, id)| MarketOp::Cancel { owner, id }),
+            2 => (0..90u64).prop_map(|secs| MarketOp::Tick { secs }),
+            1 => Just(MarketOp::EndQuarter),
+        ]
+    }
+
+    const TRADERS: usize = 3;
+    const START_BALANCE: Money = 1000;
+    const START_SHARES: u64 = 50;
+
+    fn totals(app: &mut App, company: PersistentId) -> (Money, u64) {
+        let order_book = app.world().resource::<OrderBook>();
+        let reserved_money: Money = order_book
+            .bids(company)
+            .map(|order| order.remaining_quantity as Money * order.price)
+            .sum();
+        let reserved_shares: u64 = order_book
+            .asks(company)
+            .map(|order| order.remaining_quantity)
+            .sum();
+
+        let mut query = app.world_mut().query::<(&Wallet, &SharePortfolio)>();
+        let (money, shares) =
+            query
+                .iter(app.world())
+                .fold((0, 0), |(money, shares), (wallet, portfolio)| {
+                    (
+                        money + wallet.balance,
+                        shares + portfolio.get_count(&company),
+                    )
+                });
+
+        (money + reserved_money, shares + reserved_shares)
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_orders_conserve_shares_and_money(ops in prop::collection::vec(market_op(), 1..60)) {
+            let mut app = App::new();
+            app.insert_resource(Time::<()>::default());
+            app.insert_resource(Clock::manual(DateTime::UNIX_EPOCH));
+            app.insert_resource(OrderBook::default());
+            app.insert_resource(QuarterManger::default());
+            app.add_event::<CancelStockOrder>();
+            app.add_event::<OrderFilled>();
+            app.add_systems(Update, process_orders);
+
+            let mut per_id_gen = PersistentIdGenerator::default();
+            let mut per_id_mapping = PersistentIdMapping::default();
+            let company = per_id_gen.next_id();
+            let company_entity = app
+                .world_mut()
+                .spawn((ShareHistory::new(10), company))
+                .id();
+            per_id_mapping.insert(company_entity, company);
+            let traders = (0..TRADERS)
+                .map(|_| {
+                    let per_id = per_id_gen.next_id();
+                    let mut portfolio = SharePortfolio::default();
+                    portfolio.add_shares(company, START_SHARES);
+                    let entity = app
+                        .world_mut()
+                        .spawn((Wallet { balance: START_BALANCE }, portfolio, per_id))
+                        .id();
+                    per_id_mapping.insert(entity, per_id);
+                    (entity, per_id)
+                })
+                .collect::<Vec<_>>();
+            app.insert_resource(per_id_mapping);
+
+            let expected = (
+                START_BALANCE * TRADERS as Money,
+                START_SHARES * TRADERS as u64,
+            );
+
+            for op in ops {
+                match op {
+                    MarketOp::Place { owner, kind, order_type, expiry, price, quantity } => {
+                        let (entity, per_id) = traders[owner];
+                        // Reserve the same way the scene and the ghosts do
+                        let order = match (kind, order_type) {
+                            (OrderKind::Buy, OrderType::Limit) => {
+                                let mut wallet = app.world_mut().get_mut::<Wallet>(entity).unwrap();
+                                if wallet.balance < price * quantity as Money {
+                                    continue;
+                                }
+                                wallet.balance -= price * quantity as Money;
+                                StockOrder::new_buy(company, quantity, price, per_id)
+                            }
+                            (OrderKind::Buy, OrderType::Market) => {
+                                StockOrder::new_market_buy(company, quantity, per_id)
+                            }
+                            (OrderKind::Sell, _) => {
+                                let mut portfolio =
+                                    app.world_mut().get_mut::<SharePortfolio>(entity).unwrap();
+                                if portfolio.get_count(&company) < quantity {
+                                    continue;
+                                }
+                                portfolio.remove_shares(company, quantity);
+                                match order_type {
+                                    OrderType::Limit => {
+                                        StockOrder::new_sell(company, quantity, price, per_id)
+                                    }
+                                    OrderType::Market => {
+                                        StockOrder::new_market_sell(company, quantity, per_id)
+                                    }
+                                }
+                            }
+                        };
+                        app.world_mut()
+                            .resource_mut::<OrderBook>()
+                            .add(order.with_expiry(expiry));
+                    }
+                    MarketOp::Cancel { owner, id } => {
+                        app.world_mut()
+                            .send_event(CancelStockOrder::new(id, traders[owner].1));
+                    }
+                    MarketOp::Tick { secs } => {
+                        app.world_mut()
+                            .resource_mut::<Time>()
+                            .advance_by(Duration::from_secs(secs));
+                    }
+                    MarketOp::EndQuarter => {
+                        app.world_mut().resource_mut::<QuarterManger>().current_quarter += 1;
+                    }
+                }
+
+                app.update();
+                prop_assert_eq!(totals(&mut app, company), expected);
+
+                // Whatever is left on the book must not cross
+                let order_book = app.world().resource::<OrderBook>();
+                if let (Some(bid), Some(ask)) =
+                    (order_book.bids(company).next(), order_book.asks(company).next())
+                {
+                    prop_assert!(bid.price < ask.price);
+                }
+            }
+
+            // Cancelling everything hands it all back
+            let open = app
+                .world()
+                .resource::<OrderBook>()
+                .orders()
+                .map(|order| CancelStockOrder::new(order.id, order.owner))
+                .collect::<Vec<_>>();
+            app.world_mut().send_event_batch(open);
+            app.update();
+            prop_assert!(app.world().resource::<OrderBook>().is_empty());
+            prop_assert_eq!(totals(&mut app, company), expected);
+        }
 
-        let sell_orders = order_book.get_sell_orders(company_per_id);
+        #[test]
+        fn prop_matching_respects_limits(
+            orders in prop::collection::vec((any::<bool>(), 1..20 as Money, 1..10u64), 1..40)
+        ) {
+            let mut id_gen = PersistentIdGenerator::default();
+            let company = id_gen.next_id();
+            let owner = id_gen.next_id();
+
+            let mut order_book = OrderBook::default();
+            let mut placed = HashMap::new();
+            for (buy, price, quantity) in orders {
+                let order = if buy {
+                    StockOrder::new_buy(company, quantity, price, owner)
+                } else {
+                    StockOrder::new_sell(company, quantity, price, owner)
+                };
+                let id = order_book.add(order.clone());
+                placed.insert(id, order);
+            }
+            let shares_before: u64 = order_book.orders().map(|order| order.remaining_quantity).sum();
+
+            let trades = order_book.match_orders(|_, _, _, quantity| quantity);
 
-        prices.sort();
-        for (i, order) in sell_orders.iter().enumerate() {
-            assert_eq!(order.price, prices[i]);
+            let mut traded = 0;
+            for trade in &trades {
+                let bid = &placed[&trade.buy_id];
+                let ask = &placed[&trade.sell_id];
+                prop_assert!(ask.price <= trade.price && trade.price <= bid.price);
+                traded += trade.quantity;
+            }
+            let shares_after: u64 = order_book.orders().map(|order| order.remaining_quantity).sum();
+            prop_assert_eq!(shares_before, shares_after + traded * 2);
+
+            let best_bid = order_book.bids(company).next();
+            let best_ask = order_book.asks(company).next();
+            if let (Some(bid), Some(ask)) = (best_bid, best_ask) {
+                prop_assert!(bid.price < ask.price);
+            }
         }
     }
 }
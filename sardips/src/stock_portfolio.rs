@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use sardips_core::money_core::Money;
+use sardips_core::persistent_id::PersistentId;
+use shared_deps::chrono::DateTime;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::sardip_save::file::write_atomic;
+use crate::stock_market::{CandleResolution, CompleteShareOrderHistory, OrderKind, ShareHistory};
+
+pub const LEDGER_FILE: &str = "trade_ledger.csv";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoldingAnalytics {
+    pub company: PersistentId,
+    // Negative while short
+    pub shares: i64,
+    // What the shares still held cost averaged over every buy, or what short sales brought in
+    // as a negative
+    pub cost_basis: Money,
+    pub realised: Money,
+    pub dividends: Money,
+    // None once the company has been delisted
+    pub price: Option<Money>,
+}
+
+impl HoldingAnalytics {
+    fn new(company: PersistentId) -> Self {
+        Self {
+            company,
+            shares: 0,
+            cost_basis: 0,
+            realised: 0,
+            dividends: 0,
+            price: None,
+        }
+    }
+
+    pub fn average_cost(&self) -> Money {
+        if self.shares == 0 {
+            return 0;
+        }
+        self.cost_basis / self.shares as Money
+    }
+
+    pub fn market_value(&self) -> Money {
+        self.price.unwrap_or(0) * self.shares
+    }
+
+    pub fn unrealised(&self) -> Money {
+        match self.price {
+            Some(_) => self.market_value() - self.cost_basis,
+            None => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortfolioAnalytics {
+    // In the order the player first traded them
+    pub holdings: Vec<HoldingAnalytics>,
+    // Everything ever spent buying shares
+    pub invested: Money,
+    pub first_quarter: Option<u32>,
+}
+
+impl PortfolioAnalytics {
+    pub fn new(history: &CompleteShareOrderHistory, prices: &HashMap<PersistentId, Money>) -> Self {
+        let mut holdings: Vec<HoldingAnalytics> = vec![];
+        let mut index: HashMap<PersistentId, usize> = HashMap::new();
+        let mut holding = |company: PersistentId| -> usize {
+            *index.entry(company).or_insert_with(|| {
+                holdings.push(HoldingAnalytics::new(company));
+                holdings.len() - 1
+            })
+        };
+
+        let mut invested = 0;
+        let mut entries = vec![];
+        for order in history.orders() {
+            entries.push((holding(order.company), order));
+        }
+        let dividends = history
+            .dividends()
+            .iter()
+            .map(|dividend| (holding(dividend.company), dividend))
+            .collect::<Vec<_>>();
+
+        for (i, order) in entries {
+            let holding = &mut holdings[i];
+            let mut quantity = match order.kind {
+                OrderKind::Buy => {
+                    invested += order.price * order.quantity as Money;
+                    order.quantity as i64
+                }
+                OrderKind::Sell => -(order.quantity as i64),
+            };
+
+            // Trading against the position closes it first, anything left opens the other way
+            if holding.shares != 0 && holding.shares.signum() != quantity.signum() {
+                let closed = quantity.abs().min(holding.shares.abs()) * holding.shares.signum();
+                let cost = holding.cost_basis * closed / holding.shares;
+                holding.realised += order.price * closed - cost;
+                holding.cost_basis -= cost;
+                holding.shares -= closed;
+                quantity += closed;
+            }
+            holding.shares += quantity;
+            holding.cost_basis += order.price * quantity;
+        }
+
+        for (i, dividend) in dividends {
+            holdings[i].dividends += dividend.per_share * dividend.shares as Money;
+        }
+
+        for holding in &mut holdings {
+            holding.price = prices.get(&holding.company).copied();
+            // Shares in a bankrupt company are gone for good and so is any short owed on it
+            if holding.price.is_none() {
+                holding.realised -= holding.cost_basis;
+                holding.cost_basis = 0;
+                holding.shares = 0;
+            }
+        }
+
+        Self {
+            holdings,
+            invested,
+            first_quarter: history.orders().first().map(|order| order.quarter),
+        }
+    }
+
+    pub fn realised(&self) -> Money {
+        self.holdings.iter().map(|holding| holding.realised).sum()
+    }
+
+    pub fn unrealised(&self) -> Money {
+        self.holdings
+            .iter()
+            .map(|holding| holding.unrealised())
+            .sum()
+    }
+
+    pub fn dividend_income(&self) -> Money {
+        self.holdings.iter().map(|holding| holding.dividends).sum()
+    }
+
+    pub fn total_gain(&self) -> Money {
+        self.realised() + self.unrealised() + self.dividend_income()
+    }
+
+    pub fn total_return(&self) -> f32 {
+        if self.invested <= 0 {
+            return 0.;
+        }
+        self.total_gain() as f32 / self.invested as f32
+    }
+}
+
+// Average return of every listed company since the quarter, each weighted the same
+pub fn equal_weight_index_return<'a>(
+    since_quarter: u32,
+    companies: impl Iterator<Item = &'a ShareHistory>,
+) -> Option<f32> {
+    let returns = companies
+        .filter_map(|share_history| {
+            let start = share_history
+                .candles
+                .get(CandleResolution::Quarter)
+                .iter()
+                .find(|candle| candle.period >= since_quarter as i64)?;
+            Some(share_history.cached_price as f32 / start.open.max(1) as f32 - 1.)
+        })
+        .collect::<Vec<_>>();
+
+    if returns.is_empty() {
+        return None;
+    }
+    Some(returns.iter().sum::<f32>() / returns.len() as f32)
+}
+
+fn csv_money(money: Money) -> String {
+    let sign = if money < 0 { "-" } else { "" };
+    let money = money.unsigned_abs();
+    format!("{}{}.{:02}", sign, money / 100, money % 100)
+}
+
+fn csv_time(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+// Every fill and dividend oldest first, delisted companies have a blank ticker
+pub fn ledger_csv(
+    history: &CompleteShareOrderHistory,
+    tickers: &HashMap<PersistentId, String>,
+) -> String {
+    let ticker = |company: &PersistentId| tickers.get(company).cloned().unwrap_or_default();
+
+    let mut rows = history
+        .orders()
+        .iter()
+        .map(|order| {
+            let kind = match order.kind {
+                OrderKind::Buy => "buy",
+                OrderKind::Sell => "sell",
+            };
+            (
+                order.timestamp,
+                order.quarter,
+                kind,
+                ticker(&order.company),
+                order.price,
+                order.quantity,
+            )
+        })
+        .chain(history.dividends().iter().map(|dividend| {
+            (
+                dividend.timestamp,
+                dividend.quarter,
+                "dividend",
+                ticker(&dividend.company),
+                dividend.per_share,
+                dividend.shares,
+            )
+        }))
+        .collect::<Vec<_>>();
+    rows.sort_by_key(|row| row.0);
+
+    let mut csv = String::from("time,quarter,kind,ticker,price,quantity,total\n");
+    for (timestamp, quarter, kind, ticker, price, quantity) in rows {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            csv_time(timestamp),
+            quarter,
+            kind,
+            ticker,
+            csv_money(price),
+            quantity,
+            csv_money(price * quantity as Money),
+        ));
+    }
+    csv
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn export_ledger(
+    dir: &Path,
+    history: &CompleteShareOrderHistory,
+    tickers: &HashMap<PersistentId, String>,
+) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    write_atomic(&dir.join(LEDGER_FILE), &ledger_csv(history, tickers))
+}
+
+#[cfg(test)]
+mod test {
+    use sardips_core::persistent_id::PersistentIdGenerator;
+    use shared_deps::chrono::{DateTime, Utc};
+
+    use crate::clock::Clock;
+    use crate::stock_market::ShareHistory;
+
+    use super::*;
+
+    fn record(
+        history: &mut CompleteShareOrderHistory,
+        kind: OrderKind,
+        company: PersistentId,
+        price: Money,
+        quantity: u64,
+        quarter: u32,
+    ) {
+        history.record_order(
+            kind,
+            company,
+            price,
+            quantity,
+            DateTime::UNIX_EPOCH,
+            quarter,
+        );
+    }
+
+    #[test]
+    fn test_portfolio_analytics() {
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let held = per_id_gen.next_id();
+        let delisted = per_id_gen.next_id();
+
+        let mut history = CompleteShareOrderHistory::default();
+        record(&mut history, OrderKind::Buy, held, 100, 10, 2);
+        record(&mut history, OrderKind::Buy, held, 200, 10, 3);
+        record(&mut history, OrderKind::Sell, held, 300, 5, 4);
+        record(&mut history, OrderKind::Buy, delisted, 50, 4, 4);
+        history.record_dividend(held, 4, 10, 15, DateTime::UNIX_EPOCH);
+
+        let prices = HashMap::from([(held, 250)]);
+        let analytics = PortfolioAnalytics::new(&history, &prices);
+
+        let holding = &analytics.holdings[0];
+        assert_eq!(holding.shares, 15);
+        assert_eq!(holding.average_cost(), 150);
+        assert_eq!(holding.realised, 5 * (300 - 150));
+        assert_eq!(holding.unrealised(), 15 * (250 - 150));
+        assert_eq!(holding.dividends, 150);
+
+        let gone = &analytics.holdings[1];
+        assert_eq!(gone.shares, 0);
+        assert_eq!(gone.realised, -200);
+
+        assert_eq!(analytics.invested, 1000 + 2000 + 200);
+        assert_eq!(analytics.first_quarter, Some(2));
+        assert_eq!(analytics.total_gain(), 750 + 1500 + 150 - 200);
+    }
+
+    #[test]
+    fn test_portfolio_analytics_short() {
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let company = per_id_gen.next_id();
+
+        let mut history = CompleteShareOrderHistory::default();
+        record(&mut history, OrderKind::Buy, company, 100, 5, 1);
+        record(&mut history, OrderKind::Sell, company, 200, 15, 2);
+        record(&mut history, OrderKind::Buy, company, 150, 4, 3);
+
+        let prices = HashMap::from([(company, 180)]);
+        let analytics = PortfolioAnalytics::new(&history, &prices);
+
+        let holding = &analytics.holdings[0];
+        assert_eq!(holding.shares, -6);
+        assert_eq!(holding.average_cost(), 200);
+        assert_eq!(holding.realised, 5 * (200 - 100) + 4 * (200 - 150));
+        assert_eq!(holding.unrealised(), 6 * (200 - 180));
+
+        let gone = PortfolioAnalytics::new(&history, &HashMap::new());
+        assert_eq!(gone.holdings[0].realised, 700 + 6 * 200);
+    }
+
+    #[test]
+    fn test_equal_weight_index_return() {
+        let mut up = ShareHistory::new(100);
+        up.candles.record(0, 1, 100, 1);
+        up.candles.record(0, 2, 200, 1);
+        up.cached_price = 200;
+
+        let mut down = ShareHistory::new(100);
+        down.candles.record(0, 2, 100, 1);
+        down.cached_price = 50;
+
+        let no_trades = ShareHistory::new(100);
+
+        let index = equal_weight_index_return(1, [&up, &down, &no_trades].into_iter()).unwrap();
+        assert!((index - 0.25).abs() < 0.001, "{}", index);
+        assert_eq!(equal_weight_index_return(3, [&up].into_iter()), None);
+    }
+
+    #[test]
+    fn test_ledger_csv() {
+        let mut per_id_gen = PersistentIdGenerator::default();
+        let company = per_id_gen.next_id();
+        let clock = Clock::manual(DateTime::<Utc>::UNIX_EPOCH);
+
+        let mut history = CompleteShareOrderHistory::default();
+        history.record_order(OrderKind::Buy, company, 1050, 3, clock.now(), 1);
+        history.record_dividend(company, 1, 5, 3, clock.now());
+
+        let tickers = HashMap::from([(company, "TEST".to_string())]);
+        let csv = ledger_csv(&history, &tickers);
+        assert_eq!(
+            csv,
+            "time,quarter,kind,ticker,price,quantity,total\n\
+             1970-01-01T00:00:00+00:00,1,buy,TEST,10.50,3,31.50\n\
+             1970-01-01T00:00:00+00:00,1,dividend,TEST,0.05,3,0.15\n"
+        );
+        assert_eq!(csv_money(-5), "-0.05");
+    }
+}
@@ -0,0 +1,387 @@
+use std::ops::Range;
+
+use bevy::prelude::*;
+use sardips_core::money_core::Money;
+use sardips_core::rand_utils::{gen_f32_range, NewBuilder, WalkerTable};
+use serde::{Deserialize, Serialize};
+use shared_deps::bevy_turborand::{DelegatedRng, RngComponent};
+
+use crate::stock_market::{CandleResolution, Company, CompanyRank, ShareHistory, StockMarketAI};
+
+// What a ghost gets to see about a company when deciding whether to trade it
+pub struct Quote<'a> {
+    pub rank: &'a CompanyRank,
+    pub company: &'a Company,
+    pub share_history: &'a ShareHistory,
+}
+
+impl Quote<'_> {
+    pub fn price(&self) -> Money {
+        self.share_history.cached_price
+    }
+
+    // Change since the open of the quarter `lookback` quarters ago
+    pub fn trend(&self, lookback: usize) -> Option<f32> {
+        let candles = self.share_history.candles.get(CandleResolution::Quarter);
+        let start = candles.len().checked_sub(lookback + 1)?;
+        Some(self.price() as f32 / candles[start].open.max(1) as f32 - 1.)
+    }
+
+    // How far the price is from the average close over the last quarters
+    pub fn deviation(&self, lookback: usize) -> Option<f32> {
+        let candles = self.share_history.candles.get(CandleResolution::Quarter);
+        if candles.len() <= lookback {
+            return None;
+        }
+        let recent = &candles[candles.len() - lookback..];
+        let mean = recent.iter().map(|candle| candle.close as f32).sum::<f32>() / lookback as f32;
+        Some(self.price() as f32 / mean.max(1.) - 1.)
+    }
+
+    pub fn dividend(&self) -> Money {
+        self.company
+            .history
+            .last()
+            .map_or(0, |history| history.dividend_paid)
+    }
+
+    pub fn dividend_yield(&self) -> f32 {
+        self.dividend() as f32 / self.price().max(1) as f32
+    }
+}
+
+pub trait TradingStrategy {
+    // Limit price to bid for the company at, None to stay out
+    fn bid(&self, quote: &Quote, rng: &mut RngComponent) -> Option<Money>;
+
+    // Limit price to ask for shares already held, None to keep them
+    fn ask(&self, quote: &Quote, rng: &mut RngComponent) -> Option<Money>;
+}
+
+// Moves the price by a random share within the range, never under 1
+pub(crate) fn jitter<T: DelegatedRng>(price: Money, range: &Range<f32>, rng: &mut T) -> Money {
+    (price + (price as f32 * gen_f32_range(rng, range)).floor() as Money).max(1)
+}
+
+fn scale(price: Money, by: f32) -> Money {
+    (price as f32 * by) as Money
+}
+
+// Buys what has been going up and dumps what has been going down
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MomentumTrader {
+    pub lookback: usize,
+    pub threshold: f32,
+}
+
+impl TradingStrategy for MomentumTrader {
+    fn bid(&self, quote: &Quote, rng: &mut RngComponent) -> Option<Money> {
+        let trend = quote.trend(self.lookback)?;
+        if trend < self.threshold || rng.f32() > 0.5 {
+            return None;
+        }
+        // Happy to pay up to stay on the ride
+        Some(jitter(quote.price(), &(0.0..0.03), rng))
+    }
+
+    fn ask(&self, quote: &Quote, rng: &mut RngComponent) -> Option<Money> {
+        let trend = quote.trend(self.lookback)?;
+        if trend > 0. || rng.f32() > 0.5 {
+            return None;
+        }
+        Some(jitter(quote.price(), &(-0.03..0.0), rng))
+    }
+}
+
+// Expects prices to drift back to their recent average
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MeanRevertTrader {
+    pub lookback: usize,
+    pub band: f32,
+}
+
+impl TradingStrategy for MeanRevertTrader {
+    fn bid(&self, quote: &Quote, rng: &mut RngComponent) -> Option<Money> {
+        let deviation = quote.deviation(self.lookback)?;
+        if deviation > -self.band || rng.f32() > 0.5 {
+            return None;
+        }
+        Some(jitter(quote.price(), &(0.0..0.02), rng))
+    }
+
+    fn ask(&self, quote: &Quote, rng: &mut RngComponent) -> Option<Money> {
+        let deviation = quote.deviation(self.lookback)?;
+        if deviation < self.band || rng.f32() > 0.5 {
+            return None;
+        }
+        Some(jitter(quote.price(), &(-0.02..0.0), rng))
+    }
+}
+
+// Wants a quarterly yield of at least `min_yield` and sells once it's cut
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DividendTrader {
+    pub min_yield: f32,
+}
+
+impl TradingStrategy for DividendTrader {
+    fn bid(&self, quote: &Quote, rng: &mut RngComponent) -> Option<Money> {
+        if quote.dividend_yield() < self.min_yield || rng.f32() > 0.5 {
+            return None;
+        }
+        // Pays up to where the yield would hit the minimum
+        let fair = (quote.dividend() as f32 / self.min_yield) as Money;
+        let price = fair.min(scale(quote.price(), 1.05));
+        Some(jitter(price, &(-0.02..0.0), rng))
+    }
+
+    fn ask(&self, quote: &Quote, rng: &mut RngComponent) -> Option<Money> {
+        if quote.dividend_yield() >= self.min_yield * 0.5 || rng.f32() > 0.5 {
+            return None;
+        }
+        Some(jitter(quote.price(), &(-0.03..0.0), rng))
+    }
+}
+
+// Trades at random around the price
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NoiseTrader {
+    pub activity: f32,
+}
+
+impl TradingStrategy for NoiseTrader {
+    fn bid(&self, quote: &Quote, rng: &mut RngComponent) -> Option<Money> {
+        if rng.f32() > self.activity {
+            return None;
+        }
+        Some(jitter(quote.price(), &(-0.05..0.05), rng))
+    }
+
+    fn ask(&self, quote: &Quote, rng: &mut RngComponent) -> Option<Money> {
+        if rng.f32() > self.activity {
+            return None;
+        }
+        Some(jitter(quote.price(), &(-0.05..0.05), rng))
+    }
+}
+
+// Quotes both sides of every company and earns the spread
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MarketMaker {
+    pub spread: f32,
+}
+
+impl TradingStrategy for MarketMaker {
+    fn bid(&self, quote: &Quote, _rng: &mut RngComponent) -> Option<Money> {
+        Some(scale(quote.price(), 1. - self.spread).max(1))
+    }
+
+    fn ask(&self, quote: &Quote, _rng: &mut RngComponent) -> Option<Money> {
+        Some(scale(quote.price(), 1. + self.spread).max(quote.price() + 1))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+pub enum TraderKind {
+    #[default]
+    Value,
+    Momentum,
+    MeanRevert,
+    Dividend,
+    Noise,
+    MarketMaker,
+}
+
+// Ghosts saved before strategies existed don't have one and trade on value
+#[derive(Debug, Default, Component, Clone, Copy, PartialEq, Serialize, Deserialize, Reflect)]
+#[reflect_value(Component, Serialize, Deserialize)]
+pub enum TraderStrategy {
+    // Uses the ghost's StockMarketAI weights
+    #[default]
+    Value,
+    Momentum(MomentumTrader),
+    MeanRevert(MeanRevertTrader),
+    Dividend(DividendTrader),
+    Noise(NoiseTrader),
+    MarketMaker(MarketMaker),
+}
+
+impl TraderStrategy {
+    pub fn new_from_rng<T: DelegatedRng>(kind: TraderKind, rng: &mut T) -> Self {
+        match kind {
+            TraderKind::Value => Self::Value,
+            TraderKind::Momentum => Self::Momentum(MomentumTrader {
+                lookback: rng.usize(1..=4),
+                threshold: gen_f32_range(rng, &(0.02..0.1)),
+            }),
+            TraderKind::MeanRevert => Self::MeanRevert(MeanRevertTrader {
+                lookback: rng.usize(2..=8),
+                band: gen_f32_range(rng, &(0.05..0.2)),
+            }),
+            TraderKind::Dividend => Self::Dividend(DividendTrader {
+                min_yield: gen_f32_range(rng, &(0.002..0.01)),
+            }),
+            TraderKind::Noise => Self::Noise(NoiseTrader {
+                activity: gen_f32_range(rng, &(0.05..0.3)),
+            }),
+            TraderKind::MarketMaker => Self::MarketMaker(MarketMaker {
+                spread: gen_f32_range(rng, &(0.01..0.04)),
+            }),
+        }
+    }
+
+    pub fn kind(&self) -> TraderKind {
+        match self {
+            Self::Value => TraderKind::Value,
+            Self::Momentum(_) => TraderKind::Momentum,
+            Self::MeanRevert(_) => TraderKind::MeanRevert,
+            Self::Dividend(_) => TraderKind::Dividend,
+            Self::Noise(_) => TraderKind::Noise,
+            Self::MarketMaker(_) => TraderKind::MarketMaker,
+        }
+    }
+
+    pub fn strategy<'a>(&'a self, ai: &'a StockMarketAI) -> &'a dyn TradingStrategy {
+        match self {
+            Self::Value => ai,
+            Self::Momentum(strategy) => strategy,
+            Self::MeanRevert(strategy) => strategy,
+            Self::Dividend(strategy) => strategy,
+            Self::Noise(strategy) => strategy,
+            Self::MarketMaker(strategy) => strategy,
+        }
+    }
+}
+
+// How many ghosts a new market gets and how they're split between strategies. Insert one
+// before the market is made to change it.
+#[derive(Debug, Clone, Resource)]
+pub struct TraderMix {
+    pub ghosts: usize,
+    pub weights: Vec<(TraderKind, u32)>,
+}
+
+impl TraderMix {
+    pub fn pick<T: DelegatedRng>(&self, rng: &mut T) -> TraderKind {
+        if self.weights.is_empty() {
+            return TraderKind::default();
+        }
+
+        let table = WalkerTable::new(
+            &self
+                .weights
+                .iter()
+                .map(|(_, weight)| *weight)
+                .collect::<Vec<_>>(),
+        );
+        self.weights
+            .get(table.next_rng(rng))
+            .map(|(kind, _)| *kind)
+            .unwrap_or_default()
+    }
+}
+
+impl Default for TraderMix {
+    fn default() -> Self {
+        Self {
+            ghosts: 100,
+            weights: vec![
+                (TraderKind::Value, 40),
+                (TraderKind::Momentum, 15),
+                (TraderKind::MeanRevert, 15),
+                (TraderKind::Dividend, 10),
+                (TraderKind::Noise, 10),
+                (TraderKind::MarketMaker, 10),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use strum::IntoEnumIterator;
+
+    use super::*;
+    use crate::stock_market::CompanyHistory;
+
+    fn rank() -> CompanyRank {
+        CompanyRank {
+            pe_percentile: 0.5,
+            pb_percentile: 0.5,
+            peg_percentile: 0.5,
+            dividend_percentile: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_quote() {
+        let mut share_history = ShareHistory::new(100);
+        share_history.candles.record(0, 1, 100, 1);
+        share_history.candles.record(0, 2, 50, 1);
+        share_history.candles.record(0, 3, 150, 1);
+        share_history.cached_price = 150;
+
+        let company = Company {
+            history: vec![CompanyHistory {
+                dividend_paid: 3,
+                ..default()
+            }],
+            ..default()
+        };
+        let rank = rank();
+        let quote = Quote {
+            rank: &rank,
+            company: &company,
+            share_history: &share_history,
+        };
+
+        assert_eq!(quote.trend(1), Some(2.));
+        assert_eq!(quote.trend(2), Some(0.5));
+        assert_eq!(quote.trend(3), None);
+        assert_eq!(quote.deviation(2), Some(0.5));
+        assert_eq!(quote.deviation(3), None);
+        assert_eq!(quote.dividend_yield(), 0.02);
+
+        let mut rng = RngComponent::with_seed(1);
+        let maker = MarketMaker { spread: 0.02 };
+        assert_eq!(maker.bid(&quote, &mut rng), Some(147));
+        assert_eq!(maker.ask(&quote, &mut rng), Some(153));
+
+        // Yield is well over the minimum so never pays more than 5% over the price
+        let seeker = DividendTrader { min_yield: 0.005 };
+        for _ in 0..20 {
+            if let Some(price) = seeker.bid(&quote, &mut rng) {
+                assert!((150..=157).contains(&price), "{}", price);
+            }
+            assert_eq!(seeker.ask(&quote, &mut rng), None);
+        }
+    }
+
+    #[test]
+    fn test_trader_mix() {
+        let mut rng = RngComponent::with_seed(1);
+
+        let mix = TraderMix {
+            ghosts: 10,
+            weights: vec![(TraderKind::Noise, 1), (TraderKind::MarketMaker, 3)],
+        };
+        let mut counts = HashMap::new();
+        for _ in 0..1000 {
+            *counts.entry(mix.pick(&mut rng)).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 2);
+        assert!((650..850).contains(&counts[&TraderKind::MarketMaker]));
+
+        let empty = TraderMix {
+            ghosts: 10,
+            weights: vec![],
+        };
+        assert_eq!(empty.pick(&mut rng), TraderKind::Value);
+
+        for kind in TraderKind::iter() {
+            assert_eq!(TraderStrategy::new_from_rng(kind, &mut rng).kind(), kind);
+        }
+    }
+}
@@ -4,11 +4,16 @@ use bevy::{
     prelude::*,
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
 };
-use sardips_core::{assets::FontAssets, GameState};
+use sardips_core::{
+    assets::FontAssets,
+    text_database::{Language, TextDatabase},
+    text_translation::SelectedLanguageTag,
+    GameState,
+};
 
 use crate::{
     simulation::Simulated,
-    stock_market::{Company, QuarterManger, ShareHistory},
+    stock_market::{Company, MacroEconomy, QuarterManger, ShareHistory},
 };
 
 pub struct StockTickerPlugin;
@@ -161,10 +166,26 @@ fn update_stock_ticker_rows(
     panel: Query<&Children, With<StockTickerRowHolder>>,
     mut rows: Query<(&mut StockTickerRow, &mut Text)>,
     companies: Query<(&Company, &ShareHistory)>,
+    economy: Option<Res<MacroEconomy>>,
+    text_database: Option<Res<TextDatabase>>,
+    language: Query<&Language, With<SelectedLanguageTag>>,
 ) {
     let mut companies = companies.iter().collect::<Vec<_>>();
     companies.sort_by(|(a, _), (b, _)| a.ticker.cmp(&b.ticker));
 
+    let headlines = match (economy, text_database, language.get_single()) {
+        (Some(economy), Some(text_database), Ok(language)) => economy
+            .events()
+            .map(|active| {
+                active
+                    .event
+                    .headline()
+                    .resolve_string(&text_database, *language)
+            })
+            .collect::<Vec<_>>(),
+        _ => vec![],
+    };
+
     for child in &panel {
         for child in child.iter() {
             if let Ok((mut row, mut row_text)) = rows.get_mut(*child) {
@@ -189,7 +210,7 @@ fn update_stock_ticker_rows(
 
                     to_push.push(' ');
 
-                    for _ in 0..(5 - company.ticker.len()) {
+                    for _ in 0..5usize.saturating_sub(company.ticker.len()) {
                         to_push.push(' ');
                     }
                     to_push.push_str(&company.ticker);
@@ -227,6 +248,14 @@ fn update_stock_ticker_rows(
                     text += &to_push;
                 }
 
+                for headline in &headlines {
+                    let to_push = format!(" *** {} *** ", headline);
+                    for _ in 0..to_push.chars().count() {
+                        text_color.push(Color::WHITE);
+                    }
+                    text += &to_push;
+                }
+
                 if text.is_empty() {
                     continue;
                 }
@@ -1,9 +1,14 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
+use sardips_core::age_core::Age;
+use shared_deps::bevy_turborand::GlobalRng;
 
 use crate::simulation::SimulationState;
 
 use fact_db::{
-    ActionEvent, Concept, EntityFactDatabase, FactDb, FactQuery, GlobalFactDatabase, RuleSet,
+    ActionEvent, ActionSet, Concept, EntityFactDatabase, FactDb, FactQuery, GlobalFactDatabase,
+    RuleSet,
 };
 
 pub struct ThinkingPlugin;
@@ -15,7 +20,14 @@ impl Plugin for ThinkingPlugin {
             .add_event::<TryThinkEvent>()
             .add_systems(
                 Update,
-                (trigger_idle_thoughts, handle_thought).run_if(in_state(SimulationState::Running)),
+                (
+                    trigger_idle_thoughts.run_if(in_state(SimulationState::Running)),
+                    handle_thought.run_if(
+                        in_state(SimulationState::Running).and_then(resource_exists::<RuleSet>),
+                    ),
+                    finish_thoughts.run_if(in_state(SimulationState::Running)),
+                )
+                    .chain(),
             );
     }
 }
@@ -40,10 +52,15 @@ impl Default for ThinkTimer {
     }
 }
 
+pub const THOUGHT_DISPLAY_TIME: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Component, Default, Reflect)]
 #[reflect(Component)]
 pub struct Thought {
     pub text: Option<String>,
+    // The response's after actions, sent once the thought has been shown
+    #[reflect(ignore)]
+    after: Option<(Timer, ActionSet)>,
 }
 
 fn trigger_idle_thoughts(
@@ -85,19 +102,40 @@ pub fn handle_thought(
     mut action_events: EventWriter<ActionEvent>,
     rule_set: Res<RuleSet>,
     global_fact_db: Res<GlobalFactDatabase>,
-    mut thinkers: Query<(Entity, &mut ThinkTimer, &mut Thought, &EntityFactDatabase)>,
+    mut global_rng: ResMut<GlobalRng>,
+    mut thinkers: Query<(
+        Entity,
+        &mut ThinkTimer,
+        &mut Thought,
+        &EntityFactDatabase,
+        Option<&Age>,
+    )>,
 ) {
     for event in thinking_events.read() {
-        if let Ok((entity, mut thinker, mut thought, fact_db)) = thinkers.get_mut(event.entity) {
-            let fact_query = FactQuery::new(Concept::ThinkIdle)
+        if let Ok((entity, mut thinker, mut thought, fact_db, age)) = thinkers.get_mut(event.entity)
+        {
+            let mut fact_query = FactQuery::new(event.concept)
                 .add_fact_db(&global_fact_db.0)
                 .add_fact_db(&fact_db.0)
                 .add_fact_db(&event.facts);
-            let response = fact_query.run(&rule_set);
+            // Age keeps counting while away and is saved, so cooldowns survive a restart
+            if let Some(age) = age {
+                fact_query = fact_query.at_time(age.0.as_secs_f32());
+            }
+            let response = fact_query.run_with_rng(&rule_set, &mut *global_rng);
             if let Some(response) = response {
+                // A new thought cuts the old one short
+                if let Some((_, after)) = thought.after.take() {
+                    action_events.send(ActionEvent::new(after).with_entity(entity));
+                }
+
                 thought.text = response.now.get_text().first().cloned();
                 info!("{:?} thinks: {:?}", entity, thought.text);
                 action_events.send(ActionEvent::new(response.now).with_entity(entity));
+                thought.after = Some((
+                    Timer::new(THOUGHT_DISPLAY_TIME, TimerMode::Once),
+                    response.after,
+                ));
             }
             thinker.timer.reset();
         } else {
@@ -105,3 +143,21 @@ pub fn handle_thought(
         }
     }
 }
+
+fn finish_thoughts(
+    time: Res<Time>,
+    mut action_events: EventWriter<ActionEvent>,
+    mut thinkers: Query<(Entity, &mut Thought)>,
+) {
+    for (entity, mut thought) in thinkers.iter_mut() {
+        let Some((timer, _)) = thought.after.as_mut() else {
+            continue;
+        };
+
+        if timer.tick(time.delta()).finished() {
+            let (_, after) = thought.after.take().unwrap();
+            thought.text = None;
+            action_events.send(ActionEvent::new(after).with_entity(entity));
+        }
+    }
+}
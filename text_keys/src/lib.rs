@@ -1,5 +1,16 @@
 pub const MAIN_MENU_TITLE: &str = "main_menu.title";
 pub const MAIN_MENU_PLAY_BUTTON: &str = "main_menu.play_button";
+pub const MAIN_MENU_NEW_SLOT: &str = "main_menu.new_slot";
+pub const MAIN_MENU_SLOT_SUMMARY: &str = "main_menu.slot_summary";
+pub const MAIN_MENU_SLOT_COPY: &str = "main_menu.slot_copy";
+pub const MAIN_MENU_SLOT_DELETE: &str = "main_menu.slot_delete";
+pub const MAIN_MENU_SLOT_DELETE_CONFIRM: &str = "main_menu.slot_delete_confirm";
+pub const MAIN_MENU_SLOT_EXPORT: &str = "main_menu.slot_export";
+pub const MAIN_MENU_SLOT_IMPORT: &str = "main_menu.slot_import";
+pub const MAIN_MENU_SLOT_IMPORT_FILE: &str = "main_menu.slot_import_file";
+pub const SAVE_CONFLICT_TITLE: &str = "save_conflict.title";
+pub const SAVE_CONFLICT_KEEP_LOCAL: &str = "save_conflict.keep_local";
+pub const SAVE_CONFLICT_TAKE_REMOTE: &str = "save_conflict.take_remote";
 pub const POOP: &str = "global.poop";
 pub const BACK: &str = "global.back";
 pub const DRAW: &str = "global.draw";
@@ -71,6 +82,7 @@ pub const STOCK_BUY_SCENE_FEATURE_STOCK_PRICE: &str = "stock_buy_scene.feature_s
 pub const STOCK_BUY_SCENE_FEATURE_ONE_Q_CHANGE: &str = "stock_buy_scene.feature_one_q_change";
 pub const STOCK_BUY_SCENE_FEATURE_ONE_YEAR_CHANGE: &str = "stock_buy_scene.feature_one_year_change";
 pub const STOCK_BUY_SCENE_FEATURE_INDUSTRY_HEADER: &str = "stock_buy_scene.feature_industry_header";
+pub const STOCK_BUY_SCENE_FEATURE_NEWS_HEADER: &str = "stock_buy_scene.feature_news_header";
 pub const STOCK_BUY_SCENE_FEATURE_INDUSTRY_PERCENT: &str =
     "stock_buy_scene.feature_industry_percent";
 pub const STOCK_BUY_SCENE_FEATURE_REVENUE: &str = "stock_buy_scene.feature_revenue";
@@ -86,6 +98,15 @@ pub const STOCK_BUY_SCENE_FEATURE_SELL_OPEN: &str = "stock_buy_scene.feature_sel
 pub const STOCK_BUY_SCENE_FEATURE_SELL_OPEN_NONE: &str = "stock_buy_scene.feature_sell_open_none";
 pub const STOCK_BUY_SCENE_FEATURE_SELL_BUTTON: &str = "stock_buy_scene.feature_sell_button";
 pub const STOCK_BUY_SCENE_FEATURE_BUY_BUTTON: &str = "stock_buy_scene.feature_buy_button";
+pub const STOCK_BUY_SCENE_FEATURE_CHART_MINUTE: &str = "stock_buy_scene.feature_chart_minute";
+pub const STOCK_BUY_SCENE_FEATURE_CHART_QUARTER: &str = "stock_buy_scene.feature_chart_quarter";
+pub const STOCK_BUY_SCENE_FEATURE_CHART_YEAR: &str = "stock_buy_scene.feature_chart_year";
+pub const STOCK_BUY_SCENE_FEATURE_CHART_CANDLES: &str = "stock_buy_scene.feature_chart_candles";
+pub const STOCK_BUY_SCENE_FEATURE_CHART_LINE: &str = "stock_buy_scene.feature_chart_line";
+pub const STOCK_BUY_SCENE_FEATURE_CHART_ZOOM_IN: &str = "stock_buy_scene.feature_chart_zoom_in";
+pub const STOCK_BUY_SCENE_FEATURE_CHART_ZOOM_OUT: &str = "stock_buy_scene.feature_chart_zoom_out";
+pub const STOCK_BUY_SCENE_FEATURE_CHART_RANGE: &str = "stock_buy_scene.feature_chart_range";
+pub const STOCK_BUY_SCENE_FEATURE_CHART_EMPTY: &str = "stock_buy_scene.feature_chart_empty";
 pub const STOCK_BUY_SCENE_BUY_EXISTING_BUY_TITLE: &str = "stock_buy_scene.buy_existing_buy_title";
 pub const STOCK_BUY_SCENE_BUY_EXISTING_SELL_TITLE: &str = "stock_buy_scene.buy_existing_sell_title";
 pub const STOCK_BUY_SCENE_BUY_EXISTING_BUY_LINE: &str = "stock_buy_scene.buy_existing_buy_line";
@@ -105,6 +126,45 @@ pub const STOCK_BUY_SCENE_BUY_BUY_TOTAL: &str = "stock_buy_scene.buy_buy_total";
 pub const STOCK_BUY_SCENE_BUY_REMOVE_ORDER_BUTTON: &str = "stock_buy_scene.buy_remove_order_button";
 pub const STOCK_BUY_SCENE_BUY_MODE: &str = "stock_buy_scene.buy_mode_text";
 pub const STOCK_BUY_SCENE_SELL_MODE: &str = "stock_buy_scene.sell_mode_text";
+pub const STOCK_BUY_SCENE_LIMIT_ORDER: &str = "stock_buy_scene.limit_order_text";
+pub const STOCK_BUY_SCENE_MARKET_ORDER: &str = "stock_buy_scene.market_order_text";
+pub const STOCK_BUY_SCENE_EXPIRY_TIMED: &str = "stock_buy_scene.expiry_timed_text";
+pub const STOCK_BUY_SCENE_EXPIRY_GTC: &str = "stock_buy_scene.expiry_gtc_text";
+pub const STOCK_BUY_SCENE_EXPIRY_GTQ: &str = "stock_buy_scene.expiry_gtq_text";
+pub const STOCK_BUY_SCENE_PORTFOLIO_BUTTON: &str = "stock_buy_scene.portfolio_button";
+pub const STOCK_BUY_SCENE_PORTFOLIO_TITLE: &str = "stock_buy_scene.portfolio_title";
+pub const STOCK_BUY_SCENE_PORTFOLIO_INVESTED: &str = "stock_buy_scene.portfolio_invested";
+pub const STOCK_BUY_SCENE_PORTFOLIO_VALUE: &str = "stock_buy_scene.portfolio_value";
+pub const STOCK_BUY_SCENE_PORTFOLIO_REALISED: &str = "stock_buy_scene.portfolio_realised";
+pub const STOCK_BUY_SCENE_PORTFOLIO_UNREALISED: &str = "stock_buy_scene.portfolio_unrealised";
+pub const STOCK_BUY_SCENE_PORTFOLIO_DIVIDENDS: &str = "stock_buy_scene.portfolio_dividends";
+pub const STOCK_BUY_SCENE_PORTFOLIO_RETURN: &str = "stock_buy_scene.portfolio_return";
+pub const STOCK_BUY_SCENE_PORTFOLIO_INDEX: &str = "stock_buy_scene.portfolio_index";
+pub const STOCK_BUY_SCENE_PORTFOLIO_INDEX_NONE: &str = "stock_buy_scene.portfolio_index_none";
+pub const STOCK_BUY_SCENE_PORTFOLIO_SHARES_HEADER: &str = "stock_buy_scene.portfolio_shares_header";
+pub const STOCK_BUY_SCENE_PORTFOLIO_AVG_COST_HEADER: &str =
+    "stock_buy_scene.portfolio_avg_cost_header";
+pub const STOCK_BUY_SCENE_PORTFOLIO_UNREALISED_HEADER: &str =
+    "stock_buy_scene.portfolio_unrealised_header";
+pub const STOCK_BUY_SCENE_PORTFOLIO_REALISED_HEADER: &str =
+    "stock_buy_scene.portfolio_realised_header";
+pub const STOCK_BUY_SCENE_PORTFOLIO_DIVIDENDS_HEADER: &str =
+    "stock_buy_scene.portfolio_dividends_header";
+pub const STOCK_BUY_SCENE_PORTFOLIO_DELISTED: &str = "stock_buy_scene.portfolio_delisted";
+pub const STOCK_BUY_SCENE_PORTFOLIO_EMPTY: &str = "stock_buy_scene.portfolio_empty";
+pub const STOCK_BUY_SCENE_PORTFOLIO_EXPORT: &str = "stock_buy_scene.portfolio_export";
+pub const STOCK_BUY_SCENE_PORTFOLIO_EXPORTED: &str = "stock_buy_scene.portfolio_exported";
+pub const STOCK_BUY_SCENE_PORTFOLIO_EXPORT_FAILED: &str = "stock_buy_scene.portfolio_export_failed";
+pub const STOCK_BUY_SCENE_MARGIN_OPEN: &str = "stock_buy_scene.margin_open";
+pub const STOCK_BUY_SCENE_MARGIN_LOAN: &str = "stock_buy_scene.margin_loan";
+pub const STOCK_BUY_SCENE_MARGIN_SHORTS: &str = "stock_buy_scene.margin_shorts";
+pub const STOCK_BUY_SCENE_MARGIN_EQUITY: &str = "stock_buy_scene.margin_equity";
+pub const STOCK_BUY_SCENE_MARGIN_RATIO: &str = "stock_buy_scene.margin_ratio";
+pub const STOCK_BUY_SCENE_MARGIN_RATIO_NONE: &str = "stock_buy_scene.margin_ratio_none";
+pub const STOCK_BUY_SCENE_MARGIN_CALL: &str = "stock_buy_scene.margin_call";
+pub const STOCK_BUY_SCENE_MARGIN_OK: &str = "stock_buy_scene.margin_ok";
+pub const STOCK_BUY_SCENE_MARGIN_BORROW: &str = "stock_buy_scene.margin_borrow";
+pub const STOCK_BUY_SCENE_MARGIN_REPAY: &str = "stock_buy_scene.margin_repay";
 
 pub const MINIGAME_ENDLESS_SHOOTER_COOLDOWN: &str = "minigame.endless_shooter.cooldown";
 pub const MINIGAME_ENDLESS_SHOOTER_PISTOL: &str = "minigame.endless_shooter.pistol";
@@ -143,3 +203,21 @@ pub const MINIGAME_TRANSLATE_QUIT: &str = "minigame.translate.quit";
 
 pub const MINIGAME_SNAKE_SCORE: &str = "minigame.snake.score";
 pub const MINIGAME_SNAKE_QUIT: &str = "minigame.snake.quit";
+
+pub const STOCK_NEWS_BOOM: &str = "stock_news.boom";
+pub const STOCK_NEWS_RECESSION: &str = "stock_news.recession";
+pub const STOCK_NEWS_INDUSTRY_BOOM: &str = "stock_news.industry_boom";
+pub const STOCK_NEWS_INDUSTRY_CRISIS: &str = "stock_news.industry_crisis";
+
+pub const AWAY_REPORT_TITLE: &str = "away_report.title";
+pub const AWAY_REPORT_DURATION: &str = "away_report.duration";
+pub const AWAY_REPORT_SKIPPED: &str = "away_report.skipped";
+pub const AWAY_REPORT_STARVED: &str = "away_report.starved";
+pub const AWAY_REPORT_EVOLVED: &str = "away_report.evolved";
+pub const AWAY_REPORT_HATCHED: &str = "away_report.hatched";
+pub const AWAY_REPORT_POOPS: &str = "away_report.poops";
+pub const AWAY_REPORT_QUARTERS: &str = "away_report.quarters";
+pub const AWAY_REPORT_STOCK_CHANGE: &str = "away_report.stock_change";
+pub const AWAY_REPORT_DELISTED: &str = "away_report.delisted";
+pub const AWAY_REPORT_LISTED: &str = "away_report.listed";
+pub const AWAY_REPORT_DISMISS: &str = "away_report.dismiss";